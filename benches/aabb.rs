@@ -0,0 +1,46 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::prelude::*;
+
+use ray_tracer::prelude::*;
+
+// The slab test before the rays cached their inverse direction: a division
+// and a comparison to order the planes per slab
+fn hit_interval_dividing(aabb: &Aabb<f64>, ray: &Ray<f64>, interval: &Interval<f64>) -> Option<Interval<f64>> {
+    let mut t_min = interval.min;
+    let mut t_max = interval.max;
+    for i in 0..3 {
+        let inv_d = 1.0 / ray.get_direction().get_data()[i];
+        let t0 = (aabb.get_p0().get_data()[i] - ray.get_origin().get_data()[i]) * inv_d;
+        let t1 = (aabb.get_p1().get_data()[i] - ray.get_origin().get_data()[i]) * inv_d;
+        if t0.is_nan() || t1.is_nan() {
+            continue;
+        }
+        let (t_near, t_far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+        t_min = if t_near > t_min { t_near } else { t_min };
+        t_max = if t_far < t_max { t_far } else { t_max };
+        if t_max < t_min {
+            return None;
+        }
+    }
+    Some(Interval::new(t_min, t_max))
+}
+
+fn slab_test(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut point = || Vec3::from_array([rng.gen_range(-10.0, 10.0), rng.gen_range(-10.0, 10.0), rng.gen_range(-10.0, 10.0)]);
+    let boxes: Vec<Aabb<f64>> = (0..1024).map(|_| Aabb::new(point(), point())).collect();
+    let rays: Vec<Ray<f64>> = (0..1024).map(|_| Ray::from_vec(point(), point())).collect();
+    let interval = Interval::new(0.0, f64::INFINITY);
+
+    let mut group = c.benchmark_group("slab test");
+    group.bench_function("cached inverse", |b| b.iter(|| {
+        boxes.iter().zip(rays.iter()).filter(|(aabb, ray)| aabb.hit_interval(black_box(ray), &interval).is_some()).count()
+    }));
+    group.bench_function("dividing", |b| b.iter(|| {
+        boxes.iter().zip(rays.iter()).filter(|(aabb, ray)| hit_interval_dividing(aabb, black_box(ray), &interval).is_some()).count()
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, slab_test);
+criterion_main!(benches);
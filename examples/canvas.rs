@@ -0,0 +1,37 @@
+// Renders a scene in chunks of rows into an RGBA buffer, the way a web page
+// fills a canvas: between two chunks the page can handle events and show
+// the progress with putImageData. Without threads, so the same code works
+// when compiled to wasm32-unknown-unknown.
+//
+// cargo run --release --example canvas
+
+use ray_tracer::prelude::*;
+use ray_tracer::scenes;
+use ray_tracer::error::Error;
+
+const WIDTH: usize = 160;
+const HEIGHT: usize = 90;
+// Rows rendered between two updates of the canvas
+const CHUNK_HEIGHT: usize = 10;
+
+fn main() -> Result<(), Error> {
+    let (scene, camera) = scenes::cube_scene(WIDTH, HEIGHT)?;
+    let mut renderer = Renderer::new(0, WIDTH, 0, HEIGHT, WIDTH, HEIGHT, 4, 4, true)?;
+    // No OS entropy needed, and the same picture every time
+    renderer.set_seed(Some(1));
+
+    // The pixels of the canvas, as in ImageData.data
+    let mut canvas = vec![0u8; 4 * WIDTH * HEIGHT];
+    for y in (0..HEIGHT).step_by(CHUNK_HEIGHT) {
+        let rows = renderer.render_rows(&scene, &camera, y..y + CHUNK_HEIGHT);
+        let rgba = rows.to_rgba(2.0);
+        canvas[4 * WIDTH * y..4 * WIDTH * y + rgba.len()].copy_from_slice(&rgba);
+        // In the browser: context.putImageData(new ImageData(canvas, WIDTH), 0, 0)
+        // and yield to the event loop, i.e. with requestAnimationFrame
+        eprint!("\rrendered {:3}%", 100 * (y + rows.height) / HEIGHT);
+    }
+    eprintln!();
+
+    println!("{}x{} pixels, {} bytes of RGBA", WIDTH, HEIGHT, canvas.len());
+    Ok(())
+}
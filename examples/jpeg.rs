@@ -0,0 +1,21 @@
+// Saves a render as a JPEG with the image crate, any format it can write
+// works the same way.
+//
+// cargo run --release --features image --example jpeg
+
+use ray_tracer::prelude::*;
+use ray_tracer::scenes;
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 180;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (scene, camera) = scenes::cube_scene(WIDTH, HEIGHT)?;
+    let renderer = Renderer::new(0, WIDTH, 0, HEIGHT, WIDTH, HEIGHT, 16, 8, true)?;
+    let image = renderer.render(&scene, &camera);
+
+    let rgb_image = image.to_rgb_image(2.2);
+    rgb_image.save("cube_scene.jpg")?;
+    println!("Saved cube_scene.jpg");
+    Ok(())
+}
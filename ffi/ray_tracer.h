@@ -0,0 +1,156 @@
+/* Generated with cbindgen from src/ffi/mod.rs, don't edit by hand */
+
+#ifndef RAY_TRACER_H
+#define RAY_TRACER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define RT_OK 0
+
+#define RT_NULL_POINTER 1
+
+#define RT_INVALID_ARGUMENT 2
+
+#define RT_BUFFER_TOO_SMALL 3
+
+#define RT_PANIC 4
+
+#define RT_LAMBERTIAN 0
+
+#define RT_METAL 1
+
+#define RT_DIELECTRIC 2
+
+#define RT_EMISSIVE 3
+
+#define RT_AXIS_X 0
+
+#define RT_AXIS_Y 1
+
+#define RT_AXIS_Z 2
+
+#define RT_TREE_LINEAR 0
+
+#define RT_TREE_BINARY 1
+
+#define RT_TREE_OCT 2
+
+typedef struct RtCamera RtCamera;
+
+/**
+ * Rows rendered so far, can be read from another thread while rendering
+ */
+typedef struct RtProgress RtProgress;
+
+typedef struct RtScene RtScene;
+
+/**
+ * The parameter is the dimming of lambertian materials, the fuzziness of
+ * metals and the refractive index of dielectrics, emissive ones ignore it
+ */
+typedef struct RtMaterial {
+  int32_t kind;
+  double color[3];
+  double parameter;
+} RtMaterial;
+
+/**
+ * The seed is only used if has_seed isn't 0
+ */
+typedef struct RtRenderSettings {
+  size_t width;
+  size_t height;
+  size_t samples;
+  size_t max_reflections;
+  int32_t antialiasing;
+  int32_t has_seed;
+  uint64_t seed;
+} RtRenderSettings;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+const char *rt_status_message(int32_t status);
+
+int32_t rt_scene_new(RtScene **scene);
+
+int32_t rt_scene_free(RtScene *scene);
+
+int32_t rt_scene_set_background(RtScene *scene, const double *color);
+
+int32_t rt_scene_set_tree_type(RtScene *scene, int32_t tree_type);
+
+/**
+ * The id of the new actor is written to actor_id unless it's null
+ */
+int32_t rt_scene_add_sphere(RtScene *scene,
+                            double radius,
+                            const double *position,
+                            const RtMaterial *material,
+                            size_t *actor_id);
+
+int32_t rt_scene_add_cube(RtScene *scene,
+                          double length,
+                          double width,
+                          double height,
+                          const double *position,
+                          const RtMaterial *material,
+                          size_t *actor_id);
+
+int32_t rt_scene_add_rectangle(RtScene *scene,
+                               double width,
+                               int32_t width_axis,
+                               double height,
+                               int32_t height_axis,
+                               const double *position,
+                               const RtMaterial *material,
+                               size_t *actor_id);
+
+/**
+ * Lights are sampled directly by the bidirectional integrator
+ */
+int32_t rt_scene_add_light(RtScene *scene, size_t actor_id);
+
+int32_t rt_camera_new(RtCamera **camera);
+
+int32_t rt_camera_free(RtCamera *camera);
+
+int32_t rt_camera_set_position(RtCamera *camera, const double *position);
+
+int32_t rt_camera_set_lookat(RtCamera *camera, const double *lookat);
+
+int32_t rt_camera_set_up(RtCamera *camera, const double *up);
+
+/**
+ * Field of view in radians, aspect ratio width / height, diameter of the
+ * aperture (0 for no depth of field) and distance in focus
+ */
+int32_t rt_camera_set_lens(RtCamera *camera, double fov, double aspect, double aperture, double focus);
+
+int32_t rt_progress_new(RtProgress **progress);
+
+int32_t rt_progress_free(RtProgress *progress);
+
+/**
+ * Fraction of the rows of the current or last render done, 0 before one
+ */
+int32_t rt_progress_get(const RtProgress *progress, double *fraction);
+
+/**
+ * Linear RGB, row by row from the top, into a buffer of at least
+ * 3 * width * height floats. The progress may be null.
+ */
+int32_t rt_render(const RtScene *scene,
+                  const RtCamera *camera,
+                  const RtRenderSettings *settings,
+                  const RtProgress *progress,
+                  float *buffer,
+                  size_t buffer_length);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* RAY_TRACER_H */
@@ -0,0 +1,72 @@
+/*
+ * Renders a lambertian sphere lit from above through the C API and checks the result.
+ *
+ *   cargo rustc --lib --features ffi --crate-type cdylib
+ *   cc ffi/sphere.c -Iffi -Ltarget/debug -lray_tracer -o target/sphere
+ *   LD_LIBRARY_PATH=target/debug target/sphere
+ */
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "ray_tracer.h"
+
+#define WIDTH 32
+#define HEIGHT 24
+
+#define CHECK(call) do { \
+    int32_t status = (call); \
+    if (status != RT_OK) { \
+      fprintf(stderr, "%s: %s\n", #call, rt_status_message(status)); \
+      return 1; \
+    } \
+  } while (0)
+
+int main(void) {
+  RtScene *scene = NULL;
+  RtCamera *camera = NULL;
+  RtProgress *progress = NULL;
+  size_t sphere, light;
+  double background[3] = {0.1, 0.1, 0.1};
+  double center[3] = {0.0, 0.0, -4.0};
+  double above[3] = {0.0, 3.0, -2.0};
+  double origin[3] = {0.0, 0.0, 0.0};
+  RtMaterial grey = {RT_LAMBERTIAN, {1.0, 1.0, 1.0}, 0.8};
+  RtMaterial white = {RT_EMISSIVE, {4.0, 4.0, 4.0}, 0.0};
+  RtRenderSettings settings = {WIDTH, HEIGHT, 16, 4, 1, 1, 42};
+  float *buffer = malloc(3 * WIDTH * HEIGHT * sizeof(float));
+  double fraction = 0.0;
+  int lit;
+
+  CHECK(rt_scene_new(&scene));
+  CHECK(rt_scene_set_background(scene, background));
+  CHECK(rt_scene_add_sphere(scene, 1.0, center, &grey, &sphere));
+  CHECK(rt_scene_add_sphere(scene, 1.0, above, &white, &light));
+  CHECK(rt_scene_add_light(scene, light));
+
+  CHECK(rt_camera_new(&camera));
+  CHECK(rt_camera_set_position(camera, origin));
+  CHECK(rt_camera_set_lookat(camera, center));
+  CHECK(rt_camera_set_lens(camera, 0.8, (double) WIDTH / HEIGHT, 0.0, 4.0));
+
+  if (rt_render(scene, camera, &settings, NULL, buffer, 3) != RT_BUFFER_TOO_SMALL) {
+    fprintf(stderr, "small buffer accepted\n");
+    return 1;
+  }
+
+  CHECK(rt_progress_new(&progress));
+  CHECK(rt_render(scene, camera, &settings, progress, buffer, 3 * WIDTH * HEIGHT));
+  CHECK(rt_progress_get(progress, &fraction));
+
+  /* The top of the sphere faces the light and is brighter than the bottom */
+  float *top = &buffer[3 * ((HEIGHT / 2 - 4) * WIDTH + WIDTH / 2)];
+  float *bottom = &buffer[3 * ((HEIGHT / 2 + 4) * WIDTH + WIDTH / 2)];
+  printf("progress %.2f, top %.3f, bottom %.3f\n", fraction, top[1], bottom[1]);
+  lit = fraction == 1.0 && top[1] > bottom[1];
+
+  CHECK(rt_progress_free(progress));
+  CHECK(rt_camera_free(camera));
+  CHECK(rt_scene_free(scene));
+  free(buffer);
+
+  return lit ? 0 : 1;
+}
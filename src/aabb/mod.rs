@@ -0,0 +1,503 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::interval::Interval;
+
+// Axis aligned bounding box, for the trees and for custom hitables
+pub struct Aabb<T>
+    where T : Float
+{
+    p0: Vec3<T>,
+    p1: Vec3<T>
+}
+
+impl<T> Clone for Aabb<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        Aabb {
+            p0: Vec3::from_slice(self.p0.get_data()),
+            p1: Vec3::from_slice(self.p1.get_data())
+        }
+    }
+}
+
+impl<T> Aabb<T>
+    where T : Float
+{
+    pub fn new(p0: Vec3<T>, p1: Vec3<T>) -> Self {
+        let mut p0 = p0;
+        let mut p1 = p1;
+        // Ensure the min x,y,z is always in p0, and the max is always in p1
+        let (min_x, max_x) = Aabb::<T>::calculate_axis_bounds(&p0, &p1, 0);
+        let (min_y, max_y) = Aabb::<T>::calculate_axis_bounds(&p0, &p1, 1);
+        let (min_z, max_z) = Aabb::<T>::calculate_axis_bounds(&p0, &p1, 2);
+
+        p0.set_data(&[min_x, min_y, min_z]);
+        p1.set_data(&[max_x, max_y, max_z]);
+        Aabb {p0, p1}
+    }
+
+    pub fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> bool {
+        self.hit_interval(ray, &Interval::new(t_min, t_max)).is_some()
+    }
+
+    // Slab test, returns the portion of the interval spent inside the box.
+    // The near and far planes of each slab are picked with the sign of the
+    // direction and the interval is clipped with min and max, stopping as
+    // soon as it's empty (see benches/aabb.rs). Zero direction components
+    // give infinite slab distances, or NaN with the origin on a slab plane:
+    // min and max ignore the NaN, the ray lies inside the slab which doesn't
+    // clip anything.
+    pub fn hit_interval(&self, ray: &Ray<T>, interval: &Interval<T>) -> Option<Interval<T>> {
+        let mut t_min = interval.min;
+        let mut t_max = interval.max;
+        let origin = ray.get_origin().get_data();
+        let inv_direction = ray.get_inv_direction().get_data();
+        for i in 0..3 {
+            let (near, far) = if ray.is_negative(i) {
+                (self.p1.get_data()[i], self.p0.get_data()[i])
+            } else {
+                (self.p0.get_data()[i], self.p1.get_data()[i])
+            };
+            t_min = t_min.max((near - origin[i]) * inv_direction[i]);
+            t_max = t_max.min((far - origin[i]) * inv_direction[i]);
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some(Interval::new(t_min, t_max))
+    }
+
+    pub fn union(&self, other: &Aabb<T>) -> Aabb<T> {
+        let mut result = Aabb::new(
+            Vec3::from_slice(self.p0.get_data()),
+            Vec3::from_slice(self.p1.get_data())
+        );
+        result.expand(other);
+        result
+    }
+
+    pub fn surrounds(&self, point: &Vec3<T>) -> bool {
+        for i in 0..3 {
+            let (min, max) = self.get_axis_bounds(i);
+            let x = point.get_data()[i];
+            if x.is_nan() || x < min || x > max {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Unlike overlaps, boxes that only touch on a face/edge/corner intersect
+    pub fn intersects(&self, other: &Aabb<T>) -> bool {
+        for i in 0..3 {
+            let (min_self, max_self) = self.get_axis_bounds(i);
+            let (min_other, max_other) = other.get_axis_bounds(i);
+            if max_other < min_self || min_other > max_self {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn centroid(&self) -> Vec3<T> {
+        (&self.p0 + &self.p1) * T::from_f64(0.5)
+    }
+
+    pub fn surface_area(&self) -> T {
+        let x = self.get_axis_length(0);
+        let y = self.get_axis_length(1);
+        let z = self.get_axis_length(2);
+        T::from_f64(2.0) * (x * y + y * z + z * x)
+    }
+
+    pub fn contains(&self, other: &Aabb<T>) -> bool {
+        for i in 0..3 {
+            let (min_self, max_self) = self.get_axis_bounds(i);
+            let (min_other, max_other) = other.get_axis_bounds(i);
+            if min_other < min_self || max_other > max_self {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn overlaps(&self, other: &Aabb<T>) -> bool {
+        for i in 0..3 {
+            let (min_self, max_self) = self.get_axis_bounds(i);
+            let (min_other, max_other) = other.get_axis_bounds(i);
+            if max_other <= min_self || min_other >= max_self {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn expand(&mut self, other: &Aabb<T>) -> bool {
+        let mut expanded = false;
+        for i in 0..3 {
+            let (min_self, max_self) = self.get_axis_bounds(i);
+            let (min_other, max_other) = other.get_axis_bounds(i);
+            if min_other < min_self {
+                self.p0.get_data_mut()[i] = min_other;
+                expanded = true;
+            }
+            if max_other > max_self {
+                self.p1.get_data_mut()[i] = max_other;
+                expanded = true;
+            }
+        }
+        expanded
+    }
+
+    pub fn get_p0(&self) -> &Vec3<T> {
+        &self.p0
+    }
+
+    pub fn get_p1(&self) -> &Vec3<T> {
+        &self.p1
+    }
+
+    pub fn get_volume(&self) -> T {
+        let mut volume = T::one();
+
+        for i in 0..3 {
+            let (min, max) = self.get_axis_bounds(i);
+            volume = volume * (max - min);
+        }
+
+        volume
+    }
+
+    pub fn get_axis_bounds(&self, axis: usize) -> (T, T) {
+        let min = self.p0.get_data()[axis];
+        let max = self.p1.get_data()[axis];
+        (min, max)
+    }
+
+    pub fn get_axis_length(&self, axis: usize) -> T {
+        let (min, max) = self.get_axis_bounds(axis);
+        max - min
+    }
+
+    pub fn make_cube(&mut self) {
+        let mut max_length = T::zero();
+        for i in 0..3 {
+            let length = self.get_axis_length(i);
+            if length > max_length {
+                max_length = length;
+            }
+        }
+
+        let half = T::from_f64(0.5);
+
+        for i in 0..3 {
+            let length = self.get_axis_length(i);
+            let pad = half * (max_length - length);
+            if pad > T::zero() {
+                self.pad_axis(pad, i);
+            }
+        }
+    }
+
+    pub fn pad_axis(&mut self, pad: T, axis: usize) {
+        let (min, max) = self.get_axis_bounds(axis);
+        self.p0.get_data_mut()[axis] = min - pad;
+        self.p1.get_data_mut()[axis] = max + pad;
+    }
+
+    fn calculate_axis_bounds(p0: &Vec3<T>, p1: &Vec3<T>, axis: usize) -> (T, T) {
+        let mut min = p0.get_data()[axis];
+        let mut max = p1.get_data()[axis];
+        if min > max {
+            let tmp = min;
+            min = max;
+            max = tmp;
+        }
+        (min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn contains() {
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let box0 = Aabb::new(p0, p1);
+
+        let p0 = Vec3::from_array([1.0, 1.0, 1.0]);
+        let p1 = Vec3::from_array([2.0, 2.0, 2.0]);
+        let box1 = Aabb::new(p0, p1);
+
+        assert!(box0.contains(&box1));
+        assert!(!box1.contains(&box0));
+
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let box0 = Aabb::new(p1, p0);
+
+        let p0 = Vec3::from_array([1.0, 1.0, 1.0]);
+        let p1 = Vec3::from_array([2.0, 2.0, 2.0]);
+        let box1 = Aabb::new(p1, p0);
+
+        assert!(box0.contains(&box1));
+        assert!(!box1.contains(&box0));
+
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let box0 = Aabb::new(p0, p1);
+
+        let p0 = Vec3::from_array([-1.0, 1.0, 1.0]);
+        let p1 = Vec3::from_array([2.0, 2.0, 2.0]);
+        let box1 = Aabb::new(p0, p1);
+
+        assert!(!box0.contains(&box1));
+        assert!(!box1.contains(&box0));
+    }
+
+    #[test]
+    fn overlaps() {
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let box0 = Aabb::new(p0, p1);
+
+        let p0 = Vec3::from_array([1.0, 1.0, 1.0]);
+        let p1 = Vec3::from_array([2.0, 2.0, 2.0]);
+        let box1 = Aabb::new(p0, p1);
+
+        assert!(box0.overlaps(&box1));
+        assert!(box1.overlaps(&box0));
+
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let box0 = Aabb::new(p1, p0);
+
+        let p0 = Vec3::from_array([-1.0, -1.0, -1.0]);
+        let p1 = Vec3::from_array([2.0, 2.0, 2.0]);
+        let box1 = Aabb::new(p1, p0);
+
+        assert!(box0.overlaps(&box1));
+        assert!(box1.overlaps(&box0));
+
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let box0 = Aabb::new(p0, p1);
+
+        let p0 = Vec3::from_array([-2.0, -2.0, -2.0]);
+        let p1 = Vec3::from_array([-1.0, -1.0, -1.0]);
+        let box1 = Aabb::new(p0, p1);
+
+        assert!(!box0.overlaps(&box1));
+        assert!(!box1.overlaps(&box0));
+    }
+
+    #[test]
+    fn hit() {
+        let half_size = Vec3::from_array([2.0, 1.0, 3.0]);
+        let box0 = Aabb::new(&half_size * (-1.0), &half_size * 1.0);
+
+        for i in 0..3 {
+            let mut origin = Vec3::new();
+            origin.get_data_mut()[i] = - 2.0 * half_size.get_data()[i];
+            let direction = Vec3::new();
+            origin.get_data_mut()[i] = 1.0;
+            let ray = Ray::from_vec(origin, direction);
+            assert!(box0.hit(&ray, 0.0, 100.0));
+
+            let mut origin = Vec3::new();
+            origin.get_data_mut()[i] = - 2.0 * half_size.get_data()[i];
+            let direction = Vec3::new();
+            origin.get_data_mut()[(i + 1) % 3] = 1.0;
+            let ray = Ray::from_vec(origin, direction);
+            assert!(!box0.hit(&ray, 0.0, 100.0));
+        }
+    }
+
+    #[test]
+    fn hit_flat() {
+        // A box with no thickness, like the bounds of a rectangle
+        let box0 = Aabb::new(Vec3::from_array([-1.0, -1.0, 0.0]), Vec3::from_array([1.0, 1.0, 0.0]));
+        let ray = Ray::from_array([0.5, 0.5, 2.0], [0.0, 0.0, -1.0]);
+        assert!(box0.hit(&ray, 0.0, 100.0));
+        assert_eq!(box0.hit_interval(&ray, &Interval::new(0.0, 100.0)), Some(Interval::new(2.0, 2.0)));
+        let ray = Ray::from_array([1.5, 0.5, 2.0], [0.0, 0.0, -1.0]);
+        assert!(!box0.hit(&ray, 0.0, 100.0));
+
+        // The origin lies on the slab plane of the zero direction component
+        let ray = Ray::from_array([-5.0, 1.0, 0.0], [1.0, 0.0, 0.0]);
+        assert_eq!(box0.hit_interval(&ray, &Interval::new(0.0, 100.0)), Some(Interval::new(4.0, 6.0)));
+        let ray = Ray::from_array([-5.0, 0.0, -0.0], [1.0, -0.0, 0.0]);
+        assert_eq!(box0.hit_interval(&ray, &Interval::new(0.0, 100.0)), Some(Interval::new(4.0, 6.0)));
+    }
+
+    fn random_box(rng: &mut StdRng) -> Aabb<f64> {
+        let mut p0 = Vec3::new();
+        let mut p1 = Vec3::new();
+        for i in 0..3 {
+            p0.get_data_mut()[i] = rng.gen::<f64>() * 10.0 - 5.0;
+            p1.get_data_mut()[i] = rng.gen::<f64>() * 10.0 - 5.0;
+        }
+        Aabb::new(p0, p1)
+    }
+
+    fn same_box(box0: &Aabb<f64>, box1: &Aabb<f64>) -> bool {
+        box0.get_p0().get_data() == box1.get_p0().get_data() && box0.get_p1().get_data() == box1.get_p1().get_data()
+    }
+
+    #[test]
+    fn union() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _i in 0..1000 {
+            let a = random_box(&mut rng);
+            let b = random_box(&mut rng);
+            let c = random_box(&mut rng);
+            let ab = a.union(&b);
+            assert!(same_box(&ab, &b.union(&a)));
+            assert!(same_box(&ab.union(&c), &a.union(&b.union(&c))));
+            assert!(ab.contains(&a));
+            assert!(ab.contains(&b));
+        }
+    }
+
+    #[test]
+    fn surrounds_intersects() {
+        let box0 = Aabb::new(Vec3::from_array([0.0, 0.0, 0.0]), Vec3::from_array([2.0, 4.0, 6.0]));
+        assert!(box0.surrounds(&Vec3::from_array([1.0, 1.0, 1.0])));
+        assert!(box0.surrounds(&Vec3::from_array([2.0, 4.0, 6.0])));
+        assert!(!box0.surrounds(&Vec3::from_array([2.1, 1.0, 1.0])));
+        assert!(!box0.surrounds(&Vec3::from_array([1.0, f64::NAN, 1.0])));
+
+        let box1 = Aabb::new(Vec3::from_array([2.0, 1.0, 1.0]), Vec3::from_array([3.0, 2.0, 2.0]));
+        assert!(box0.intersects(&box1));
+        assert!(!box0.overlaps(&box1));
+        let box1 = Aabb::new(Vec3::from_array([2.5, 1.0, 1.0]), Vec3::from_array([3.0, 2.0, 2.0]));
+        assert!(!box0.intersects(&box1));
+
+        assert_eq!(box0.centroid().get_data(), [1.0, 2.0, 3.0]);
+        assert_eq!(box0.surface_area(), 2.0 * (8.0 + 24.0 + 12.0));
+    }
+
+    #[test]
+    fn hit_random() {
+        // Compare the slab test against points sampled along the ray
+        let mut rng = StdRng::seed_from_u64(11);
+        let n_steps = 2000;
+        for _i in 0..2000 {
+            let box0 = random_box(&mut rng);
+            let mut origin = [0.0; 3];
+            let mut direction = [0.0; 3];
+            for k in 0..3 {
+                origin[k] = rng.gen::<f64>() * 20.0 - 10.0;
+                // Some rays have zero direction components
+                direction[k] = if rng.gen::<f64>() < 0.2 { 0.0 } else { rng.gen::<f64>() * 2.0 - 1.0 };
+            }
+            let ray = Ray::from_array(origin, direction);
+            let (t_min, t_max) = (0.0, 40.0);
+
+            match box0.hit_interval(&ray, &Interval::new(t_min, t_max)) {
+                Some(Interval { min: t0, max: t1 }) => {
+                    assert!(t0 <= t1);
+                    assert!(t0 >= t_min && t1 <= t_max);
+                    let mut padded = box0.union(&box0);
+                    for k in 0..3 {
+                        padded.pad_axis(1e-9, k);
+                    }
+                    assert!(padded.surrounds(&ray.at(t0)));
+                    assert!(padded.surrounds(&ray.at(t1)));
+                    assert!(padded.surrounds(&ray.at(0.5 * (t0 + t1))));
+                },
+                None => {
+                    for step in 0..n_steps + 1 {
+                        let t = t_min + (t_max - t_min) * step as f64 / n_steps as f64;
+                        assert!(!box0.surrounds(&ray.at(t)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn volume() {
+        let p0 = Vec3::from_array([-1.0, 2.0, -4.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let box0 = Aabb::new(p0, p1);
+        assert_eq!(box0.get_volume(), 6.0 * 2.0 * 7.0);
+    }
+
+    #[test]
+    fn expand() {
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let mut box0 = Aabb::new(p0, p1);
+
+        let p0 = Vec3::from_array([1.0, 1.0, 1.0]);
+        let p1 = Vec3::from_array([2.0, 2.0, 2.0]);
+        let box1 = Aabb::new(p0, p1);
+
+        assert!(box0.contains(&box1));
+        assert!(!box0.expand(&box1));
+        assert!(box0.contains(&box1));
+
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let mut box0 = Aabb::new(p0, p1);
+
+        let p0 = Vec3::from_array([-1.0, -1.0, -1.0]);
+        let p1 = Vec3::from_array([2.0, 2.0, 2.0]);
+        let box1 = Aabb::new(p1, p0);
+
+        assert!(!box0.contains(&box1));
+        assert!(box0.expand(&box1));
+        assert!(box0.contains(&box1));
+    }
+
+    #[test]
+    fn pad() {
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let box0 = Aabb::new(p0, p1);
+
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let mut box1 = Aabb::new(p0, p1);
+
+        assert!(box0.contains(&box1));
+        assert!(box1.contains(&box0));
+
+        box1.pad_axis(1.0, 0);
+        box1.pad_axis(2.0, 1);
+        box1.pad_axis(3.0, 2);
+        assert!(!box0.contains(&box1));
+        assert!(box1.contains(&box0));
+        assert_eq!(box1.get_axis_length(0), 7.0);
+        assert_eq!(box1.get_axis_length(1), 8.0);
+        assert_eq!(box1.get_axis_length(2), 9.0);
+    }
+
+    #[test]
+    fn make_cube() {
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let box0 = Aabb::new(p0, p1);
+
+        let p0 = Vec3::from_array([0.0, 0.0, 0.0]);
+        let p1 = Vec3::from_array([5.0, 4.0, 3.0]);
+        let mut box1 = Aabb::new(p0, p1);
+
+        assert!(box0.contains(&box1));
+        assert!(box1.contains(&box0));
+
+        box1.make_cube();
+        assert!(!box0.contains(&box1));
+        assert!(box1.contains(&box0));
+        assert_eq!(box1.get_axis_length(0), 5.0);
+        assert_eq!(box1.get_axis_length(1), 5.0);
+        assert_eq!(box1.get_axis_length(2), 5.0);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::hitable::Hitable;
+use crate::hitable::primitive::{Sphere, Cube, Rectangle};
+use crate::material::Material;
+use crate::material::lambertian::LambertianMaterial;
+use crate::material::metal::MetalMaterial;
+use crate::material::dielectric::DielectricMaterial;
+use crate::material::plain::PlainMaterial;
+use crate::texture::uniform::UniformTexture;
+use crate::constants::Axis;
+use crate::actor::{Actor, translated};
+
+// Shorthand for the common actors, i.e.
+// Actor::sphere(1.0).at([0.0, 0.0, -4.0]).lambertian([1.0, 0.2, 0.2], 0.5)
+pub struct ActorBuilder<T>
+    where T: Float
+{
+    hitable: Box<dyn Hitable<T>>
+}
+
+impl<T> ActorBuilder<T>
+    where T: Float
+{
+    pub fn new<H>(hitable: H) -> Self
+        where H: Hitable<T> + 'static
+    {
+        ActorBuilder {
+            hitable: Box::new(hitable)
+        }
+    }
+
+    // Moves the hitable by the offset, consecutive moves add up
+    pub fn at(self, offset: [T; 3]) -> Self {
+        ActorBuilder {
+            hitable: translated(self.hitable, &Vec3::from_array(offset))
+        }
+    }
+
+    pub fn material<M>(self, material: M) -> Actor<T>
+        where M: Material<T> + 'static
+    {
+        Actor::new(self.hitable, Box::new(material))
+    }
+
+    pub fn lambertian(self, color: [T; 3], dimming: T) -> Actor<T> {
+        self.material(LambertianMaterial::new(uniform(color), dimming))
+    }
+
+    pub fn metal(self, color: [T; 3], fuzziness: T) -> Actor<T> {
+        self.material(MetalMaterial::new(uniform(color), fuzziness))
+    }
+
+    pub fn dielectric(self, color: [T; 3], n: T) -> Actor<T> {
+        self.material(DielectricMaterial::new(uniform(color), n))
+    }
+
+    // Light source, the color is returned as is
+    pub fn emissive(self, color: [T; 3]) -> Actor<T> {
+        self.material(PlainMaterial::new(uniform(color)))
+    }
+}
+
+fn uniform<T>(color: [T; 3]) -> Box<UniformTexture<T>>
+    where T: Float
+{
+    Box::new(UniformTexture::new(Vec3::from_array(color)))
+}
+
+impl<T> Actor<T>
+    where T: Float
+{
+    pub fn sphere(radius: T) -> ActorBuilder<T> {
+        ActorBuilder::new(Sphere::new(radius))
+    }
+
+    pub fn cube(length: T, width: T, height: T) -> ActorBuilder<T> {
+        ActorBuilder::new(Cube::new(length, width, height))
+    }
+
+    pub fn rectangle(width: T, width_axis: Axis, height: T, height_axis: Axis) -> ActorBuilder<T> {
+        ActorBuilder::new(Rectangle::new(width, width_axis, height, height_axis))
+    }
+}
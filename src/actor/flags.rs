@@ -0,0 +1,77 @@
+use std::ops::{BitOr, BitOrAssign};
+
+// Which rays see an actor, combined with |. Rays an actor isn't visible to
+// pass through it. By default an actor is seen by all of them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ActorFlags(u32);
+
+impl ActorFlags {
+    // Rays from the camera, the first segment of a path
+    pub const VISIBLE_TO_CAMERA: ActorFlags = ActorFlags(1);
+    // Occlusion tests, i.e. shadow rays towards a light or the environment
+    pub const CASTS_SHADOWS: ActorFlags = ActorFlags(1 << 1);
+    // Rays scattered at a surface, and the paths of light and photons
+    pub const VISIBLE_IN_REFLECTIONS: ActorFlags = ActorFlags(1 << 2);
+
+    pub const fn empty() -> Self {
+        ActorFlags(0)
+    }
+
+    pub const fn all() -> Self {
+        ActorFlags(0b111)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        ActorFlags(bits & ActorFlags::all().0)
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(&self, other: ActorFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl Default for ActorFlags {
+    fn default() -> Self {
+        ActorFlags::all()
+    }
+}
+
+impl BitOr for ActorFlags {
+    type Output = ActorFlags;
+
+    fn bitor(self, other: ActorFlags) -> ActorFlags {
+        ActorFlags(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for ActorFlags {
+    fn bitor_assign(&mut self, other: ActorFlags) {
+        self.0 |= other.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags() {
+        assert_eq!(ActorFlags::default(), ActorFlags::all());
+        let flags = ActorFlags::CASTS_SHADOWS | ActorFlags::VISIBLE_IN_REFLECTIONS;
+        assert!(!flags.contains(ActorFlags::VISIBLE_TO_CAMERA));
+        assert!(flags.contains(ActorFlags::CASTS_SHADOWS));
+        let mut all = flags;
+        all |= ActorFlags::VISIBLE_TO_CAMERA;
+        assert_eq!(all, ActorFlags::all());
+        assert_eq!(ActorFlags::from_bits(0xFF), ActorFlags::all());
+        assert!(ActorFlags::empty().is_empty());
+    }
+}
@@ -1,10 +1,200 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use arc_swap::{ArcSwap, Guard};
+
 use crate::float::Float;
+use crate::vector::Vec3;
 use crate::hitable::Hitable;
+use crate::hitable::primitive::Group;
+use crate::hitable::transform::Translation;
 use crate::material::Material;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError};
+
+pub mod builder;
+pub mod flags;
+
+pub use builder::ActorBuilder;
+pub use flags::ActorFlags;
+
+// Index of an actor in its scene, in the order the actors were added
+pub type ActorId = usize;
+
+// Layer mask matching every layer
+pub const ALL_LAYERS: u32 = 0xFFFFFFFF;
 
 pub struct Actor<T>
     where T: Float
 {
     pub hitable: Box<dyn Hitable<T>>,
-    pub material: Box<dyn Material<T>>
+    // Replaceable while the actor is shared with a tree, without a lock
+    // for the renders reading it
+    material: ArcSwap<Box<dyn Material<T>>>,
+    visible: bool,
+    layer: u32,
+    // Like the material changeable while shared with a tree
+    flags: AtomicU32
+}
+
+impl<T> Actor<T>
+    where T: Float
+{
+    pub fn new(hitable: Box<dyn Hitable<T>>, material: Box<dyn Material<T>>) -> Self {
+        Actor {
+            hitable,
+            material: ArcSwap::from_pointee(material),
+            visible: true,
+            layer: ALL_LAYERS,
+            flags: AtomicU32::new(ActorFlags::all().bits())
+        }
+    }
+
+    pub fn get_material(&self) -> Guard<Arc<Box<dyn Material<T>>>> {
+        self.material.load()
+    }
+
+    // Materials don't change the placement of the actor, so the scene
+    // doesn't need to rebuild its tree
+    pub fn set_material(&self, material: Box<dyn Material<T>>) {
+        self.material.store(Arc::new(material));
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    // Invisible actors stay in the scene, but rays don't hit them
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn get_layer(&self) -> u32 {
+        self.layer
+    }
+
+    // Bitmask of the layers the actor belongs to, rays only hit the actor
+    // if it shares a layer with the mask they are traced with
+    pub fn set_layer(&mut self, layer: u32) {
+        self.layer = layer;
+    }
+
+    pub fn in_layers(&self, layer_mask: u32) -> bool {
+        self.layer & layer_mask != 0
+    }
+
+    pub fn get_flags(&self) -> ActorFlags {
+        ActorFlags::from_bits(self.flags.load(Ordering::Relaxed))
+    }
+
+    // Unlike an invisible actor, one hidden from some rays only stays in the
+    // tree, the rays it's hidden from pass through it
+    pub fn set_flags(&self, flags: ActorFlags) {
+        self.flags.store(flags.bits(), Ordering::Relaxed);
+    }
+
+    // Moves the actor by offsetting the data of the hitable (i.e. the center
+    // of a sphere or the vertices of a mesh, or an existing translation).
+    // Only hitables that can't be moved directly get a Translation around.
+    pub fn translate_in_place(&mut self, offset: &Vec3<T>) {
+        let hitable = std::mem::replace(&mut self.hitable, Box::new(Group::new()));
+        self.hitable = translated(hitable, offset);
+    }
+}
+
+fn translated<T>(hitable: Box<dyn Hitable<T>>, offset: &Vec3<T>) -> Box<dyn Hitable<T>>
+    where T: Float
+{
+    let mut hitable = hitable;
+    if hitable.translate(offset) {
+        return hitable;
+    }
+    Box::new(Translation::new(hitable, Vec3::from_slice(offset.get_data())))
+}
+
+impl<T> Clone for Actor<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        let mut actor = Actor::new(self.hitable.clone(), self.get_material().clone_box());
+        actor.set_visible(self.visible);
+        actor.set_layer(self.layer);
+        actor.set_flags(self.get_flags());
+        actor
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Actor<T>
+    where T: Float, U: Precision
+{
+    type Output = Actor<U>;
+
+    fn convert_precision(&self) -> Result<Actor<U>, PrecisionError> {
+        let mut actor = Actor::new(self.hitable.convert_precision()?, self.get_material().convert_precision()?);
+        actor.set_visible(self.visible);
+        actor.set_layer(self.layer);
+        actor.set_flags(self.get_flags());
+        Ok(actor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+    use crate::hitable::primitive::{Sphere, Rectangle, Cube};
+    use crate::constants::Axis;
+    use crate::material::null::NullMaterial;
+    use crate::material::MaterialBuilder;
+
+    #[test]
+    fn set_material() {
+        let actor = Actor::<f64>::new(Box::new(Sphere::new(1.0)), Box::new(NullMaterial::new()));
+        assert!(actor.get_material().get_refractive_index().is_none());
+        actor.set_material(MaterialBuilder::dielectric().color([0.0; 3]).ior(1.5).build());
+        assert_eq!(actor.get_material().get_refractive_index(), Some(1.5));
+    }
+
+    #[test]
+    fn clone() {
+        let mut actor = Actor::<f64>::new(Box::new(Sphere::new(1.0)), Box::new(NullMaterial::new()));
+        actor.set_layer(2);
+        let copy = actor.clone();
+        copy.set_material(MaterialBuilder::dielectric().color([0.0; 3]).ior(1.5).build());
+        actor.translate_in_place(&Vec3::from_array([1.0, 0.0, 0.0]));
+        assert!(actor.get_material().get_refractive_index().is_none());
+        assert_eq!(copy.get_material().get_refractive_index(), Some(1.5));
+        assert_eq!(copy.get_layer(), 2);
+        assert_eq!(copy.hitable.get_bounds().get_p1().get_data(), [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn translate_in_place() {
+        let mut actor = Actor::<f64>::new(Box::new(Sphere::new(1.0)), Box::new(NullMaterial::new()));
+        actor.translate_in_place(&Vec3::from_array([1.0, 0.0, 0.0]));
+        actor.translate_in_place(&Vec3::from_array([0.0, 2.0, 3.0]));
+        assert_eq!(actor.hitable.get_bounds().get_p0().get_data(), [0.0, 1.0, 2.0]);
+        assert_eq!(actor.hitable.get_bounds().get_p1().get_data(), [2.0, 3.0, 4.0]);
+
+        let ray = Ray::from_array([1.0, 2.0, 10.0], [0.0, 0.0, -1.0]);
+        let hit = actor.hitable.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(hit.point.get_data(), [1.0, 2.0, 4.0]);
+
+        // The sphere itself moved, without wrapping
+        assert!(actor.hitable.is_primitive());
+
+        let mut actor = Actor::<f64>::new(Box::new(Rectangle::new(2.0, Axis::X, 2.0, Axis::Y)), Box::new(NullMaterial::new()));
+        actor.translate_in_place(&Vec3::from_array([0.0, 0.0, -1.0]));
+        assert!(actor.hitable.is_primitive());
+        let hit = actor.hitable.hit(&Ray::from_array([0.5, 0.5, 1.0], [0.0, 0.0, -1.0]), 0.0, 100.0).unwrap();
+        assert_eq!(hit.t, 2.0);
+        assert_eq!((hit.u, hit.v), (0.75, 0.75));
+
+        // Consecutive translations of a cube merge into a single level of wrapping
+        let mut actor = Actor::<f64>::new(Box::new(Cube::new(1.0, 1.0, 1.0)), Box::new(NullMaterial::new()));
+        actor.translate_in_place(&Vec3::from_array([1.0, 0.0, 0.0]));
+        actor.translate_in_place(&Vec3::from_array([1.0, 0.0, 0.0]));
+        assert_eq!(actor.hitable.get_bounds().get_p1().get_data(), [2.5, 0.5, 0.5]);
+        assert!(!actor.hitable.is_primitive());
+        assert!(actor.hitable.unwrap().is_primitive());
+    }
 }
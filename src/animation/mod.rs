@@ -0,0 +1,276 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::camera::Camera;
+use crate::camera::perspective::PerspectiveCamera;
+use crate::scene::Scene;
+use crate::renderer::{Renderer, Image};
+use crate::error::Error;
+
+pub struct Keyframe<T>
+    where T: Float
+{
+    pub time: T,
+    pub position: Vec3<T>,
+    pub lookat: Vec3<T>,
+    pub up: Vec3<T>,
+    pub fov: T
+}
+
+impl<T> Keyframe<T>
+    where T: Float
+{
+    pub fn new(time: T, position: Vec3<T>, lookat: Vec3<T>, up: Vec3<T>, fov: T) -> Self {
+        Keyframe {
+            time,
+            position,
+            lookat,
+            up,
+            fov
+        }
+    }
+}
+
+impl<T> Clone for Keyframe<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        Keyframe {
+            time: self.time,
+            position: Vec3::from_slice(self.position.get_data()),
+            lookat: Vec3::from_slice(self.lookat.get_data()),
+            up: Vec3::from_slice(self.up.get_data()),
+            fov: self.fov
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    // Passes through all keyframes with a continuous first derivative
+    CatmullRom
+}
+
+pub struct CameraTrack<T>
+    where T: Float
+{
+    keyframes: Vec<Keyframe<T>>,
+    interpolation: Interpolation
+}
+
+impl<T> CameraTrack<T>
+    where T: Float
+{
+    pub fn new(keyframes: Vec<Keyframe<T>>) -> Result<Self, Error> {
+        let times: Vec<T> = keyframes.iter().map(|k| k.time).collect();
+        check_times(&times)?;
+        Ok(CameraTrack {
+            keyframes,
+            interpolation: Interpolation::Linear
+        })
+    }
+
+    pub fn get_interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation;
+    }
+
+    pub fn get_start_time(&self) -> T {
+        self.keyframes[0].time
+    }
+
+    pub fn get_end_time(&self) -> T {
+        self.keyframes[self.keyframes.len() - 1].time
+    }
+
+    // Times outside of the track are clamped to the first or last keyframe
+    pub fn evaluate(&self, time: T) -> Keyframe<T> {
+        let last = self.keyframes.len() - 1;
+        if time.is_nan() || time <= self.get_start_time() {
+            return self.keyframes[0].clone();
+        }
+        if time >= self.get_end_time() {
+            return self.keyframes[last].clone();
+        }
+
+        // Index of the segment [k1, k2] containing the time
+        let i = self.keyframes.iter().rposition(|k| k.time <= time).unwrap_or(0);
+        let k1 = &self.keyframes[i];
+        let k2 = &self.keyframes[i + 1];
+        let t = (time - k1.time) / (k2.time - k1.time);
+
+        match self.interpolation {
+            Interpolation::Linear => {
+                Keyframe {
+                    time,
+                    position: Vec3::lerp(&k1.position, &k2.position, t),
+                    lookat: Vec3::lerp(&k1.lookat, &k2.lookat, t),
+                    up: Vec3::lerp(&k1.up, &k2.up, t),
+                    fov: k1.fov + (k2.fov - k1.fov) * t
+                }
+            },
+            Interpolation::CatmullRom => {
+                // The end points are repeated at the ends of the track
+                let k0 = &self.keyframes[i.saturating_sub(1)];
+                let k3 = &self.keyframes[usize::min(i + 2, last)];
+                let spline = |p0: &Vec3<T>, p1: &Vec3<T>, p2: &Vec3<T>, p3: &Vec3<T>| {
+                    let (p0, p1, p2, p3) = (p0.get_data(), p1.get_data(), p2.get_data(), p3.get_data());
+                    Vec3::from_array([
+                        catmull_rom(p0[0], p1[0], p2[0], p3[0], t),
+                        catmull_rom(p0[1], p1[1], p2[1], p3[1], t),
+                        catmull_rom(p0[2], p1[2], p2[2], p3[2], t)
+                    ])
+                };
+                Keyframe {
+                    time,
+                    position: spline(&k0.position, &k1.position, &k2.position, &k3.position),
+                    lookat: spline(&k0.lookat, &k1.lookat, &k2.lookat, &k3.lookat),
+                    up: spline(&k0.up, &k1.up, &k2.up, &k3.up),
+                    fov: catmull_rom(k0.fov, k1.fov, k2.fov, k3.fov, t)
+                }
+            }
+        }
+    }
+
+    // Moves the camera to the state of the track at the given time
+    pub fn apply(&self, time: T, camera: &mut dyn Camera<T>) -> Result<(), Error> {
+        let keyframe = self.evaluate(time);
+        camera.set_fov(keyframe.fov)?;
+        camera.set_position(keyframe.position.get_data());
+        camera.set_up(keyframe.up.get_data());
+        camera.set_lookat(keyframe.lookat.get_data());
+        Ok(())
+    }
+}
+
+// Shared by all keyframed things: at least one keyframe, strictly increasing times
+pub(crate) fn check_times<T>(times: &[T]) -> Result<(), Error>
+    where T: Float
+{
+    if times.is_empty() {
+        return Err(Error::InvalidTrack("has no keyframes"));
+    }
+    for pair in times.windows(2) {
+        if pair[0].is_nan() || pair[1].is_nan() || pair[0] >= pair[1] {
+            return Err(Error::InvalidTrack("keyframe times are not strictly increasing"));
+        }
+    }
+    Ok(())
+}
+
+// Uniform Catmull-Rom spline between p1 (t = 0) and p2 (t = 1)
+fn catmull_rom<T>(p0: T, p1: T, p2: T, p3: T, t: T) -> T
+    where T: Float
+{
+    let half = T::from_f64(0.5);
+    let two = T::from_f64(2.0);
+    let three = T::from_f64(3.0);
+    let four = T::from_f64(4.0);
+    let five = T::from_f64(5.0);
+    let t2 = t * t;
+    let t3 = t2 * t;
+    half * (two * p1 + (p2 - p0) * t
+        + (two * p0 - five * p1 + four * p2 - p3) * t2
+        + (three * p1 - p0 - three * p2 + p3) * t3)
+}
+
+// Renders frame i at time start + i / fps of the track and hands the image
+// to the sink. The scene is set to the time of each frame, the camera has
+// the aspect of the renderer and is focused on the lookat point.
+pub fn render_sequence<T, F>(scene: &mut Scene<T>, track: &CameraTrack<T>, frames: usize, fps: T, renderer: &Renderer, mut sink: F) -> Result<(), Error>
+    where T: Float, F: FnMut(usize, Image<T>)
+{
+    if fps <= T::zero() || !fps.is_finite() {
+        return Err(Error::InvalidTrack("frame rate out of range"));
+    }
+
+    let mut camera = PerspectiveCamera::<T>::new();
+    let aspect = T::from_f64(renderer.get_width() as f64 / renderer.get_height() as f64);
+    camera.set_aspect(aspect)?;
+
+    for frame in 0..frames {
+        let time = track.get_start_time() + T::from_f64(frame as f64) / fps;
+        scene.set_time(time);
+        track.apply(time, &mut camera)?;
+        let focus = (camera.get_lookat() - camera.get_position()).norm();
+        camera.set_focus(focus)?;
+        sink(frame, renderer.render(scene, &camera));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f64, x: f64) -> Keyframe<f64> {
+        Keyframe::new(time, Vec3::from_array([x, 0.0, 0.0]), Vec3::from_array([x, 0.0, -1.0]), Vec3::from_array([0.0, 1.0, 0.0]), 1.0 + x)
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(CameraTrack::<f64>::new(Vec::new()).is_err());
+        assert!(CameraTrack::new(vec![keyframe(0.0, 0.0), keyframe(0.0, 1.0)]).is_err());
+        assert!(CameraTrack::new(vec![keyframe(1.0, 0.0), keyframe(0.5, 1.0)]).is_err());
+        assert!(CameraTrack::new(vec![keyframe(0.0, 0.0), keyframe(f64::NAN, 1.0)]).is_err());
+        assert!(CameraTrack::new(vec![keyframe(0.0, 0.0)]).is_ok());
+    }
+
+    #[test]
+    fn linear() {
+        let track = CameraTrack::new(vec![keyframe(0.0, 0.0), keyframe(1.0, 2.0), keyframe(3.0, 0.0)]).unwrap();
+        assert_eq!(track.evaluate(0.5).position.get_data(), [1.0, 0.0, 0.0]);
+        assert_eq!(track.evaluate(1.0).position.get_data(), [2.0, 0.0, 0.0]);
+        assert_eq!(track.evaluate(2.0).position.get_data(), [1.0, 0.0, 0.0]);
+        assert_eq!(track.evaluate(0.5).fov, 2.0);
+        // Clamped outside of the track
+        assert_eq!(track.evaluate(-1.0).position.get_data(), [0.0, 0.0, 0.0]);
+        assert_eq!(track.evaluate(5.0).position.get_data(), [0.0, 0.0, 0.0]);
+        assert_eq!(track.evaluate(f64::NAN).position.get_data(), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn catmull_rom() {
+        let mut track = CameraTrack::new(vec![keyframe(0.0, 0.0), keyframe(1.0, 1.0), keyframe(2.0, 4.0), keyframe(3.0, 9.0)]).unwrap();
+        track.set_interpolation(Interpolation::CatmullRom);
+        for i in 0..4 {
+            let x = (i * i) as f64;
+            assert!((track.evaluate(i as f64).position.get_data()[0] - x).abs() < 1e-12);
+        }
+        // Exact for the inner segment of a quadratic
+        assert!((track.evaluate(1.5).position.get_data()[0] - 2.25).abs() < 1e-12);
+        let x = track.evaluate(0.5).position.get_data()[0];
+        assert!(x > 0.0 && x < 1.0);
+    }
+
+    #[test]
+    fn apply() {
+        let track = CameraTrack::new(vec![keyframe(0.0, 0.0), keyframe(1.0, 2.0)]).unwrap();
+        let mut camera = PerspectiveCamera::<f64>::new();
+        track.apply(0.5, &mut camera).unwrap();
+        assert_eq!(camera.get_position().get_data(), [1.0, 0.0, 0.0]);
+        assert_eq!(camera.get_lookat().get_data(), [1.0, 0.0, -1.0]);
+        assert_eq!(camera.get_fov(), 2.0);
+
+        // A field of view of 180 degrees is rejected by the camera
+        let track = CameraTrack::new(vec![keyframe(0.0, 3.0)]).unwrap();
+        assert!(track.apply(0.0, &mut camera).is_err());
+    }
+
+    #[test]
+    fn render_sequence() {
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.5, 0.7, 0.9]));
+        let track = CameraTrack::new(vec![keyframe(0.0, 0.0), keyframe(1.0, 1.0)]).unwrap();
+        let renderer = Renderer::new(0, 4, 0, 2, 4, 2, 1, 1, false).unwrap();
+        let mut frames = Vec::new();
+        super::render_sequence(&mut scene, &track, 3, 24.0, &renderer, |i, image| frames.push((i, image))).unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[2].0, 2);
+        assert_eq!(frames[2].1.data.len(), 3 * 4 * 2);
+        assert!(super::render_sequence(&mut scene, &track, 3, 0.0, &renderer, |_, _| ()).is_err());
+    }
+}
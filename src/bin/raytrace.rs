@@ -0,0 +1,219 @@
+use std::env;
+use std::process;
+use std::time::Instant;
+
+use ray_tracer::prelude::*;
+use ray_tracer::scenes;
+
+const USAGE: &str = "Usage: raytrace [options]
+
+Renders one of the built-in scenes.
+
+Options:
+    --scene NAME        scene to render (default: cube_scene)
+    --list              list the built-in scenes and exit
+    --width N           image width in pixels (default: 320)
+    --height N          image height in pixels (default: 180)
+    --samples N         samples per pixel (default: 16)
+    --depth N           maximum number of reflections (default: 8)
+    --tree TYPE         linear, binary or oct, or embree with the embree
+                        feature (default: the scene's)
+    --photons N         photons emitted for the caustics (default: 0, none)
+    --photon-radius R   radius the caustics are gathered in (default: 0.2)
+    --integrator NAME   path or bdpt, bidirectional (default: path)
+    --sampler NAME      independent, stratified, halton or blue-noise (default: independent)
+    --spectral          trace a wavelength per path and photon, for dispersive glass
+    --gamma G           gamma correction of the output (default: 2.0)
+    --auto-exposure     scale the colors so the 90th percentile of the luminance is 0.8
+    --output PATH       output file (default: out.ppm)
+    --format FORMAT     ppm (text) or ppm-binary (default: ppm-binary)
+    --help              print this message";
+
+// Rows rendered between two progress updates
+const STRIP_HEIGHT: usize = 8;
+
+enum Format {
+    Ppm,
+    PpmBinary
+}
+
+struct Options {
+    scene: String,
+    width: usize,
+    height: usize,
+    samples: usize,
+    depth: usize,
+    tree: Option<TreeType>,
+    photons: usize,
+    photon_radius: f64,
+    integrator: Integrator,
+    sampler: Sampler,
+    spectral: bool,
+    gamma: f64,
+    auto_exposure: bool,
+    output: String,
+    format: Format
+}
+
+impl Options {
+    fn new() -> Self {
+        Options {
+            scene: "cube_scene".to_string(),
+            width: 320,
+            height: 180,
+            samples: 16,
+            depth: 8,
+            tree: None,
+            photons: 0,
+            photon_radius: 0.2,
+            integrator: Integrator::Path,
+            sampler: Sampler::Independent,
+            spectral: false,
+            gamma: 2.0,
+            auto_exposure: false,
+            output: "out.ppm".to_string(),
+            format: Format::PpmBinary
+        }
+    }
+}
+
+fn parse_value<T>(option: &str, value: Option<String>) -> Result<T, String>
+    where T: std::str::FromStr
+{
+    let value = value.ok_or_else(|| format!("missing value for {}", option))?;
+    value.parse().map_err(|_| format!("invalid value for {}: {}", option, value))
+}
+
+// None if the program should exit without rendering
+fn parse_args<I>(mut args: I) -> Result<Option<Options>, String>
+    where I: Iterator<Item = String>
+{
+    let mut options = Options::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scene" => options.scene = parse_value(&arg, args.next())?,
+            "--width" => options.width = parse_value(&arg, args.next())?,
+            "--height" => options.height = parse_value(&arg, args.next())?,
+            "--samples" => options.samples = parse_value(&arg, args.next())?,
+            "--depth" => options.depth = parse_value(&arg, args.next())?,
+            "--photons" => options.photons = parse_value(&arg, args.next())?,
+            "--photon-radius" => options.photon_radius = parse_value(&arg, args.next())?,
+            "--gamma" => options.gamma = parse_value(&arg, args.next())?,
+            "--auto-exposure" => options.auto_exposure = true,
+            "--spectral" => options.spectral = true,
+            "--output" => options.output = parse_value(&arg, args.next())?,
+            "--tree" => {
+                let tree: String = parse_value(&arg, args.next())?;
+                options.tree = Some(match tree.as_str() {
+                    "linear" => TreeType::Linear,
+                    "binary" => TreeType::Binary,
+                    "oct" => TreeType::Oct,
+                    #[cfg(feature = "embree")]
+                    "embree" => TreeType::Embree,
+                    _ => return Err(format!("unknown tree type: {}", tree))
+                });
+            },
+            "--integrator" => {
+                let integrator: String = parse_value(&arg, args.next())?;
+                options.integrator = match integrator.as_str() {
+                    "path" => Integrator::Path,
+                    "bdpt" => Integrator::Bdpt,
+                    _ => return Err(format!("unknown integrator: {}", integrator))
+                };
+            },
+            "--sampler" => {
+                let sampler: String = parse_value(&arg, args.next())?;
+                options.sampler = match sampler.as_str() {
+                    "independent" => Sampler::Independent,
+                    "stratified" => Sampler::Stratified,
+                    "halton" => Sampler::Halton,
+                    "blue-noise" => Sampler::BlueNoise,
+                    _ => return Err(format!("unknown sampler: {}", sampler))
+                };
+            },
+            "--format" => {
+                let format: String = parse_value(&arg, args.next())?;
+                options.format = match format.as_str() {
+                    "ppm" => Format::Ppm,
+                    "ppm-binary" => Format::PpmBinary,
+                    _ => return Err(format!("unknown format: {}", format))
+                };
+            },
+            "--list" => {
+                for name in scenes::NAMES.iter() {
+                    println!("{}", name);
+                }
+                return Ok(None);
+            },
+            "--help" => {
+                println!("{}", USAGE);
+                return Ok(None);
+            },
+            _ => return Err(format!("unknown argument: {}\n\n{}", arg, USAGE))
+        }
+    }
+    Ok(Some(options))
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let (width, height) = (options.width, options.height);
+    let mut renderer = Renderer::new(0, width, 0, height, width, height, options.samples, options.depth, true)
+        .map_err(|e| e.to_string())?;
+    renderer.set_integrator(options.integrator);
+    renderer.set_sampler(options.sampler);
+    renderer.set_spectral(options.spectral);
+
+    let create = scenes::by_name(&options.scene)
+        .ok_or_else(|| format!("unknown scene: {}, use --list to see the available ones", options.scene))?;
+    let (mut scene, camera) = create(width, height).map_err(|e| e.to_string())?;
+    if let Some(tree) = options.tree {
+        scene.set_tree_type(tree);
+    }
+
+    if options.photons > 0 {
+        let start = Instant::now();
+        if options.spectral {
+            scene.build_spectral_caustics(options.photons, options.photon_radius, &mut rand::thread_rng());
+        } else {
+            scene.build_caustics(options.photons, options.photon_radius, &mut rand::thread_rng());
+        }
+        let stored = scene.get_caustics().map_or(0, |caustics| caustics.len());
+        eprintln!("{} caustic photons in {:.1}s", stored, start.elapsed().as_secs_f64());
+    }
+
+    scene.reset_counters();
+    let start = Instant::now();
+    let mut image = Image::new(width, height);
+    let n_strips = (height + STRIP_HEIGHT - 1) / STRIP_HEIGHT;
+    for strip in 0..n_strips {
+        let tile = renderer.render_tile(&scene, &camera, 0, strip, width, STRIP_HEIGHT);
+        image.blit(&tile, 0, strip * STRIP_HEIGHT);
+        eprint!("\r{}: {:3}%", options.scene, 100 * (strip + 1) / n_strips);
+    }
+    let seconds = start.elapsed().as_secs_f64();
+    eprintln!(" in {:.1}s, {:.0} samples/s, {:.0} rays/s", seconds, renderer.sample_count() as f64 / seconds, scene.ray_count() as f64 / seconds);
+
+    if options.auto_exposure {
+        if let Some(exposure) = image.auto_expose(0.8, 90.0) {
+            eprintln!("Exposure {:.3}", exposure);
+        }
+    }
+
+    match options.format {
+        Format::Ppm => image.save_ppm(&options.output, options.gamma),
+        Format::PpmBinary => image.save_ppm_binary(&options.output, options.gamma)
+    }.map_err(|e| format!("couldn't write {}: {}", options.output, e))?;
+    eprintln!("Saved {}", options.output);
+    Ok(())
+}
+
+fn main() {
+    let result = parse_args(env::args().skip(1)).and_then(|options| match options {
+        Some(options) => run(&options),
+        None => Ok(())
+    });
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
@@ -1,9 +1,13 @@
+use rand::RngCore;
+
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
+use crate::error::Error;
 
 pub mod perspective;
 
+#[derive(Copy, Clone)]
 pub enum CameraLock {
     Direction,
     LookAt
@@ -24,17 +28,98 @@ pub trait Camera<T>
     fn get_up(&self) -> &Vec3<T>;
     fn set_up(&mut self, up: &[T]);
 
+    // The unit vector the camera looks along. By default the direction,
+    // cameras that can also aim at a lookat override it.
+    fn get_view_direction(&self) -> Vec3<T> {
+        let mut direction = Vec3::from_slice(self.get_direction().get_data());
+        direction.normalize();
+        direction
+    }
+
+    // The setters below reject values which don't describe a camera,
+    // i.e. a negative aperture or a field of view of 180 degrees
     fn get_aperture(&self) -> T;
-    fn set_aperture(&mut self, aperture: T);
+    fn set_aperture(&mut self, aperture: T) -> Result<(), Error>;
 
     fn get_focus(&self) -> T;
-    fn set_focus(&mut self, focus: T);
+    fn set_focus(&mut self, focus: T) -> Result<(), Error>;
 
     fn get_aspect(&self) -> T;
-    fn set_aspect(&mut self, aspect: T);
+    fn set_aspect(&mut self, aspect: T) -> Result<(), Error>;
 
     fn get_fov(&self) -> T;
-    fn set_fov(&mut self, fov: T);
+    fn set_fov(&mut self, fov: T) -> Result<(), Error>;
+
+    // Distances along the viewing direction between which the scene is
+    // expected, i.e. to normalize depths or for a projection matrix
+    fn get_near_far(&self) -> (T, T);
 
     fn get_ray(&self, r: T, s: T) -> Ray<T>;
+
+    // Like get_ray, with the random numbers of i.e. the aperture taken from rng
+    fn get_ray_with(&self, r: T, s: T, _rng: &mut dyn RngCore) -> Ray<T> {
+        self.get_ray(r, s)
+    }
+
+    // Like get_ray, with the point on the aperture given by two numbers in
+    // [0, 1), i.e. from a Sampler
+    fn get_ray_at(&self, r: T, s: T, _lens: (T, T)) -> Ray<T> {
+        self.get_ray(r, s)
+    }
+
+    // Inverse of get_ray: the screen coordinates (r, s) in [-1, 1] of a point,
+    // None if the point is behind the camera or outside of the field of view.
+    fn project(&self, point: &Vec3<T>) -> Option<(T, T)>;
+
+    // Placement from a world to camera matrix as exported by i.e. Blender:
+    // rows, acting on column vectors, the camera looking along -z with +y
+    // up. The rotation part may be scaled, the last row must be [0, 0, 0, 1].
+    fn set_transform(&mut self, matrix: [[T; 4]; 4]) -> Result<(), Error> {
+        let invalid = Err(Error::InvalidCamera("transform"));
+        let affine = [T::zero(), T::zero(), T::zero(), T::one()];
+        if matrix[3] != affine || matrix.iter().flatten().any(|value| !value.is_finite()) {
+            return invalid;
+        }
+        let row = |i: usize| Vec3::from_array([matrix[i][0], matrix[i][1], matrix[i][2]]);
+        let (x, y, z) = (row(0), row(1), row(2));
+        // The inverse of the rotation by its adjugate
+        let determinant = x.dot(&y.cross(&z));
+        if determinant == T::zero() || !determinant.is_finite() {
+            return invalid;
+        }
+        let columns = [y.cross(&z), z.cross(&x), x.cross(&y)];
+        let translation = [matrix[0][3], matrix[1][3], matrix[2][3]];
+        let mut position = Vec3::new();
+        for (column, t) in columns.iter().zip(translation.iter()) {
+            position = position - column * (*t / determinant);
+        }
+
+        let mut direction = z * (-T::one());
+        direction.normalize();
+        let mut up = y;
+        up.normalize();
+        self.set_position(position.get_data());
+        self.set_up(up.get_data());
+        self.set_direction(direction.get_data());
+        Ok(())
+    }
+
+    // The world to camera matrix of the placement, see set_transform
+    fn get_transform(&self) -> [[T; 4]; 4] {
+        // The viewing axis, not a ray through the center which is offset on
+        // the lens of a camera with an aperture
+        let forward = self.get_view_direction();
+        let mut right = forward.cross(self.get_up());
+        right.normalize();
+        let up = right.cross(&forward);
+        let back = forward * (-T::one());
+        let position = self.get_position();
+        let mut matrix = [[T::zero(); 4]; 4];
+        for (i, axis) in [right, up, back].iter().enumerate() {
+            matrix[i][..3].copy_from_slice(axis.get_data());
+            matrix[i][3] = -axis.dot(position);
+        }
+        matrix[3][3] = T::one();
+        matrix
+    }
 }
@@ -1,8 +1,12 @@
+use rand::RngCore;
+
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::camera::{Camera, CameraLock};
-use crate::utils::random_point_in_circle;
+use crate::error::Error;
+use crate::sampling;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
 
 pub struct PerspectiveCamera<T>
     where T: Float
@@ -21,6 +25,8 @@ pub struct PerspectiveCamera<T>
     half_width: T,
     aperture: T,
     focus: T,
+    near: T,
+    far: T,
     lock: CameraLock
 }
 
@@ -42,13 +48,50 @@ impl<T> PerspectiveCamera<T>
             half_width: T::one(),
             aperture: T::zero(),
             focus: T::one(),
-            fov: T::from(0.5 * 3.1415).unwrap(),
+            near: T::from_f64(0.1),
+            far: T::from_f64(1000.0),
+            fov: T::from_f64(0.5 * 3.1415),
             lock: CameraLock::Direction
         };
         camera.update();
         camera
     }
 
+    // Rays aren't clipped, the planes only describe the depth range.
+    // 0 < near < far is required: move far first to increase both.
+    pub fn set_near(&mut self, near: T) -> Result<(), Error> {
+        if near <= T::zero() || near >= self.far || near.is_nan() {
+            return Err(Error::InvalidCamera("near"));
+        }
+        self.near = near;
+        Ok(())
+    }
+
+    pub fn set_far(&mut self, far: T) -> Result<(), Error> {
+        if far <= self.near || !far.is_finite() {
+            return Err(Error::InvalidCamera("far"));
+        }
+        self.far = far;
+        Ok(())
+    }
+
+    // Like set_lookat, but if the camera would look (nearly) along the up
+    // vector, i.e. straight down with up [0, 0, 1], the up vector is replaced
+    // by [0, 1, 0] or [1, 0, 0], whichever is further from the direction
+    pub fn set_lookat_up_auto(&mut self, lookat: Vec3<T>) {
+        let mut direction = &lookat - &self.position;
+        if !direction.is_near_zero(T::epsilon()) {
+            direction.normalize();
+            let parallel = |up: &Vec3<T>| (direction.dot(up) / up.norm()).abs();
+            if parallel(&self.up) > T::from_f64(0.999) {
+                let y = Vec3::from_array([T::zero(), T::one(), T::zero()]);
+                let x = Vec3::from_array([T::one(), T::zero(), T::zero()]);
+                self.up = if parallel(&y) <= parallel(&x) { y } else { x };
+            }
+        }
+        self.set_lookat(lookat.get_data());
+    }
+
     pub fn update(&mut self) {
         let direction = match self.lock {
             CameraLock::Direction => {
@@ -65,9 +108,22 @@ impl<T> PerspectiveCamera<T>
         self.v.set_data(self.w.cross(&self.u).get_data());
         self.v.normalize();
         self.center = &self.position + &self.w * self.focus;
-        self.half_height = ( T::from(0.5).unwrap() * self.fov ).tan() * self.focus;
+        self.half_height = ( T::from_f64(0.5) * self.fov ).tan() * self.focus;
         self.half_width = self.aspect * self.half_height;
     }
+
+    // The ray from the offset on the aperture to the point (r, s) in focus
+    fn get_ray_through(&self, r: T, s: T, offset: Vec3<T>) -> Ray<T> {
+        let mut ray_direction = &self.center + &self.u * r * self.half_width + &self.v * s * self.half_height - &self.position - &offset;
+        // If the target point coincides with the ray origin (i.e. zero focus distance)
+        // the direction is undefined, fall back to the viewing direction
+        if ray_direction.is_near_zero(T::epsilon()) {
+            ray_direction = Vec3::from_slice(self.w.get_data());
+        }
+        ray_direction.normalize();
+        let origin = &self.position + &offset;
+        Ray::<T>::from_slice(origin.get_data(), ray_direction.get_data())
+    }
 }
 
 impl<T> Camera<T> for PerspectiveCamera<T>
@@ -110,52 +166,127 @@ impl<T> Camera<T> for PerspectiveCamera<T>
         self.update();
     }
 
+    // Whichever of the direction and the lookat was set last
+    fn get_view_direction(&self) -> Vec3<T> {
+        Vec3::from_slice(self.w.get_data())
+    }
+
     fn get_aperture(&self) -> T {
         self.aperture
     }
 
-    fn set_aperture(&mut self, aperture: T) {
+    fn set_aperture(&mut self, aperture: T) -> Result<(), Error> {
+        if aperture < T::zero() || !aperture.is_finite() {
+            return Err(Error::InvalidCamera("aperture"));
+        }
         self.aperture = aperture;
         self.update();
+        Ok(())
     }
 
     fn get_focus(&self) -> T {
         self.focus
     }
 
-    fn set_focus(&mut self, focus: T) {
+    fn set_focus(&mut self, focus: T) -> Result<(), Error> {
+        if focus < T::zero() || !focus.is_finite() {
+            return Err(Error::InvalidCamera("focus"));
+        }
         self.focus = focus;
         self.update();
+        Ok(())
     }
 
     fn get_aspect(&self) -> T {
         self.aspect
     }
 
-    fn set_aspect(&mut self, aspect: T) {
+    fn set_aspect(&mut self, aspect: T) -> Result<(), Error> {
+        if aspect <= T::zero() || !aspect.is_finite() {
+            return Err(Error::InvalidCamera("aspect"));
+        }
         self.aspect = aspect;
         self.update();
+        Ok(())
     }
 
     fn get_fov(&self) -> T {
         self.fov
     }
 
-    fn set_fov(&mut self, fov: T) {
+    fn set_fov(&mut self, fov: T) -> Result<(), Error> {
+        if fov.is_nan() || fov <= T::zero() || fov >= T::from_f64(std::f64::consts::PI) {
+            return Err(Error::InvalidCamera("fov"));
+        }
         self.fov = fov;
         self.update();
+        Ok(())
+    }
+
+    fn get_near_far(&self) -> (T, T) {
+        (self.near, self.far)
     }
 
     fn get_ray(&self, r: T, s: T) -> Ray<T> {
+        sampling::with_thread_rng(|rng| self.get_ray_with(r, s, rng))
+    }
+
+    fn get_ray_with(&self, r: T, s: T, rng: &mut dyn RngCore) -> Ray<T> {
         let offset = if self.aperture > T::zero() {
-            random_point_in_circle(self.aperture * T::from(0.5).unwrap())
+            Vec3::random_in_unit_disk(rng) * (self.aperture * T::from_f64(0.5))
         } else {
             Vec3::<T>::new()
         };
-        let mut ray_direction = &self.center + &self.u * r * self.half_width + &self.v * s * self.half_height - &self.position - &offset;
-        ray_direction.normalize();
-        let origin = &self.position + &offset;
-        Ray::<T>::from_slice(origin.get_data(), ray_direction.get_data())
+        self.get_ray_through(r, s, offset)
+    }
+
+    fn get_ray_at(&self, r: T, s: T, lens: (T, T)) -> Ray<T> {
+        let offset = if self.aperture > T::zero() {
+            sampling::concentric_disk(lens.0, lens.1) * (self.aperture * T::from_f64(0.5))
+        } else {
+            Vec3::<T>::new()
+        };
+        self.get_ray_through(r, s, offset)
+    }
+    fn project(&self, point: &Vec3<T>) -> Option<(T, T)> {
+        let relative = point - &self.position;
+        let depth = relative.dot(&self.w);
+        if depth <= T::zero() {
+            return None;
+        }
+        // Same as dividing by the half width/height at the focus distance,
+        // but also valid for a zero focus
+        let half_height = (T::from_f64(0.5) * self.fov).tan();
+        let half_width = self.aspect * half_height;
+        let r = relative.dot(&self.u) / (depth * half_width);
+        let s = relative.dot(&self.v) / (depth * half_height);
+        if r.abs() > T::one() || s.abs() > T::one() {
+            return None;
+        }
+        Some((r, s))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for PerspectiveCamera<T>
+    where T: Float, U: Precision
+{
+    type Output = PerspectiveCamera<U>;
+
+    fn convert_precision(&self) -> Result<PerspectiveCamera<U>, PrecisionError> {
+        let mut camera = PerspectiveCamera::<U>::new();
+        camera.position = self.position.convert();
+        camera.direction = self.direction.convert();
+        camera.lookat = self.lookat.convert();
+        camera.up = self.up.convert();
+        camera.aspect = convert(self.aspect);
+        camera.fov = convert(self.fov);
+        camera.aperture = convert(self.aperture);
+        camera.focus = convert(self.focus);
+        camera.near = convert(self.near);
+        camera.far = convert(self.far);
+        camera.lock = self.lock;
+        camera.update();
+        Ok(camera)
     }
 }
 
@@ -201,13 +332,18 @@ mod tests {
         assert_eq!(camera.w.get_data(), [1.0, 0.0, 0.0]);
         assert_eq!(camera.u.get_data(), [0.0, 0.0, 1.0]);
         assert_eq!(camera.v.get_data(), [0.0, -1.0, 0.0]);
+        assert_eq!(camera.get_view_direction().get_data(), [1.0, 0.0, 0.0]);
+
+        // The lookat takes over from the direction
+        camera.set_lookat(&[-2.0, 0.0, 3.0]);
+        assert_eq!(camera.get_view_direction().get_data(), [0.0, 0.0, 1.0]);
     }
 
     #[test]
     fn rays() {
         let mut camera = PerspectiveCamera::<f64>::new();
-        camera.set_fov(0.5 * std::f64::consts::PI);
-        camera.set_aspect(2.0);
+        camera.set_fov(0.5 * std::f64::consts::PI).unwrap();
+        camera.set_aspect(2.0).unwrap();
         camera.set_position(&[0., 0., -10.]);
 
         let ray = camera.get_ray(0.0, 1.0);
@@ -219,4 +355,192 @@ mod tests {
         let ray = camera.get_ray(-1.0, 0.0);
         assert_eq!(ray.get_origin().get_data(), camera.get_position().get_data());
     }
+
+    #[test]
+    fn lens() {
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[1., 2., 3.]);
+        camera.set_direction(&[0., 1., 0.]);
+        camera.set_up(&[0., 0., 1.]);
+        camera.set_focus(4.0).unwrap();
+        camera.set_aperture(2.0).unwrap();
+
+        // The middle of the lens is the position, all rays meet in focus
+        let ray = camera.get_ray_at(0.0, 0.0, (0.5, 0.5));
+        assert_eq!(ray.get_origin().get_data(), [1., 2., 3.]);
+        for lens in [(0.0, 0.0), (1.0, 0.5), (0.2, 0.9)].iter() {
+            let ray = camera.get_ray_at(0.0, 0.0, *lens);
+            assert!((ray.get_origin() - camera.get_position()).norm() <= 1.0 + 1e-12);
+            let t = (6.0 - ray.get_origin().get_data()[1]) / ray.get_direction().get_data()[1];
+            let focus = ray.get_origin() + ray.get_direction() * t;
+            assert!((focus - Vec3::from_array([1., 6., 3.])).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn degenerate_rays() {
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[1., 2., 3.]);
+        camera.set_direction(&[0., 1., 0.]);
+        camera.set_up(&[0., 0., 1.]);
+        camera.set_focus(0.0).unwrap();
+
+        for (r, s) in [(0.0, 0.0), (1.0, 1.0), (-0.5, 0.25)].iter() {
+            let ray = camera.get_ray(*r, *s);
+            let direction = ray.get_direction().get_data();
+            assert!(direction.iter().all(|x| x.is_finite()));
+            assert_eq!(direction, [0.0, 1.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn project() {
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[1., 2., 3.]);
+        camera.set_lookat(&[-2., 0., 1.]);
+        camera.set_aspect(1.5).unwrap();
+        camera.set_focus(2.5).unwrap();
+
+        for (r, s) in [(0.0, 0.0), (0.9, -0.9), (-0.5, 0.25), (0.99, 0.99)].iter() {
+            let ray = camera.get_ray(*r, *s);
+            for t in [0.5, 1.0, 20.0].iter() {
+                let (u, v) = camera.project(&ray.at(*t)).unwrap();
+                assert!((u - r).abs() < 1e-9);
+                assert!((v - s).abs() < 1e-9);
+            }
+        }
+
+        // Behind the camera
+        let behind = camera.get_position() - camera.get_ray(0.0, 0.0).get_direction();
+        assert!(camera.project(&behind).is_none());
+        // Outside of the field of view, the points of the focus plane are linear in r
+        let w = Vec3::from_slice(camera.get_ray(0.0, 0.0).get_direction().get_data());
+        let ray = camera.get_ray(0.5, 0.0);
+        let inside = ray.at(2.5 / ray.get_direction().dot(&w));
+        let center = camera.get_position() + &w * 2.5;
+        let outside = &inside * 3.0 - &center * 2.0;
+        let (u, v) = camera.project(&inside).unwrap();
+        assert!((u - 0.5).abs() < 1e-9 && v.abs() < 1e-9);
+        assert!(camera.project(&outside).is_none());
+        // The camera position itself
+        assert!(camera.project(&Vec3::from_array([1., 2., 3.])).is_none());
+    }
+
+    #[test]
+    fn invalid() {
+        let mut camera = PerspectiveCamera::<f64>::new();
+        for fov in [0.0, -1.0, std::f64::consts::PI, f64::NAN].iter() {
+            match camera.set_fov(*fov) {
+                Err(Error::InvalidCamera("fov")) => (),
+                _ => panic!("expected an invalid fov")
+            }
+        }
+        assert!(camera.set_aspect(0.0).is_err());
+        assert!(camera.set_aspect(f64::INFINITY).is_err());
+        assert!(camera.set_focus(-1.0).is_err());
+        assert!(camera.set_aperture(f64::NAN).is_err());
+        // The camera is left unchanged
+        assert_eq!(camera.get_fov(), 0.5 * 3.1415);
+        assert_eq!(camera.get_aspect(), 1.0);
+        assert_eq!(camera.get_focus(), 1.0);
+        assert_eq!(camera.get_aperture(), 0.0);
+        check_camera(&camera);
+    }
+
+    #[test]
+    fn near_far() {
+        let mut camera = PerspectiveCamera::<f64>::new();
+        assert_eq!(camera.get_near_far(), (0.1, 1000.0));
+        assert!(camera.set_near(2000.0).is_err());
+        camera.set_far(5000.0).unwrap();
+        camera.set_near(2000.0).unwrap();
+        assert_eq!(camera.get_near_far(), (2000.0, 5000.0));
+
+        for near in [0.0, -1.0, 5000.0, f64::NAN].iter() {
+            match camera.set_near(*near) {
+                Err(Error::InvalidCamera("near")) => (),
+                _ => panic!("expected an invalid near plane")
+            }
+        }
+        for far in [2000.0, 1.0, f64::INFINITY, f64::NAN].iter() {
+            match camera.set_far(*far) {
+                Err(Error::InvalidCamera("far")) => (),
+                _ => panic!("expected an invalid far plane")
+            }
+        }
+        assert_eq!(camera.get_near_far(), (2000.0, 5000.0));
+        let camera: PerspectiveCamera<f32> = camera.convert_precision().unwrap();
+        assert_eq!(camera.get_near_far(), (2000.0, 5000.0));
+    }
+
+    #[test]
+    fn up_auto() {
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0., 0., 5.]);
+        camera.set_up(&[0., 0., 1.]);
+        camera.set_lookat_up_auto(Vec3::from_array([0., 0., 0.]));
+        assert_eq!(camera.get_up().get_data(), [0., 1., 0.]);
+        assert_eq!(camera.w.get_data(), [0., 0., -1.]);
+        check_camera(&camera);
+
+        // Looking along y, x is the one left
+        camera.set_position(&[0., 0., 0.]);
+        camera.set_lookat_up_auto(Vec3::from_array([0., -3., 0.]));
+        assert_eq!(camera.get_up().get_data(), [1., 0., 0.]);
+        check_camera(&camera);
+
+        // An up vector that works is kept
+        camera.set_up(&[0., 0., 1.]);
+        camera.set_lookat_up_auto(Vec3::from_array([2., 1., 0.5]));
+        assert_eq!(camera.get_up().get_data(), [0., 0., 1.]);
+        assert!(camera.u.get_data().iter().chain(camera.v.get_data()).all(|x| x.is_finite()));
+    }
+
+    #[test]
+    fn transform() {
+        // At (0, 0, 5), turned by 90 degrees to look along -x
+        let matrix = [[0., 0., -1., 5.], [0., 1., 0., 0.], [1., 0., 0., 0.], [0., 0., 0., 1.]];
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_transform(matrix).unwrap();
+        assert_eq!(camera.get_position().get_data(), [0., 0., 5.]);
+        assert_eq!(camera.get_direction().get_data(), [-1., 0., 0.]);
+        assert_eq!(camera.get_up().get_data(), [0., 1., 0.]);
+        assert_eq!(camera.get_transform(), matrix);
+
+        // Round trip of a camera set by its lookat, the scale is ignored
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[1., 2., 3.]);
+        camera.set_lookat(&[-2., 0.5, 1.]);
+        let mut matrix = camera.get_transform();
+        for row in matrix[..3].iter_mut() {
+            for value in row.iter_mut() {
+                *value *= 2.;
+            }
+        }
+        let mut other = PerspectiveCamera::<f64>::new();
+        other.set_transform(matrix).unwrap();
+        for (a, b) in other.get_transform().iter().flatten().zip(camera.get_transform().iter().flatten()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+        let (ray, other_ray) = (camera.get_ray(0.3, -0.6), other.get_ray(0.3, -0.6));
+        for k in 0..3 {
+            assert!((ray.get_origin().get_data()[k] - other_ray.get_origin().get_data()[k]).abs() < 1e-12);
+            assert!((ray.get_direction().get_data()[k] - other_ray.get_direction().get_data()[k]).abs() < 1e-12);
+        }
+
+        // Not changed by the aperture, which only moves the rays on the lens
+        camera.set_aperture(0.5).unwrap();
+        let with_aperture = camera.get_transform();
+        assert_eq!(camera.get_transform(), with_aperture);
+        for (a, b) in with_aperture.iter().flatten().zip(other.get_transform().iter().flatten()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+
+        let mut singular = matrix;
+        singular[2] = singular[1];
+        assert!(other.set_transform(singular).is_err());
+        let mut projective = matrix;
+        projective[3][2] = -1.;
+        assert!(other.set_transform(projective).is_err());
+    }
 }
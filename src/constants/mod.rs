@@ -1,6 +1,59 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::utils::axis_to_index;
+
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Axis {
   X,
   Y,
   Z
 }
+
+impl Axis {
+  // Unit vector along the axis
+  pub fn to_vec3<T>(self) -> Vec3<T>
+    where T: Float
+  {
+    let mut data = [T::zero(); 3];
+    data[axis_to_index(&self)] = T::one();
+    Vec3::from_array(data)
+  }
+
+  // The axis closest to the direction of the vector, the first one on ties
+  pub fn from_vec3<T>(v: &Vec3<T>) -> Axis
+    where T: Float
+  {
+    let d = v.abs().to_array();
+    if d[0] >= d[1] && d[0] >= d[2] {
+      Axis::X
+    } else if d[1] >= d[2] {
+      Axis::Y
+    } else {
+      Axis::Z
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn to_vec3() {
+    assert_eq!(Axis::X.to_vec3::<f64>().get_data(), [1.0, 0.0, 0.0]);
+    assert_eq!(Axis::Y.to_vec3::<f64>().get_data(), [0.0, 1.0, 0.0]);
+    assert_eq!(Axis::Z.to_vec3::<f32>().get_data(), [0.0, 0.0, 1.0]);
+  }
+
+  #[test]
+  fn from_vec3() {
+    for axis in [Axis::X, Axis::Y, Axis::Z].iter() {
+      let v = axis.to_vec3::<f64>() * -2.0;
+      assert_eq!(axis_to_index(&Axis::from_vec3(&v)), axis_to_index(axis));
+    }
+    assert!(matches!(Axis::from_vec3(&Vec3::from_array([0.1, -0.5, 0.4])), Axis::Y));
+    assert!(matches!(Axis::from_vec3(&Vec3::from_array([0.5, 0.5, 0.5])), Axis::X));
+    assert!(matches!(Axis::from_vec3(&Vec3::<f64>::new()), Axis::X));
+  }
+}
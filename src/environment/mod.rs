@@ -0,0 +1,216 @@
+use rand::RngCore;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::renderer::Image;
+use crate::sampling::{uniform, unit_sphere_surface};
+
+// Light arriving from far away, an equirectangular image of radiance: the
+// columns go once around the z axis starting at -x, the rows from +z at the
+// top to -z at the bottom. Each texel is constant over its area.
+#[derive(Clone)]
+pub struct Environment<T>
+    where T: Float
+{
+    image: Image<T>,
+    // Running sums of the sampling weights, luminance times sin theta: over
+    // the rows, and over the texels within each row
+    rows: Vec<T>,
+    columns: Vec<T>,
+    importance_sampling: bool
+}
+
+impl<T> Environment<T>
+    where T: Float
+{
+    pub fn new(image: Image<T>) -> Self {
+        let (width, height) = (image.width, image.height);
+        let mut rows = Vec::with_capacity(height);
+        let mut columns = Vec::with_capacity(width * height);
+        let mut total = T::zero();
+        for j in 0..height {
+            let mut sum = T::zero();
+            for i in 0..width {
+                sum = sum + Environment::weight(&image, i, j);
+                columns.push(sum);
+            }
+            total = total + sum;
+            rows.push(total);
+        }
+        Environment {
+            image,
+            rows,
+            columns,
+            importance_sampling: true
+        }
+    }
+
+    pub fn get_image(&self) -> &Image<T> {
+        &self.image
+    }
+
+    pub fn get_importance_sampling(&self) -> bool {
+        self.importance_sampling
+    }
+
+    // Without importance sampling the directions are uniform over the sphere,
+    // i.e. to compare. Also used if the whole image is black.
+    pub fn set_importance_sampling(&mut self, importance_sampling: bool) {
+        self.importance_sampling = importance_sampling;
+    }
+
+    fn weight(image: &Image<T>, i: usize, j: usize) -> T {
+        let index = 3 * (j * image.width + i);
+        let luminance = Vec3::from_slice(&image.data[index..index + 3]).luminance().max(T::zero());
+        let theta = T::from_f64(std::f64::consts::PI * (j as f64 + 0.5) / image.height as f64);
+        luminance * theta.sin()
+    }
+
+    fn total(&self) -> T {
+        self.rows.last().copied().unwrap_or_else(T::zero)
+    }
+
+    fn importance_sampled(&self) -> bool {
+        self.importance_sampling && self.total() > T::zero()
+    }
+
+    // Texel seen in the direction
+    fn texel(&self, direction: &Vec3<T>) -> (usize, usize) {
+        let mut direction = Vec3::from_slice(direction.get_data());
+        direction.normalize();
+        let [x, y, z] = direction.to_array();
+        let pi = std::f64::consts::PI;
+        let u = (y.atan2(x).to_f64().unwrap() + pi) / (2.0 * pi);
+        let v = z.max(-T::one()).min(T::one()).acos().to_f64().unwrap() / pi;
+        let i = ((u * self.image.width as f64) as usize).min(self.image.width - 1);
+        let j = ((v * self.image.height as f64) as usize).min(self.image.height - 1);
+        (i, j)
+    }
+
+    pub fn get_radiance(&self, direction: &Vec3<T>) -> Vec3<T> {
+        let (i, j) = self.texel(direction);
+        let index = 3 * (j * self.image.width + i);
+        Vec3::from_slice(&self.image.data[index..index + 3])
+    }
+
+    // A direction towards the environment, with more of them towards bright
+    // texels, and its density per solid angle
+    pub fn sample_direction(&self, rng: &mut dyn RngCore) -> (Vec3<T>, T) {
+        if !self.importance_sampled() {
+            let direction = unit_sphere_surface::<T, dyn RngCore>(rng);
+            return (direction, T::from_f64(0.25 / std::f64::consts::PI));
+        }
+        let (width, height) = (self.image.width, self.image.height);
+
+        // The row, then the texel within the row
+        let x = uniform::<T, dyn RngCore>(rng) * self.total();
+        let j = self.rows.partition_point(|sum| *sum <= x).min(height - 1);
+        let row = &self.columns[j * width..(j + 1) * width];
+        let y = uniform::<T, dyn RngCore>(rng) * row[width - 1];
+        let i = row.partition_point(|sum| *sum <= y).min(width - 1);
+
+        let pi = T::from_f64(std::f64::consts::PI);
+        let u = (T::from_f64(i as f64) + uniform::<T, dyn RngCore>(rng)) / T::from_f64(width as f64);
+        let v = (T::from_f64(j as f64) + uniform::<T, dyn RngCore>(rng)) / T::from_f64(height as f64);
+        let phi = (u + u - T::one()) * pi;
+        let theta = v * pi;
+        let direction = Vec3::from_array([theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()]);
+        let pdf = self.texel_pdf(i, j, theta.sin());
+        (direction, pdf)
+    }
+
+    // Density per solid angle of sample_direction returning the direction
+    pub fn pdf(&self, direction: &Vec3<T>) -> T {
+        if !self.importance_sampled() {
+            return T::from_f64(0.25 / std::f64::consts::PI);
+        }
+        let (i, j) = self.texel(direction);
+        let z = direction.get_data()[2] / direction.norm();
+        self.texel_pdf(i, j, (T::one() - z * z).max(T::zero()).sqrt())
+    }
+
+    // Uniform within the texel in the image coordinates, which cover
+    // 2 pi^2 sin theta of solid angle per unit area
+    fn texel_pdf(&self, i: usize, j: usize, sin_theta: T) -> T {
+        if sin_theta <= T::zero() {
+            return T::zero();
+        }
+        let n = T::from_f64((self.image.width * self.image.height) as f64);
+        let pi = T::from_f64(std::f64::consts::PI);
+        Environment::weight(&self.image, i, j) * n / (self.total() * T::from_f64(2.0) * pi * pi * sin_theta)
+    }
+
+    pub fn convert<U>(&self) -> Environment<U>
+        where U: Float
+    {
+        let mut environment = Environment::new(self.image.convert());
+        environment.importance_sampling = self.importance_sampling;
+        environment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    // Dark, with a single bright texel
+    fn sun(width: usize, height: usize, i: usize, j: usize) -> Environment<f64> {
+        let mut image = Image::new(width, height);
+        for value in image.data.iter_mut() {
+            *value = 0.01;
+        }
+        let index = 3 * (j * width + i);
+        image.data[index..index + 3].copy_from_slice(&[1000.0, 1000.0, 1000.0]);
+        Environment::new(image)
+    }
+
+    #[test]
+    fn radiance() {
+        let environment = sun(8, 4, 0, 0);
+        // Towards -x at the top
+        assert_eq!(environment.get_radiance(&Vec3::from_array([-1.0, -0.01, 2.0])).get_data(), [1000.0, 1000.0, 1000.0]);
+        assert_eq!(environment.get_radiance(&Vec3::from_array([1.0, 0.0, 0.0])).get_data(), [0.01, 0.01, 0.01]);
+    }
+
+    #[test]
+    fn sample_direction() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut environment = sun(16, 8, 5, 2);
+        let bright = |environment: &Environment<f64>, direction: &Vec3<f64>| environment.get_radiance(direction).get_data()[0] > 1.0;
+
+        // Mostly towards the sun, with the density of pdf
+        let n = 10000;
+        let mut hits = 0;
+        for _ in 0..n {
+            let (direction, pdf) = environment.sample_direction(&mut rng);
+            assert!((direction.norm() - 1.0).abs() < 1e-9);
+            assert!((pdf - environment.pdf(&direction)).abs() < 1e-9 * pdf);
+            if bright(&environment, &direction) {
+                hits += 1;
+            }
+        }
+        assert!(hits > 9 * n / 10);
+
+        // Both densities integrate to 1 over the sphere
+        let (n_phi, n_theta) = (64, 32);
+        let pi = std::f64::consts::PI;
+        for importance_sampling in [true, false].iter() {
+            environment.set_importance_sampling(*importance_sampling);
+            let mut integral = 0.0;
+            for j in 0..n_theta {
+                let theta = pi * (j as f64 + 0.5) / n_theta as f64;
+                for i in 0..n_phi {
+                    let phi = 2.0 * pi * (i as f64 + 0.5) / n_phi as f64;
+                    let direction = Vec3::from_array([theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()]);
+                    integral += environment.pdf(&direction) * theta.sin() * (pi / n_theta as f64) * (2.0 * pi / n_phi as f64);
+                }
+            }
+            assert!((integral - 1.0).abs() < 1e-3);
+        }
+        // Uniform without importance sampling
+        let (_, pdf) = environment.sample_direction(&mut rng);
+        assert_eq!(pdf, 0.25 / pi);
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+use std::error;
+use std::io;
+use std::path::PathBuf;
+
+use crate::precision::PrecisionError;
+
+#[derive(Debug)]
+pub enum Error {
+    // The image to render has no pixels
+    EmptyImage {
+        width: usize,
+        height: usize
+    },
+    // The region to render isn't inside the image, or has x1 < x0 or y1 < y0
+    InvalidRegion {
+        x0: usize,
+        x1: usize,
+        y0: usize,
+        y1: usize,
+        width: usize,
+        height: usize
+    },
+    // A camera parameter out of its valid range, the name of the parameter
+    InvalidCamera(&'static str),
+    // A scene found broken by Renderer::warm_up_scene, what is wrong
+    InvalidScene(&'static str),
+    // A camera track without keyframes or with unordered keyframes
+    InvalidTrack(&'static str),
+    // A frame of a sequence with another size than the first one
+    FrameSize {
+        width: usize,
+        height: usize,
+        expected_width: usize,
+        expected_height: usize
+    },
+    // An image combined with another one of a different size
+    ImageSize {
+        width: usize,
+        height: usize,
+        expected_width: usize,
+        expected_height: usize
+    },
+    // Writing would replace a file of an existing sequence
+    FileExists(PathBuf),
+    Bounds(BoundsError),
+    Precision(PrecisionError),
+    Io(io::Error),
+    #[cfg(feature = "image")]
+    Image(image::ImageError)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::EmptyImage { width, height } => {
+                write!(f, "image of size {}x{} has no pixels", width, height)
+            },
+            Error::InvalidRegion { x0, x1, y0, y1, width, height } => {
+                write!(f, "region [{}, {}) x [{}, {}) is not inside the {}x{} image", x0, x1, y0, y1, width, height)
+            },
+            Error::InvalidCamera(parameter) => {
+                write!(f, "camera {} out of range", parameter)
+            },
+            Error::InvalidScene(reason) => {
+                write!(f, "scene {}", reason)
+            },
+            Error::InvalidTrack(reason) => {
+                write!(f, "camera track {}", reason)
+            },
+            Error::FrameSize { width, height, expected_width, expected_height } => {
+                write!(f, "frame of size {}x{} doesn't match the {}x{} sequence", width, height, expected_width, expected_height)
+            },
+            Error::ImageSize { width, height, expected_width, expected_height } => {
+                write!(f, "image of size {}x{} doesn't match the {}x{} image", width, height, expected_width, expected_height)
+            },
+            Error::FileExists(path) => {
+                write!(f, "{} already exists", path.display())
+            },
+            Error::Bounds(e) => e.fmt(f),
+            Error::Precision(e) => e.fmt(f),
+            Error::Io(e) => e.fmt(f),
+            #[cfg(feature = "image")]
+            Error::Image(e) => e.fmt(f)
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Bounds(e) => Some(e),
+            Error::Precision(e) => Some(e),
+            Error::Io(e) => Some(e),
+            #[cfg(feature = "image")]
+            Error::Image(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+// A pixel outside of an image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundsError {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize
+}
+
+impl fmt::Display for BoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pixel ({}, {}) is outside of the {}x{} image", self.x, self.y, self.width, self.height)
+    }
+}
+
+impl error::Error for BoundsError {}
+
+impl From<BoundsError> for Error {
+    fn from(e: BoundsError) -> Self {
+        Error::Bounds(e)
+    }
+}
+
+impl From<PrecisionError> for Error {
+    fn from(e: PrecisionError) -> Self {
+        Error::Precision(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Error::Image(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vec3;
+    use crate::ray::Ray;
+    use crate::hit::Hit;
+    use crate::boundingbox::BoundingBox;
+    use crate::hitable::Hitable;
+    use crate::precision::ConvertPrecision;
+
+    #[derive(Clone)]
+    struct External {
+        bounds: BoundingBox<f64>
+    }
+
+    impl Hitable<f64> for External {
+        fn hit(&self, _ray: &Ray<f64>, _t_min: f64, _t_max: f64) -> Option<Hit<f64>> {
+            None
+        }
+
+        fn get_bounds(&self) -> &BoundingBox<f64> {
+            &self.bounds
+        }
+
+        fn unwrap(self: Box<Self>) -> Box<dyn Hitable<f64>> {
+            self
+        }
+
+        fn clone_box(&self) -> Box<dyn Hitable<f64>> {
+            Box::new(self.clone())
+        }
+    }
+
+    fn convert(hitable: Box<dyn Hitable<f64>>) -> Result<Box<dyn Hitable<f32>>, Error> {
+        let converted = hitable.convert_precision()?;
+        Ok(converted)
+    }
+
+    #[test]
+    fn precision() {
+        let hitable: Box<dyn Hitable<f64>> = Box::new(External { bounds: BoundingBox::new(Vec3::new(), Vec3::new()) });
+        match convert(hitable) {
+            Err(Error::Precision(e)) => assert!(e.get_type_name().ends_with("External")),
+            _ => panic!("expected a precision error")
+        }
+    }
+
+    #[test]
+    fn display() {
+        let error = Error::InvalidRegion { x0: 2, x1: 1, y0: 0, y1: 4, width: 4, height: 4 };
+        assert_eq!(error.to_string(), "region [2, 1) x [0, 4) is not inside the 4x4 image");
+        assert_eq!(Error::InvalidCamera("fov").to_string(), "camera fov out of range");
+        let error: Error = io::Error::new(io::ErrorKind::NotFound, "missing").into();
+        assert!(error::Error::source(&error).is_some());
+        let error: Error = BoundsError { x: 3, y: 1, width: 3, height: 2 }.into();
+        assert_eq!(error.to_string(), "pixel (3, 1) is outside of the 3x2 image");
+    }
+}
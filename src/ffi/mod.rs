@@ -0,0 +1,422 @@
+// Flat C API over f64 scenes, see ffi/ray_tracer.h for the declarations.
+//
+// Handles are opaque pointers created by the rt_*_new functions and owned by
+// the caller, who frees each one exactly once with its rt_*_free function.
+// The other functions only borrow the handles for the duration of the call.
+// Every function returns a status code, a panic inside is caught and
+// returned as RT_PANIC instead of unwinding into the caller.
+#![allow(clippy::missing_safety_doc)]
+
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::actor::{Actor, ActorBuilder};
+use crate::camera::Camera;
+use crate::camera::perspective::PerspectiveCamera;
+use crate::constants::Axis;
+use crate::renderer::Renderer;
+use crate::scene::Scene;
+use crate::tree::TreeType;
+use crate::vector::Vec3;
+
+pub const RT_OK: i32 = 0;
+pub const RT_NULL_POINTER: i32 = 1;
+pub const RT_INVALID_ARGUMENT: i32 = 2;
+pub const RT_BUFFER_TOO_SMALL: i32 = 3;
+pub const RT_PANIC: i32 = 4;
+
+pub const RT_LAMBERTIAN: i32 = 0;
+pub const RT_METAL: i32 = 1;
+pub const RT_DIELECTRIC: i32 = 2;
+pub const RT_EMISSIVE: i32 = 3;
+
+pub const RT_AXIS_X: i32 = 0;
+pub const RT_AXIS_Y: i32 = 1;
+pub const RT_AXIS_Z: i32 = 2;
+
+pub const RT_TREE_LINEAR: i32 = 0;
+pub const RT_TREE_BINARY: i32 = 1;
+pub const RT_TREE_OCT: i32 = 2;
+
+// The parameter is the dimming of lambertian materials, the fuzziness of
+// metals and the refractive index of dielectrics, emissive ones ignore it
+#[repr(C)]
+pub struct RtMaterial {
+    pub kind: i32,
+    pub color: [f64; 3],
+    pub parameter: f64
+}
+
+// The seed is only used if has_seed isn't 0
+#[repr(C)]
+pub struct RtRenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub samples: usize,
+    pub max_reflections: usize,
+    pub antialiasing: i32,
+    pub has_seed: i32,
+    pub seed: u64
+}
+
+pub struct RtScene {
+    scene: Scene<f64>
+}
+
+pub struct RtCamera {
+    camera: PerspectiveCamera<f64>
+}
+
+// Rows rendered so far, can be read from another thread while rendering
+pub struct RtProgress {
+    done: AtomicUsize,
+    total: AtomicUsize
+}
+
+fn guard<F>(f: F) -> i32
+    where F: FnOnce() -> Result<(), i32>
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => RT_OK,
+        Ok(Err(status)) => status,
+        Err(_) => RT_PANIC
+    }
+}
+
+unsafe fn reference<'a, T>(pointer: *const T) -> Result<&'a T, i32> {
+    pointer.as_ref().ok_or(RT_NULL_POINTER)
+}
+
+unsafe fn reference_mut<'a, T>(pointer: *mut T) -> Result<&'a mut T, i32> {
+    pointer.as_mut().ok_or(RT_NULL_POINTER)
+}
+
+unsafe fn vector(pointer: *const f64) -> Result<[f64; 3], i32> {
+    if pointer.is_null() {
+        return Err(RT_NULL_POINTER);
+    }
+    let values = [*pointer, *pointer.add(1), *pointer.add(2)];
+    if values.iter().all(|value| value.is_finite()) {
+        Ok(values)
+    } else {
+        Err(RT_INVALID_ARGUMENT)
+    }
+}
+
+fn positive(value: f64) -> Result<f64, i32> {
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(RT_INVALID_ARGUMENT)
+    }
+}
+
+fn axis(value: i32) -> Result<Axis, i32> {
+    match value {
+        RT_AXIS_X => Ok(Axis::X),
+        RT_AXIS_Y => Ok(Axis::Y),
+        RT_AXIS_Z => Ok(Axis::Z),
+        _ => Err(RT_INVALID_ARGUMENT)
+    }
+}
+
+fn with_material(builder: ActorBuilder<f64>, material: &RtMaterial) -> Result<Actor<f64>, i32> {
+    if !material.color.iter().all(|value| value.is_finite()) || !material.parameter.is_finite() {
+        return Err(RT_INVALID_ARGUMENT);
+    }
+    match material.kind {
+        RT_LAMBERTIAN => Ok(builder.lambertian(material.color, material.parameter)),
+        RT_METAL => Ok(builder.metal(material.color, material.parameter)),
+        RT_DIELECTRIC => Ok(builder.dielectric(material.color, positive(material.parameter)?)),
+        RT_EMISSIVE => Ok(builder.emissive(material.color)),
+        _ => Err(RT_INVALID_ARGUMENT)
+    }
+}
+
+unsafe fn add_actor(scene: *mut RtScene, builder: ActorBuilder<f64>, position: *const f64, material: *const RtMaterial, actor_id: *mut usize) -> Result<(), i32> {
+    let scene = reference_mut(scene)?;
+    let actor = with_material(builder.at(vector(position)?), reference(material)?)?;
+    let id = scene.scene.add_actor(actor);
+    if let Some(actor_id) = actor_id.as_mut() {
+        *actor_id = id;
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_status_message(status: i32) -> *const c_char {
+    let message: &'static [u8] = match status {
+        RT_OK => b"ok\0",
+        RT_NULL_POINTER => b"null pointer\0",
+        RT_INVALID_ARGUMENT => b"invalid argument\0",
+        RT_BUFFER_TOO_SMALL => b"buffer too small\0",
+        RT_PANIC => b"internal error\0",
+        _ => b"unknown status\0"
+    };
+    message.as_ptr() as *const c_char
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_new(scene: *mut *mut RtScene) -> i32 {
+    guard(|| {
+        let scene = reference_mut(scene)?;
+        *scene = Box::into_raw(Box::new(RtScene { scene: Scene::new() }));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_free(scene: *mut RtScene) -> i32 {
+    guard(|| {
+        if !scene.is_null() {
+            drop(Box::from_raw(scene));
+        }
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_set_background(scene: *mut RtScene, color: *const f64) -> i32 {
+    guard(|| {
+        let scene = reference_mut(scene)?;
+        scene.scene.set_background(Vec3::from_slice(&vector(color)?));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_set_tree_type(scene: *mut RtScene, tree_type: i32) -> i32 {
+    guard(|| {
+        let scene = reference_mut(scene)?;
+        let tree_type = match tree_type {
+            RT_TREE_LINEAR => TreeType::Linear,
+            RT_TREE_BINARY => TreeType::Binary,
+            RT_TREE_OCT => TreeType::Oct,
+            _ => return Err(RT_INVALID_ARGUMENT)
+        };
+        scene.scene.set_tree_type(tree_type);
+        Ok(())
+    })
+}
+
+// The id of the new actor is written to actor_id unless it's null
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_add_sphere(scene: *mut RtScene, radius: f64, position: *const f64, material: *const RtMaterial, actor_id: *mut usize) -> i32 {
+    guard(|| add_actor(scene, Actor::sphere(positive(radius)?), position, material, actor_id))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_add_cube(scene: *mut RtScene, length: f64, width: f64, height: f64, position: *const f64, material: *const RtMaterial, actor_id: *mut usize) -> i32 {
+    guard(|| add_actor(scene, Actor::cube(positive(length)?, positive(width)?, positive(height)?), position, material, actor_id))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_add_rectangle(scene: *mut RtScene, width: f64, width_axis: i32, height: f64, height_axis: i32, position: *const f64, material: *const RtMaterial, actor_id: *mut usize) -> i32 {
+    guard(|| {
+        if width_axis == height_axis {
+            return Err(RT_INVALID_ARGUMENT);
+        }
+        add_actor(scene, Actor::rectangle(positive(width)?, axis(width_axis)?, positive(height)?, axis(height_axis)?), position, material, actor_id)
+    })
+}
+
+// Lights are sampled directly by the bidirectional integrator
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_add_light(scene: *mut RtScene, actor_id: usize) -> i32 {
+    guard(|| {
+        let scene = reference_mut(scene)?;
+        if scene.scene.get_actor(actor_id).is_none() {
+            return Err(RT_INVALID_ARGUMENT);
+        }
+        scene.scene.add_light(actor_id);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_camera_new(camera: *mut *mut RtCamera) -> i32 {
+    guard(|| {
+        let camera = reference_mut(camera)?;
+        *camera = Box::into_raw(Box::new(RtCamera { camera: PerspectiveCamera::new() }));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_camera_free(camera: *mut RtCamera) -> i32 {
+    guard(|| {
+        if !camera.is_null() {
+            drop(Box::from_raw(camera));
+        }
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_camera_set_position(camera: *mut RtCamera, position: *const f64) -> i32 {
+    guard(|| {
+        let camera = reference_mut(camera)?;
+        camera.camera.set_position(&vector(position)?);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_camera_set_lookat(camera: *mut RtCamera, lookat: *const f64) -> i32 {
+    guard(|| {
+        let camera = reference_mut(camera)?;
+        camera.camera.set_lookat(&vector(lookat)?);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_camera_set_up(camera: *mut RtCamera, up: *const f64) -> i32 {
+    guard(|| {
+        let camera = reference_mut(camera)?;
+        camera.camera.set_up(&vector(up)?);
+        Ok(())
+    })
+}
+
+// Field of view in radians, aspect ratio width / height, diameter of the
+// aperture (0 for no depth of field) and distance in focus
+#[no_mangle]
+pub unsafe extern "C" fn rt_camera_set_lens(camera: *mut RtCamera, fov: f64, aspect: f64, aperture: f64, focus: f64) -> i32 {
+    guard(|| {
+        let camera = reference_mut(camera)?;
+        let camera = &mut camera.camera;
+        camera.set_fov(fov).map_err(|_| RT_INVALID_ARGUMENT)?;
+        camera.set_aspect(aspect).map_err(|_| RT_INVALID_ARGUMENT)?;
+        camera.set_aperture(aperture).map_err(|_| RT_INVALID_ARGUMENT)?;
+        camera.set_focus(focus).map_err(|_| RT_INVALID_ARGUMENT)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_progress_new(progress: *mut *mut RtProgress) -> i32 {
+    guard(|| {
+        let progress = reference_mut(progress)?;
+        *progress = Box::into_raw(Box::new(RtProgress { done: AtomicUsize::new(0), total: AtomicUsize::new(0) }));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rt_progress_free(progress: *mut RtProgress) -> i32 {
+    guard(|| {
+        if !progress.is_null() {
+            drop(Box::from_raw(progress));
+        }
+        Ok(())
+    })
+}
+
+// Fraction of the rows of the current or last render done, 0 before one
+#[no_mangle]
+pub unsafe extern "C" fn rt_progress_get(progress: *const RtProgress, fraction: *mut f64) -> i32 {
+    guard(|| {
+        let progress = reference(progress)?;
+        let fraction = reference_mut(fraction)?;
+        let total = progress.total.load(Ordering::SeqCst);
+        *fraction = match total {
+            0 => 0.0,
+            _ => progress.done.load(Ordering::SeqCst) as f64 / total as f64
+        };
+        Ok(())
+    })
+}
+
+// Linear RGB, row by row from the top, into a buffer of at least
+// 3 * width * height floats. The progress may be null.
+#[no_mangle]
+pub unsafe extern "C" fn rt_render(scene: *const RtScene, camera: *const RtCamera, settings: *const RtRenderSettings, progress: *const RtProgress, buffer: *mut f32, buffer_length: usize) -> i32 {
+    guard(|| {
+        let scene = &reference(scene)?.scene;
+        let camera = &reference(camera)?.camera;
+        let settings = reference(settings)?;
+        let progress = progress.as_ref();
+        if buffer.is_null() {
+            return Err(RT_NULL_POINTER);
+        }
+        let (width, height) = (settings.width, settings.height);
+        let length = width.checked_mul(height).and_then(|pixels| pixels.checked_mul(3)).ok_or(RT_INVALID_ARGUMENT)?;
+        if buffer_length < length {
+            return Err(RT_BUFFER_TOO_SMALL);
+        }
+        let mut renderer = Renderer::new(0, width, 0, height, width, height, settings.samples, settings.max_reflections, settings.antialiasing != 0)
+            .map_err(|_| RT_INVALID_ARGUMENT)?;
+        if settings.has_seed != 0 {
+            renderer.set_seed(Some(settings.seed));
+        }
+
+        let buffer = std::slice::from_raw_parts_mut(buffer, length);
+        if let Some(progress) = progress {
+            progress.done.store(0, Ordering::SeqCst);
+            progress.total.store(height, Ordering::SeqCst);
+        }
+        for row in 0..height {
+            let image = renderer.render_rows(scene, camera, row..row + 1);
+            for (value, pixel) in buffer[3 * row * width..3 * (row + 1) * width].iter_mut().zip(image.data.iter()) {
+                *value = *pixel as f32;
+            }
+            if let Some(progress) = progress {
+                progress.done.store(row + 1, Ordering::SeqCst);
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn sphere() {
+        unsafe {
+            let mut scene = ptr::null_mut();
+            assert_eq!(rt_scene_new(&mut scene), RT_OK);
+            assert_eq!(rt_scene_set_background(scene, [0.2, 0.3, 0.4].as_ptr()), RT_OK);
+            let red = RtMaterial { kind: RT_EMISSIVE, color: [1.0, 0.0, 0.0], parameter: 0.0 };
+            let mut id = usize::MAX;
+            assert_eq!(rt_scene_add_sphere(scene, 1.0, [0.0, 0.0, -3.0].as_ptr(), &red, &mut id), RT_OK);
+            assert_eq!(id, 0);
+            assert_eq!(rt_scene_add_light(scene, id), RT_OK);
+            assert_eq!(rt_scene_add_light(scene, 7), RT_INVALID_ARGUMENT);
+            assert_eq!(rt_scene_add_sphere(scene, -1.0, [0.0; 3].as_ptr(), &red, ptr::null_mut()), RT_INVALID_ARGUMENT);
+            let unknown = RtMaterial { kind: 9, color: [1.0; 3], parameter: 0.0 };
+            assert_eq!(rt_scene_add_cube(scene, 1.0, 1.0, 1.0, [0.0; 3].as_ptr(), &unknown, ptr::null_mut()), RT_INVALID_ARGUMENT);
+            assert_eq!(rt_scene_add_rectangle(scene, 1.0, RT_AXIS_X, 1.0, RT_AXIS_X, [0.0; 3].as_ptr(), &red, ptr::null_mut()), RT_INVALID_ARGUMENT);
+
+            let mut camera = ptr::null_mut();
+            assert_eq!(rt_camera_new(&mut camera), RT_OK);
+            assert_eq!(rt_camera_set_position(camera, [0.0; 3].as_ptr()), RT_OK);
+            assert_eq!(rt_camera_set_lookat(camera, [0.0, 0.0, -3.0].as_ptr()), RT_OK);
+            assert_eq!(rt_camera_set_lens(camera, 0.5 * std::f64::consts::PI, 1.0, 0.0, 1.0), RT_OK);
+            assert_eq!(rt_camera_set_lens(camera, -1.0, 1.0, 0.0, 1.0), RT_INVALID_ARGUMENT);
+
+            let mut progress = ptr::null_mut();
+            assert_eq!(rt_progress_new(&mut progress), RT_OK);
+            let settings = RtRenderSettings { width: 8, height: 6, samples: 1, max_reflections: 2, antialiasing: 0, has_seed: 1, seed: 3 };
+            let mut buffer = vec![-1.0f32; 3 * 8 * 6];
+            assert_eq!(rt_render(scene, camera, &settings, progress, buffer.as_mut_ptr(), 3 * 8 * 6 - 1), RT_BUFFER_TOO_SMALL);
+            assert_eq!(rt_render(scene, camera, &settings, progress, buffer.as_mut_ptr(), buffer.len()), RT_OK);
+            let mut fraction = 0.0;
+            assert_eq!(rt_progress_get(progress, &mut fraction), RT_OK);
+            assert_eq!(fraction, 1.0);
+
+            // The sphere in the middle, the background in the corners
+            let pixel = |i: usize, j: usize| &buffer[3 * (j * 8 + i)..3 * (j * 8 + i) + 3];
+            assert_eq!(pixel(4, 3), [1.0, 0.0, 0.0]);
+            assert_eq!(pixel(0, 0), [0.2, 0.3, 0.4]);
+
+            assert_eq!(rt_render(ptr::null(), camera, &settings, ptr::null(), buffer.as_mut_ptr(), buffer.len()), RT_NULL_POINTER);
+            assert_eq!(rt_progress_free(progress), RT_OK);
+            assert_eq!(rt_camera_free(camera), RT_OK);
+            assert_eq!(rt_scene_free(scene), RT_OK);
+        }
+    }
+}
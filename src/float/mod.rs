@@ -3,6 +3,14 @@ use num_traits::float::{FloatCore as NumFloat};
 pub trait Number {
   fn sqrt(&self) -> Self;
   fn tan(&self) -> Self;
+  fn sin(&self) -> Self;
+  fn cos(&self) -> Self;
+  fn acos(&self) -> Self;
+  fn atan2(&self, other: Self) -> Self;
+  fn powf(&self, n: Self) -> Self;
+  fn ln(&self) -> Self;
+  // Unlike NumCast::from, converting a f64 constant can't fail
+  fn from_f64(x: f64) -> Self;
 }
 
 impl Number for f64 {
@@ -13,6 +21,34 @@ impl Number for f64 {
   fn tan(&self) -> Self {
     f64::tan(*self)
   }
+
+  fn sin(&self) -> Self {
+    f64::sin(*self)
+  }
+
+  fn cos(&self) -> Self {
+    f64::cos(*self)
+  }
+
+  fn acos(&self) -> Self {
+    f64::acos(*self)
+  }
+
+  fn atan2(&self, other: Self) -> Self {
+    f64::atan2(*self, other)
+  }
+
+  fn powf(&self, n: Self) -> Self {
+    f64::powf(*self, n)
+  }
+
+  fn ln(&self) -> Self {
+    f64::ln(*self)
+  }
+
+  fn from_f64(x: f64) -> Self {
+    x
+  }
 }
 impl Number for f32 {
   fn sqrt(&self) -> Self {
@@ -22,9 +58,37 @@ impl Number for f32 {
   fn tan(&self) -> Self {
     f32::tan(*self)
   }
+
+  fn sin(&self) -> Self {
+    f32::sin(*self)
+  }
+
+  fn cos(&self) -> Self {
+    f32::cos(*self)
+  }
+
+  fn acos(&self) -> Self {
+    f32::acos(*self)
+  }
+
+  fn atan2(&self, other: Self) -> Self {
+    f32::atan2(*self, other)
+  }
+
+  fn powf(&self, n: Self) -> Self {
+    f32::powf(*self, n)
+  }
+
+  fn ln(&self) -> Self {
+    f32::ln(*self)
+  }
+
+  fn from_f64(x: f64) -> Self {
+    x as f32
+  }
 }
 
-pub trait Float : 'static + NumFloat + Number {}
+pub trait Float : 'static + Send + Sync + NumFloat + Number {}
 
 impl Float for f64 {}
 impl Float for f32 {}
@@ -1,10 +1,117 @@
 use crate::float::Float;
 use crate::vector::Vec3;
+use crate::ray::Ray;
 
+#[derive(Debug)]
 pub struct Hit<T>
     where T: Float
 {
     pub point: Vec3<T>,
+    // Always points against the incoming ray
     pub normal: Vec3<T>,
-    pub t: T
+    pub t: T,
+    // Surface coordinates for texture mapping, both in [0, 1]
+    pub u: T,
+    pub v: T,
+    // True if the ray hit the outside of the surface
+    pub front_face: bool,
+    // Weights of the corners of the triangle hit and its index, for meshes
+    pub barycentric: Option<[T; 3]>,
+    pub face: Option<usize>,
+    // Width in UV units of the area the ray sees around the hit, for the
+    // textures to filter over. None unless the ray has a spread.
+    pub footprint: Option<T>
+}
+
+impl<T> Hit<T>
+    where T: Float
+{
+    pub fn with_face_normal(ray: &Ray<T>, point: Vec3<T>, outward_normal: Vec3<T>, t: T, u: T, v: T) -> Self {
+        let front_face = ray.get_direction().dot(&outward_normal) <= T::zero();
+        let normal = if front_face { outward_normal } else { outward_normal * (-T::one()) };
+        Hit {
+            point,
+            normal,
+            t,
+            u,
+            v,
+            front_face,
+            barycentric: None,
+            face: None,
+            footprint: None
+        }
+    }
+
+    // The footprint from the spread of the ray and how many UV units the
+    // surface has per unit of length around the hit. Seen at a grazing
+    // angle the area stretches along the ray, the larger width is kept.
+    pub fn set_footprint(&mut self, ray: &Ray<T>, uv_per_length: T) {
+        self.footprint = ray.get_spread().map(|spread| {
+            let direction = ray.get_direction();
+            let length = direction.norm();
+            let cosine = (direction.dot(&self.normal) / (length * self.normal.norm())).abs();
+            spread * self.t * length / cosine * uv_per_length
+        });
+    }
+
+    // The geometric normal, pointing to the outside of the surface
+    pub fn get_outward_normal(&self) -> Vec3<T> {
+        if self.front_face {
+            Vec3::from_slice(self.normal.get_data())
+        } else {
+            &self.normal * (-T::one())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_normal() {
+        let outward_normal = Vec3::from_array([0.0, 0.0, 1.0]);
+
+        let ray = Ray::from_array([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]);
+        let hit = Hit::with_face_normal(&ray, Vec3::new(), Vec3::from_slice(outward_normal.get_data()), 5.0, 0.0, 0.0);
+        assert!(hit.front_face);
+        assert_eq!(hit.normal.get_data(), [0.0, 0.0, 1.0]);
+        assert_eq!(hit.get_outward_normal().get_data(), [0.0, 0.0, 1.0]);
+
+        let ray = Ray::from_array([0.0, 0.0, -5.0], [0.0, 1.0, 1.0]);
+        let hit = Hit::with_face_normal(&ray, Vec3::new(), Vec3::from_slice(outward_normal.get_data()), 5.0, 0.0, 0.0);
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal.get_data(), [0.0, 0.0, -1.0]);
+        assert_eq!(hit.get_outward_normal().get_data(), [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn footprint() {
+        let normal = || Vec3::from_array([0.0, 0.0, 1.0]);
+        let mut ray = Ray::<f64>::from_array([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]);
+        let mut hit = Hit::with_face_normal(&ray, Vec3::new(), normal(), 5.0, 0.0, 0.0);
+        hit.set_footprint(&ray, 0.5);
+        assert!(hit.footprint.is_none());
+
+        ray.set_spread(Some(0.01));
+        hit.set_footprint(&ray, 0.5);
+        assert!((hit.footprint.unwrap() - 0.025).abs() < 1e-12);
+
+        // Twice as wide at 60 degrees from the normal
+        let mut ray = Ray::from_array([0.0, 0.0, 5.0], [3.0f64.sqrt(), 0.0, -1.0]);
+        ray.set_spread(Some(0.01));
+        let mut hit = Hit::with_face_normal(&ray, Vec3::new(), normal(), 5.0, 0.0, 0.0);
+        hit.set_footprint(&ray, 0.5);
+        assert!((hit.footprint.unwrap() - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn debug() {
+        let ray = Ray::from_array([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]);
+        let hit = Hit::with_face_normal(&ray, Vec3::new(), Vec3::from_array([0.0, 0.0, 1.0]), 5.0, 0.25, 0.5);
+        let text = format!("{:?}", hit);
+        assert!(text.starts_with("Hit { point: Vec3"));
+        assert!(text.contains("t: 5.0, u: 0.25, v: 0.5, front_face: true"));
+        assert!(format!("{:?}", ray).starts_with("Ray { origin: Vec3"));
+    }
 }
@@ -1,20 +1,117 @@
+use rand::RngCore;
+
 use crate::float::Float;
+use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::hit::Hit;
 use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
+use crate::interval::Interval;
+use crate::precision::PrecisionError;
+use crate::tree::TraversalCounts;
+
+use primitive::TriangleMesh;
 
 pub mod primitive;
 pub mod transform;
 
-pub trait Hitable<T>
+pub trait Hitable<T>: Send + Sync
     where T: Float
 {
+    // Closest hit with t_min <= t <= t_max
     fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>>;
+    fn hit_within(&self, ray: &Ray<T>, interval: &Interval<T>) -> Option<Hit<T>> {
+        self.hit(ray, interval.min, interval.max)
+    }
+    fn hit_counted(&self, ray: &Ray<T>, interval: &Interval<T>, _counts: &mut TraversalCounts) -> Option<Hit<T>> {
+        // Like hit_within, adding the work to the counts of the tree, i.e.
+        // the triangles a mesh tests. Wrappers pass the counts on.
+        self.hit_within(ray, interval)
+    }
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        // Every hit with t_min <= t <= t_max sorted by t, entering and leaving.
+        // Only the closest one unless the hitable knows better.
+        self.hit(ray, t_min, t_max).into_iter().collect()
+    }
+    fn hit_intervals(&self, _ray: &Ray<T>, _interval: &Interval<T>) -> Vec<Interval<T>> {
+        // The parts of the interval spent inside the hitable, sorted and
+        // disjoint. Only closed surfaces have an inside: open ones like
+        // rectangles return no intervals.
+        vec!()
+    }
     fn get_bounds(&self) -> &BoundingBox<T>;
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        // The box the trees place the hitable with, None if it's unbounded.
+        // It must fully contain the surface and should be as tight as possible.
+        Some(self.get_bounds().clone())
+    }
+    fn surface_area(&self) -> T {
+        // Area of the surface, i.e. for the cost of a hitable in a tree or
+        // the probability density of a point sampled on a light. 0 if unknown.
+        T::zero()
+    }
+    fn sample_surface(&self, _rng: &mut dyn RngCore) -> Option<Hit<T>> {
+        // Uniformly distributed point on the surface with its outward normal
+        // (t = 0, front_face), i.e. to emit light from. None if unsupported.
+        None
+    }
     fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>>;
+    // A copy in a new box, to clone actors and scenes. Required of every
+    // hitable, for one that is Clone it's Box::new(self.clone()).
+    fn clone_box(&self) -> Box<dyn Hitable<T>>;
     fn is_primitive(&self) -> bool {
         // Primitives (i.e. spheres, boxes, rectangles) return true,
         // Decorators (i.e. translations, rotations) return false
         true
     }
+    fn translate(&mut self, _offset: &Vec3<T>) -> bool {
+        // Hitables that can move by changing their own data return true,
+        // the others need to be wrapped in a Translation
+        false
+    }
+    fn set_time(&mut self, _time: T) -> bool {
+        // Animated hitables move to their placement at the given time and
+        // return true, their bounds may have changed
+        false
+    }
+    fn is_animated(&self) -> bool {
+        // True if set_time can move the hitable, the scene doesn't touch its
+        // tree for a new time if none of the actors is animated
+        false
+    }
+    fn teleport(&self, _ray: &Ray<T>, _point: &Vec3<T>) -> Option<Ray<T>> {
+        // Portals send on a ray that hit them at the point from somewhere
+        // else: the ray leaving there. None for ordinary surfaces.
+        None
+    }
+    fn get_mesh(&self) -> Option<&TriangleMesh<T>> {
+        // Bare triangle meshes hand their triangles to trees that intersect
+        // them on their own (i.e. Embree). None for everything else.
+        None
+    }
+    fn convert_f32(&self) -> Result<Box<dyn Hitable<f32>>, PrecisionError> {
+        Err(PrecisionError::unsupported::<Self>())
+    }
+    fn convert_f64(&self) -> Result<Box<dyn Hitable<f64>>, PrecisionError> {
+        Err(PrecisionError::unsupported::<Self>())
+    }
+}
+
+impl<T> Clone for Box<dyn Hitable<T>>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+// Primitives with a surface a point can be sampled on uniformly, i.e. area
+// lights. Both work in the space of the primitive, before its transformations.
+pub trait SampleablePrimitive<T>: Hitable<T>
+    where T: Float
+{
+    // If the point lies on the surface, up to round-off
+    fn contains(&self, point: &Vec3<T>) -> bool;
+    // Uniformly distributed point on the surface
+    fn sample_point(&self, rng: &mut dyn RngCore) -> Vec3<T>;
 }
@@ -0,0 +1,363 @@
+use rand::RngCore;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
+use crate::interval::Interval;
+use crate::sampling::uniform;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
+
+// Closed cone around the Y axis, the base at -height / 2 and the apex at
+// height / 2. Hollowed, the top is cut off at a radius of the given fraction
+// of the base and closed by a disc: a frustum, or a cylinder at 1.
+#[derive(Clone)]
+pub struct Cone<T>
+    where T: Float
+{
+    radius: T,
+    height: T,
+    hollow: T,
+    bounds: BoundingBox<T>
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Part {
+    Side,
+    Base,
+    Top
+}
+
+impl<T> Cone<T>
+    where T: Float
+{
+    pub fn new(radius: T, height: T) -> Self {
+        let half = Vec3::from_array([radius, height * T::from_f64(0.5), radius]);
+        Cone {
+            radius,
+            height,
+            hollow: T::zero(),
+            bounds: BoundingBox::new(&half * (-T::one()), half)
+        }
+    }
+
+    pub fn get_radius(&self) -> T {
+        self.radius
+    }
+
+    pub fn get_height(&self) -> T {
+        self.height
+    }
+
+    pub fn get_hollow(&self) -> T {
+        self.hollow
+    }
+
+    // Radius of the top relative to the base, clamped to [0, 1]: 0 is
+    // a solid cone with an apex, 1 a cylinder
+    pub fn set_hollow(&mut self, inner_radius_fraction: T) {
+        self.hollow = inner_radius_fraction.max(T::zero()).min(T::one());
+    }
+
+    fn top_radius(&self) -> T {
+        self.radius * self.hollow
+    }
+
+    // Radius of the side at the height y
+    fn radius_at(&self, y: T) -> T {
+        let half = self.height * T::from_f64(0.5);
+        self.radius + (self.top_radius() - self.radius) * (y + half) / self.height
+    }
+
+    // Every intersection with the side and the two discs, unsorted. At most
+    // two with the side and one with each disc, without allocating.
+    fn roots(&self, ray: &Ray<T>) -> [Option<(T, Part)>; 4] {
+        let (o, d) = (ray.get_origin().get_data(), ray.get_direction().get_data());
+        let half = self.height * T::from_f64(0.5);
+        let on_side = |t: &T| {
+            let y = o[1] + d[1] * *t;
+            y >= -half && y <= half
+        };
+
+        // The radius along the ray is r(t) = a + b * t,
+        // (ox + t * dx)^2 + (oz + t * dz)^2 = r(t)^2
+        let slope = (self.radius - self.top_radius()) / self.height;
+        let a = self.radius - slope * (o[1] + half);
+        let b = -slope * d[1];
+        let qa = d[0] * d[0] + d[2] * d[2] - b * b;
+        let qb = T::from_f64(2.0) * (o[0] * d[0] + o[2] * d[2] - a * b);
+        let qc = o[0] * o[0] + o[2] * o[2] - a * a;
+        let mut side = [None, None];
+        if qa == T::zero() {
+            // Parallel to the side, only one intersection
+            if qb != T::zero() {
+                side[0] = Some(-qc / qb);
+            }
+        } else {
+            let discriminant = qb * qb - T::from_f64(4.0) * qa * qc;
+            if discriminant >= T::zero() {
+                let discriminant = discriminant.sqrt();
+                let two_a = qa + qa;
+                side = [Some((-qb - discriminant) / two_a), Some((-qb + discriminant) / two_a)];
+            }
+        }
+        let mut roots = [None; 4];
+        for (root, t) in roots.iter_mut().zip(side.iter()) {
+            *root = t.filter(on_side).map(|t| (t, Part::Side));
+        }
+
+        if d[1] != T::zero() {
+            let discs = [(-half, self.radius, Part::Base), (half, self.top_radius(), Part::Top)];
+            for (root, (y, radius, part)) in roots[2..].iter_mut().zip(discs.iter()) {
+                if *radius <= T::zero() {
+                    continue;
+                }
+                let t = (*y - o[1]) / d[1];
+                let (x, z) = (o[0] + d[0] * t, o[2] + d[2] * t);
+                if x * x + z * z <= *radius * *radius {
+                    *root = Some((t, *part));
+                }
+            }
+        }
+        roots
+    }
+
+    // The intersections within the interval, nearest first and the missing
+    // ones at the end
+    fn sorted_roots(&self, ray: &Ray<T>, interval: &Interval<T>) -> [Option<(T, Part)>; 4] {
+        let mut roots = self.roots(ray);
+        for root in roots.iter_mut() {
+            *root = root.filter(|(t, _)| interval.contains(*t));
+        }
+        let key = |root: &Option<(T, Part)>| root.map_or(T::infinity(), |(t, _)| t);
+        roots.sort_unstable_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        roots
+    }
+
+    fn surface_at(&self, point: &Vec3<T>, part: Part) -> (Vec3<T>, T, T) {
+        let p = point.get_data();
+        let half = T::from_f64(0.5);
+        let two_pi = T::from_f64(2.0 * std::f64::consts::PI);
+        match part {
+            Part::Side => {
+                // The gradient of x^2 + z^2 - r(y)^2
+                let slope = (self.radius - self.top_radius()) / self.height;
+                let mut normal = Vec3::from_array([p[0], self.radius_at(p[1]) * slope, p[2]]);
+                if normal.dot(&normal) == T::zero() {
+                    normal = Vec3::from_array([T::zero(), T::one(), T::zero()]);
+                }
+                normal.normalize();
+                (normal, half + p[0].atan2(p[2]) / two_pi, half + p[1] / self.height)
+            },
+            Part::Base | Part::Top => {
+                let sign = if part == Part::Base { -T::one() } else { T::one() };
+                let normal = Vec3::from_array([T::zero(), sign, T::zero()]);
+                let scale = half / self.radius;
+                (normal, half + p[0] * scale, half + p[2] * scale)
+            }
+        }
+    }
+
+    fn hit_at(&self, ray: &Ray<T>, t: T, part: Part) -> Hit<T> {
+        let point = ray.at(t);
+        let (normal, u, v) = self.surface_at(&point, part);
+        Hit::with_face_normal(ray, point, normal, t, u, v)
+    }
+
+    fn part_areas(&self) -> [T; 3] {
+        let pi = T::from_f64(std::f64::consts::PI);
+        let top = self.top_radius();
+        let slant = ((self.radius - top) * (self.radius - top) + self.height * self.height).sqrt();
+        [pi * (self.radius + top) * slant, pi * self.radius * self.radius, pi * top * top]
+    }
+}
+
+impl<T> Hitable<T> for Cone<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let roots = self.sorted_roots(ray, &Interval::new(t_min, t_max));
+        roots[0].map(|(t, part)| self.hit_at(ray, t, part))
+    }
+
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        let roots = self.sorted_roots(ray, &Interval::new(t_min, t_max));
+        roots.iter().flatten().map(|(t, part)| self.hit_at(ray, *t, *part)).collect()
+    }
+
+    fn hit_intervals(&self, ray: &Ray<T>, interval: &Interval<T>) -> Vec<Interval<T>> {
+        // Convex, the ray is inside between the first and the last intersection
+        let roots = self.roots(ray);
+        if roots.iter().flatten().count() < 2 {
+            return vec!();
+        }
+        let t0 = roots.iter().flatten().fold(T::infinity(), |t0, (t, _)| t0.min(*t));
+        let t1 = roots.iter().flatten().fold(T::neg_infinity(), |t1, (t, _)| t1.max(*t));
+        let inside = Interval::new(t0, t1).intersect(interval);
+        if inside.is_empty() {
+            return vec!();
+        }
+        vec!(inside)
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn surface_area(&self) -> T {
+        let areas = self.part_areas();
+        areas[0] + areas[1] + areas[2]
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<Hit<T>> {
+        let areas = self.part_areas();
+        let pick = uniform::<T, dyn RngCore>(rng) * self.surface_area();
+        let part = if pick < areas[0] { Part::Side } else if pick < areas[0] + areas[1] { Part::Base } else { Part::Top };
+        let phi = T::from_f64(2.0 * std::f64::consts::PI) * uniform::<T, dyn RngCore>(rng);
+        let half = self.height * T::from_f64(0.5);
+        let top = self.top_radius();
+        let (radius, y) = match part {
+            Part::Side => {
+                // The area grows with the radius, r^2 is uniform
+                let r2 = top * top + uniform::<T, dyn RngCore>(rng) * (self.radius * self.radius - top * top);
+                let radius = r2.sqrt();
+                let y = if self.radius > top {
+                    half - (radius - top) / (self.radius - top) * self.height
+                } else {
+                    half - uniform::<T, dyn RngCore>(rng) * self.height
+                };
+                (radius, y)
+            },
+            Part::Base => (self.radius * uniform::<T, dyn RngCore>(rng).sqrt(), -half),
+            Part::Top => (top * uniform::<T, dyn RngCore>(rng).sqrt(), half)
+        };
+        let point = Vec3::from_array([radius * phi.sin(), y, radius * phi.cos()]);
+        let (normal, u, v) = self.surface_at(&point, part);
+        Some(Hit { point, normal, t: T::zero(), u, v, front_face: true, barycentric: None, face: None, footprint: None })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        Some(self.bounds.clone())
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Hitable<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Hitable<f32>>, PrecisionError> {
+        let cone: Cone<f32> = self.convert_precision()?;
+        Ok(Box::new(cone))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Hitable<f64>>, PrecisionError> {
+        let cone: Cone<f64> = self.convert_precision()?;
+        Ok(Box::new(cone))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Cone<T>
+    where T: Float, U: Precision
+{
+    type Output = Cone<U>;
+
+    fn convert_precision(&self) -> Result<Cone<U>, PrecisionError> {
+        let mut cone = Cone::new(convert(self.radius), convert(self.height));
+        cone.set_hollow(convert(self.hollow));
+        Ok(cone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn hit() {
+        let cone = Cone::<f64>::new(1.0, 2.0);
+        assert_eq!(cone.get_bounds().get_p0().get_data(), [-1.0, -1.0, -1.0]);
+
+        // Halfway up the side the radius is 0.5
+        let ray = Ray::from_array([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        let hit = cone.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(hit.point.get_data(), [-0.5, 0.0, 0.0]);
+        let expected = [-2.0 / 5.0f64.sqrt(), 1.0 / 5.0f64.sqrt(), 0.0];
+        for k in 0..3 {
+            assert!((hit.normal.get_data()[k] - expected[k]).abs() < 1e-12);
+        }
+        assert_eq!(cone.hit_intervals(&ray, &Interval::new(0.0, 100.0)), [Interval::new(4.5, 5.5)]);
+        // From inside only the way out is left
+        assert_eq!(cone.hit(&ray, 5.0, 100.0).unwrap().point.get_data(), [0.5, 0.0, 0.0]);
+
+        // Up through the base to the apex, and past the apex
+        let ray = Ray::from_array([0.0, -5.0, 0.0], [0.0, 1.0, 0.0]);
+        let hits = cone.hit_all(&ray, 0.0, 100.0);
+        assert_eq!(hits[0].point.get_data(), [0.0, -1.0, 0.0]);
+        assert_eq!(hits[0].normal.get_data(), [0.0, -1.0, 0.0]);
+        assert_eq!(hits.last().unwrap().point.get_data(), [0.0, 1.0, 0.0]);
+        let ray = Ray::from_array([-5.0, 1.5, 0.0], [1.0, 0.0, 0.0]);
+        assert!(cone.hit(&ray, 0.0, 100.0).is_none());
+        let ray = Ray::from_array([0.0, 5.0, 0.0], [1.0, 0.0, 0.0]);
+        assert!(cone.hit(&ray, 0.0, 100.0).is_none());
+    }
+
+    #[test]
+    fn hollow() {
+        let mut cone = Cone::<f64>::new(1.0, 2.0);
+        cone.set_hollow(0.5);
+        assert_eq!(cone.get_hollow(), 0.5);
+
+        // The top is a disc of radius 0.5
+        let ray = Ray::from_array([0.4, 5.0, 0.0], [0.0, -1.0, 0.0]);
+        let hit = cone.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(hit.point.get_data(), [0.4, 1.0, 0.0]);
+        assert_eq!(hit.normal.get_data(), [0.0, 1.0, 0.0]);
+        assert_eq!(cone.hit_all(&ray, 0.0, 100.0).len(), 2);
+        // Outside of it the side is hit
+        let ray = Ray::from_array([0.6, 5.0, 0.0], [0.0, -1.0, 0.0]);
+        let hit = cone.hit(&ray, 0.0, 100.0).unwrap();
+        assert!((hit.point.get_data()[1] - 0.6).abs() < 1e-12);
+
+        // A cylinder, also for rays parallel to the side
+        cone.set_hollow(2.0);
+        assert_eq!(cone.get_hollow(), 1.0);
+        let ray = Ray::from_array([-5.0, 0.9, 0.0], [1.0, 0.0, 0.0]);
+        assert_eq!(cone.hit(&ray, 0.0, 100.0).unwrap().point.get_data(), [-1.0, 0.9, 0.0]);
+        let ray = Ray::from_array([0.9, -5.0, 0.0], [0.0, 1.0, 0.0]);
+        assert_eq!(cone.hit_intervals(&ray, &Interval::new(0.0, 100.0)), [Interval::new(4.0, 6.0)]);
+        let ray = Ray::from_array([1.1, -5.0, 0.0], [0.0, 1.0, 0.0]);
+        assert!(cone.hit(&ray, 0.0, 100.0).is_none());
+        let pi = std::f64::consts::PI;
+        assert!((cone.surface_area() - (4.0 * pi + 2.0 * pi)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sample_surface() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for hollow in [0.0, 0.3, 1.0].iter() {
+            let mut cone = Cone::<f64>::new(1.0, 2.0);
+            cone.set_hollow(*hollow);
+            let (mut side, n) = (0, 10000);
+            for _i in 0..n {
+                let hit = cone.sample_surface(&mut rng).unwrap();
+                // On the surface, with the normal pointing out
+                let ray = Ray::from_vec(&hit.point + &hit.normal, &hit.normal * (-1.0));
+                let found = cone.hit(&ray, 0.0, 2.0).unwrap();
+                assert!((found.t - 1.0).abs() < 1e-9);
+                if hit.normal.get_data()[1].abs() < 1.0 {
+                    side += 1;
+                }
+            }
+            let expected = cone.part_areas()[0] / cone.surface_area();
+            assert!((side as f64 / n as f64 - expected).abs() < 0.02);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::float::Float;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
+use crate::interval::Interval;
+use crate::sampling;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
+
+// Homogeneous participating medium (i.e. fog or smoke) filling a closed
+// boundary. Rays are hit at random points inside, to be used together
+// with a material scattering in all directions (IsotropicMaterial).
+#[derive(Clone)]
+pub struct ConstantMedium<T>
+    where T: Float
+{
+    boundary: Box<dyn Hitable<T>>,
+    density: T
+}
+
+impl<T> ConstantMedium<T>
+    where T: Float
+{
+    pub fn new(boundary: Box<dyn Hitable<T>>, density: T) -> Self {
+        ConstantMedium {
+            boundary,
+            density
+        }
+    }
+
+    pub fn get_density(&self) -> T {
+        self.density
+    }
+}
+
+impl<T> Hitable<T> for ConstantMedium<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        // The density is per unit of length, t is in units of the direction
+        let density = self.density * ray.get_direction().norm();
+
+        // Woodcock (delta) tracking: fly exponentially distributed distances
+        // until a collision happens inside the boundary. With a constant
+        // density every collision is a real one.
+        for inside in self.boundary.hit_intervals(ray, &Interval::new(t_min, t_max)) {
+            // 1 - uniform is in (0, 1], which avoids ln(0). The generator of
+            // the thread is seeded from the path, see Scene::get_color.
+            let uniform = sampling::with_thread_rng(|rng| sampling::uniform::<T, _>(rng));
+            let flight = - (T::one() - uniform).ln() / density;
+            let t = inside.min + flight;
+            if !inside.contains(t) {
+                continue;
+            }
+            let point = ray.at(t);
+            // There's no surface, any normal facing the ray will do
+            let normal = ray.get_direction() * (-T::one());
+            return Some(Hit::with_face_normal(ray, point, normal, t, T::zero(), T::zero()));
+        }
+        None
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        self.boundary.get_bounds()
+    }
+
+    // Of the boundary, the medium itself has no surface
+    fn surface_area(&self) -> T {
+        self.boundary.surface_area()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        self.boundary.bounding_box()
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Hitable<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Hitable<f32>>, PrecisionError> {
+        let medium: ConstantMedium<f32> = self.convert_precision()?;
+        Ok(Box::new(medium))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Hitable<f64>>, PrecisionError> {
+        let medium: ConstantMedium<f64> = self.convert_precision()?;
+        Ok(Box::new(medium))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for ConstantMedium<T>
+    where T: Float, U: Precision
+{
+    type Output = ConstantMedium<U>;
+
+    fn convert_precision(&self) -> Result<ConstantMedium<U>, PrecisionError> {
+        Ok(ConstantMedium::new(self.boundary.convert_precision()?, convert(self.density)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::primitive::Sphere;
+
+    #[test]
+    fn free_flight() {
+        // The mean free path is 1 / density, whatever the length of the direction
+        let boundary = Box::new(Sphere::new(1000.0));
+        let medium = ConstantMedium::<f64>::new(boundary, 0.5);
+        let ray = Ray::from_array([-2000.0, 0.0, 0.0], [0.5, 0.0, 0.0]);
+        let n = 20000;
+        let mut sum = 0.0;
+        for _i in 0..n {
+            let hit = medium.hit(&ray, 0.0, 2200.0).unwrap();
+            assert!(hit.front_face);
+            assert!(hit.t >= 2000.0);
+            sum += hit.point.get_data()[0] + 1000.0;
+        }
+        assert!((sum / n as f64 - 2.0).abs() < 0.05);
+
+        // The chance of crossing a thin medium without collision is exp(-density * length)
+        let boundary = Box::new(Sphere::new(1.0));
+        let medium = ConstantMedium::<f64>::new(boundary, 0.5);
+        let ray = Ray::from_array([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        let crossed = (0..n).filter(|_| medium.hit(&ray, 0.0, 100.0).is_none()).count();
+        assert!((crossed as f64 / n as f64 - (-1.0f64).exp()).abs() < 0.02);
+        // Only the inside of the boundary is filled
+        let ray = Ray::from_array([-5.0, 2.0, 0.0], [1.0, 0.0, 0.0]);
+        assert!(medium.hit(&ray, 0.0, 100.0).is_none());
+    }
+}
@@ -1,3 +1,5 @@
+use rand::RngCore;
+
 use crate::float::Float;
 use crate::ray::Ray;
 use crate::hit::Hit;
@@ -6,15 +8,20 @@ use crate::hitable::primitive::Rectangle;
 use crate::hitable::transform::Translation;
 use crate::hitable::primitive::Group;
 use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
+use crate::interval::Interval;
 use crate::constants::Axis;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
 
+#[derive(Clone)]
 pub struct Cube<T>
     where T: Float
 {
     length: T,
     width: T,
     height: T,
-    faces: Group<T>
+    faces: Group<T>,
+    normals: [[T; 3]; 6]
 }
 
 impl<T> Cube<T>
@@ -39,13 +46,15 @@ impl<T> Cube<T>
             (length, height, width)
         ];
 
-        let half = T::from(0.5).unwrap();
+        let half = T::from_f64(0.5);
         let mut faces = Group::<T>::new();
+        let mut normals = [[T::zero(); 3]; 6];
 
         for i in 0..6 {
             let (width, height, depth) = lengths[i];
             let (width_axis, height_axis) = axes[i];
             let face = Box::new(Rectangle::<T>::new(width, width_axis, height, height_axis));
+            normals[i].copy_from_slice(face.get_normal().get_data());
             let translation = face.get_normal() * depth * half;
             let face : Box<dyn Hitable<T>> = Box::new(Translation::new(face, translation));
             faces.add_hitable(face);
@@ -55,9 +64,16 @@ impl<T> Cube<T>
             length,
             width,
             height,
-            faces
+            faces,
+            normals
         }
     }
+
+    // Outward normal of each face, in the order the faces were added:
+    // +z, -z, +x, -x, +y, -y
+    pub fn face_normals(&self) -> [[T; 3]; 6] {
+        self.normals
+    }
 }
 
 impl<T> Hitable<T> for Cube<T>
@@ -67,18 +83,65 @@ impl<T> Hitable<T> for Cube<T>
         self.faces.hit(ray, t_min, t_max)
     }
 
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        self.faces.hit_all(ray, t_min, t_max)
+    }
+
+    fn hit_intervals(&self, ray: &Ray<T>, interval: &Interval<T>) -> Vec<Interval<T>> {
+        // The cube is its own bounding box
+        self.get_bounds().hit_interval(ray, interval).into_iter().collect()
+    }
+
     fn get_bounds(&self) -> &BoundingBox<T> {
         self.faces.get_bounds()
     }
 
+    fn surface_area(&self) -> T {
+        let two = T::from_f64(2.0);
+        two * (self.length * self.width + self.length * self.height + self.width * self.height)
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<Hit<T>> {
+        self.faces.sample_surface(rng)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        self.faces.bounding_box()
+    }
+
     fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
         self
     }
+
+    fn clone_box(&self) -> Box<dyn Hitable<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Hitable<f32>>, PrecisionError> {
+        let cube: Cube<f32> = self.convert_precision()?;
+        Ok(Box::new(cube))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Hitable<f64>>, PrecisionError> {
+        let cube: Cube<f64> = self.convert_precision()?;
+        Ok(Box::new(cube))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Cube<T>
+    where T: Float, U: Precision
+{
+    type Output = Cube<U>;
+
+    fn convert_precision(&self) -> Result<Cube<U>, PrecisionError> {
+        Ok(Cube::new(convert(self.length), convert(self.width), convert(self.height)))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::vector::Vec3;
 
     #[test]
     fn init() {
@@ -214,6 +277,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn face_normals() {
+        let half = [1.0, 2.0, 3.0];
+        let cube = Cube::<f64>::new(2.0 * half[0], 2.0 * half[1], 2.0 * half[2]);
+        let normals = cube.face_normals();
+        assert_eq!(normals, [
+            [0.0, 0.0, 1.0], [0.0, 0.0, -1.0],
+            [1.0, 0.0, 0.0], [-1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0], [0.0, -1.0, 0.0]
+        ]);
+
+        // A ray towards the middle of each face, from outside and from inside
+        for normal in normals.iter() {
+            let normal = Vec3::from_array(*normal);
+            let face: f64 = (Vec3::from_array(half) * &normal).abs().get_data().iter().sum();
+            let origin = &normal * 10.0;
+            let ray = Ray::from_array(origin.to_array(), (&normal * -1.0).to_array());
+            let hit = cube.hit(&ray, 0.0, 100.0).unwrap();
+            assert_eq!(hit.normal.get_data(), normal.get_data());
+            assert_eq!(hit.point.get_data(), (&normal * face).get_data());
+            assert_eq!(hit.t, 10.0 - face);
+            assert!(hit.front_face);
+
+            let ray = Ray::from_array([0.0; 3], normal.to_array());
+            let hit = cube.hit(&ray, 0.0, 100.0).unwrap();
+            assert_eq!(hit.point.get_data(), (&normal * face).get_data());
+            assert!(!hit.front_face);
+        }
+    }
+
     #[test]
     fn bounds() {
         let length = 2.0;
@@ -224,4 +317,37 @@ mod tests {
         assert_eq!(bounds.get_p0().get_data(), [-1.0, -2.0, -3.0]);
         assert_eq!(bounds.get_p1().get_data(), [1.0, 2.0, 3.0]);
     }
+
+    #[test]
+    fn surface_area() {
+        let cube = Cube::<f64>::new(2.0, 4.0, 6.0);
+        assert_eq!(cube.surface_area(), 88.0);
+        // The same as the sum of the faces
+        assert_eq!(cube.faces.surface_area(), 88.0);
+    }
+
+    #[test]
+    fn intervals() {
+        let cube = Cube::<f64>::new(2.0, 4.0, 6.0);
+        let ray = Ray::from_array([0.0, 0.0, -10.0], [0.0, 0.0, 1.0]);
+        let intervals = cube.hit_intervals(&ray, &Interval::new(0.0, 100.0));
+        assert_eq!(intervals.len(), 1);
+        let size = intervals[0].size();
+        assert!(size == 2.0 || size == 4.0 || size == 6.0);
+        let ray = Ray::from_array([10.0, 10.0, -10.0], [0.0, 0.0, 1.0]);
+        assert!(cube.hit_intervals(&ray, &Interval::new(0.0, 100.0)).is_empty());
+    }
+
+    #[test]
+    fn hit_all() {
+        let cube: Box<dyn Hitable<f64>> = Box::new(Cube::new(2.0, 2.0, 2.0));
+        let cube = Translation::new(cube, Vec3::from_array([0.0, 0.0, 5.0]));
+        let ray = Ray::from_array([0.5, 0.5, -10.0], [0.0, 0.0, 1.0]);
+        let hits = cube.hit_all(&ray, 0.0, 100.0);
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[0].t, hits[1].t), (14.0, 16.0));
+        assert_eq!(hits[0].point.get_data(), [0.5, 0.5, 4.0]);
+        assert_eq!(hits[1].point.get_data(), [0.5, 0.5, 6.0]);
+        assert!(hits[0].front_face && !hits[1].front_face);
+    }
 }
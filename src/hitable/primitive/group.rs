@@ -1,10 +1,20 @@
+use std::cmp::Ordering;
+
+use rand::RngCore;
+
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::hit::Hit;
 use crate::hitable::Hitable;
 use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
+use crate::interval::Interval;
+use crate::tree::TraversalCounts;
+use crate::sampling::uniform;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError};
 
+#[derive(Clone)]
 pub struct Group<T>
     where T: Float
 {
@@ -26,6 +36,31 @@ impl<T> Group<T>
         self.bounds.expand(hitable.get_bounds());
         self.hitables.push(hitable);
     }
+
+    pub fn len(&self) -> usize {
+        self.hitables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hitables.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Box<dyn Hitable<T>>> {
+        self.hitables.iter()
+    }
+
+    // Call update_bounds after moving the children
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Box<dyn Hitable<T>>> {
+        self.hitables.iter_mut()
+    }
+
+    pub fn update_bounds(&mut self) {
+        let mut bounds = BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new());
+        for hitable in self.hitables.iter() {
+            bounds.expand(hitable.get_bounds());
+        }
+        self.bounds = bounds;
+    }
 }
 
 impl<T> Hitable<T> for Group<T>
@@ -44,11 +79,179 @@ impl<T> Hitable<T> for Group<T>
         result
     }
 
+    fn hit_counted(&self, ray: &Ray<T>, interval: &Interval<T>, counts: &mut TraversalCounts) -> Option<Hit<T>> {
+        let mut interval = *interval;
+        let mut result : Option<Hit<T>> = None;
+
+        for hitable in self.hitables.iter() {
+            if let Some(hit) = hitable.hit_counted(ray, &interval, counts) {
+                interval.max = hit.t;
+                result = Some(hit);
+            }
+        }
+        result
+    }
+
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        let mut hits = vec!();
+        for hitable in self.hitables.iter() {
+            hits.extend(hitable.hit_all(ray, t_min, t_max));
+        }
+        hits.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(Ordering::Equal));
+        hits
+    }
+
+    // The union of the members, overlapping intervals are merged
+    fn hit_intervals(&self, ray: &Ray<T>, interval: &Interval<T>) -> Vec<Interval<T>> {
+        let mut intervals = vec!();
+        for hitable in self.hitables.iter() {
+            intervals.extend(hitable.hit_intervals(ray, interval));
+        }
+        intervals.sort_by(|a, b| a.min.partial_cmp(&b.min).unwrap_or(Ordering::Equal));
+
+        let mut merged: Vec<Interval<T>> = vec!();
+        for current in intervals {
+            match merged.last_mut() {
+                Some(last) if current.min <= last.max => {
+                    if current.max > last.max {
+                        last.max = current.max;
+                    }
+                },
+                _ => merged.push(current)
+            }
+        }
+        merged
+    }
+
     fn get_bounds(&self) -> &BoundingBox<T> {
         &self.bounds
     }
 
+    fn surface_area(&self) -> T {
+        self.hitables.iter().fold(T::zero(), |area, hitable| area + hitable.surface_area())
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<Hit<T>> {
+        // Pick a member proportionally to its area, to be uniform over all of them
+        let mut area = uniform::<T, dyn RngCore>(rng) * self.surface_area();
+        let mut picked = self.hitables.last()?;
+        for hitable in self.hitables.iter() {
+            area = area - hitable.surface_area();
+            if area < T::zero() {
+                picked = hitable;
+                break;
+            }
+        }
+        picked.sample_surface(rng)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        // Unbounded if any of the members is
+        let mut result: Option<Aabb<T>> = None;
+        for hitable in self.hitables.iter() {
+            let bounds = hitable.bounding_box()?;
+            result = match result {
+                Some(result) => Some(result.union(&bounds)),
+                None => Some(bounds)
+            };
+        }
+        Some(result.unwrap_or_else(|| self.bounds.clone()))
+    }
+
     fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
         self
     }
+
+    fn clone_box(&self) -> Box<dyn Hitable<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Hitable<f32>>, PrecisionError> {
+        let group: Group<f32> = self.convert_precision()?;
+        Ok(Box::new(group))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Hitable<f64>>, PrecisionError> {
+        let group: Group<f64> = self.convert_precision()?;
+        Ok(Box::new(group))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Group<T>
+    where T: Float, U: Precision
+{
+    type Output = Group<U>;
+
+    fn convert_precision(&self) -> Result<Group<U>, PrecisionError> {
+        let mut group = Group::<U>::new();
+        for hitable in self.hitables.iter() {
+            group.add_hitable(hitable.convert_precision()?);
+        }
+        Ok(group)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::primitive::{Sphere, Rectangle};
+    use crate::hitable::transform::Translation;
+    use crate::constants::Axis;
+
+    fn sphere_at(x: f64, radius: f64) -> Box<dyn Hitable<f64>> {
+        let sphere: Box<dyn Hitable<f64>> = Box::new(Sphere::new(radius));
+        Box::new(Translation::new(sphere, Vec3::from_array([x, 0.0, 0.0])))
+    }
+
+    #[test]
+    fn intervals() {
+        let ray = Ray::from_array([-10.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        let interval = Interval::new(0.0, 100.0);
+
+        let mut group = Group::new();
+        group.add_hitable(sphere_at(4.0, 1.0));
+        group.add_hitable(sphere_at(-4.0, 1.0));
+        // Open surfaces have no inside
+        group.add_hitable(Box::new(Rectangle::new(5.0, Axis::Y, 5.0, Axis::Z)));
+        assert_eq!(group.hit_intervals(&ray, &interval), [Interval::new(5.0, 7.0), Interval::new(13.0, 15.0)]);
+
+        let mut group = Group::new();
+        group.add_hitable(sphere_at(1.0, 2.0));
+        group.add_hitable(sphere_at(-1.0, 2.0));
+        group.add_hitable(sphere_at(0.0, 1.0));
+        assert_eq!(group.hit_intervals(&ray, &interval), [Interval::new(7.0, 13.0)]);
+    }
+
+    #[test]
+    fn children() {
+        let mut group = Group::new();
+        assert!(group.is_empty());
+        group.add_hitable(sphere_at(4.0, 1.0));
+        group.add_hitable(sphere_at(-4.0, 1.0));
+        assert_eq!(group.len(), 2);
+        assert!(!group.is_empty());
+        let xs: Vec<f64> = group.iter().map(|hitable| hitable.get_bounds().centroid().get_data()[0]).collect();
+        assert_eq!(xs, [4.0, -4.0]);
+
+        for hitable in group.iter_mut() {
+            assert!(hitable.translate(&Vec3::from_array([0.0, 0.0, 10.0])));
+        }
+        group.update_bounds();
+        assert_eq!(group.get_bounds().get_p1().get_data()[2], 11.0);
+    }
+
+    #[test]
+    fn sample_surface() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        assert!(Group::<f64>::new().sample_surface(&mut rng).is_none());
+
+        // Three times as many points on the sphere with three times the area
+        let mut group = Group::new();
+        group.add_hitable(sphere_at(4.0, 1.0));
+        group.add_hitable(sphere_at(-4.0, 3.0_f64.sqrt()));
+        let n = 4000;
+        let right = (0..n).filter(|_| group.sample_surface(&mut rng).unwrap().point.get_data()[0] > 0.0).count();
+        assert!((right as f64 / n as f64 - 0.25).abs() < 0.03);
+    }
 }
@@ -0,0 +1,781 @@
+use rand::RngCore;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
+use crate::interval::Interval;
+use crate::tree::TraversalCounts;
+use crate::sampling::uniform;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
+
+// Triangles sharing a list of vertices. The vertices of a triangle go
+// counter clockwise seen from the outside. With vertex normals the shading
+// normal is interpolated across the triangles, which makes a coarse mesh
+// look smooth, otherwise each triangle is flat. The texture coordinates
+// are interpolated the same way, from UVs given per vertex or per corner of
+// each triangle.
+//
+// The triangles are found through a bounding volume hierarchy of their own,
+// the trees of the scene only see the mesh as a whole.
+#[derive(Clone)]
+pub struct TriangleMesh<T>
+    where T: Float
+{
+    vertices: Vec<[T; 3]>,
+    triangles: Vec<[usize; 3]>,
+    normals: Option<Vec<[T; 3]>>,
+    uvs: Vec<[T; 2]>,
+    // For each triangle the indices of the UVs of its corners, None for
+    // triangles without UVs. Empty if the mesh has no UVs at all.
+    uv_triangles: Vec<Option<[usize; 3]>>,
+    // Running sum of the areas of the triangles, to sample the surface
+    areas: Vec<T>,
+    bounds: BoundingBox<T>,
+    nodes: Vec<MeshNode<T>>,
+    // The triangles in the order of the leaves of the hierarchy
+    order: Vec<usize>,
+    use_bvh: bool
+}
+
+// At most LEAF_SIZE triangles per leaf of the hierarchy
+const LEAF_SIZE: usize = 4;
+
+#[derive(Clone)]
+struct MeshNode<T>
+    where T: Float
+{
+    bounds: Aabb<T>,
+    // Leaves hold order[start..start + count], inner nodes have no triangles
+    // and their children at start and start + 1
+    start: usize,
+    count: usize
+}
+
+impl<T> TriangleMesh<T>
+    where T: Float
+{
+    pub fn new(vertices: Vec<[T; 3]>, triangles: Vec<[usize; 3]>) -> Self {
+        assert!(triangles.iter().flatten().all(|index| *index < vertices.len()), "triangle vertex out of range");
+
+        let mut p0 = [T::infinity(); 3];
+        let mut p1 = [T::neg_infinity(); 3];
+        for vertex in triangles.iter().flatten().map(|index| &vertices[*index]) {
+            for c in 0..3 {
+                p0[c] = p0[c].min(vertex[c]);
+                p1[c] = p1[c].max(vertex[c]);
+            }
+        }
+        if triangles.is_empty() {
+            p0 = [T::zero(); 3];
+            p1 = [T::zero(); 3];
+        }
+
+        let mut mesh = TriangleMesh {
+            vertices,
+            triangles,
+            normals: None,
+            uvs: Vec::new(),
+            uv_triangles: Vec::new(),
+            areas: Vec::new(),
+            bounds: BoundingBox::new(Vec3::from_array(p0), Vec3::from_array(p1)),
+            nodes: Vec::new(),
+            order: Vec::new(),
+            use_bvh: true
+        };
+        let half = T::from_f64(0.5);
+        let mut sum = T::zero();
+        for k in 0..mesh.triangles.len() {
+            sum = sum + mesh.cross(k).norm() * half;
+            mesh.areas.push(sum);
+        }
+        mesh.build_bvh();
+        mesh
+    }
+
+    // Splits the triangles at the median of their centroids along the axis
+    // the centroids spread the most, until the leaves are small enough
+    fn build_bvh(&mut self) {
+        let boxes: Vec<Aabb<T>> = (0..self.triangles.len()).map(|k| {
+            let mut bounds = BoundingBox::new(self.vertex(k, 0), self.vertex(k, 0));
+            bounds.expand(&BoundingBox::new(self.vertex(k, 1), self.vertex(k, 1)));
+            bounds.expand(&BoundingBox::new(self.vertex(k, 2), self.vertex(k, 2)));
+            bounds
+        }).collect();
+        let centroids: Vec<Vec3<T>> = boxes.iter().map(|bounds| bounds.centroid()).collect();
+        self.order = (0..self.triangles.len()).collect();
+        self.nodes.clear();
+        if self.triangles.is_empty() {
+            return;
+        }
+
+        // Nodes still to split: their index and their range of order
+        self.nodes.push(MeshNode { bounds: self.bounds.clone(), start: 0, count: self.order.len() });
+        let mut pending = vec![0];
+        while let Some(index) = pending.pop() {
+            let (start, count) = (self.nodes[index].start, self.nodes[index].count);
+            let triangles = &mut self.order[start..start + count];
+            let mut bounds = boxes[triangles[0]].clone();
+            let mut spread = BoundingBox::new(Vec3::from_slice(centroids[triangles[0]].get_data()), Vec3::from_slice(centroids[triangles[0]].get_data()));
+            for k in triangles.iter() {
+                bounds.expand(&boxes[*k]);
+                spread.expand(&BoundingBox::new(Vec3::from_slice(centroids[*k].get_data()), Vec3::from_slice(centroids[*k].get_data())));
+            }
+            self.nodes[index].bounds = bounds;
+            if count <= LEAF_SIZE {
+                continue;
+            }
+
+            let axis = (0..3).fold(0, |best, axis| if spread.get_axis_length(axis) > spread.get_axis_length(best) { axis } else { best });
+            let middle = count / 2;
+            triangles.select_nth_unstable_by(middle, |a, b| {
+                let (a, b) = (centroids[*a].get_data()[axis], centroids[*b].get_data()[axis]);
+                a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+            });
+            let children = self.nodes.len();
+            self.nodes.push(MeshNode { bounds: BoundingBox::new(Vec3::new(), Vec3::new()), start, count: middle });
+            self.nodes.push(MeshNode { bounds: BoundingBox::new(Vec3::new(), Vec3::new()), start: start + middle, count: count - middle });
+            self.nodes[index].start = children;
+            self.nodes[index].count = 0;
+            pending.push(children);
+            pending.push(children + 1);
+        }
+    }
+
+    // Sphere made of an icosahedron with each triangle split into four
+    // subdivisions times, 20 * 4^subdivisions triangles with smooth normals
+    pub fn icosphere(radius: T, subdivisions: usize) -> Self {
+        let phi = (1.0 + 5.0f64.sqrt()) / 2.0;
+        let mut points: Vec<[f64; 3]> = vec![
+            [-1.0, phi, 0.0], [1.0, phi, 0.0], [-1.0, -phi, 0.0], [1.0, -phi, 0.0],
+            [0.0, -1.0, phi], [0.0, 1.0, phi], [0.0, -1.0, -phi], [0.0, 1.0, -phi],
+            [phi, 0.0, -1.0], [phi, 0.0, 1.0], [-phi, 0.0, -1.0], [-phi, 0.0, 1.0]
+        ];
+        let mut triangles = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1]
+        ];
+
+        for _ in 0..subdivisions {
+            // The middle of each edge, shared by the two triangles along it
+            let mut middles = std::collections::HashMap::new();
+            let mut middle = |a: usize, b: usize, points: &mut Vec<[f64; 3]>| {
+                *middles.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    let (p, q) = (points[a], points[b]);
+                    points.push([(p[0] + q[0]) / 2.0, (p[1] + q[1]) / 2.0, (p[2] + q[2]) / 2.0]);
+                    points.len() - 1
+                })
+            };
+            let mut split = Vec::with_capacity(4 * triangles.len());
+            for [a, b, c] in triangles {
+                let ab = middle(a, b, &mut points);
+                let bc = middle(b, c, &mut points);
+                let ca = middle(c, a, &mut points);
+                split.extend_from_slice(&[[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+            }
+            triangles = split;
+        }
+
+        let normals: Vec<[T; 3]> = points.iter().map(|p| {
+            let length = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+            [T::from_f64(p[0] / length), T::from_f64(p[1] / length), T::from_f64(p[2] / length)]
+        }).collect();
+        let vertices = normals.iter().map(|n| [n[0] * radius, n[1] * radius, n[2] * radius]).collect();
+        let mut mesh = TriangleMesh::new(vertices, triangles);
+        mesh.set_normals(normals);
+        mesh
+    }
+
+    // With the hierarchy (the default) or testing every triangle
+    pub fn uses_bvh(&self) -> bool {
+        self.use_bvh
+    }
+
+    pub fn set_use_bvh(&mut self, use_bvh: bool) {
+        self.use_bvh = use_bvh;
+    }
+
+    pub fn get_vertices(&self) -> &[[T; 3]] {
+        &self.vertices
+    }
+
+    pub fn get_triangles(&self) -> &[[usize; 3]] {
+        &self.triangles
+    }
+
+    pub fn get_normals(&self) -> Option<&[[T; 3]]> {
+        self.normals.as_deref()
+    }
+
+    // One normal per vertex, normalized here
+    pub fn set_normals(&mut self, normals: Vec<[T; 3]>) {
+        assert_eq!(normals.len(), self.vertices.len(), "one normal per vertex");
+        self.normals = Some(normals.iter().map(|normal| {
+            let mut normal = Vec3::from_slice(normal);
+            normal.normalize();
+            normal.to_array()
+        }).collect());
+    }
+
+    // Back to flat triangles
+    pub fn clear_normals(&mut self) {
+        self.normals = None;
+    }
+
+    // Vertex normals averaged from the triangles around each vertex,
+    // weighted by their area so slivers don't tilt them
+    pub fn compute_smooth_normals(&mut self) {
+        let mut sums: Vec<Vec3<T>> = (0..self.vertices.len()).map(|_| Vec3::new()).collect();
+        for (k, triangle) in self.triangles.iter().enumerate() {
+            // The cross product is twice the area long
+            let cross = self.cross(k);
+            for index in triangle.iter() {
+                sums[*index] = &sums[*index] + &cross;
+            }
+        }
+        self.normals = Some(sums.iter().map(|sum| {
+            if sum.is_near_zero(T::zero()) {
+                // Unused vertex
+                return [T::zero(); 3];
+            }
+            let mut normal = Vec3::from_slice(sum.get_data());
+            normal.normalize();
+            normal.to_array()
+        }).collect());
+    }
+
+    // One UV per vertex, shared by the triangles around it
+    pub fn set_uvs(&mut self, uvs: Vec<[T; 2]>) {
+        assert_eq!(uvs.len(), self.vertices.len(), "one UV per vertex");
+        let uv_triangles = self.triangles.iter().map(|triangle| Some(*triangle)).collect();
+        self.set_corner_uvs(uvs, uv_triangles);
+    }
+
+    // UVs indexed separately for each triangle, like the vertices, i.e. for
+    // seams where a vertex has a different UV on either side. Triangles with
+    // None get the UV (0, 0).
+    pub fn set_corner_uvs(&mut self, uvs: Vec<[T; 2]>, uv_triangles: Vec<Option<[usize; 3]>>) {
+        assert_eq!(uv_triangles.len(), self.triangles.len(), "one entry per triangle");
+        assert!(uv_triangles.iter().flatten().flatten().all(|index| *index < uvs.len()), "triangle UV out of range");
+        self.uvs = uvs;
+        self.uv_triangles = uv_triangles;
+    }
+
+    pub fn get_uvs(&self) -> &[[T; 2]] {
+        &self.uvs
+    }
+
+    pub fn get_uv_triangles(&self) -> &[Option<[usize; 3]>] {
+        &self.uv_triangles
+    }
+
+    fn uv(&self, k: usize, b1: T, b2: T) -> (T, T) {
+        match self.uv_triangles.get(k) {
+            Some(Some([i0, i1, i2])) => {
+                let b0 = T::one() - b1 - b2;
+                let (uv0, uv1, uv2) = (self.uvs[*i0], self.uvs[*i1], self.uvs[*i2]);
+                (b0 * uv0[0] + b1 * uv1[0] + b2 * uv2[0], b0 * uv0[1] + b1 * uv1[1] + b2 * uv2[1])
+            }
+            _ => (T::zero(), T::zero())
+        }
+    }
+
+    // How many UV units the triangle has per unit of length, from the ratio
+    // of its area in UV space to its area. None without UVs.
+    fn uv_per_length(&self, k: usize) -> Option<T> {
+        match self.uv_triangles.get(k) {
+            Some(Some([i0, i1, i2])) => {
+                let (uv0, uv1, uv2) = (self.uvs[*i0], self.uvs[*i1], self.uvs[*i2]);
+                let (e1, e2) = ([uv1[0] - uv0[0], uv1[1] - uv0[1]], [uv2[0] - uv0[0], uv2[1] - uv0[1]]);
+                let uv_area = (e1[0] * e2[1] - e1[1] * e2[0]).abs();
+                let area = self.cross(k).norm();
+                if area > T::zero() { Some((uv_area / area).sqrt()) } else { None }
+            }
+            _ => None
+        }
+    }
+
+    fn vertex(&self, k: usize, i: usize) -> Vec3<T> {
+        Vec3::from_slice(&self.vertices[self.triangles[k][i]])
+    }
+
+    fn cross(&self, k: usize) -> Vec3<T> {
+        let p0 = self.vertex(k, 0);
+        let e1 = &self.vertex(k, 1) - &p0;
+        let e2 = &self.vertex(k, 2) - &p0;
+        e1.cross(&e2)
+    }
+
+    // Möller-Trumbore: the distance and the barycentric coordinates of the
+    // second and the third vertex
+    fn intersect(&self, k: usize, ray: &Ray<T>) -> Option<(T, T, T)> {
+        let p0 = self.vertex(k, 0);
+        let e1 = &self.vertex(k, 1) - &p0;
+        let e2 = &self.vertex(k, 2) - &p0;
+        let direction = ray.get_direction();
+        let p = direction.cross(&e2);
+        let determinant = e1.dot(&p);
+        if determinant == T::zero() || !determinant.is_finite() {
+            return None;
+        }
+        let inverse = T::one() / determinant;
+        let s = ray.get_origin() - &p0;
+        let b1 = s.dot(&p) * inverse;
+        if b1 < T::zero() || b1 > T::one() {
+            return None;
+        }
+        let q = s.cross(&e1);
+        let b2 = direction.dot(&q) * inverse;
+        if b2 < T::zero() || b1 + b2 > T::one() {
+            return None;
+        }
+        Some((e2.dot(&q) * inverse, b1, b2))
+    }
+
+    fn face_hit(&self, k: usize, ray: &Ray<T>, t: T, b1: T, b2: T) -> Hit<T> {
+        let mut geometric = self.cross(k);
+        geometric.normalize();
+        let normal = self.shading_normal(k, b1, b2, &geometric);
+        // Which side was hit depends on the geometric normal only
+        let front_face = ray.get_direction().dot(&geometric) <= T::zero();
+        let normal = if front_face { normal } else { normal * (-T::one()) };
+        let (u, v) = self.uv(k, b1, b2);
+        let barycentric = Some([T::one() - b1 - b2, b1, b2]);
+        let mut hit = Hit { point: ray.at(t), normal, t, u, v, front_face, barycentric, face: Some(k), footprint: None };
+        if let Some(uv_per_length) = self.uv_per_length(k) {
+            hit.set_footprint(ray, uv_per_length);
+        }
+        hit
+    }
+
+    // The hit of a single triangle with t_min <= t <= t_max, i.e. for a
+    // tree that found the triangle itself
+    pub fn hit_triangle(&self, k: usize, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let (t, b1, b2) = self.intersect(k, ray)?;
+        if t < t_min || t > t_max {
+            return None;
+        }
+        Some(self.face_hit(k, ray, t, b1, b2))
+    }
+
+    // The closest hit, adding the triangles tested to tests
+    fn closest_hit(&self, ray: &Ray<T>, t_min: T, t_max: T, tests: &mut u64) -> Option<Hit<T>> {
+        let mut closest: Option<(usize, T, T, T)> = None;
+        let mut t_max = t_max;
+        // Of two triangles at the same distance the first one wins, so the
+        // order of the tests doesn't change the hit
+        let mut test = |k: usize, t_max: &mut T, closest: &mut Option<(usize, T, T, T)>| {
+            *tests += 1;
+            if let Some((t, b1, b2)) = self.intersect(k, ray) {
+                let nearer = match closest {
+                    Some((j, t_closest, _, _)) => t < *t_closest || (t == *t_closest && k < *j),
+                    None => true
+                };
+                if t >= t_min && t <= *t_max && nearer {
+                    *t_max = t;
+                    *closest = Some((k, t, b1, b2));
+                }
+            }
+        };
+
+        if self.use_bvh {
+            let mut stack = if self.nodes.is_empty() { vec![] } else { vec![0] };
+            while let Some(index) = stack.pop() {
+                let node = &self.nodes[index];
+                if node.bounds.hit_interval(ray, &Interval::new(t_min, t_max)).is_none() {
+                    continue;
+                }
+                if node.count > 0 {
+                    for k in self.order[node.start..node.start + node.count].iter() {
+                        test(*k, &mut t_max, &mut closest);
+                    }
+                } else {
+                    stack.push(node.start + 1);
+                    stack.push(node.start);
+                }
+            }
+        } else {
+            for k in 0..self.triangles.len() {
+                test(k, &mut t_max, &mut closest);
+            }
+        }
+
+        let (k, t, b1, b2) = closest?;
+        Some(self.face_hit(k, ray, t, b1, b2))
+    }
+
+    // The interpolated vertex normal, on the same side as the geometric one
+    fn shading_normal(&self, k: usize, b1: T, b2: T, geometric: &Vec3<T>) -> Vec3<T> {
+        let normals = match &self.normals {
+            Some(normals) => normals,
+            None => return Vec3::from_slice(geometric.get_data())
+        };
+        let [i0, i1, i2] = self.triangles[k];
+        let b0 = T::one() - b1 - b2;
+        let mut normal = Vec3::from_slice(&normals[i0]) * b0
+            + Vec3::from_slice(&normals[i1]) * b1
+            + Vec3::from_slice(&normals[i2]) * b2;
+        if normal.is_near_zero(T::zero()) || !normal.norm().is_finite() {
+            return Vec3::from_slice(geometric.get_data());
+        }
+        normal.normalize();
+        if normal.dot(geometric) < T::zero() {
+            normal = normal * (-T::one());
+        }
+        normal
+    }
+}
+
+impl<T> Hitable<T> for TriangleMesh<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        self.closest_hit(ray, t_min, t_max, &mut 0)
+    }
+
+    fn hit_counted(&self, ray: &Ray<T>, interval: &Interval<T>, counts: &mut TraversalCounts) -> Option<Hit<T>> {
+        self.closest_hit(ray, interval.min, interval.max, &mut counts.triangle_tests)
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn surface_area(&self) -> T {
+        self.areas.last().copied().unwrap_or_else(T::zero)
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<Hit<T>> {
+        let area = self.surface_area();
+        if area <= T::zero() {
+            return None;
+        }
+        // The triangle with a probability proportional to its area
+        let target = uniform::<T, dyn RngCore>(rng) * area;
+        let k = self.areas.partition_point(|sum| *sum < target).min(self.triangles.len() - 1);
+
+        let r1 = uniform::<T, dyn RngCore>(rng).sqrt();
+        let r2 = uniform::<T, dyn RngCore>(rng);
+        let (b1, b2) = (r1 * (T::one() - r2), r1 * r2);
+        let p0 = self.vertex(k, 0);
+        let point = &p0 + &(&self.vertex(k, 1) - &p0) * b1 + &(&self.vertex(k, 2) - &p0) * b2;
+        let mut normal = self.cross(k);
+        normal.normalize();
+        let (u, v) = self.uv(k, b1, b2);
+        let barycentric = Some([T::one() - b1 - b2, b1, b2]);
+        Some(Hit { point, normal, t: T::zero(), u, v, front_face: true, barycentric, face: Some(k), footprint: None })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        Some(self.bounds.clone())
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Hitable<T>> {
+        Box::new(self.clone())
+    }
+
+    fn translate(&mut self, offset: &Vec3<T>) -> bool {
+        let offset = offset.get_data();
+        for vertex in self.vertices.iter_mut() {
+            for (x, dx) in vertex.iter_mut().zip(offset.iter()) {
+                *x = *x + *dx;
+            }
+        }
+        let offset = Vec3::from_slice(offset);
+        let moved = |bounds: &Aabb<T>| BoundingBox::new(bounds.get_p0() + &offset, bounds.get_p1() + &offset);
+        self.bounds = moved(&self.bounds);
+        for node in self.nodes.iter_mut() {
+            node.bounds = moved(&node.bounds);
+        }
+        true
+    }
+
+    fn get_mesh(&self) -> Option<&TriangleMesh<T>> {
+        Some(self)
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Hitable<f32>>, PrecisionError> {
+        let mesh: TriangleMesh<f32> = self.convert_precision()?;
+        Ok(Box::new(mesh))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Hitable<f64>>, PrecisionError> {
+        let mesh: TriangleMesh<f64> = self.convert_precision()?;
+        Ok(Box::new(mesh))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for TriangleMesh<T>
+    where T: Float, U: Precision
+{
+    type Output = TriangleMesh<U>;
+
+    fn convert_precision(&self) -> Result<TriangleMesh<U>, PrecisionError> {
+        let convert_all = |points: &[[T; 3]]| -> Vec<[U; 3]> {
+            points.iter().map(|p| [convert(p[0]), convert(p[1]), convert(p[2])]).collect()
+        };
+        let mut mesh = TriangleMesh::new(convert_all(&self.vertices), self.triangles.clone());
+        mesh.normals = self.normals.as_deref().map(convert_all);
+        mesh.uvs = self.uvs.iter().map(|uv| [convert(uv[0]), convert(uv[1])]).collect();
+        mesh.uv_triangles = self.uv_triangles.clone();
+        mesh.use_bvh = self.use_bvh;
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::primitive::Sphere;
+    use crate::renderer::Image;
+    use crate::texture::Texture;
+
+    fn square() -> TriangleMesh<f64> {
+        let vertices = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+        TriangleMesh::new(vertices, vec![[0, 1, 2], [0, 2, 3]])
+    }
+
+    #[test]
+    fn hit() {
+        let mesh = square();
+        assert_eq!(mesh.surface_area(), 1.0);
+        assert_eq!(mesh.get_bounds().get_p1().get_data(), [1.0, 1.0, 0.0]);
+
+        let ray = Ray::from_array([0.25, 0.75, 2.0], [0.0, 0.0, -1.0]);
+        let hit = mesh.hit(&ray, 0.0, 10.0).unwrap();
+        assert_eq!(hit.t, 2.0);
+        assert!(hit.front_face);
+        assert_eq!(hit.normal.get_data(), [0.0, 0.0, 1.0]);
+
+        let ray = Ray::from_array([0.25, 0.75, -2.0], [0.0, 0.0, 1.0]);
+        let hit = mesh.hit(&ray, 0.0, 10.0).unwrap();
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal.get_data(), [0.0, 0.0, -1.0]);
+
+        assert!(mesh.hit(&ray, 0.0, 1.0).is_none());
+        assert!(mesh.hit(&Ray::from_array([1.5, 0.5, 2.0], [0.0, 0.0, -1.0]), 0.0, 10.0).is_none());
+        assert!(mesh.hit(&Ray::from_array([0.5, 0.5, 2.0], [1.0, 0.0, 0.0]), 0.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn barycentric() {
+        let mesh = square();
+        // The corner both triangles share, the first one wins
+        let hit = mesh.hit(&Ray::from_array([0.0, 0.0, 1.0], [0.0, 0.0, -1.0]), 0.0, 2.0).unwrap();
+        assert_eq!(hit.barycentric, Some([1.0, 0.0, 0.0]));
+        assert_eq!(hit.face, Some(0));
+
+        let third = 1.0 / 3.0;
+        let hit = mesh.hit(&Ray::from_array([third, 2.0 * third, 1.0], [0.0, 0.0, -1.0]), 0.0, 2.0).unwrap();
+        assert_eq!(hit.face, Some(1));
+        for weight in hit.barycentric.unwrap().iter() {
+            assert!((weight - third).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn barycentric_convention() {
+        // A single tilted triangle, hit from above its centroid and each
+        // vertex: the weights follow the order of the vertices
+        let vertices = vec![[1.0, 0.0, 0.0], [0.0, 2.0, 0.0], [0.0, 0.0, 3.0]];
+        let mesh = TriangleMesh::new(vertices.clone(), vec![[0, 1, 2]]);
+        let check = |target: [f64; 3], expected: [f64; 3]| {
+            let origin = [target[0] + 1.0, target[1] + 1.0, target[2] + 1.0];
+            let ray = Ray::from_array(origin, [-1.0, -1.0, -1.0]);
+            let hit = mesh.hit(&ray, 0.0, 10.0).unwrap();
+            assert_eq!(hit.face, Some(0));
+            let barycentric = hit.barycentric.unwrap();
+            for k in 0..3 {
+                assert!((barycentric[k] - expected[k]).abs() < 1e-12, "{:?} at {:?}", barycentric, target);
+            }
+        };
+
+        let third = 1.0 / 3.0;
+        check([third, 2.0 * third, 1.0], [third, third, third]);
+        check(vertices[0], [1.0, 0.0, 0.0]);
+        check(vertices[1], [0.0, 1.0, 0.0]);
+        check(vertices[2], [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn smooth_normals() {
+        // A roof of two triangles folded along x = 0
+        let vertices = vec![[-1.0, 0.0, -1.0], [0.0, 0.0, 1.0], [0.0, 1.0, 1.0], [-1.0, 1.0, -1.0], [1.0, 0.0, -1.0], [1.0, 1.0, -1.0]];
+        let mut mesh = TriangleMesh::new(vertices, vec![[0, 1, 2], [0, 2, 3], [1, 4, 2], [4, 5, 2]]);
+        let normal = |mesh: &TriangleMesh<f64>, x: f64| {
+            mesh.hit(&Ray::from_array([x, 0.5, 5.0], [0.0, 0.0, -1.0]), 0.0, 10.0).unwrap().normal
+        };
+        assert!(normal(&mesh, -0.01).get_data()[0] < -0.8);
+
+        mesh.compute_smooth_normals();
+        // Straight up along the ridge, the average of both sides
+        let ridge = &mesh.get_normals().unwrap()[1];
+        assert!((ridge[0]).abs() < 1e-12 && (ridge[2] - 1.0).abs() < 1e-12);
+        let n = normal(&mesh, -0.01);
+        assert!((n.norm() - 1.0).abs() < 1e-12);
+        assert!(n.get_data()[0] < 0.0 && n.get_data()[0] > -0.05);
+        assert!(normal(&mesh, -0.5).get_data()[0] < normal(&mesh, -0.01).get_data()[0]);
+
+        // Vertex normals pointing inward don't turn the normal around
+        mesh.set_normals(vec![[0.0, 0.0, -1.0]; 6]);
+        assert_eq!(normal(&mesh, -0.5).get_data(), [0.0, 0.0, 1.0]);
+        mesh.clear_normals();
+        assert!(mesh.get_normals().is_none());
+    }
+
+    #[test]
+    fn sample_surface() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let mesh = TriangleMesh::<f64>::icosphere(2.0, 1);
+        assert_eq!(mesh.get_triangles().len(), 80);
+        for _ in 0..100 {
+            let hit = mesh.sample_surface(&mut rng).unwrap();
+            let distance = hit.point.norm();
+            assert!(distance <= 2.0 + 1e-12 && distance > 1.5);
+            assert!(hit.normal.dot(&hit.point) > 0.0);
+        }
+    }
+
+    // Black and white squares, 2 x 2 over the unit square of UVs
+    #[derive(Clone)]
+    struct UvChecker {}
+
+    impl Texture<f64> for UvChecker {
+        fn get_color(&self, u: f64, v: f64, _point: &Vec3<f64>) -> Vec3<f64> {
+            let white = ((2.0 * u).floor() + (2.0 * v).floor()) as i64 % 2 == 0;
+            Vec3::from_array([if white { 1.0 } else { 0.0 }; 3])
+        }
+
+        fn clone_box(&self) -> Box<dyn Texture<f64>> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn uv() {
+        // The image of the texture, flipped left to right
+        let mut mesh = square();
+        mesh.set_uvs(vec![[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]]);
+        let size = 8;
+        for y in 0..size {
+            for x in 0..size {
+                let (px, py) = ((x as f64 + 0.5) / size as f64, (y as f64 + 0.5) / size as f64);
+                let hit = mesh.hit(&Ray::from_array([px, py, 1.0], [0.0, 0.0, -1.0]), 0.0, 2.0).unwrap();
+                assert!((hit.u - (1.0 - px)).abs() < 1e-12 && (hit.v - py).abs() < 1e-12);
+                let color = UvChecker {}.get_color(hit.u, hit.v, &hit.point);
+                let white = (x < size / 2) == (y < size / 2);
+                assert_eq!(color.get_data()[0], if white { 0.0 } else { 1.0 });
+            }
+        }
+
+        // A seam along the diagonal: the corners of the second triangle
+        // have their own UVs
+        mesh.set_corner_uvs(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.5, 0.5], [0.5, 0.5], [0.5, 0.5]], vec![Some([0, 1, 2]), Some([3, 4, 5])]);
+        let below = mesh.hit(&Ray::from_array([0.6, 0.4, 1.0], [0.0, 0.0, -1.0]), 0.0, 2.0).unwrap();
+        assert!((below.u - 0.6).abs() < 1e-12 && (below.v - 0.4).abs() < 1e-12);
+        let above = mesh.hit(&Ray::from_array([0.4, 0.6, 1.0], [0.0, 0.0, -1.0]), 0.0, 2.0).unwrap();
+        assert!((above.u - 0.5).abs() < 1e-12 && (above.v - 0.5).abs() < 1e-12);
+
+        // No UVs for the second triangle, or none at all
+        mesh.set_corner_uvs(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]], vec![Some([0, 1, 2]), None]);
+        let above = mesh.hit(&Ray::from_array([0.4, 0.6, 1.0], [0.0, 0.0, -1.0]), 0.0, 2.0).unwrap();
+        assert_eq!((above.u, above.v), (0.0, 0.0));
+        assert_eq!(mesh.get_uv_triangles().len(), 2);
+        let hit = square().hit(&Ray::from_array([0.6, 0.4, 1.0], [0.0, 0.0, -1.0]), 0.0, 2.0).unwrap();
+        assert_eq!((hit.u, hit.v), (0.0, 0.0));
+    }
+
+    // Through the pixel of an orthographic view from above
+    fn view_ray(x: usize, y: usize, size: usize) -> Ray<f64> {
+        let u = 2.4 * (x as f64 + 0.5) / size as f64 - 1.2;
+        let v = 2.4 * (y as f64 + 0.5) / size as f64 - 1.2;
+        Ray::from_array([u, v, 5.0], [0.0, 0.0, -1.0])
+    }
+
+    // Triangles tested for all the pixels of the view
+    fn count_tests(mesh: &TriangleMesh<f64>, size: usize) -> u64 {
+        let mut counts = TraversalCounts::default();
+        for y in 0..size {
+            for x in 0..size {
+                mesh.hit_counted(&view_ray(x, y, size), &Interval::new(0.0, 10.0), &mut counts);
+            }
+        }
+        counts.triangle_tests
+    }
+
+    // Normal shading of a hitable in orthographic view, NaN where it's missed
+    fn shade(hitable: &dyn Hitable<f64>, size: usize) -> Image<f64> {
+        let mut image = Image::new(size, size);
+        for y in 0..size {
+            for x in 0..size {
+                let color = match hitable.hit(&view_ray(x, y, size), 0.0, 10.0) {
+                    Some(hit) => {
+                        let light = Vec3::from_array([1.0, 1.0, 1.0]) * (1.0 / 3.0f64.sqrt());
+                        [hit.normal.dot(&light).max(0.0); 3]
+                    }
+                    None => [f64::NAN; 3]
+                };
+                image.set_pixel(x, y, color).unwrap();
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn icosphere() {
+        let size = 48;
+        let mut mesh = TriangleMesh::<f64>::icosphere(1.0, 2);
+        assert_eq!(mesh.get_triangles().len(), 320);
+        let sphere = shade(&Sphere::new(1.0), size);
+        // The mean difference inside both silhouettes
+        let difference = |image: &Image<f64>| {
+            let differences: Vec<f64> = image.data.iter().zip(sphere.data.iter()).map(|(a, b)| (a - b).abs()).filter(|d| !d.is_nan()).collect();
+            differences.iter().sum::<f64>() / differences.len() as f64
+        };
+
+        // Hard to tell apart from the sphere with the exact vertex normals,
+        // and with the averaged ones
+        let smooth = difference(&shade(&mesh, size));
+        assert!(smooth < 0.01);
+        mesh.compute_smooth_normals();
+        assert!(difference(&shade(&mesh, size)) < 0.01);
+
+        // Unlike the facets
+        mesh.clear_normals();
+        assert!(difference(&shade(&mesh, size)) > 5.0 * smooth);
+    }
+
+    #[test]
+    fn bvh() {
+        let size = 16;
+        let mut mesh = TriangleMesh::<f64>::icosphere(1.0, 4);
+        assert_eq!(mesh.get_triangles().len(), 5120);
+        assert!(mesh.uses_bvh());
+        // Bit for bit, NaN where the mesh is missed
+        let bits = |image: Image<f64>| image.data.iter().map(|value| value.to_bits()).collect::<Vec<u64>>();
+        let image = bits(shade(&mesh, size));
+        let with_bvh = count_tests(&mesh, size);
+
+        mesh.set_use_bvh(false);
+        assert_eq!(bits(shade(&mesh, size)), image);
+        let without_bvh = count_tests(&mesh, size);
+        assert_eq!(without_bvh, (5120 * size * size) as u64);
+        assert!(with_bvh * 100 < without_bvh);
+
+        // Every triangle in exactly one leaf
+        let mut order = mesh.order.clone();
+        order.sort_unstable();
+        assert_eq!(order, (0..5120).collect::<Vec<usize>>());
+        assert!(mesh.nodes.iter().all(|node| node.count <= LEAF_SIZE));
+    }
+}
@@ -2,8 +2,18 @@ pub mod sphere;
 pub mod rectangle;
 pub mod cube;
 pub mod group;
+pub mod constant_medium;
+pub mod mesh;
+pub mod cone;
+pub mod wedge;
+pub mod portal;
 
 pub use sphere::Sphere;
 pub use rectangle::Rectangle;
 pub use cube::Cube;
 pub use group::Group;
+pub use constant_medium::ConstantMedium;
+pub use mesh::TriangleMesh;
+pub use cone::Cone;
+pub use wedge::Wedge;
+pub use portal::Portal;
@@ -0,0 +1,233 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::hitable::primitive::Rectangle;
+use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError};
+
+// A rectangle that rays entering its front (the side of its normal) leave
+// from somewhere else: at the exit position, out of a plane with the exit
+// normal, turned like the portal is turned from the entry to the exit.
+// The back of the entry is invisible, and the exit isn't a surface at all.
+// The paths of Integrator::Path and the photons of the caustics follow
+// portals, the material of their actor is never used.
+pub struct Portal<T>
+    where T: Float
+{
+    entry: Rectangle<T>,
+    entry_normal: Vec3<T>,
+    exit_position: Vec3<T>,
+    exit_normal: Vec3<T>,
+    // Rows of the rotation from the entry to the exit
+    rotation: [Vec3<T>; 3]
+}
+
+impl<T> Clone for Portal<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        Portal {
+            entry: self.entry.clone(),
+            entry_normal: Vec3::from_slice(self.entry_normal.get_data()),
+            exit_position: Vec3::from_slice(self.exit_position.get_data()),
+            exit_normal: Vec3::from_slice(self.exit_normal.get_data()),
+            rotation: [
+                Vec3::from_slice(self.rotation[0].get_data()),
+                Vec3::from_slice(self.rotation[1].get_data()),
+                Vec3::from_slice(self.rotation[2].get_data())
+            ]
+        }
+    }
+}
+
+impl<T> Portal<T>
+    where T: Float
+{
+    pub fn new(entry: Rectangle<T>, exit_position: Vec3<T>, exit_normal: Vec3<T>) -> Self {
+        let entry_normal = Vec3::from_slice(entry.get_normal().get_data());
+        let mut exit_normal = exit_normal;
+        exit_normal.normalize();
+        let rotation = Portal::rotation(&(&entry_normal * (-T::one())), &exit_normal);
+        Portal {
+            entry,
+            entry_normal,
+            exit_position,
+            exit_normal,
+            rotation
+        }
+    }
+
+    pub fn get_exit_position(&self) -> &Vec3<T> {
+        &self.exit_position
+    }
+
+    pub fn get_exit_normal(&self) -> &Vec3<T> {
+        &self.exit_normal
+    }
+
+    // The shortest rotation turning the unit vector a into b. Turning into
+    // the opposite direction it's half a turn, around Y if possible to keep
+    // up where it is.
+    fn rotation(a: &Vec3<T>, b: &Vec3<T>) -> [Vec3<T>; 3] {
+        let c = a.dot(b);
+        let identity = [
+            Vec3::from_array([T::one(), T::zero(), T::zero()]),
+            Vec3::from_array([T::zero(), T::one(), T::zero()]),
+            Vec3::from_array([T::zero(), T::zero(), T::one()])
+        ];
+        let mut m = [[T::zero(); 3]; 3];
+        if c > T::from_f64(-0.999999) {
+            // Rodrigues: I + [v]x + [v]x^2 / (1 + c) with v = a x b,
+            // where [v]x^2 = v v^T - |v|^2 I
+            let v = a.cross(b);
+            let v2 = v.dot(&v);
+            let v = v.get_data();
+            let k = [
+                [T::zero(), -v[2], v[1]],
+                [v[2], T::zero(), -v[0]],
+                [-v[1], v[0], T::zero()]
+            ];
+            for i in 0..3 {
+                for j in 0..3 {
+                    let one = if i == j { T::one() } else { T::zero() };
+                    m[i][j] = one + k[i][j] + (v[i] * v[j] - one * v2) / (T::one() + c);
+                }
+            }
+        } else {
+            // 2 u u^T - I with u perpendicular to a, from the axis least
+            // along a
+            let mut axis = &identity[1];
+            for other in [&identity[0], &identity[2]].iter() {
+                if a.dot(other).abs() < a.dot(axis).abs() {
+                    axis = other;
+                }
+            }
+            let mut u = axis - &(a * a.dot(axis));
+            u.normalize();
+            let u = u.get_data();
+            for i in 0..3 {
+                for j in 0..3 {
+                    let one = if i == j { T::one() } else { T::zero() };
+                    m[i][j] = T::from_f64(2.0) * u[i] * u[j] - one;
+                }
+            }
+        }
+        [Vec3::from_array(m[0]), Vec3::from_array(m[1]), Vec3::from_array(m[2])]
+    }
+
+    fn rotate(&self, v: &Vec3<T>) -> Vec3<T> {
+        Vec3::from_array([self.rotation[0].dot(v), self.rotation[1].dot(v), self.rotation[2].dot(v)])
+    }
+}
+
+impl<T> Hitable<T> for Portal<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        if ray.get_direction().dot(&self.entry_normal) >= T::zero() {
+            return None;
+        }
+        self.entry.hit(ray, t_min, t_max)
+    }
+
+    fn teleport(&self, ray: &Ray<T>, point: &Vec3<T>) -> Option<Ray<T>> {
+        // The point relative to the center of the entry lands at the same
+        // place relative to the exit
+        let origin = &self.exit_position + &self.rotate(point);
+        let mut ray_out = Ray::from_vec(origin, self.rotate(ray.get_direction()));
+        ray_out.set_wavelength(ray.get_wavelength());
+        Some(ray_out)
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        self.entry.get_bounds()
+    }
+
+    fn surface_area(&self) -> T {
+        self.entry.surface_area()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        self.entry.bounding_box()
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Hitable<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Hitable<f32>>, PrecisionError> {
+        let portal: Portal<f32> = self.convert_precision()?;
+        Ok(Box::new(portal))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Hitable<f64>>, PrecisionError> {
+        let portal: Portal<f64> = self.convert_precision()?;
+        Ok(Box::new(portal))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Portal<T>
+    where T: Float, U: Precision
+{
+    type Output = Portal<U>;
+
+    fn convert_precision(&self) -> Result<Portal<U>, PrecisionError> {
+        Ok(Portal::new(self.entry.convert_precision()?, self.exit_position.convert(), self.exit_normal.convert()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::Axis;
+
+    fn assert_near(a: &Vec3<f64>, b: [f64; 3]) {
+        for k in 0..3 {
+            assert!((a.get_data()[k] - b[k]).abs() < 1e-12, "{:?} != {:?}", a.get_data(), b);
+        }
+    }
+
+    #[test]
+    fn teleport() {
+        // Entry facing +z, exit at x = 10 facing +x
+        let entry = Rectangle::new(2.0, Axis::X, 2.0, Axis::Y);
+        let portal = Portal::<f64>::new(entry, Vec3::from_array([10.0, 0.0, 0.0]), Vec3::from_array([2.0, 0.0, 0.0]));
+        assert_eq!(portal.get_exit_normal().get_data(), [1.0, 0.0, 0.0]);
+
+        let ray = Ray::from_array([0.5, 0.25, 5.0], [0.0, 0.0, -1.0]);
+        let hit = portal.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(hit.t, 5.0);
+        let ray_out = portal.teleport(&ray, &hit.point).unwrap();
+        assert_near(ray_out.get_direction(), [1.0, 0.0, 0.0]);
+        assert_near(ray_out.get_origin(), [10.0, 0.25, 0.5]);
+
+        // From behind the entry isn't there
+        let ray = Ray::from_array([0.5, 0.25, -5.0], [0.0, 0.0, 1.0]);
+        assert!(portal.hit(&ray, 0.0, 100.0).is_none());
+
+        // An exit facing the same way as the entry turns the rays around Y
+        let entry = Rectangle::new(2.0, Axis::X, 2.0, Axis::Y);
+        let portal = Portal::<f64>::new(entry, Vec3::from_array([0.0, 0.0, -10.0]), Vec3::from_array([0.0, 0.0, 1.0]));
+        let ray = Ray::from_array([0.5, 0.25, 5.0], [0.0, 0.1, -1.0]);
+        let hit = portal.hit(&ray, 0.0, 100.0).unwrap();
+        let ray_out = portal.teleport(&ray, &hit.point).unwrap();
+        assert_near(ray_out.get_direction(), [0.0, 0.1, 1.0]);
+        assert_near(ray_out.get_origin(), [-0.5, 0.75, -10.0]);
+
+        // Straight through to an exit facing away
+        let entry = Rectangle::new(2.0, Axis::X, 2.0, Axis::Y);
+        let portal = Portal::<f64>::new(entry, Vec3::from_array([0.0, 3.0, 0.0]), Vec3::from_array([0.0, 0.0, -1.0]));
+        let ray = Ray::from_array([0.5, 0.25, 5.0], [0.0, 0.0, -1.0]);
+        let hit = portal.hit(&ray, 0.0, 100.0).unwrap();
+        let ray_out = portal.teleport(&ray, &hit.point).unwrap();
+        assert_near(ray_out.get_direction(), [0.0, 0.0, -1.0]);
+        assert_near(ray_out.get_origin(), [0.5, 3.25, 0.0]);
+    }
+}
@@ -1,11 +1,17 @@
+use rand::RngCore;
+
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::hit::Hit;
-use crate::hitable::Hitable;
+use crate::hitable::{Hitable, SampleablePrimitive};
 use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
+use crate::interval::Interval;
 use crate::constants::Axis;
 use crate::utils::axis_to_index;
+use crate::sampling::uniform;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
 
 pub struct Rectangle<T>
     where T: Float
@@ -16,15 +22,36 @@ pub struct Rectangle<T>
     height_axis: Axis,
     normal_axis: Axis,
     normal: Vec3<T>,
+    double_sided: bool,
+    // Translations are folded into the center instead of wrapping the rectangle
+    center: Vec3<T>,
     bounds: BoundingBox<T>
 }
 
+impl<T> Clone for Rectangle<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        Rectangle {
+            width: self.width,
+            width_axis: self.width_axis,
+            height: self.height,
+            height_axis: self.height_axis,
+            normal_axis: self.normal_axis,
+            normal: Vec3::from_slice(self.normal.get_data()),
+            double_sided: self.double_sided,
+            center: Vec3::from_slice(self.center.get_data()),
+            bounds: self.bounds.clone()
+        }
+    }
+}
+
 impl<T> Rectangle<T>
     where T: Float
 {
     pub fn new(width: T, width_axis: Axis, height: T, height_axis: Axis) -> Self {
         let (normal_axis, normal_direction) = Rectangle::<T>::normal_axis(&width_axis, &height_axis);
-        let half = T::from(0.5).unwrap();
+        let half = T::from_f64(0.5);
         let p0 = Rectangle::<T>::length_to_point(- width * half, &width_axis);
         let p0 = p0 + Rectangle::<T>::length_to_point(- height * half, &height_axis);
         
@@ -41,6 +68,8 @@ impl<T> Rectangle<T>
             height_axis,
             normal_axis,
             normal,
+            double_sided: false,
+            center: Vec3::new(),
             bounds
         }
     }
@@ -49,12 +78,22 @@ impl<T> Rectangle<T>
         &self.normal
     }
 
+    pub fn get_center(&self) -> &Vec3<T> {
+        &self.center
+    }
+
+    pub fn get_double_sided(&self) -> bool {
+        self.double_sided
+    }
+
+    // Both sides are a front face, e.g. for a floor or a thin wall seen from
+    // either side, otherwise the back is the inside for the material
+    pub fn set_double_sided(&mut self, double_sided: bool) {
+        self.double_sided = double_sided;
+    }
+
     fn length_to_point(length: T, axis: &Axis) -> Vec3<T> {
-        match axis {
-            Axis::X => Vec3::<T>::from_array([length, T::zero(), T::zero()]),
-            Axis::Y => Vec3::<T>::from_array([T::zero(), length, T::zero()]),
-            Axis::Z => Vec3::<T>::from_array([T::zero(), T::zero(), length])
-        }
+        axis.to_vec3() * length
     }
 
     fn normal_axis(width_axis: &Axis, height_axis: &Axis) -> (Axis, T) {
@@ -82,8 +121,9 @@ impl<T> Hitable<T> for Rectangle<T>
 
         let normal_index = axis_to_index(&self.normal_axis);
 
-        let t = - oc.get_data()[normal_index] / direction.get_data()[normal_index];
-        if t <= t_min || t > t_max {
+        let t = (self.center.get_data()[normal_index] - oc.get_data()[normal_index]) / direction.get_data()[normal_index];
+        // Also rejects the NaN of rays parallel to the rectangle
+        if !Interval::new(t_min, t_max).contains(t) {
             return None;
         }
 
@@ -102,13 +142,15 @@ impl<T> Hitable<T> for Rectangle<T>
             return None;
         }
 
-        let point = ray.get_point(t);
+        let point = ray.at(t);
         let normal = &self.normal * T::one();
-        let hit = Hit {
-            point,
-            normal,
-            t
-        };
+        let (u, v) = ((width - w0) / (w1 - w0), (height - h0) / (h1 - h0));
+        let mut hit = Hit::with_face_normal(ray, point, normal, t, u, v);
+        // The normal already points against the ray
+        if self.double_sided {
+            hit.front_face = true;
+        }
+        hit.set_footprint(ray, T::one() / self.width.min(self.height));
 
         Some(hit)
     }
@@ -117,9 +159,91 @@ impl<T> Hitable<T> for Rectangle<T>
         &self.bounds
     }
 
+    fn surface_area(&self) -> T {
+        self.width * self.height
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<Hit<T>> {
+        let point = self.sample_point(rng);
+        let normal = Vec3::from_slice(self.normal.get_data());
+        let half = T::from_f64(0.5);
+        let local = &point - &self.center;
+        let u = local.get_data()[axis_to_index(&self.width_axis)] / self.width + half;
+        let v = local.get_data()[axis_to_index(&self.height_axis)] / self.height + half;
+        Some(Hit { point, normal, t: T::zero(), u, v, front_face: true, barycentric: None, face: None, footprint: None })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        Some(self.bounds.clone())
+    }
+
     fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
         self
     }
+
+    fn clone_box(&self) -> Box<dyn Hitable<T>> {
+        Box::new(self.clone())
+    }
+
+    fn translate(&mut self, offset: &Vec3<T>) -> bool {
+        self.center = &self.center + offset;
+        self.bounds = BoundingBox::new(self.bounds.get_p0() + offset, self.bounds.get_p1() + offset);
+        true
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Hitable<f32>>, PrecisionError> {
+        let rectangle: Rectangle<f32> = self.convert_precision()?;
+        Ok(Box::new(rectangle))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Hitable<f64>>, PrecisionError> {
+        let rectangle: Rectangle<f64> = self.convert_precision()?;
+        Ok(Box::new(rectangle))
+    }
+}
+
+impl<T> SampleablePrimitive<T> for Rectangle<T>
+    where T: Float
+{
+    fn contains(&self, point: &Vec3<T>) -> bool {
+        // Points found by rays are off the plane by a little round-off
+        let tolerance = T::epsilon().sqrt() * self.width.max(self.height);
+        let normal_index = axis_to_index(&self.normal_axis);
+        let distance = point.get_data()[normal_index] - self.center.get_data()[normal_index];
+        if distance.is_nan() || distance.abs() > tolerance {
+            return false;
+        }
+        [&self.width_axis, &self.height_axis].iter().all(|axis| {
+            let index = axis_to_index(axis);
+            let x = point.get_data()[index];
+            x >= self.bounds.get_p0().get_data()[index] && x <= self.bounds.get_p1().get_data()[index]
+        })
+    }
+
+    fn sample_point(&self, rng: &mut dyn RngCore) -> Vec3<T> {
+        let half = T::from_f64(0.5);
+        let w = (uniform::<T, dyn RngCore>(rng) - half) * self.width;
+        let h = (uniform::<T, dyn RngCore>(rng) - half) * self.height;
+        &self.center + Rectangle::<T>::length_to_point(w, &self.width_axis) + Rectangle::<T>::length_to_point(h, &self.height_axis)
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Rectangle<T>
+    where T: Float, U: Precision
+{
+    type Output = Rectangle<U>;
+
+    fn convert_precision(&self) -> Result<Rectangle<U>, PrecisionError> {
+        let mut rectangle = Rectangle::new(
+            convert(self.width),
+            self.width_axis,
+            convert(self.height),
+            self.height_axis
+        );
+        rectangle.set_double_sided(self.double_sided);
+        rectangle.translate(&self.center.convert());
+        Ok(rectangle)
+    }
 }
 
 #[cfg(test)]
@@ -191,7 +315,9 @@ mod tests {
         match hit {
             Some(hit) => {
                 assert_eq!(hit.point.get_data(), [0.5, 1.5, 0.0]);
-                assert_eq!(hit.normal.get_data(), [0.0, 0.0, 1.0]);
+                // Seen from behind, the normal points against the ray
+                assert_eq!(hit.normal.get_data(), [0.0, 0.0, -1.0]);
+                assert!(!hit.front_face);
                 assert_eq!(hit.t, 4.0);
             },
             None => {
@@ -199,6 +325,12 @@ mod tests {
             }
         }
 
+        // Parallel to the rectangle
+        let ray = Ray::from_array([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        assert!(rectangle.hit(&ray, 0.0, 100.0).is_none());
+        let ray = Ray::from_array([0.0, 0.0, 1.0], [1.0, 0.0, 0.0]);
+        assert!(rectangle.hit(&ray, 0.0, 100.0).is_none());
+
         let origin = [1.0001, 2.0001, 8.0];
         let direction = [0.0, 0.0, -2.0];
         let ray = Ray::from_array(origin, direction);
@@ -208,6 +340,82 @@ mod tests {
         };
     }
 
+    #[test]
+    fn double_sided() {
+        let mut rectangle = Rectangle::<f64>::new(2.0, Axis::X, 4.0, Axis::Y);
+        rectangle.set_double_sided(true);
+        assert!(rectangle.get_double_sided());
+
+        let ray = Ray::from_array([0.5, 1.5, -8.0], [0.0, 0.0, 2.0]);
+        let hit = rectangle.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(hit.normal.get_data(), [0.0, 0.0, -1.0]);
+        assert!(hit.front_face);
+        assert_eq!(hit.t, 4.0);
+
+        let ray = Ray::from_array([0.5, 1.5, 8.0], [0.0, 0.0, -2.0]);
+        let hit = rectangle.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(hit.normal.get_data(), [0.0, 0.0, 1.0]);
+        assert!(hit.front_face);
+
+        let converted: Rectangle<f32> = rectangle.convert_precision().unwrap();
+        assert!(converted.get_double_sided());
+    }
+
+    #[test]
+    fn surface_area() {
+        let rectangle = Rectangle::<f64>::new(2.0, Axis::Z, 4.5, Axis::X);
+        assert_eq!(rectangle.surface_area(), 9.0);
+    }
+
+    #[test]
+    fn sample_surface() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let rectangle = Rectangle::<f64>::new(2.0, Axis::Z, 4.0, Axis::X);
+        for _ in 0..100 {
+            let hit = rectangle.sample_surface(&mut rng).unwrap();
+            let [x, y, z] = hit.point.to_array();
+            assert!(x.abs() <= 2.0 && y == 0.0 && z.abs() <= 1.0);
+            assert_eq!(hit.normal.get_data(), rectangle.get_normal().get_data());
+            assert!(hit.front_face);
+        }
+    }
+
+    #[test]
+    fn contains() {
+        let rectangle = Rectangle::<f64>::new(2.0, Axis::Z, 4.0, Axis::X);
+        for point in [[0.0, 0.0, 0.0], [2.0, 0.0, 1.0], [-1.5, 1e-12, -0.5]].iter() {
+            assert!(rectangle.contains(&Vec3::from_array(*point)));
+        }
+        for point in [[2.1, 0.0, 0.0], [0.0, 0.0, -1.1], [0.0, 0.01, 0.0], [f64::NAN, 0.0, 0.0]].iter() {
+            assert!(!rectangle.contains(&Vec3::from_array(*point)));
+        }
+
+        // The points rays hit
+        let ray = Ray::from_array([0.3, 5.0, 0.7], [0.1, -0.7, -0.2]);
+        let hit = rectangle.hit(&ray, 0.0, 100.0).unwrap();
+        assert!(rectangle.contains(&hit.point));
+    }
+
+    #[test]
+    fn sample_point() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let rectangle = Rectangle::<f64>::new(2.0, Axis::Z, 4.0, Axis::X);
+        // Uniform: as many points in each quarter along the width
+        let n = 40000;
+        let mut quarters = [0; 4];
+        for _ in 0..n {
+            let point = rectangle.sample_point(&mut rng);
+            assert!(rectangle.contains(&point));
+            let x = point.get_data()[0];
+            quarters[usize::min(((x + 2.0) / 1.0) as usize, 3)] += 1;
+        }
+        for count in quarters.iter() {
+            assert!((*count as f64 - n as f64 / 4.0).abs() < 400.0);
+        }
+    }
+
     #[test]
     fn bounds() {
         let width = 2.0;
@@ -1,23 +1,44 @@
+use rand::RngCore;
+
 use crate::float::Float;
-use crate::vector::Vec3;
+use crate::vector::{Vec3, Onb};
 use crate::ray::Ray;
 use crate::hit::Hit;
 use crate::hitable::Hitable;
 use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
+use crate::interval::Interval;
+use crate::sampling::{uniform, unit_sphere_surface};
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
 
 pub struct Sphere<T>
     where T: Float
 {
     radius: T,
+    // Translations are folded into the center instead of wrapping the sphere
+    center: Vec3<T>,
     bounds: BoundingBox<T>
 }
 
+impl<T> Clone for Sphere<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        Sphere {
+            radius: self.radius,
+            center: Vec3::from_slice(self.center.get_data()),
+            bounds: self.bounds.clone()
+        }
+    }
+}
+
 impl<T> Sphere<T>
     where T: Float
 {
     pub fn new(radius: T) -> Self {
         let mut sphere = Sphere {
             radius,
+            center: Vec3::new(),
             bounds: BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new())
         };
         sphere.update_bounds();
@@ -33,18 +54,69 @@ impl<T> Sphere<T>
         self.update_bounds();
     }
 
-    fn update_bounds(&mut self) {
-        let one = Vec3::<T>::from_array([T::one(), T::one(), T::one()]);
-        let p0 = &one * self.get_radius() * (- T::one());
-        let p1 = &one * self.get_radius();
-        self.bounds = BoundingBox::<T>::new(p0, p1);
+    pub fn get_center(&self) -> &Vec3<T> {
+        &self.center
     }
-}
 
-impl<T> Hitable<T> for Sphere<T>
-    where T: Float
-{
-    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+    // Point on the part of the sphere seen from from_point, and the density
+    // of the direction to it per solid angle. The directions are uniform in
+    // the cone around the sphere, which for a large light close by is much
+    // less noisy than sampling the whole surface. From inside, the point is
+    // uniform on the whole surface.
+    pub fn sample_point(&self, rng: &mut dyn RngCore, from_point: &Vec3<T>) -> (Vec3<T>, T) {
+        let (point, pdf) = self.sample_centered(rng, &(from_point - &self.center));
+        (&point + &self.center, pdf)
+    }
+
+    // sample_point of the sphere around the origin
+    fn sample_centered(&self, rng: &mut dyn RngCore, from_point: &Vec3<T>) -> (Vec3<T>, T) {
+        let two_pi = T::from_f64(2.0 * std::f64::consts::PI);
+        let radius2 = self.radius * self.radius;
+        let distance2 = from_point.dot(from_point);
+        if distance2 <= radius2 {
+            let normal = unit_sphere_surface::<T, dyn RngCore>(rng);
+            let point = &normal * self.radius;
+            let mut direction = &point - from_point;
+            let d2 = direction.dot(&direction);
+            direction.normalize();
+            let cos = normal.dot(&direction).abs();
+            return (point, d2 / (cos * T::from_f64(2.0) * two_pi * radius2));
+        }
+
+        let distance = distance2.sqrt();
+        let sin2_max = radius2 / distance2;
+        let cos_max = (T::one() - sin2_max).max(T::zero()).sqrt();
+        // 1 - cos_max without the cancellation of tiny cones
+        let one_minus_cos_max = sin2_max / (T::one() + cos_max);
+        let cos = T::one() - uniform::<T, dyn RngCore>(rng) * one_minus_cos_max;
+        let sin2 = (T::one() - cos * cos).max(T::zero());
+        let phi = two_pi * uniform::<T, dyn RngCore>(rng);
+        let sin = sin2.sqrt();
+
+        let onb = Onb::from_w(&(from_point * (-T::one())));
+        let direction = onb.local_to_world(&Vec3::from_array([sin * phi.cos(), sin * phi.sin(), cos]));
+        // The closest intersection along the direction
+        let length = distance * cos - (radius2 - distance2 * sin2).max(T::zero()).sqrt();
+        let point = from_point + &direction * length;
+        (point, T::one() / (two_pi * one_minus_cos_max))
+    }
+
+    // Latitude / longitude mapping of a point on the unit sphere:
+    // u goes around the Y axis starting with 0 at -Z, through 0.5 at +Z,
+    // v goes from 0 at the south pole (-Y) to 1 at the north pole (+Y)
+    pub fn get_uv(normal: &Vec3<T>) -> (T, T) {
+        let pi = T::from_f64(std::f64::consts::PI);
+        let half = T::from_f64(0.5);
+        let (x, y, z) = (normal.get_data()[0], normal.get_data()[1], normal.get_data()[2]);
+        // atan2(0, 0) is 0, so the poles get u = 0.5 instead of NaN
+        let u = half + x.atan2(z) / (pi + pi);
+        // Round-off can push y slightly outside of [-1, 1]
+        let y = y.max(-T::one()).min(T::one());
+        let v = (-y).acos() / pi;
+        (u, v)
+    }
+
+    fn roots(&self, ray: &Ray<T>) -> Option<(T, T)> {
         // Intersection of a line and a sphere:
         //
         // p(t) = origin + t * direction
@@ -61,7 +133,7 @@ impl<T> Hitable<T> for Sphere<T>
         // t = (- b +/- sqrt(b * b - 4 * a * c)) / (2 * a)
         // 
         // drop 2s coming from b
-        let oc = ray.get_origin();
+        let oc = ray.get_origin() - &self.center;
         let a = ray.get_direction().dot(ray.get_direction());
         let b = ray.get_direction().dot(&oc);
         let c = oc.dot(&oc) - self.get_radius() * self.get_radius();
@@ -70,35 +142,126 @@ impl<T> Hitable<T> for Sphere<T>
             return None;
         }
         let discriminant = discriminant.sqrt();
-        let t0 = (- b - discriminant) / a;
-        let t1 = (- b + discriminant) / a;
-        let t = if t0 >= t_min && t0 < t_max { t0 }
-                else if t1 >= t_min && t1 < t_max { t1 }
+        Some(((- b - discriminant) / a, (- b + discriminant) / a))
+    }
+
+    fn hit_at(&self, ray: &Ray<T>, t: T) -> Hit<T> {
+        let point = ray.at(t);
+        let normal = (&point - &self.center) / self.get_radius();
+        let (u, v) = Sphere::<T>::get_uv(&normal);
+        let mut hit = Hit::with_face_normal(ray, point, normal, t, u, v);
+        // v goes from pole to pole, half way around
+        hit.set_footprint(ray, T::one() / (T::from_f64(std::f64::consts::PI) * self.radius));
+        hit
+    }
+
+    fn update_bounds(&mut self) {
+        let one = Vec3::<T>::from_array([T::one(), T::one(), T::one()]);
+        let p0 = &self.center - &one * self.get_radius();
+        let p1 = &self.center + &one * self.get_radius();
+        self.bounds = BoundingBox::<T>::new(p0, p1);
+    }
+}
+
+impl<T> Hitable<T> for Sphere<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let (t0, t1) = self.roots(ray)?;
+        // The first root may be in front of t_min, i.e. the ray starts inside the sphere
+        let interval = Interval::new(t_min, t_max);
+        let t = if interval.contains(t0) { t0 }
+                else if interval.contains(t1) { t1 }
                 else { return None; };
 
-        let point = ray.get_point(t);
-        let normal = (&point) / self.get_radius();
-        let hit = Hit {
-            point,
-            normal,
-            t
-        };
+        Some(self.hit_at(ray, t))
+    }
 
-        Some(hit)
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        let interval = Interval::new(t_min, t_max);
+        match self.roots(ray) {
+            Some((t0, t1)) => [t0, t1].iter()
+                .filter(|t| interval.contains(**t))
+                .map(|t| self.hit_at(ray, *t))
+                .collect(),
+            None => vec!()
+        }
+    }
+
+    fn hit_intervals(&self, ray: &Ray<T>, interval: &Interval<T>) -> Vec<Interval<T>> {
+        let inside = match self.roots(ray) {
+            Some((t0, t1)) => Interval::new(t0, t1).intersect(interval),
+            None => return vec!()
+        };
+        if inside.is_empty() {
+            return vec!();
+        }
+        vec!(inside)
     }
 
     fn get_bounds(&self) -> &BoundingBox<T> {
         &self.bounds
     }
 
+    fn surface_area(&self) -> T {
+        T::from_f64(4.0 * std::f64::consts::PI) * self.radius * self.radius
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<Hit<T>> {
+        let normal = unit_sphere_surface::<T, dyn RngCore>(rng);
+        let point = &self.center + &normal * self.radius;
+        let (u, v) = Sphere::<T>::get_uv(&normal);
+        Some(Hit { point, normal, t: T::zero(), u, v, front_face: true, barycentric: None, face: None, footprint: None })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        Some(self.bounds.clone())
+    }
+
     fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
         self
     }
+
+    fn clone_box(&self) -> Box<dyn Hitable<T>> {
+        Box::new(self.clone())
+    }
+
+    fn translate(&mut self, offset: &Vec3<T>) -> bool {
+        self.center = &self.center + offset;
+        self.update_bounds();
+        true
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Hitable<f32>>, PrecisionError> {
+        let sphere: Sphere<f32> = self.convert_precision()?;
+        Ok(Box::new(sphere))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Hitable<f64>>, PrecisionError> {
+        let sphere: Sphere<f64> = self.convert_precision()?;
+        Ok(Box::new(sphere))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Sphere<T>
+    where T: Float, U: Precision
+{
+    type Output = Sphere<U>;
+
+    fn convert_precision(&self) -> Result<Sphere<U>, PrecisionError> {
+        let mut sphere = Sphere::new(convert(self.radius));
+        sphere.translate(&self.center.convert());
+        Ok(sphere)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::texture::Texture;
+    use crate::material::Material;
+    use crate::material::plain::PlainMaterial;
+    use crate::hitable::transform::Translation;
 
     #[test]
     fn init() {
@@ -161,6 +324,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn roots() {
+        // The ray crosses the sphere at t = 6 and t = 10
+        let sphere = Sphere::<f64>::new(2.0);
+        let ray = Ray::from_array([-8.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+
+        assert_eq!(sphere.hit(&ray, 0.0, 100.0).unwrap().t, 6.0);
+        // The first root is before t_min
+        let hit = sphere.hit(&ray, 7.0, 100.0).unwrap();
+        assert_eq!(hit.t, 10.0);
+        assert_eq!(hit.point.get_data(), [2.0, 0.0, 0.0]);
+        assert!(!hit.front_face);
+        // Both ends of the interval are included
+        assert_eq!(sphere.hit(&ray, 6.0, 100.0).unwrap().t, 6.0);
+        assert_eq!(sphere.hit(&ray, 0.0, 6.0).unwrap().t, 6.0);
+        assert_eq!(sphere.hit(&ray, 10.0, 100.0).unwrap().t, 10.0);
+        // No root in the interval
+        assert!(sphere.hit(&ray, 0.0, 5.0).is_none());
+        assert!(sphere.hit(&ray, 6.5, 9.5).is_none());
+        assert!(sphere.hit(&ray, 10.5, 100.0).is_none());
+    }
+
     #[test]
     fn bounds() {
         let radius = 2.5;
@@ -169,4 +354,189 @@ mod tests {
         assert_eq!(bounds.get_p0().get_data(), [-2.5, -2.5, -2.5]);
         assert_eq!(bounds.get_p1().get_data(), [2.5, 2.5, 2.5]);
     }
+
+    #[test]
+    fn surface_area() {
+        let sphere = Sphere::<f64>::new(2.0);
+        assert!((sphere.surface_area() - 16.0 * std::f64::consts::PI).abs() < 1e-12);
+        let translated = Translation::new(Box::new(sphere), Vec3::from_array([1.0, 2.0, 3.0]));
+        assert!((translated.surface_area() - 16.0 * std::f64::consts::PI).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sample_surface() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let translated = Translation::new(Box::new(Sphere::<f64>::new(2.0)), Vec3::from_array([1.0, 2.0, 3.0]));
+        for _ in 0..100 {
+            let hit = translated.sample_surface(&mut rng).unwrap();
+            let offset = &hit.point - Vec3::from_array([1.0, 2.0, 3.0]);
+            assert!((offset.norm() - 2.0).abs() < 1e-12);
+            assert!((offset / 2.0 - &hit.normal).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn translate() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let offset = Vec3::from_array([1.0, 2.0, 3.0]);
+        let mut sphere = Sphere::<f64>::new(2.0);
+        assert!(sphere.translate(&offset));
+        assert_eq!(sphere.get_center().get_data(), [1.0, 2.0, 3.0]);
+        assert_eq!(sphere.get_bounds().get_p0().get_data(), [-1.0, 0.0, 1.0]);
+
+        // The same as wrapped in a translation
+        let translated = Translation::new(Box::new(Sphere::<f64>::new(2.0)), Vec3::from_slice(offset.get_data()));
+        let ray = Ray::from_array([-5.0, 2.5, 3.0], [1.0, 0.0, 0.0]);
+        let (hit, expected) = (sphere.hit(&ray, 0.0, 100.0).unwrap(), translated.hit(&ray, 0.0, 100.0).unwrap());
+        assert!((hit.t - expected.t).abs() < 1e-12);
+        assert!((&hit.normal - &expected.normal).norm() < 1e-12);
+        assert!((hit.u - expected.u).abs() < 1e-12 && (hit.v - expected.v).abs() < 1e-12);
+
+        for _ in 0..100 {
+            let (point, _) = sphere.sample_point(&mut rng, &Vec3::from_array([1.0, 2.0, 10.0]));
+            assert!(((&point - &offset).norm() - 2.0).abs() < 1e-12);
+            assert!(point.get_data()[2] > 3.0);
+        }
+    }
+
+    #[test]
+    fn sample_point() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let sphere = Sphere::<f64>::new(1.0);
+        let from = Vec3::from_array([0.0, 0.0, 3.0]);
+        let normal = Vec3::from_array([0.0, 0.0, -1.0]);
+
+        // Irradiance of a light of radiance 1 on a surface facing it,
+        // pi sin^2 of the angle of the cone
+        let expected = std::f64::consts::PI / 9.0;
+        let n = 4000;
+        let (mut cone, mut cone2, mut area, mut area2) = (0.0, 0.0, 0.0, 0.0);
+        for _ in 0..n {
+            let (point, pdf) = sphere.sample_point(&mut rng, &from);
+            assert!((point.norm() - 1.0).abs() < 1e-12);
+            let mut direction = &point - &from;
+            direction.normalize();
+            // On the side seen from the point
+            assert!(point.dot(&direction) <= 1e-12);
+            let value = direction.dot(&normal) / pdf;
+            cone += value;
+            cone2 += value * value;
+
+            let hit = sphere.sample_surface(&mut rng).unwrap();
+            let mut direction = &hit.point - &from;
+            let d2 = direction.dot(&direction);
+            direction.normalize();
+            let cos_light = -hit.normal.dot(&direction);
+            let value = if cos_light > 0.0 { direction.dot(&normal) * cos_light / d2 * 4.0 * std::f64::consts::PI } else { 0.0 };
+            area += value;
+            area2 += value * value;
+        }
+        let (cone, area) = (cone / n as f64, area / n as f64);
+        let cone_variance = cone2 / n as f64 - cone * cone;
+        let area_variance = area2 / n as f64 - area * area;
+        assert!((cone - expected).abs() < 0.005);
+        assert!((area - expected).abs() < 0.03);
+        assert!(cone_variance < 0.05 * area_variance);
+
+        // From inside the whole surface
+        let from = Vec3::from_array([0.2, -0.3, 0.1]);
+        for _ in 0..100 {
+            let (point, pdf) = sphere.sample_point(&mut rng, &from);
+            assert!((point.norm() - 1.0).abs() < 1e-12);
+            assert!(pdf > 0.0 && pdf.is_finite());
+        }
+    }
+
+    #[derive(Clone)]
+    struct UvTexture {}
+
+    impl Texture<f64> for UvTexture {
+        fn get_color(&self, u: f64, v: f64, _point: &Vec3<f64>) -> Vec3<f64> {
+            Vec3::from_array([u, v, 0.0])
+        }
+
+        fn clone_box(&self) -> Box<dyn Texture<f64>> {
+            Box::new(self.clone())
+        }
+    }
+
+    fn uv_color(hitable: &dyn Hitable<f64>, origin: [f64; 3], direction: [f64; 3]) -> [f64; 3] {
+        let material = PlainMaterial::new(Box::new(UvTexture {}));
+        let hit = hitable.hit(&Ray::from_array(origin, direction), 0.0, 100.0).unwrap();
+        let color = material.scatter(&Ray::from_array(origin, direction), &hit, &mut rand::thread_rng()).attenuation;
+        [color.get_data()[0], color.get_data()[1], color.get_data()[2]]
+    }
+
+    #[test]
+    fn uv() {
+        let sphere = Sphere::<f64>::new(2.0);
+        let eps = 1e-12;
+
+        // Poles
+        let color = uv_color(&sphere, [0.0, 8.0, 0.0], [0.0, -1.0, 0.0]);
+        assert_eq!(color, [0.5, 1.0, 0.0]);
+        let color = uv_color(&sphere, [0.0, -8.0, 0.0], [0.0, 1.0, 0.0]);
+        assert_eq!(color, [0.5, 0.0, 0.0]);
+
+        // Equator
+        let color = uv_color(&sphere, [0.0, 0.0, 8.0], [0.0, 0.0, -1.0]);
+        assert!((color[0] - 0.5).abs() < eps && (color[1] - 0.5).abs() < eps);
+        let color = uv_color(&sphere, [8.0, 0.0, 0.0], [-1.0, 0.0, 0.0]);
+        assert!((color[0] - 0.75).abs() < eps && (color[1] - 0.5).abs() < eps);
+        let color = uv_color(&sphere, [-8.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        assert!((color[0] - 0.25).abs() < eps && (color[1] - 0.5).abs() < eps);
+
+        // Both sides of the seam at -Z
+        let color = uv_color(&sphere, [1e-6, 0.0, -8.0], [0.0, 0.0, 1.0]);
+        assert!(color[0] > 0.99 && color[0] <= 1.0);
+        let color = uv_color(&sphere, [-1e-6, 0.0, -8.0], [0.0, 0.0, 1.0]);
+        assert!(color[0] >= 0.0 && color[0] < 0.01);
+
+        // Translations keep the surface coordinates
+        let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::<f64>::new(2.0));
+        let translation = Translation::new(hitable, Vec3::from_array([1.0, 2.0, 3.0]));
+        let color = uv_color(&translation, [1.0, 10.0, 3.0], [0.0, -1.0, 0.0]);
+        assert_eq!(color, [0.5, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn uv_round_off() {
+        // Normals slightly longer than one must not produce NaN
+        let (u, v) = Sphere::<f64>::get_uv(&Vec3::from_array([0.0, 1.0 + 1e-15, 0.0]));
+        assert_eq!((u, v), (0.5, 1.0));
+        let (u, v) = Sphere::<f64>::get_uv(&Vec3::from_array([0.0, -1.0 - 1e-15, 0.0]));
+        assert_eq!((u, v), (0.5, 0.0));
+    }
+
+    #[test]
+    fn intervals() {
+        let sphere = Sphere::<f64>::new(2.0);
+        let ray = Ray::from_array([-8.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        assert_eq!(sphere.hit_intervals(&ray, &Interval::new(0.0, 100.0)), [Interval::new(6.0, 10.0)]);
+        // Starting inside the sphere
+        assert_eq!(sphere.hit_intervals(&ray, &Interval::new(7.0, 100.0)), [Interval::new(7.0, 10.0)]);
+        assert!(sphere.hit_intervals(&ray, &Interval::new(0.0, 5.0)).is_empty());
+        let ray = Ray::from_array([-8.0, 3.0, 0.0], [1.0, 0.0, 0.0]);
+        assert!(sphere.hit_intervals(&ray, &Interval::new(0.0, 100.0)).is_empty());
+    }
+
+    #[test]
+    fn hit_all() {
+        let sphere = Sphere::<f64>::new(2.0);
+        let ray = Ray::from_array([-8.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        let hits = sphere.hit_all(&ray, 0.0, 100.0);
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[0].t, hits[1].t), (6.0, 10.0));
+        assert!(hits[0].front_face);
+        assert!(!hits[1].front_face);
+        assert_eq!(hits[1].point.get_data(), [2.0, 0.0, 0.0]);
+
+        let hits = sphere.hit_all(&ray, 7.0, 100.0);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].t, 10.0);
+        assert!(sphere.hit_all(&ray, 6.5, 9.5).is_empty());
+    }
 }
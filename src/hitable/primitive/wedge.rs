@@ -0,0 +1,244 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
+use crate::interval::Interval;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
+
+// Triangular prism, i.e. a glass prism: the cross section in the XY plane
+// is an isosceles triangle with the base of the width at -height / 2 and
+// the apex at height / 2, extruded along Z by the depth
+pub struct Wedge<T>
+    where T: Float
+{
+    width: T,
+    height: T,
+    depth: T,
+    // Outward normal and offset of the faces, a point p is inside if
+    // normal . p <= offset for all of them
+    planes: Vec<(Vec3<T>, T)>,
+    bounds: BoundingBox<T>
+}
+
+impl<T> Clone for Wedge<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        Wedge {
+            width: self.width,
+            height: self.height,
+            depth: self.depth,
+            planes: self.planes.iter().map(|(normal, offset)| (Vec3::from_slice(normal.get_data()), *offset)).collect(),
+            bounds: self.bounds.clone()
+        }
+    }
+}
+
+impl<T> Wedge<T>
+    where T: Float
+{
+    pub fn new(width: T, height: T, depth: T) -> Self {
+        let half = T::from_f64(0.5);
+        let (w, h, d) = (width * half, height * half, depth * half);
+
+        let side = |sign: T| {
+            // Perpendicular to the side from the corner of the base to the apex
+            let mut normal = Vec3::from_array([sign * height, w, T::zero()]);
+            normal.normalize();
+            let offset = normal.dot(&Vec3::from_array([sign * w, -h, T::zero()]));
+            (normal, offset)
+        };
+        let planes = vec!(
+            (Vec3::from_array([T::zero(), -T::one(), T::zero()]), h),
+            side(-T::one()),
+            side(T::one()),
+            (Vec3::from_array([T::zero(), T::zero(), -T::one()]), d),
+            (Vec3::from_array([T::zero(), T::zero(), T::one()]), d)
+        );
+
+        let half = Vec3::from_array([w, h, d]);
+        Wedge {
+            width,
+            height,
+            depth,
+            planes,
+            bounds: BoundingBox::new(&half * (-T::one()), half)
+        }
+    }
+
+    pub fn get_width(&self) -> T {
+        self.width
+    }
+
+    pub fn get_height(&self) -> T {
+        self.height
+    }
+
+    pub fn get_depth(&self) -> T {
+        self.depth
+    }
+
+    // Where the ray enters and leaves the wedge and the faces there
+    fn clip(&self, ray: &Ray<T>) -> Option<(T, usize, T, usize)> {
+        let (origin, direction) = (ray.get_origin(), ray.get_direction());
+        let (mut t0, mut i0) = (T::neg_infinity(), 0);
+        let (mut t1, mut i1) = (T::infinity(), 0);
+        for (i, (normal, offset)) in self.planes.iter().enumerate() {
+            let cosine = normal.dot(direction);
+            let distance = *offset - normal.dot(origin);
+            if cosine == T::zero() {
+                // Parallel to the face, on the outside of it
+                if distance < T::zero() {
+                    return None;
+                }
+                continue;
+            }
+            let t = distance / cosine;
+            if cosine < T::zero() {
+                if t > t0 {
+                    t0 = t;
+                    i0 = i;
+                }
+            } else if t < t1 {
+                t1 = t;
+                i1 = i;
+            }
+        }
+        if t0 > t1 {
+            return None;
+        }
+        Some((t0, i0, t1, i1))
+    }
+
+    fn hit_at(&self, ray: &Ray<T>, t: T, face: usize) -> Hit<T> {
+        let normal = Vec3::from_slice(self.planes[face].0.get_data());
+        Hit::with_face_normal(ray, ray.at(t), normal, t, T::zero(), T::zero())
+    }
+}
+
+impl<T> Hitable<T> for Wedge<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        self.hit_all(ray, t_min, t_max).into_iter().next()
+    }
+
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        let interval = Interval::new(t_min, t_max);
+        match self.clip(ray) {
+            Some((t0, i0, t1, i1)) => {
+                [(t0, i0), (t1, i1)].iter()
+                    .filter(|(t, _)| interval.contains(*t))
+                    .map(|(t, face)| self.hit_at(ray, *t, *face))
+                    .collect()
+            },
+            None => vec!()
+        }
+    }
+
+    fn hit_intervals(&self, ray: &Ray<T>, interval: &Interval<T>) -> Vec<Interval<T>> {
+        match self.clip(ray) {
+            Some((t0, _, t1, _)) => {
+                let inside = Interval::new(t0, t1).intersect(interval);
+                if inside.is_empty() {
+                    return vec!();
+                }
+                vec!(inside)
+            },
+            None => vec!()
+        }
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn surface_area(&self) -> T {
+        let half = T::from_f64(0.5);
+        let side = (self.width * half * self.width * half + self.height * self.height).sqrt();
+        self.width * self.height + (self.width + side + side) * self.depth
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        Some(self.bounds.clone())
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Hitable<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Hitable<f32>>, PrecisionError> {
+        let wedge: Wedge<f32> = self.convert_precision()?;
+        Ok(Box::new(wedge))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Hitable<f64>>, PrecisionError> {
+        let wedge: Wedge<f64> = self.convert_precision()?;
+        Ok(Box::new(wedge))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Wedge<T>
+    where T: Float, U: Precision
+{
+    type Output = Wedge<U>;
+
+    fn convert_precision(&self) -> Result<Wedge<U>, PrecisionError> {
+        Ok(Wedge::new(convert(self.width), convert(self.height), convert(self.depth)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit() {
+        let wedge = Wedge::<f64>::new(2.0, 2.0, 4.0);
+        assert_eq!(wedge.get_bounds().get_p1().get_data(), [1.0, 1.0, 2.0]);
+
+        // Through both slanted sides, halfway up they are 0.5 from the middle
+        let ray = Ray::from_array([5.0, 0.0, 0.0], [-1.0, 0.0, 0.0]);
+        let hits = wedge.hit_all(&ray, 0.0, 100.0);
+        assert_eq!(hits.len(), 2);
+        assert!((hits[0].point.get_data()[0] - 0.5).abs() < 1e-12);
+        let s = 1.0 / 5.0f64.sqrt();
+        let expected = [2.0 * s, s, 0.0];
+        for k in 0..3 {
+            assert!((hits[0].normal.get_data()[k] - expected[k]).abs() < 1e-12);
+        }
+        assert!(hits[0].front_face);
+        assert!((hits[1].point.get_data()[0] + 0.5).abs() < 1e-12);
+        assert!(!hits[1].front_face);
+        let inside = wedge.hit_intervals(&ray, &Interval::new(0.0, 100.0));
+        assert!((inside[0].min - 4.5).abs() < 1e-12 && (inside[0].max - 5.5).abs() < 1e-12);
+
+        // Up through the base, and from the inside
+        let ray = Ray::from_array([0.0, -5.0, 1.0], [0.0, 1.0, 0.0]);
+        let hit = wedge.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(hit.point.get_data(), [0.0, -1.0, 1.0]);
+        assert_eq!(hit.normal.get_data(), [0.0, -1.0, 0.0]);
+        let ray = Ray::from_array([0.0, 0.0, 0.0], [0.0, 0.0, 1.0]);
+        let hit = wedge.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(hit.point.get_data(), [0.0, 0.0, 2.0]);
+        assert!(!hit.front_face);
+
+        // Past the apex and parallel to the base below it
+        let ray = Ray::from_array([5.0, 0.9, 0.0], [-1.0, 0.0, 0.0]);
+        assert_eq!(wedge.hit_all(&ray, 0.0, 100.0).len(), 2);
+        let ray = Ray::from_array([5.0, 1.1, 0.0], [-1.0, 0.0, 0.0]);
+        assert!(wedge.hit(&ray, 0.0, 100.0).is_none());
+        let ray = Ray::from_array([5.0, -1.1, 0.0], [-1.0, 0.0, 0.0]);
+        assert!(wedge.hit(&ray, 0.0, 100.0).is_none());
+
+        let side = 5.0f64.sqrt();
+        assert!((wedge.surface_area() - (4.0 + (2.0 + 2.0 * side) * 4.0)).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,253 @@
+use rand::RngCore;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::hitable::transform::Translation;
+use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
+use crate::interval::Interval;
+use crate::tree::TraversalCounts;
+use crate::animation::check_times;
+use crate::error::Error;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
+
+// Translation which changes over time, linearly interpolated between
+// (time, translation) keyframes and clamped outside of them
+pub struct KeyframedTransform<T>
+    where T: Float
+{
+    keyframes: Vec<(T, Vec3<T>)>,
+    time: T,
+    translation: Vec3<T>,
+    wrapped: Box<dyn Hitable<T>>,
+    bounds: BoundingBox<T>
+}
+
+impl<T> Clone for KeyframedTransform<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        KeyframedTransform {
+            keyframes: self.keyframes.iter().map(|(time, translation)| (*time, Vec3::from_slice(translation.get_data()))).collect(),
+            time: self.time,
+            translation: Vec3::from_slice(self.translation.get_data()),
+            wrapped: self.wrapped.clone(),
+            bounds: self.bounds.clone()
+        }
+    }
+}
+
+impl<T> KeyframedTransform<T>
+    where T: Float
+{
+    pub fn new(wrapped: Box<dyn Hitable<T>>, keyframes: Vec<(T, Vec3<T>)>) -> Result<Self, Error> {
+        let times: Vec<T> = keyframes.iter().map(|k| k.0).collect();
+        check_times(&times)?;
+        let time = keyframes[0].0;
+        let mut transform = KeyframedTransform {
+            keyframes,
+            time,
+            translation: Vec3::new(),
+            wrapped,
+            bounds: BoundingBox::new(Vec3::new(), Vec3::new())
+        };
+        transform.update();
+        Ok(transform)
+    }
+
+    pub fn get_time(&self) -> T {
+        self.time
+    }
+
+    pub fn get_translation(&self) -> &Vec3<T> {
+        &self.translation
+    }
+
+    fn update(&mut self) {
+        let last = self.keyframes.len() - 1;
+        let translation = if self.time.is_nan() || self.time <= self.keyframes[0].0 {
+            Vec3::from_slice(self.keyframes[0].1.get_data())
+        } else if self.time >= self.keyframes[last].0 {
+            Vec3::from_slice(self.keyframes[last].1.get_data())
+        } else {
+            let i = self.keyframes.iter().rposition(|k| k.0 <= self.time).unwrap_or(0);
+            let (t1, p1) = &self.keyframes[i];
+            let (t2, p2) = &self.keyframes[i + 1];
+            Vec3::lerp(p1, p2, (self.time - *t1) / (*t2 - *t1))
+        };
+        self.translation = translation;
+        self.bounds = Translation::compute_bounds(self.wrapped.get_bounds(), &self.translation);
+    }
+}
+
+impl<T> Hitable<T> for KeyframedTransform<T>
+    where T: Float
+{
+    fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
+        let translated_ray = ray.with_origin(ray.get_origin() - &self.translation);
+        let mut hit = self.wrapped.hit(&translated_ray, t_min, t_max)?;
+        hit.point = hit.point + &self.translation;
+        Some(hit)
+    }
+
+    fn hit_counted(&self, ray: &Ray<T>, interval: &Interval<T>, counts: &mut TraversalCounts) -> Option<Hit<T>> {
+        let translated_ray = ray.with_origin(ray.get_origin() - &self.translation);
+        let mut hit = self.wrapped.hit_counted(&translated_ray, interval, counts)?;
+        hit.point = hit.point + &self.translation;
+        Some(hit)
+    }
+
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        let translated_ray = ray.with_origin(ray.get_origin() - &self.translation);
+        let mut hits = self.wrapped.hit_all(&translated_ray, t_min, t_max);
+        for hit in hits.iter_mut() {
+            hit.point = &hit.point + &self.translation;
+        }
+        hits
+    }
+
+    fn hit_intervals(&self, ray: &Ray<T>, interval: &Interval<T>) -> Vec<Interval<T>> {
+        let translated_ray = ray.with_origin(ray.get_origin() - &self.translation);
+        self.wrapped.hit_intervals(&translated_ray, interval)
+    }
+
+    fn teleport(&self, ray: &Ray<T>, point: &Vec3<T>) -> Option<Ray<T>> {
+        let translated_ray = ray.with_origin(ray.get_origin() - &self.translation);
+        let ray_out = self.wrapped.teleport(&translated_ray, &(point - &self.translation))?;
+        Some(ray_out.with_origin(ray_out.get_origin() + &self.translation))
+    }
+
+    fn get_bounds(&self) -> &BoundingBox<T> {
+        &self.bounds
+    }
+
+    fn surface_area(&self) -> T {
+        self.wrapped.surface_area()
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<Hit<T>> {
+        let mut hit = self.wrapped.sample_surface(rng)?;
+        hit.point = hit.point + &self.translation;
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        let bounds = self.wrapped.bounding_box()?;
+        Some(Translation::compute_bounds(&bounds, &self.translation))
+    }
+
+    fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
+        self.wrapped
+    }
+
+    fn clone_box(&self) -> Box<dyn Hitable<T>> {
+        Box::new(self.clone())
+    }
+
+    fn is_primitive(&self) -> bool {
+        false
+    }
+
+    fn translate(&mut self, offset: &Vec3<T>) -> bool {
+        for keyframe in self.keyframes.iter_mut() {
+            keyframe.1 = &keyframe.1 + offset;
+        }
+        self.update();
+        true
+    }
+
+    fn is_animated(&self) -> bool {
+        true
+    }
+
+    fn set_time(&mut self, time: T) -> bool {
+        self.wrapped.set_time(time);
+        self.time = time;
+        self.update();
+        true
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Hitable<f32>>, PrecisionError> {
+        let transform: KeyframedTransform<f32> = self.convert_precision()?;
+        Ok(Box::new(transform))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Hitable<f64>>, PrecisionError> {
+        let transform: KeyframedTransform<f64> = self.convert_precision()?;
+        Ok(Box::new(transform))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for KeyframedTransform<T>
+    where T: Float, U: Precision
+{
+    type Output = KeyframedTransform<U>;
+
+    fn convert_precision(&self) -> Result<KeyframedTransform<U>, PrecisionError> {
+        let mut transform = KeyframedTransform {
+            keyframes: self.keyframes.iter().map(|(t, p)| (convert(*t), p.convert())).collect(),
+            time: convert(self.time),
+            translation: Vec3::new(),
+            wrapped: self.wrapped.convert_precision()?,
+            bounds: BoundingBox::new(Vec3::new(), Vec3::new())
+        };
+        transform.update();
+        Ok(transform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::primitive::Sphere;
+
+    fn rolling() -> KeyframedTransform<f64> {
+        let keyframes = vec![
+            (0.0, Vec3::from_array([0.0, 0.0, 0.0])),
+            (2.0, Vec3::from_array([2.0, 0.0, 0.0])),
+            (3.0, Vec3::from_array([2.0, 4.0, 0.0]))
+        ];
+        KeyframedTransform::new(Box::new(Sphere::new(1.0)), keyframes).unwrap()
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(KeyframedTransform::<f64>::new(Box::new(Sphere::new(1.0)), vec![]).is_err());
+        let keyframes = vec![(1.0, Vec3::new()), (1.0, Vec3::new())];
+        assert!(KeyframedTransform::new(Box::new(Sphere::new(1.0)), keyframes).is_err());
+    }
+
+    #[test]
+    fn set_time() {
+        let mut transform = rolling();
+        assert_eq!(transform.get_translation().get_data(), [0.0, 0.0, 0.0]);
+        assert!(transform.set_time(1.0));
+        assert_eq!(transform.get_translation().get_data(), [1.0, 0.0, 0.0]);
+        assert_eq!(transform.get_bounds().get_p0().get_data(), [0.0, -1.0, -1.0]);
+        transform.set_time(2.5);
+        assert_eq!(transform.get_translation().get_data(), [2.0, 2.0, 0.0]);
+        transform.set_time(10.0);
+        assert_eq!(transform.get_translation().get_data(), [2.0, 4.0, 0.0]);
+        transform.set_time(-1.0);
+        assert_eq!(transform.get_translation().get_data(), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn hit() {
+        let mut transform = rolling();
+        let ray = Ray::from_array([1.5, 0.0, 10.0], [0.0, 0.0, -1.0]);
+        assert!(transform.hit(&ray, 0.0, 100.0).is_none());
+        transform.set_time(1.5);
+        let hit = transform.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(hit.t, 9.0);
+        assert_eq!(hit.point.get_data(), [1.5, 0.0, 1.0]);
+
+        // Wrapped in a translation the bounds follow the time too
+        let mut translation = Translation::new(Box::new(rolling()), Vec3::from_array([0.0, 0.0, 5.0]));
+        assert!(translation.set_time(2.0));
+        assert_eq!(translation.get_bounds().get_p1().get_data(), [3.0, 1.0, 6.0]);
+    }
+}
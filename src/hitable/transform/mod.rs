@@ -1,3 +1,5 @@
 pub mod translation;
+pub mod keyframed;
 
-pub use translation::Translation;
\ No newline at end of file
+pub use translation::Translation;
+pub use keyframed::KeyframedTransform;
@@ -1,9 +1,15 @@
+use rand::RngCore;
+
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::hit::Hit;
 use crate::hitable::Hitable;
 use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
+use crate::interval::Interval;
+use crate::tree::TraversalCounts;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError};
 
 pub struct Translation<T>
     where T: Float
@@ -13,6 +19,18 @@ pub struct Translation<T>
     bounds: BoundingBox<T>
 }
 
+impl<T> Clone for Translation<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        Translation {
+            translation: Vec3::from_slice(self.translation.get_data()),
+            wrapped: self.wrapped.clone(),
+            bounds: self.bounds.clone()
+        }
+    }
+}
+
 impl<T> Translation<T>
     where T: Float
 {
@@ -48,9 +66,7 @@ impl<T> Hitable<T> for Translation<T>
     where T: Float
 {
     fn hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<Hit<T>> {
-        let origin = ray.get_origin() - &self.translation;
-        let direction = Vec3::from_slice(ray.get_direction().get_data());
-        let translated_ray = Ray::from_vec(origin, direction);
+        let translated_ray = ray.with_origin(ray.get_origin() - &self.translation);
         if let Some(mut hit) = self.wrapped.hit(&translated_ray, t_min, t_max) {
             hit.point = hit.point + &self.translation;
             return Some(hit);
@@ -58,17 +74,104 @@ impl<T> Hitable<T> for Translation<T>
         None
     }
 
+    fn hit_counted(&self, ray: &Ray<T>, interval: &Interval<T>, counts: &mut TraversalCounts) -> Option<Hit<T>> {
+        let translated_ray = ray.with_origin(ray.get_origin() - &self.translation);
+        let mut hit = self.wrapped.hit_counted(&translated_ray, interval, counts)?;
+        hit.point = hit.point + &self.translation;
+        Some(hit)
+    }
+
+    fn hit_all(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Vec<Hit<T>> {
+        let translated_ray = ray.with_origin(ray.get_origin() - &self.translation);
+        let mut hits = self.wrapped.hit_all(&translated_ray, t_min, t_max);
+        for hit in hits.iter_mut() {
+            hit.point = &hit.point + &self.translation;
+        }
+        hits
+    }
+
+    fn hit_intervals(&self, ray: &Ray<T>, interval: &Interval<T>) -> Vec<Interval<T>> {
+        let translated_ray = ray.with_origin(ray.get_origin() - &self.translation);
+        self.wrapped.hit_intervals(&translated_ray, interval)
+    }
+
+    fn teleport(&self, ray: &Ray<T>, point: &Vec3<T>) -> Option<Ray<T>> {
+        let translated_ray = ray.with_origin(ray.get_origin() - &self.translation);
+        let ray_out = self.wrapped.teleport(&translated_ray, &(point - &self.translation))?;
+        Some(ray_out.with_origin(ray_out.get_origin() + &self.translation))
+    }
+
     fn get_bounds(&self) -> &BoundingBox<T> {
         &self.bounds
     }
 
+    fn surface_area(&self) -> T {
+        self.wrapped.surface_area()
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<Hit<T>> {
+        let mut hit = self.wrapped.sample_surface(rng)?;
+        hit.point = hit.point + &self.translation;
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        let bounds = self.wrapped.bounding_box()?;
+        Some(Translation::compute_bounds(&bounds, &self.translation))
+    }
+
     fn unwrap(self: Box<Self>) -> Box<dyn Hitable<T>> {
         self.wrapped
     }
 
+    fn clone_box(&self) -> Box<dyn Hitable<T>> {
+        Box::new(self.clone())
+    }
+
     fn is_primitive(&self) -> bool {
         false
     }
+
+    fn translate(&mut self, offset: &Vec3<T>) -> bool {
+        self.translation = &self.translation + offset;
+        self.update_bounds();
+        true
+    }
+
+    fn set_time(&mut self, time: T) -> bool {
+        let changed = self.wrapped.set_time(time);
+        if changed {
+            self.update_bounds();
+        }
+        changed
+    }
+
+    fn is_animated(&self) -> bool {
+        self.wrapped.is_animated()
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Hitable<f32>>, PrecisionError> {
+        let translation: Translation<f32> = self.convert_precision()?;
+        Ok(Box::new(translation))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Hitable<f64>>, PrecisionError> {
+        let translation: Translation<f64> = self.convert_precision()?;
+        Ok(Box::new(translation))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Translation<T>
+    where T: Float, U: Precision
+{
+    type Output = Translation<U>;
+
+    fn convert_precision(&self) -> Result<Translation<U>, PrecisionError> {
+        Ok(Translation::new(
+            self.wrapped.convert_precision()?,
+            self.translation.convert()
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,124 @@
+use crate::float::Float;
+
+// Closed range [min, max] of ray parameters, empty if min > max
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Interval<T>
+    where T: Float
+{
+    pub min: T,
+    pub max: T
+}
+
+impl<T> Interval<T>
+    where T: Float
+{
+    pub fn new(min: T, max: T) -> Self {
+        Interval {
+            min,
+            max
+        }
+    }
+
+    pub fn empty() -> Self {
+        Interval::new(T::infinity(), T::neg_infinity())
+    }
+
+    pub fn universe() -> Self {
+        Interval::new(T::neg_infinity(), T::infinity())
+    }
+
+    // Also empty if either end is NaN
+    pub fn is_empty(&self) -> bool {
+        self.min.is_nan() || self.max.is_nan() || self.min > self.max
+    }
+
+    pub fn size(&self) -> T {
+        self.max - self.min
+    }
+
+    pub fn contains(&self, x: T) -> bool {
+        self.min <= x && x <= self.max
+    }
+
+    // Like contains, but excluding the end points
+    pub fn surrounds(&self, x: T) -> bool {
+        self.min < x && x < self.max
+    }
+
+    pub fn clamp(&self, x: T) -> T {
+        if x < self.min {
+            self.min
+        } else if x > self.max {
+            self.max
+        } else {
+            x
+        }
+    }
+
+    pub fn intersect(&self, other: &Interval<T>) -> Interval<T> {
+        let min = if other.min > self.min { other.min } else { self.min };
+        let max = if other.max < self.max { other.max } else { self.max };
+        Interval::new(min, max)
+    }
+}
+
+impl Interval<f32> {
+    pub const EMPTY: Interval<f32> = Interval { min: f32::INFINITY, max: f32::NEG_INFINITY };
+    pub const UNIVERSE: Interval<f32> = Interval { min: f32::NEG_INFINITY, max: f32::INFINITY };
+}
+
+impl Interval<f64> {
+    pub const EMPTY: Interval<f64> = Interval { min: f64::INFINITY, max: f64::NEG_INFINITY };
+    pub const UNIVERSE: Interval<f64> = Interval { min: f64::NEG_INFINITY, max: f64::INFINITY };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init() {
+        let interval = Interval::new(1.0, 3.0);
+        assert_eq!(interval.min, 1.0);
+        assert_eq!(interval.max, 3.0);
+        assert_eq!(interval.size(), 2.0);
+        assert!(!interval.is_empty());
+        assert!(Interval::new(1.0, 1.0).contains(1.0));
+
+        assert!(Interval::<f64>::EMPTY.is_empty());
+        assert_eq!(Interval::<f64>::empty(), Interval::<f64>::EMPTY);
+        assert!(!Interval::<f32>::UNIVERSE.is_empty());
+        assert_eq!(Interval::<f32>::universe(), Interval::<f32>::UNIVERSE);
+        assert!(Interval::<f64>::UNIVERSE.contains(1e300));
+        assert!(!Interval::<f64>::EMPTY.contains(0.0));
+        assert!(Interval::new(f64::NAN, 1.0).is_empty());
+    }
+
+    #[test]
+    fn contains() {
+        let interval = Interval::new(1.0, 3.0);
+        assert!(interval.contains(1.0));
+        assert!(interval.contains(3.0));
+        assert!(!interval.contains(3.5));
+        assert!(!interval.contains(f64::NAN));
+        assert!(!interval.surrounds(1.0));
+        assert!(interval.surrounds(2.0));
+    }
+
+    #[test]
+    fn clamp() {
+        let interval = Interval::new(1.0, 3.0);
+        assert_eq!(interval.clamp(0.0), 1.0);
+        assert_eq!(interval.clamp(2.0), 2.0);
+        assert_eq!(interval.clamp(4.0), 3.0);
+    }
+
+    #[test]
+    fn intersect() {
+        let interval = Interval::new(1.0, 3.0);
+        assert_eq!(interval.intersect(&Interval::new(2.0, 5.0)), Interval::new(2.0, 3.0));
+        assert_eq!(interval.intersect(&Interval::<f64>::UNIVERSE), interval);
+        assert!(interval.intersect(&Interval::new(4.0, 5.0)).is_empty());
+        assert!(interval.intersect(&Interval::<f64>::EMPTY).is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::float::Float;
+use crate::renderer::Image;
+#[cfg(feature = "image")]
+use crate::renderer::ImageRgba;
+use crate::error::Error;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FrameFormat {
+    // Plain text PPM (P3)
+    Ppm,
+    // Binary PPM (P6)
+    PpmBinary,
+    // 8 bit PNG, opaque, written by the image crate
+    #[cfg(feature = "image")]
+    Png
+}
+
+impl FrameFormat {
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            FrameFormat::Ppm | FrameFormat::PpmBinary => "ppm",
+            #[cfg(feature = "image")]
+            FrameFormat::Png => "png"
+        }
+    }
+
+    fn get_name(&self) -> &'static str {
+        match self {
+            FrameFormat::Ppm => "ppm",
+            FrameFormat::PpmBinary => "ppm-binary",
+            #[cfg(feature = "image")]
+            FrameFormat::Png => "png"
+        }
+    }
+}
+
+// Writes the frames of an animation as directory/basename_0001.ppm (or .png),
+// basename_0002.ppm, ... next to a basename.manifest describing the
+// sequence. All frames are encoded with the same settings.
+pub struct FrameWriter {
+    directory: PathBuf,
+    basename: String,
+    format: FrameFormat,
+    padding: usize,
+    overwrite: bool,
+    gamma: f64,
+    fps: f64,
+    seed: Option<u64>,
+    frames: usize,
+    // Of the first frame, all the others must match it
+    resolution: Option<(usize, usize)>
+}
+
+impl FrameWriter {
+    // Creates the directory if it doesn't exist yet
+    pub fn new<P>(directory: P, basename: &str, format: FrameFormat) -> Result<Self, Error>
+        where P: AsRef<Path>
+    {
+        fs::create_dir_all(directory.as_ref())?;
+        Ok(FrameWriter {
+            directory: directory.as_ref().to_path_buf(),
+            basename: basename.to_string(),
+            format,
+            padding: 4,
+            overwrite: false,
+            gamma: 2.0,
+            fps: 24.0,
+            seed: None,
+            frames: 0,
+            resolution: None
+        })
+    }
+
+    pub fn get_padding(&self) -> usize {
+        self.padding
+    }
+
+    // Minimum number of digits of the frame numbers, larger numbers
+    // just get more digits
+    pub fn set_padding(&mut self, padding: usize) {
+        self.padding = padding;
+    }
+
+    pub fn get_overwrite(&self) -> bool {
+        self.overwrite
+    }
+
+    // Existing files of the sequence are only replaced if allowed
+    pub fn set_overwrite(&mut self, overwrite: bool) {
+        self.overwrite = overwrite;
+    }
+
+    pub fn get_gamma(&self) -> f64 {
+        self.gamma
+    }
+
+    pub fn set_gamma(&mut self, gamma: f64) {
+        self.gamma = gamma;
+    }
+
+    pub fn get_fps(&self) -> f64 {
+        self.fps
+    }
+
+    pub fn set_fps(&mut self, fps: f64) {
+        self.fps = fps;
+    }
+
+    pub fn get_seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    // Only recorded in the manifest, to be able to render the sequence again
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    pub fn get_frame_count(&self) -> usize {
+        self.frames
+    }
+
+    // Frames are numbered from 1
+    pub fn frame_path(&self, frame: usize) -> PathBuf {
+        self.directory.join(self.frame_name(frame))
+    }
+
+    fn frame_name(&self, frame: usize) -> String {
+        format!("{}_{:0width$}.{}", self.basename, frame, self.format.get_extension(), width = self.padding)
+    }
+
+    pub fn manifest_path(&self) -> PathBuf {
+        self.directory.join(format!("{}.manifest", self.basename))
+    }
+
+    // Writes the next frame and updates the manifest, returns the path of the frame
+    pub fn write_frame<T>(&mut self, image: &Image<T>) -> Result<PathBuf, Error>
+        where T: Float
+    {
+        let (expected_width, expected_height) = self.resolution.unwrap_or((image.width, image.height));
+        if image.width != expected_width || image.height != expected_height {
+            return Err(Error::FrameSize { width: image.width, height: image.height, expected_width, expected_height });
+        }
+
+        let path = self.frame_path(self.frames + 1);
+        if !self.overwrite && path.exists() {
+            return Err(Error::FileExists(path));
+        }
+        let gamma = T::from_f64(self.gamma);
+        match self.format {
+            FrameFormat::Ppm => image.save_ppm(&path, gamma)?,
+            FrameFormat::PpmBinary => image.save_ppm_binary(&path, gamma)?,
+            #[cfg(feature = "image")]
+            FrameFormat::Png => {
+                let alpha = vec![T::one(); image.width * image.height];
+                ImageRgba::from_rgb(image, &alpha).save_png(&path, gamma)?
+            }
+        }
+
+        self.frames += 1;
+        self.resolution = Some((expected_width, expected_height));
+        self.write_manifest()?;
+        Ok(path)
+    }
+
+    fn write_manifest(&self) -> Result<(), Error> {
+        let (width, height) = self.resolution.unwrap_or((0, 0));
+        let seed = self.seed.map_or("none".to_string(), |seed| seed.to_string());
+        let text = format!(
+            "frames {}\nwidth {}\nheight {}\nfps {}\nseed {}\nformat {}\ngamma {}\nfirst {}\n",
+            self.frames, width, height, self.fps, seed, self.format.get_name(), self.gamma,
+            self.frame_name(1)
+        );
+        fs::write(self.manifest_path(), text)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("frame_writer_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn numbering() {
+        let directory = directory("numbering");
+        let mut writer = FrameWriter::new(&directory, "shot", FrameFormat::Ppm).unwrap();
+        assert!(directory.is_dir());
+        assert_eq!(writer.frame_path(1), directory.join("shot_0001.ppm"));
+        writer.set_padding(3);
+        assert_eq!(writer.frame_path(999), directory.join("shot_999.ppm"));
+        assert_eq!(writer.frame_path(1000), directory.join("shot_1000.ppm"));
+
+        #[cfg(feature = "image")]
+        {
+            let writer = FrameWriter::new(&directory, "shot", FrameFormat::Png).unwrap();
+            assert_eq!(writer.frame_path(1), directory.join("shot_0001.png"));
+            assert_eq!(writer.frame_path(12345), directory.join("shot_12345.png"));
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn overwrite() {
+        let directory = directory("overwrite");
+        let image = Image::<f64>::new(2, 1);
+        let mut writer = FrameWriter::new(&directory, "shot", FrameFormat::PpmBinary).unwrap();
+        assert_eq!(writer.write_frame(&image).unwrap(), directory.join("shot_0001.ppm"));
+        match writer.write_frame(&Image::<f64>::new(1, 2)) {
+            Err(Error::FrameSize { width: 1, height: 2, expected_width: 2, expected_height: 1 }) => (),
+            _ => panic!("expected a frame size error")
+        }
+
+        // A second writer would replace the sequence of the first one
+        let mut writer = FrameWriter::new(&directory, "shot", FrameFormat::PpmBinary).unwrap();
+        match writer.write_frame(&image) {
+            Err(Error::FileExists(path)) => assert_eq!(path, directory.join("shot_0001.ppm")),
+            _ => panic!("expected a file exists error")
+        }
+        writer.set_overwrite(true);
+        assert!(writer.write_frame(&image).is_ok());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn manifest() {
+        let directory = directory("manifest");
+        let mut writer = FrameWriter::new(&directory, "orbit", FrameFormat::Ppm).unwrap();
+        writer.set_fps(30.0);
+        writer.set_seed(Some(7));
+        writer.set_gamma(2.2);
+        let mut image = Image::<f32>::new(4, 3);
+        image.data[0] = 0.5;
+        for _ in 0..3 {
+            writer.write_frame(&image).unwrap();
+        }
+        assert_eq!(writer.get_frame_count(), 3);
+
+        let manifest = fs::read_to_string(writer.manifest_path()).unwrap();
+        assert_eq!(manifest, "frames 3\nwidth 4\nheight 3\nfps 30\nseed 7\nformat ppm\ngamma 2.2\nfirst orbit_0001.ppm\n");
+        // Every frame is encoded identically
+        let first = fs::read(writer.frame_path(1)).unwrap();
+        assert_eq!(first, fs::read(writer.frame_path(3)).unwrap());
+
+        #[cfg(feature = "image")]
+        {
+            let mut writer = FrameWriter::new(&directory, "still", FrameFormat::Png).unwrap();
+            assert_eq!(writer.write_frame(&image).unwrap(), directory.join("still_0001.png"));
+            let manifest = fs::read_to_string(writer.manifest_path()).unwrap();
+            assert_eq!(manifest, "frames 1\nwidth 4\nheight 3\nfps 24\nseed none\nformat png\ngamma 2\nfirst still_0001.png\n");
+            assert_eq!(&fs::read(writer.frame_path(1)).unwrap()[..8], b"\x89PNG\r\n\x1a\n");
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
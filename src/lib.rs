@@ -8,10 +8,23 @@ pub mod scene;
 pub mod renderer;
 pub mod material;
 pub mod actor;
+pub mod aabb;
 pub mod boundingbox;
 pub mod texture;
 pub mod tree;
 pub mod constants;
+pub mod sampling;
+pub mod precision;
+pub mod interval;
+pub mod error;
+pub mod animation;
+pub mod io;
+pub mod photon;
+pub mod environment;
+pub mod prelude;
+pub mod scenes;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 
 mod utils;
 
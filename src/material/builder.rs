@@ -0,0 +1,132 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::material::Material;
+use crate::material::lambertian::LambertianMaterial;
+use crate::material::metal::MetalMaterial;
+use crate::material::dielectric::DielectricMaterial;
+use crate::material::plain::PlainMaterial;
+use crate::material::isotropic::IsotropicMaterial;
+use crate::texture::Texture;
+use crate::texture::uniform::UniformTexture;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Kind {
+    Lambertian,
+    Metal,
+    Dielectric,
+    Plain,
+    Isotropic
+}
+
+// Shorthand for materials, i.e.
+// MaterialBuilder::<f64>::metal().color([0.8, 0.8, 0.8]).fuzziness(0.1).build()
+// The color is white unless given, the parameters that don't belong to the
+// material are ignored.
+pub struct MaterialBuilder<T>
+    where T: Float
+{
+    kind: Kind,
+    texture: Option<Box<dyn Texture<T>>>,
+    dimming: T,
+    fuzziness: T,
+    ior: T
+}
+
+impl<T> MaterialBuilder<T>
+    where T: Float
+{
+    fn new(kind: Kind) -> Self {
+        MaterialBuilder {
+            kind,
+            texture: None,
+            dimming: T::one(),
+            fuzziness: T::zero(),
+            ior: T::from_f64(1.5)
+        }
+    }
+
+    pub fn lambertian() -> Self {
+        MaterialBuilder::new(Kind::Lambertian)
+    }
+
+    pub fn metal() -> Self {
+        MaterialBuilder::new(Kind::Metal)
+    }
+
+    pub fn dielectric() -> Self {
+        MaterialBuilder::new(Kind::Dielectric)
+    }
+
+    // Light source, the color is returned as is
+    pub fn plain() -> Self {
+        MaterialBuilder::new(Kind::Plain)
+    }
+
+    // Scatters into any direction, for the inside of a ConstantMedium
+    pub fn isotropic() -> Self {
+        MaterialBuilder::new(Kind::Isotropic)
+    }
+
+    pub fn color(self, color: [T; 3]) -> Self {
+        self.texture(Box::new(UniformTexture::new(Vec3::from_array(color))))
+    }
+
+    pub fn texture(mut self, texture: Box<dyn Texture<T>>) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    // Lambertian only, 1 by default
+    pub fn dimming(mut self, dimming: T) -> Self {
+        self.dimming = dimming;
+        self
+    }
+
+    // Metal only, 0 (a perfect mirror) by default
+    pub fn fuzziness(mut self, fuzziness: T) -> Self {
+        self.fuzziness = fuzziness;
+        self
+    }
+
+    // Dielectric only, 1.5 (glass) by default
+    pub fn ior(mut self, ior: T) -> Self {
+        self.ior = ior;
+        self
+    }
+
+    pub fn build(self) -> Box<dyn Material<T>> {
+        let texture = self.texture.unwrap_or_else(|| Box::new(UniformTexture::new(Vec3::from_array([T::one(); 3]))));
+        match self.kind {
+            Kind::Lambertian => Box::new(LambertianMaterial::new(texture, self.dimming)),
+            Kind::Metal => Box::new(MetalMaterial::new(texture, self.fuzziness)),
+            Kind::Dielectric => Box::new(DielectricMaterial::new(texture, self.ior)),
+            Kind::Plain => Box::new(PlainMaterial::new(texture)),
+            Kind::Isotropic => Box::new(IsotropicMaterial::new(texture))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+    use crate::hit::Hit;
+
+    #[test]
+    fn build() {
+        let ray = Ray::from_array([0.0, 0.0, 2.0], [0.0, 0.0, -1.0]);
+        let hit = Hit::with_face_normal(&ray, Vec3::new(), Vec3::from_array([0.0, 0.0, 1.0]), 2.0, 0.0, 0.0);
+
+        let material = MaterialBuilder::<f64>::lambertian().color([0.2, 0.4, 0.6]).dimming(0.5).build();
+        assert_eq!(material.get_base_color(&hit).get_data(), [0.1, 0.2, 0.3]);
+        assert!(!material.is_specular());
+
+        let material = MaterialBuilder::<f64>::metal().fuzziness(0.0).build();
+        assert!(material.is_specular());
+        assert_eq!(material.get_base_color(&hit).get_data(), [1.0, 1.0, 1.0]);
+
+        let material = MaterialBuilder::<f64>::dielectric().color([0.9, 0.9, 0.9]).ior(1.33).build();
+        assert_eq!(material.get_refractive_index(), Some(1.33));
+        assert_eq!(MaterialBuilder::<f64>::dielectric().build().get_refractive_index(), Some(1.5));
+    }
+}
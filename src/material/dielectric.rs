@@ -1,4 +1,6 @@
 
+use rand::RngCore;
+
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
@@ -6,12 +8,70 @@ use crate::hit::Hit;
 use crate::material::{Scatter, Material};
 use crate::utils::refract;
 use crate::texture::Texture;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
+
+// How the refractive index depends on the wavelength, in micrometers in
+// the formulas (the rays carry it in nm)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Dispersion<T>
+    where T: Float
+{
+    // n = a + b / λ^2
+    Cauchy { a: T, b: T },
+    // n^2 = 1 + Σ b_i λ^2 / (λ^2 - c_i), c_i in µm^2
+    Sellmeier { b: [T; 3], c: [T; 3] }
+}
+
+impl<T> Dispersion<T>
+    where T: Float
+{
+    // Borosilicate crown glass (Schott N-BK7)
+    pub fn bk7() -> Self {
+        Dispersion::Sellmeier {
+            b: [T::from_f64(1.03961212), T::from_f64(0.231792344), T::from_f64(1.01046945)],
+            c: [T::from_f64(0.00600069867), T::from_f64(0.0200179144), T::from_f64(103.560653)]
+        }
+    }
+
+    pub fn refractive_index(&self, wavelength: T) -> T {
+        let micrometers = wavelength / T::from_f64(1000.0);
+        let l2 = micrometers * micrometers;
+        match self {
+            Dispersion::Cauchy { a, b } => *a + *b / l2,
+            Dispersion::Sellmeier { b, c } => {
+                let mut n2 = T::one();
+                for i in 0..3 {
+                    n2 = n2 + b[i] * l2 / (l2 - c[i]);
+                }
+                n2.sqrt()
+            }
+        }
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Dispersion<T>
+    where T: Float, U: Precision
+{
+    type Output = Dispersion<U>;
 
+    fn convert_precision(&self) -> Result<Dispersion<U>, PrecisionError> {
+        Ok(match self {
+            Dispersion::Cauchy { a, b } => Dispersion::Cauchy { a: convert(*a), b: convert(*b) },
+            Dispersion::Sellmeier { b, c } => Dispersion::Sellmeier {
+                b: [convert(b[0]), convert(b[1]), convert(b[2])],
+                c: [convert(c[0]), convert(c[1]), convert(c[2])]
+            }
+        })
+    }
+}
+
+#[derive(Clone)]
 pub struct DielectricMaterial<T>
     where T: Float
 {
     texture: Box<dyn Texture<T>>,
-    n: T
+    n: T,
+    dispersion: Option<Dispersion<T>>
 }
 
 impl<T> DielectricMaterial<T>
@@ -20,36 +80,119 @@ impl<T> DielectricMaterial<T>
     pub fn new(texture: Box<dyn Texture<T>>, n: T) -> Self {
         DielectricMaterial {
             texture,
-            n
+            n,
+            dispersion: None
         }
     }
+
+    pub fn get_dispersion(&self) -> Option<&Dispersion<T>> {
+        self.dispersion.as_ref()
+    }
+
+    // Only rays carrying a wavelength (spectral rendering) are dispersed,
+    // the others keep the refractive index of new
+    pub fn set_dispersion(&mut self, dispersion: Option<Dispersion<T>>) {
+        self.dispersion = dispersion;
+    }
 }
 
 impl<T> Material<T> for DielectricMaterial<T>
     where T: Float
 {
-    fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T> {
-        let mut outward_normal = &hit.normal * (-T::one());
-        let mut n0 = self.n;
-        let mut n1 = T::one();
-        let color = self.texture.get_color(T::zero(), T::zero(), &hit.point);
-        let attenuation = Vec3::<T>::from_slice(color.get_data());
-        let c = incident.get_direction().dot(&hit.normal);
+    fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>, rng: &mut dyn RngCore) -> Scatter<T> {
+        self.scatter_in_medium(incident, hit, T::one(), rng)
+    }
 
-        if c < T::zero() {
-            outward_normal.set_data(hit.normal.get_data());
-            n1 = self.n;
-            n0 = T::one();
+    fn get_refractive_index(&self) -> Option<T> {
+        Some(self.n)
+    }
+
+    fn get_refractive_index_at(&self, wavelength: Option<T>) -> Option<T> {
+        match (&self.dispersion, wavelength) {
+            (Some(dispersion), Some(wavelength)) => Some(dispersion.refractive_index(wavelength)),
+            _ => Some(self.n)
         }
+    }
+
+    fn scatter_in_medium(&self, incident: &Ray<T>, hit: &Hit<T>, n_outside: T, rng: &mut dyn RngCore) -> Scatter<T> {
+        let color = self.texture.get_color_at_hit(hit);
+        let attenuation = Vec3::<T>::from_slice(color.get_data());
+        // Entering or leaving the material
+        let n = self.get_refractive_index_at(incident.get_wavelength()).unwrap_or(self.n);
+        let (n0, n1) = if hit.front_face { (n_outside, n) } else { (n, n_outside) };
 
-        let mut direction = refract(incident.get_direction(), &outward_normal, n0, n1);
+        let mut direction = refract(incident.get_direction(), &hit.normal, n0, n1, rng);
         let origin = Vec3::from_slice(hit.point.get_data());
         direction.normalize();
 
-        let scattered = Some(Ray::<T>::from_vec(origin, direction));
+        let mut scattered = Ray::<T>::from_vec(origin, direction);
+        scattered.set_wavelength(incident.get_wavelength());
+        let scattered = Some(scattered);
         Scatter::<T> {
             attenuation,
             scattered
         }
     }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn get_base_color(&self, hit: &Hit<T>) -> Vec3<T> {
+        self.texture.get_color_at_hit(hit)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Material<f32>>, PrecisionError> {
+        let material: DielectricMaterial<f32> = self.convert_precision()?;
+        Ok(Box::new(material))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Material<f64>>, PrecisionError> {
+        let material: DielectricMaterial<f64> = self.convert_precision()?;
+        Ok(Box::new(material))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for DielectricMaterial<T>
+    where T: Float, U: Precision
+{
+    type Output = DielectricMaterial<U>;
+
+    fn convert_precision(&self) -> Result<DielectricMaterial<U>, PrecisionError> {
+        let mut material = DielectricMaterial::new(self.texture.convert_precision()?, convert(self.n));
+        if let Some(dispersion) = &self.dispersion {
+            material.set_dispersion(Some(dispersion.convert_precision()?));
+        }
+        Ok(material)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::uniform::UniformTexture;
+
+    #[test]
+    fn dispersion() {
+        // N-BK7 at the Fraunhofer lines F, d and C
+        let bk7 = Dispersion::<f64>::bk7();
+        assert!((bk7.refractive_index(486.13) - 1.52238).abs() < 1e-5);
+        assert!((bk7.refractive_index(587.56) - 1.51680).abs() < 1e-5);
+        assert!((bk7.refractive_index(656.27) - 1.51432).abs() < 1e-5);
+        let cauchy = Dispersion::<f64>::Cauchy { a: 1.5, b: 0.01 };
+        assert!((cauchy.refractive_index(500.0) - 1.54).abs() < 1e-12);
+
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+        let mut material = DielectricMaterial::<f64>::new(texture, 1.5);
+        assert_eq!(material.get_refractive_index_at(Some(500.0)), Some(1.5));
+        material.set_dispersion(Some(cauchy));
+        assert_eq!(material.get_refractive_index_at(Some(500.0)), Some(cauchy.refractive_index(500.0)));
+        assert_eq!(material.get_refractive_index_at(None), Some(1.5));
+        let converted: DielectricMaterial<f32> = material.convert_precision().unwrap();
+        assert_eq!(converted.get_dispersion(), Some(&Dispersion::Cauchy { a: 1.5, b: 0.01 }));
+    }
 }
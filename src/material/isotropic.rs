@@ -0,0 +1,71 @@
+use rand::RngCore;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::material::{Scatter, Material};
+use crate::texture::Texture;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError};
+
+// Scatters uniformly in all directions, i.e. in participating media
+#[derive(Clone)]
+pub struct IsotropicMaterial<T>
+    where T: Float
+{
+    texture: Box<dyn Texture<T>>
+}
+
+impl<T> IsotropicMaterial<T>
+    where T: Float
+{
+    pub fn new(texture: Box<dyn Texture<T>>) -> Self {
+        IsotropicMaterial {
+            texture
+        }
+    }
+}
+
+impl<T> Material<T> for IsotropicMaterial<T>
+    where T: Float
+{
+    fn scatter(&self, _incident: &Ray<T>, hit: &Hit<T>, rng: &mut dyn RngCore) -> Scatter<T> {
+        let color = self.texture.get_color_at_hit(hit);
+        let attenuation = Vec3::<T>::from_slice(color.get_data());
+        let origin = Vec3::from_slice(hit.point.get_data());
+        let direction = Vec3::random_on_unit_sphere(rng);
+        let scattered = Some(Ray::<T>::from_vec(origin, direction));
+        Scatter::<T> {
+            attenuation,
+            scattered
+        }
+    }
+
+    fn get_base_color(&self, hit: &Hit<T>) -> Vec3<T> {
+        self.texture.get_color_at_hit(hit)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Material<f32>>, PrecisionError> {
+        let material: IsotropicMaterial<f32> = self.convert_precision()?;
+        Ok(Box::new(material))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Material<f64>>, PrecisionError> {
+        let material: IsotropicMaterial<f64> = self.convert_precision()?;
+        Ok(Box::new(material))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for IsotropicMaterial<T>
+    where T: Float, U: Precision
+{
+    type Output = IsotropicMaterial<U>;
+
+    fn convert_precision(&self) -> Result<IsotropicMaterial<U>, PrecisionError> {
+        Ok(IsotropicMaterial::new(self.texture.convert_precision()?))
+    }
+}
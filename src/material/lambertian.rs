@@ -1,12 +1,15 @@
 
+use rand::RngCore;
+
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::hit::Hit;
 use crate::material::{Scatter, Material};
-use crate::utils::random_point_in_sphere;
 use crate::texture::Texture;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
 
+#[derive(Clone)]
 pub struct LambertianMaterial<T>
     where T: Float
 {
@@ -28,13 +31,17 @@ impl<T> LambertianMaterial<T>
 impl<T> Material<T> for LambertianMaterial<T>
     where T: Float
 {
-    fn scatter(&self, _incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T> {
-        let color = self.texture.get_color(T::zero(), T::zero(), &hit.point);
+    fn scatter(&self, _incident: &Ray<T>, hit: &Hit<T>, rng: &mut dyn RngCore) -> Scatter<T> {
+        let color = self.texture.get_color_at_hit(hit);
         let attenuation = Vec3::<T>::from_slice(color.get_data()) * self.dimming;
         let mut normal = Vec3::from_slice(hit.normal.get_data());
         normal.normalize();
         let origin = Vec3::from_slice(hit.point.get_data());
-        let mut direction = normal + random_point_in_sphere(T::one());
+        let mut direction = &normal + Vec3::random_in_unit_sphere(rng);
+        // The random offset can cancel out the normal, which would produce NaNs
+        if direction.is_near_zero(T::from_f64(1e-8)) {
+            direction = normal;
+        }
         direction.normalize();
         let scattered = Some(Ray::<T>::from_vec(origin, direction));
         Scatter::<T> {
@@ -42,4 +49,47 @@ impl<T> Material<T> for LambertianMaterial<T>
             scattered
         }
     }
+
+    // Offsetting the normal by a point in the unit sphere gives directions
+    // with the density 2 cos^3 / pi instead of cos / pi
+    fn brdf(&self, hit: &Hit<T>, incoming: &Vec3<T>) -> Vec3<T> {
+        let color = self.texture.get_color_at_hit(hit);
+        let cos = -incoming.dot(&hit.normal) / (incoming.norm() * hit.normal.norm());
+        let cos = cos.max(T::zero());
+        color * (self.dimming * T::from_f64(2.0 / std::f64::consts::PI) * cos * cos)
+    }
+
+    fn scatter_pdf(&self, hit: &Hit<T>, direction: &Vec3<T>) -> Option<T> {
+        let cos = direction.dot(&hit.normal) / (direction.norm() * hit.normal.norm());
+        let cos = cos.max(T::zero());
+        Some(T::from_f64(2.0 / std::f64::consts::PI) * cos * cos * cos)
+    }
+
+    fn get_base_color(&self, hit: &Hit<T>) -> Vec3<T> {
+        self.texture.get_color_at_hit(hit) * self.dimming
+    }
+
+    fn clone_box(&self) -> Box<dyn Material<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Material<f32>>, PrecisionError> {
+        let material: LambertianMaterial<f32> = self.convert_precision()?;
+        Ok(Box::new(material))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Material<f64>>, PrecisionError> {
+        let material: LambertianMaterial<f64> = self.convert_precision()?;
+        Ok(Box::new(material))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for LambertianMaterial<T>
+    where T: Float, U: Precision
+{
+    type Output = LambertianMaterial<U>;
+
+    fn convert_precision(&self) -> Result<LambertianMaterial<U>, PrecisionError> {
+        Ok(LambertianMaterial::new(self.texture.convert_precision()?, convert(self.dimming)))
+    }
 }
@@ -1,12 +1,16 @@
 
+use rand::RngCore;
+
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::hit::Hit;
 use crate::material::{Scatter, Material};
-use crate::utils::{random_point_in_sphere, reflect};
+use crate::utils::reflect;
 use crate::texture::Texture;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
 
+#[derive(Clone)]
 pub struct MetalMaterial<T>
     where T: Float
 {
@@ -28,15 +32,15 @@ impl<T> MetalMaterial<T>
 impl<T> Material<T> for MetalMaterial<T>
     where T: Float
 {
-    fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T> {
-        let color = self.texture.get_color(T::zero(), T::zero(), &hit.point);
+    fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>, rng: &mut dyn RngCore) -> Scatter<T> {
+        let color = self.texture.get_color_at_hit(hit);
         let attenuation = Vec3::<T>::from_slice(color.get_data());
         let normal = &hit.normal;
         let origin = Vec3::from_slice(hit.point.get_data());
         let mut direction = reflect(incident.get_direction(), &normal);
         direction.normalize();
         if self.fuzziness > T::zero() {
-          direction = direction + random_point_in_sphere(self.fuzziness);
+          direction = direction + Vec3::random_in_unit_sphere(rng) * self.fuzziness;
           direction.normalize();
         }
         let scattered = Some(Ray::<T>::from_vec(origin, direction));
@@ -45,4 +49,36 @@ impl<T> Material<T> for MetalMaterial<T>
             scattered
         }
     }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn get_base_color(&self, hit: &Hit<T>) -> Vec3<T> {
+        self.texture.get_color_at_hit(hit)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Material<f32>>, PrecisionError> {
+        let material: MetalMaterial<f32> = self.convert_precision()?;
+        Ok(Box::new(material))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Material<f64>>, PrecisionError> {
+        let material: MetalMaterial<f64> = self.convert_precision()?;
+        Ok(Box::new(material))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for MetalMaterial<T>
+    where T: Float, U: Precision
+{
+    type Output = MetalMaterial<U>;
+
+    fn convert_precision(&self) -> Result<MetalMaterial<U>, PrecisionError> {
+        Ok(MetalMaterial::new(self.texture.convert_precision()?, convert(self.fuzziness)))
+    }
 }
@@ -1,12 +1,20 @@
+use rand::RngCore;
+
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::hit::Hit;
+use crate::precision::PrecisionError;
 
 pub mod plain;
 pub mod lambertian;
 pub mod metal;
 pub mod dielectric;
+pub mod null;
+pub mod isotropic;
+pub mod builder;
+
+pub use builder::MaterialBuilder;
 
 pub struct Scatter<T>
     where T: Float
@@ -15,8 +23,72 @@ pub struct Scatter<T>
     pub scattered: Option<Ray<T>>
 }
 
-pub trait Material<T>
+pub trait Material<T>: Send + Sync
+    where T: Float
+{
+    // The random numbers come from rng, to make the paths reproducible
+    fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>, rng: &mut dyn RngCore) -> Scatter<T>;
+
+    // The unlit color of the surface at the hit, i.e. for an albedo pass
+    fn get_base_color(&self, _hit: &Hit<T>) -> Vec3<T> {
+        Vec3::new()
+    }
+
+    // Refractive index of the medium enclosed by the surface, None if
+    // the material doesn't bound a medium
+    fn get_refractive_index(&self) -> Option<T> {
+        None
+    }
+
+    // The refractive index for light of the wavelength (in nm), the same for
+    // all of them unless the material disperses light
+    fn get_refractive_index_at(&self, _wavelength: Option<T>) -> Option<T> {
+        self.get_refractive_index()
+    }
+
+    // Share of the light arriving in the direction incoming that scatter
+    // sends to any other direction, per solid angle, i.e. to gather photons.
+    // The default is an ideal diffuse surface of the base color.
+    fn brdf(&self, hit: &Hit<T>, _incoming: &Vec3<T>) -> Vec3<T> {
+        self.get_base_color(hit) / T::from_f64(std::f64::consts::PI)
+    }
+
+    // Density per solid angle of scatter sending the ray into the direction,
+    // None if it isn't known. The environment is only sampled directly at
+    // hits with a known density, to weight both ways of finding it.
+    fn scatter_pdf(&self, _hit: &Hit<T>, _direction: &Vec3<T>) -> Option<T> {
+        None
+    }
+
+    // Mirrors and glass scatter into a single direction (or a few). Only
+    // diffuse surfaces receive caustics from a photon map.
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    // Like scatter, but the medium on the other side of the surface has
+    // the refractive index n_outside instead of vacuum
+    fn scatter_in_medium(&self, incident: &Ray<T>, hit: &Hit<T>, _n_outside: T, rng: &mut dyn RngCore) -> Scatter<T> {
+        self.scatter(incident, hit, rng)
+    }
+
+    // A copy in a new box, to clone actors and scenes. Required of every
+    // material, for one that is Clone it's Box::new(self.clone()).
+    fn clone_box(&self) -> Box<dyn Material<T>>;
+
+    fn convert_f32(&self) -> Result<Box<dyn Material<f32>>, PrecisionError> {
+        Err(PrecisionError::unsupported::<Self>())
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Material<f64>>, PrecisionError> {
+        Err(PrecisionError::unsupported::<Self>())
+    }
+}
+
+impl<T> Clone for Box<dyn Material<T>>
     where T: Float
 {
-    fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T>;
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
 }
@@ -0,0 +1,56 @@
+use rand::RngCore;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::material::{Scatter, Material};
+use crate::precision::PrecisionError;
+
+// A material that doesn't interact with light: rays go through the surface
+// unchanged. Useful as the boundary of volumes, where the scattering happens
+// inside the medium rather than on its surface.
+#[derive(Clone, Default)]
+pub struct NullMaterial {}
+
+impl NullMaterial {
+    pub fn new() -> Self {
+        NullMaterial {}
+    }
+}
+
+impl<T> Material<T> for NullMaterial
+    where T: Float
+{
+    fn scatter(&self, incident: &Ray<T>, hit: &Hit<T>, _rng: &mut dyn RngCore) -> Scatter<T> {
+        let attenuation = Vec3::<T>::from_array([T::one(), T::one(), T::one()]);
+        let origin = Vec3::from_slice(hit.point.get_data());
+        let direction = Vec3::from_slice(incident.get_direction().get_data());
+        let scattered = Some(Ray::<T>::from_vec(origin, direction));
+        Scatter::<T> {
+            attenuation,
+            scattered
+        }
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    // Lets everything through, like the attenuation
+    fn get_base_color(&self, _hit: &Hit<T>) -> Vec3<T> {
+        Vec3::from_array([T::one(), T::one(), T::one()])
+    }
+
+    fn clone_box(&self) -> Box<dyn Material<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Material<f32>>, PrecisionError> {
+        Ok(Box::new(NullMaterial::new()))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Material<f64>>, PrecisionError> {
+        Ok(Box::new(NullMaterial::new()))
+    }
+}
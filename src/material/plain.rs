@@ -1,10 +1,14 @@
+use rand::RngCore;
+
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::hit::Hit;
 use crate::material::{Scatter, Material};
 use crate::texture::Texture;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError};
 
+#[derive(Clone)]
 pub struct PlainMaterial<T>
     where T: Float
 {
@@ -24,12 +28,40 @@ impl<T> PlainMaterial<T>
 impl<T> Material<T> for PlainMaterial<T>
     where T: Float
 {
-    fn scatter(&self, _incident: &Ray<T>, hit: &Hit<T>) -> Scatter<T> {
-        let color = self.texture.get_color(T::zero(), T::zero(), &hit.point);
+    fn scatter(&self, _incident: &Ray<T>, hit: &Hit<T>, _rng: &mut dyn RngCore) -> Scatter<T> {
+        let color = self.texture.get_color_at_hit(hit);
         let attenuation = Vec3::<T>::from_slice(color.get_data());
         Scatter::<T> {
             attenuation,
             scattered: None
         }
     }
+
+    fn get_base_color(&self, hit: &Hit<T>) -> Vec3<T> {
+        self.texture.get_color_at_hit(hit)
+    }
+
+    fn clone_box(&self) -> Box<dyn Material<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Material<f32>>, PrecisionError> {
+        let material: PlainMaterial<f32> = self.convert_precision()?;
+        Ok(Box::new(material))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Material<f64>>, PrecisionError> {
+        let material: PlainMaterial<f64> = self.convert_precision()?;
+        Ok(Box::new(material))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for PlainMaterial<T>
+    where T: Float, U: Precision
+{
+    type Output = PlainMaterial<U>;
+
+    fn convert_precision(&self) -> Result<PlainMaterial<U>, PrecisionError> {
+        Ok(PlainMaterial::new(self.texture.convert_precision()?))
+    }
 }
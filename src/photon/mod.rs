@@ -0,0 +1,209 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::hit::Hit;
+
+// Light arriving at a diffuse surface, stored by the caustic pre-pass
+pub struct Photon<T>
+    where T: Float
+{
+    pub position: Vec3<T>,
+    // Direction the photon traveled in, towards the surface
+    pub direction: Vec3<T>,
+    pub power: Vec3<T>
+}
+
+impl<T> Clone for Photon<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        Photon {
+            position: Vec3::from_slice(self.position.get_data()),
+            direction: Vec3::from_slice(self.direction.get_data()),
+            power: Vec3::from_slice(self.power.get_data())
+        }
+    }
+}
+
+// Photons in a balanced kd-tree, stored in place: the node of the range
+// [begin, end) is the photon in the middle, split along axes[middle]
+#[derive(Clone)]
+pub struct PhotonMap<T>
+    where T: Float
+{
+    photons: Vec<Photon<T>>,
+    axes: Vec<usize>,
+    radius: T
+}
+
+impl<T> PhotonMap<T>
+    where T: Float
+{
+    // Estimates gather the photons within radius of a point
+    pub fn new(photons: Vec<Photon<T>>, radius: T) -> Self {
+        let mut map = PhotonMap {
+            axes: vec![0; photons.len()],
+            photons,
+            radius
+        };
+        let n = map.photons.len();
+        map.build(0, n);
+        map
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    pub fn get_radius(&self) -> T {
+        self.radius
+    }
+
+    pub fn set_radius(&mut self, radius: T) {
+        self.radius = radius;
+    }
+
+    fn build(&mut self, begin: usize, end: usize) {
+        if end - begin < 2 {
+            return;
+        }
+        // Split along the axis with the largest extent
+        let mut p0 = [T::infinity(); 3];
+        let mut p1 = [T::neg_infinity(); 3];
+        for photon in self.photons[begin..end].iter() {
+            for (i, x) in photon.position.get_data().iter().enumerate() {
+                p0[i] = p0[i].min(*x);
+                p1[i] = p1[i].max(*x);
+            }
+        }
+        let mut axis = 0;
+        for i in 1..3 {
+            if p1[i] - p0[i] > p1[axis] - p0[axis] {
+                axis = i;
+            }
+        }
+
+        let middle = (begin + end) / 2;
+        self.photons[begin..end].select_nth_unstable_by(middle - begin, |a, b| {
+            a.position.get_data()[axis].partial_cmp(&b.position.get_data()[axis]).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        self.axes[middle] = axis;
+        self.build(begin, middle);
+        self.build(middle + 1, end);
+    }
+
+    // Calls f with every photon closer than radius to the point
+    pub fn for_each_within<F>(&self, point: &Vec3<T>, radius: T, mut f: F)
+        where F: FnMut(&Photon<T>)
+    {
+        self.search(0, self.photons.len(), point, radius * radius, &mut f);
+    }
+
+    fn search<F>(&self, begin: usize, end: usize, point: &Vec3<T>, radius2: T, f: &mut F)
+        where F: FnMut(&Photon<T>)
+    {
+        if begin >= end {
+            return;
+        }
+        let middle = (begin + end) / 2;
+        let photon = &self.photons[middle];
+        let offset = &photon.position - point;
+        if offset.dot(&offset) < radius2 {
+            f(photon);
+        }
+
+        let axis = self.axes[middle];
+        let distance = point.get_data()[axis] - photon.position.get_data()[axis];
+        let (near, far) = if distance < T::zero() {
+            ((begin, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (begin, middle))
+        };
+        self.search(near.0, near.1, point, radius2, f);
+        if distance * distance < radius2 {
+            self.search(far.0, far.1, point, radius2, f);
+        }
+    }
+
+    // Irradiance at the hit from the photons around it that arrived on the
+    // side the hit is seen from, the power per area of the gather disk
+    pub fn estimate_irradiance(&self, hit: &Hit<T>) -> Vec3<T> {
+        let mut power = Vec3::new();
+        self.for_each_within(&hit.point, self.radius, |photon| {
+            if photon.direction.dot(&hit.normal) < T::zero() {
+                power = &power + &photon.power;
+            }
+        });
+        let area = T::from_f64(std::f64::consts::PI) * self.radius * self.radius;
+        power / area
+    }
+
+    // Light leaving the hit, each photon weighted by the brdf of its direction
+    pub fn estimate_radiance<F>(&self, hit: &Hit<T>, brdf: F) -> Vec3<T>
+        where F: Fn(&Vec3<T>) -> Vec3<T>
+    {
+        let mut radiance = Vec3::new();
+        self.for_each_within(&hit.point, self.radius, |photon| {
+            if photon.direction.dot(&hit.normal) < T::zero() {
+                radiance = &radiance + brdf(&photon.direction) * &photon.power;
+            }
+        });
+        let area = T::from_f64(std::f64::consts::PI) * self.radius * self.radius;
+        radiance / area
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn photon(position: [f64; 3], direction: [f64; 3]) -> Photon<f64> {
+        Photon {
+            position: Vec3::from_array(position),
+            direction: Vec3::from_array(direction),
+            power: Vec3::from_array([1.0, 1.0, 1.0])
+        }
+    }
+
+    #[test]
+    fn search() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let positions: Vec<[f64; 3]> = (0..500).map(|_| [rng.gen(), rng.gen::<f64>() * 4.0, rng.gen::<f64>() * 0.1]).collect();
+        let map = PhotonMap::new(positions.iter().map(|p| photon(*p, [0.0, -1.0, 0.0])).collect(), 0.2);
+        assert_eq!(map.len(), 500);
+
+        // The same photons as a brute force search
+        for _ in 0..20 {
+            let point = Vec3::from_array([rng.gen(), rng.gen::<f64>() * 4.0, 0.0]);
+            let mut found = Vec::new();
+            map.for_each_within(&point, 0.2, |photon| found.push(photon.position.to_array()));
+            let expected: Vec<[f64; 3]> = positions.iter().filter(|p| (&Vec3::from_array(**p) - &point).norm() < 0.2).copied().collect();
+            assert_eq!(found.len(), expected.len());
+            assert!(expected.iter().all(|p| found.contains(p)));
+        }
+    }
+
+    #[test]
+    fn irradiance() {
+        let photons = vec![
+            photon([0.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+            photon([0.5, 0.0, 0.0], [0.0, -1.0, 0.0]),
+            // Arrived from below, or too far away
+            photon([0.0, 0.0, 0.5], [0.0, 1.0, 0.0]),
+            photon([2.0, 0.0, 0.0], [0.0, -1.0, 0.0])
+        ];
+        let map = PhotonMap::new(photons, 1.0);
+        let hit = Hit { point: Vec3::new(), normal: Vec3::from_array([0.0, 1.0, 0.0]), t: 1.0, u: 0.0, v: 0.0, front_face: true, barycentric: None, face: None, footprint: None };
+        let irradiance = map.estimate_irradiance(&hit);
+        assert!((irradiance.get_data()[0] - 2.0 / std::f64::consts::PI).abs() < 1e-12);
+        // Radiance of a white ideal diffuse surface
+        let radiance = map.estimate_radiance(&hit, |_| Vec3::from_array([1.0, 1.0, 1.0]) / std::f64::consts::PI);
+        assert!((radiance.get_data()[0] - irradiance.get_data()[0] / std::f64::consts::PI).abs() < 1e-12);
+        assert!(PhotonMap::<f64>::new(Vec::new(), 1.0).estimate_irradiance(&hit).get_data() == [0.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+use std::error;
+
+use crate::float::Float;
+use crate::hitable::Hitable;
+use crate::material::Material;
+use crate::texture::Texture;
+
+#[derive(Debug)]
+pub struct PrecisionError {
+    type_name: &'static str
+}
+
+impl PrecisionError {
+    pub fn unsupported<S>() -> Self
+        where S: ?Sized
+    {
+        PrecisionError {
+            type_name: std::any::type_name::<S>()
+        }
+    }
+
+    pub fn get_type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl fmt::Display for PrecisionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} does not support precision conversion, override convert_f32/convert_f64 in its trait implementation",
+            self.type_name
+        )
+    }
+}
+
+impl error::Error for PrecisionError {}
+
+// Converts an object built for one floating point precision into the
+// equivalent object for precision U (i.e. a Scene<f32> into a Scene<f64>)
+pub trait ConvertPrecision<U>
+    where U: Precision
+{
+    type Output;
+
+    fn convert_precision(&self) -> Result<Self::Output, PrecisionError>;
+}
+
+// Trait objects can't be converted to a generic precision directly,
+// each supported precision knows which trait method to dispatch to.
+pub trait Precision: Float {
+    fn convert_hitable<T>(hitable: &dyn Hitable<T>) -> Result<Box<dyn Hitable<Self>>, PrecisionError>
+        where T: Float;
+
+    fn convert_material<T>(material: &dyn Material<T>) -> Result<Box<dyn Material<Self>>, PrecisionError>
+        where T: Float;
+
+    fn convert_texture<T>(texture: &dyn Texture<T>) -> Result<Box<dyn Texture<Self>>, PrecisionError>
+        where T: Float;
+}
+
+impl Precision for f32 {
+    fn convert_hitable<T>(hitable: &dyn Hitable<T>) -> Result<Box<dyn Hitable<f32>>, PrecisionError>
+        where T: Float
+    {
+        hitable.convert_f32()
+    }
+
+    fn convert_material<T>(material: &dyn Material<T>) -> Result<Box<dyn Material<f32>>, PrecisionError>
+        where T: Float
+    {
+        material.convert_f32()
+    }
+
+    fn convert_texture<T>(texture: &dyn Texture<T>) -> Result<Box<dyn Texture<f32>>, PrecisionError>
+        where T: Float
+    {
+        texture.convert_f32()
+    }
+}
+
+impl Precision for f64 {
+    fn convert_hitable<T>(hitable: &dyn Hitable<T>) -> Result<Box<dyn Hitable<f64>>, PrecisionError>
+        where T: Float
+    {
+        hitable.convert_f64()
+    }
+
+    fn convert_material<T>(material: &dyn Material<T>) -> Result<Box<dyn Material<f64>>, PrecisionError>
+        where T: Float
+    {
+        material.convert_f64()
+    }
+
+    fn convert_texture<T>(texture: &dyn Texture<T>) -> Result<Box<dyn Texture<f64>>, PrecisionError>
+        where T: Float
+    {
+        texture.convert_f64()
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Box<dyn Hitable<T>>
+    where T: Float, U: Precision
+{
+    type Output = Box<dyn Hitable<U>>;
+
+    fn convert_precision(&self) -> Result<Self::Output, PrecisionError> {
+        U::convert_hitable(self.as_ref())
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Box<dyn Material<T>>
+    where T: Float, U: Precision
+{
+    type Output = Box<dyn Material<U>>;
+
+    fn convert_precision(&self) -> Result<Self::Output, PrecisionError> {
+        U::convert_material(self.as_ref())
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Box<dyn Texture<T>>
+    where T: Float, U: Precision
+{
+    type Output = Box<dyn Texture<U>>;
+
+    fn convert_precision(&self) -> Result<Self::Output, PrecisionError> {
+        U::convert_texture(self.as_ref())
+    }
+}
+
+pub fn convert<T, U>(value: T) -> U
+    where T: Float, U: Float
+{
+    U::from(value).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vec3;
+    use crate::ray::Ray;
+    use crate::hit::Hit;
+    use crate::boundingbox::BoundingBox;
+    use crate::hitable::primitive::Sphere;
+    use crate::hitable::transform::Translation;
+
+    #[derive(Clone)]
+    struct External {
+        bounds: BoundingBox<f64>
+    }
+
+    impl Hitable<f64> for External {
+        fn hit(&self, _ray: &Ray<f64>, _t_min: f64, _t_max: f64) -> Option<Hit<f64>> {
+            None
+        }
+
+        fn get_bounds(&self) -> &BoundingBox<f64> {
+            &self.bounds
+        }
+
+        fn unwrap(self: Box<Self>) -> Box<dyn Hitable<f64>> {
+            self
+        }
+
+        fn clone_box(&self) -> Box<dyn Hitable<f64>> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn hitable() {
+        let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::new(2.0));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([1.0, 2.0, 3.0])));
+        let hitable: Box<dyn Hitable<f64>> = hitable;
+        let converted: Box<dyn Hitable<f32>> = hitable.convert_precision().unwrap();
+        assert_eq!(converted.get_bounds().get_p0().get_data(), [-1.0, 0.0, 1.0]);
+        assert_eq!(converted.get_bounds().get_p1().get_data(), [3.0, 4.0, 5.0]);
+
+        let back: Box<dyn Hitable<f64>> = converted.convert_precision().unwrap();
+        assert_eq!(back.get_bounds().get_p1().get_data(), [3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn unsupported() {
+        let bounds = BoundingBox::new(Vec3::new(), Vec3::new());
+        let hitable: Box<dyn Hitable<f64>> = Box::new(External { bounds });
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([1.0, 2.0, 3.0])));
+        let hitable: Box<dyn Hitable<f64>> = hitable;
+        let result: Result<Box<dyn Hitable<f32>>, PrecisionError> = hitable.convert_precision();
+        match result {
+            Ok(_) => assert!(false),
+            Err(e) => {
+                assert!(e.get_type_name().ends_with("External"));
+                assert!(e.to_string().contains("External"));
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! The types needed to build and render a scene, for a single glob import.
+//! No names collide, so everything keeps the name of its module.
+//!
+//! ```
+//! use ray_tracer::prelude::*;
+//!
+//! let mut scene = Scene::<f64>::new();
+//! scene.set_background(Vec3::from_array([0.5, 0.7, 0.9]));
+//! scene.add_actor(Actor::sphere(1.0).at([0.0, 0.0, -4.0]).lambertian([1.0, 0.2, 0.2], 0.5));
+//! scene.set_tree_type(TreeType::Oct);
+//!
+//! let mut camera = PerspectiveCamera::<f64>::new();
+//! camera.set_position(&[0.0, 0.0, 0.0]);
+//! camera.set_lookat(&[0.0, 0.0, -4.0]);
+//!
+//! let renderer = Renderer::new(0, 8, 0, 8, 8, 8, 4, 4, true).unwrap();
+//! let image: Image<f64> = renderer.render(&scene, &camera);
+//! assert_eq!(image.data.len(), 3 * 8 * 8);
+//! ```
+
+pub use crate::float::Float;
+pub use crate::vector::Vec3;
+pub use crate::ray::Ray;
+pub use crate::hit::Hit;
+pub use crate::interval::Interval;
+pub use crate::aabb::Aabb;
+pub use crate::constants::Axis;
+pub use crate::hitable::{Hitable, SampleablePrimitive};
+pub use crate::hitable::primitive::{Sphere, Rectangle, Cube, Group, ConstantMedium, TriangleMesh, Cone, Wedge, Portal};
+pub use crate::hitable::transform::{Translation, KeyframedTransform};
+pub use crate::material::Material;
+pub use crate::material::plain::PlainMaterial;
+pub use crate::material::lambertian::LambertianMaterial;
+pub use crate::material::metal::MetalMaterial;
+pub use crate::material::dielectric::{DielectricMaterial, Dispersion};
+pub use crate::material::null::NullMaterial;
+pub use crate::material::isotropic::IsotropicMaterial;
+pub use crate::material::MaterialBuilder;
+pub use crate::texture::Texture;
+pub use crate::texture::uniform::UniformTexture;
+pub use crate::texture::checker::CheckerTexture;
+pub use crate::texture::perlin::PerlinNoiseTexture;
+pub use crate::texture::image::ImageTexture;
+pub use crate::actor::{Actor, ActorBuilder, ActorFlags};
+pub use crate::scene::{Scene, SceneStats, SelfIntersectPolicy};
+pub use crate::tree::TreeType;
+pub use crate::camera::Camera;
+pub use crate::camera::perspective::PerspectiveCamera;
+pub use crate::renderer::{Renderer, Integrator, Image, ImageRgba, RenderReport, DepthEncoding, DepthFilter, EdgeParams, RenderPassFlags, RenderResult, Exposure};
+pub use crate::io::{FrameWriter, FrameFormat};
+pub use crate::photon::{Photon, PhotonMap};
+pub use crate::environment::Environment;
+pub use crate::sampling::Sampler;
+pub use crate::animation::{Keyframe, CameraTrack, Interpolation, render_sequence};
+pub use crate::precision::ConvertPrecision;
@@ -6,7 +6,17 @@ pub struct Ray<T>
     where T: Float
 {
     origin: Vec3<T>,
-    direction: Vec3<T>
+    direction: Vec3<T>,
+    // Cached for the bounding box tests of the trees
+    inv_direction: Vec3<T>,
+    negative: [bool; 3],
+    // In nm, the path of the ray carries a single wavelength with spectral
+    // rendering, None for RGB
+    wavelength: Option<T>,
+    // Angle by which the ray widens per unit of distance, the size of a pixel
+    // for rays from the camera. None for the others, whose hits don't filter
+    // textures.
+    spread: Option<T>
 }
 
 impl<T> Ray<T>
@@ -15,37 +25,53 @@ impl<T> Ray<T>
     pub fn new() -> Self {
         let origin = [T::zero(); 3];
         let direction = [T::zero(); 3];
-        Ray {
-            origin: Vec3::from_array(origin),
-            direction: Vec3::from_array(direction),
-        }
+        Ray::from_array(origin, direction)
     }
 
     pub fn from_vec(origin: Vec3<T>, direction: Vec3<T>) -> Self {
+        let d = direction.get_data();
+        // Zero components give infinite inverses, keeping the sign of the zero
+        let inv_direction = Vec3::from_array([T::one() / d[0], T::one() / d[1], T::one() / d[2]]);
+        let inv = inv_direction.get_data();
+        let negative = [inv[0] < T::zero(), inv[1] < T::zero(), inv[2] < T::zero()];
         Ray {
             origin,
-            direction
+            direction,
+            inv_direction,
+            negative,
+            wavelength: None,
+            spread: None
         }
     }
 
     pub fn from_array(origin: [T; 3], direction: [T; 3]) -> Self {
-        Ray {
-            origin: Vec3::from_array(origin),
-            direction: Vec3::from_array(direction),
-        }
+        Ray::from_vec(Vec3::from_array(origin), Vec3::from_array(direction))
     }
 
     pub fn from_slice(origin: &[T], direction: &[T]) -> Self {
-        Ray {
-            origin: Vec3::from_slice(origin),
-            direction: Vec3::from_slice(direction),
-        }
+        Ray::from_vec(Vec3::from_slice(origin), Vec3::from_slice(direction))
     }
 
     pub fn from_ray(ray: &Ray<T>) -> Self {
         Ray {
             origin: Vec3::from_slice(ray.get_origin().get_data()),
-            direction: Vec3::from_slice(ray.get_direction().get_data())
+            direction: Vec3::from_slice(ray.get_direction().get_data()),
+            inv_direction: Vec3::from_slice(ray.get_inv_direction().get_data()),
+            negative: ray.negative,
+            wavelength: ray.wavelength,
+            spread: ray.spread
+        }
+    }
+
+    // Same direction from another origin, reusing the cached inverse
+    pub fn with_origin(&self, origin: Vec3<T>) -> Self {
+        Ray {
+            origin,
+            direction: Vec3::from_slice(self.direction.get_data()),
+            inv_direction: Vec3::from_slice(self.inv_direction.get_data()),
+            negative: self.negative,
+            wavelength: self.wavelength,
+            spread: self.spread
         }
     }
 
@@ -57,9 +83,38 @@ impl<T> Ray<T>
         &self.direction
     }
 
-    pub fn get_point(&self, t: T) -> Vec3<T> {
+    pub fn get_inv_direction(&self) -> &Vec3<T> {
+        &self.inv_direction
+    }
+
+    // True if the direction points towards -infinity on the axis, including -0
+    pub fn is_negative(&self, axis: usize) -> bool {
+        self.negative[axis]
+    }
+
+    pub fn get_wavelength(&self) -> Option<T> {
+        self.wavelength
+    }
+
+    pub fn set_wavelength(&mut self, wavelength: Option<T>) {
+        self.wavelength = wavelength;
+    }
+
+    pub fn get_spread(&self) -> Option<T> {
+        self.spread
+    }
+
+    pub fn set_spread(&mut self, spread: Option<T>) {
+        self.spread = spread;
+    }
+
+    pub fn at(&self, t: T) -> Vec3<T> {
         &self.origin + &self.direction * t
     }
+
+    pub fn get_point(&self, t: T) -> Vec3<T> {
+        self.at(t)
+    }
 }
 
 #[cfg(test)]
@@ -100,4 +155,43 @@ mod tests {
         let p = ray.get_point(t);
         assert_eq!(p.get_data(), [-1.5, -2.0, -2.5]);
     }
+
+    #[test]
+    fn at() {
+        let origin = [0.0, 1.0, 2.0];
+        let direction = [1.0, 2.0, 3.0];
+        let ray = Ray::from_array(origin, direction);
+        assert_eq!(ray.at(0.0).get_data(), origin);
+        assert_eq!(ray.at(1.0).get_data(), [1.0, 3.0, 5.0]);
+        assert_eq!(ray.at(-1.5).get_data(), [-1.5, -2.0, -2.5]);
+    }
+
+    #[test]
+    fn inv_direction() {
+        let ray = Ray::from_array([0.0, 0.0, 0.0], [2.0, -4.0, 0.0]);
+        assert_eq!(ray.get_inv_direction().get_data(), [0.5, -0.25, f64::INFINITY]);
+        assert!(!ray.is_negative(0));
+        assert!(ray.is_negative(1));
+        assert!(!ray.is_negative(2));
+
+        let ray = Ray::from_array([0.0, 0.0, 0.0], [1.0, 0.0, -0.0]);
+        assert_eq!(ray.get_inv_direction().get_data()[2], f64::NEG_INFINITY);
+        assert!(ray.is_negative(2));
+        let copy = Ray::from_ray(&ray);
+        assert_eq!(copy.get_inv_direction().get_data(), ray.get_inv_direction().get_data());
+        assert!(copy.is_negative(2));
+        let moved = ray.with_origin(Vec3::from_array([1.0, 2.0, 3.0]));
+        assert_eq!(moved.get_origin().get_data(), [1.0, 2.0, 3.0]);
+        assert_eq!(moved.get_inv_direction().get_data(), ray.get_inv_direction().get_data());
+    }
+
+    #[test]
+    fn wavelength() {
+        let mut ray = Ray::from_array([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        assert_eq!(ray.get_wavelength(), None);
+        ray.set_wavelength(Some(550.0));
+        assert_eq!(Ray::from_ray(&ray).get_wavelength(), Some(550.0));
+        assert_eq!(ray.with_origin(Vec3::from_array([1.0, 2.0, 3.0])).get_wavelength(), Some(550.0));
+        assert_eq!(Ray::from_vec(Vec3::new(), Vec3::from_array([1.0, 0.0, 0.0])).get_wavelength(), None);
+    }
 }
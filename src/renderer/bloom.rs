@@ -0,0 +1,156 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::renderer::Image;
+
+// Weights of a gaussian of sigma radius / 3 at offsets -radius..=radius,
+// summing to one
+fn gaussian<T>(radius: usize) -> Vec<T>
+    where T: Float
+{
+    let sigma = radius as f64 / 3.0;
+    let weights: Vec<f64> = (0..2 * radius + 1).map(|i| {
+        let x = i as f64 - radius as f64;
+        (-x * x / (2.0 * sigma * sigma)).exp()
+    }).collect();
+    let sum: f64 = weights.iter().sum();
+    weights.iter().map(|weight| T::from_f64(weight / sum)).collect()
+}
+
+// Convolves the rows or the columns of the channels. Outside of the image
+// the pixels at the border are repeated, so the borders don't turn darker.
+fn blur<T>(data: &[T], width: usize, height: usize, kernel: &[T], horizontal: bool) -> Vec<T>
+    where T: Float
+{
+    let radius = kernel.len() / 2;
+    let mut blurred = vec![T::zero(); data.len()];
+    for j in 0..height {
+        for i in 0..width {
+            let (position, length) = if horizontal { (i, width) } else { (j, height) };
+            let mut sum = [T::zero(); 3];
+            for (offset, weight) in kernel.iter().enumerate() {
+                let k = usize::min((position + offset).saturating_sub(radius), length - 1);
+                let index = if horizontal { 3 * (j * width + k) } else { 3 * (k * width + i) };
+                for c in 0..3 {
+                    sum[c] = sum[c] + *weight * data[index + c];
+                }
+            }
+            let index = 3 * (j * width + i);
+            blurred[index..index + 3].copy_from_slice(&sum);
+        }
+    }
+    blurred
+}
+
+impl<T> Image<T>
+    where T: Float
+{
+    // Adds a glow around the pixels brighter than the threshold: the part of
+    // their luminance above it is blurred by a gaussian reaching radius
+    // pixels and added back times the strength. Meant for the linear colors
+    // of a render, before the gamma correction. Pixels with a NaN or infinite
+    // channel don't glow.
+    pub fn bloom(&self, threshold: T, radius: usize, strength: T) -> Image<T> {
+        let mut bright: Vec<T> = vec![T::zero(); self.data.len()];
+        for (pixel, bright) in self.data.chunks(3).zip(bright.chunks_mut(3)) {
+            if !pixel.iter().all(|value| value.is_finite()) {
+                continue;
+            }
+            let luminance = Vec3::from_slice(pixel).luminance();
+            if luminance > threshold {
+                let scale = (luminance - threshold) / luminance;
+                for c in 0..3 {
+                    bright[c] = pixel[c].max(T::zero()) * scale;
+                }
+            }
+        }
+
+        // Separable, once along the rows and once along the columns
+        if radius > 0 {
+            let kernel = gaussian::<T>(radius);
+            bright = blur(&bright, self.width, self.height, &kernel, true);
+            bright = blur(&bright, self.width, self.height, &kernel, false);
+        }
+
+        let data = self.data.iter().zip(bright.iter()).map(|(value, glow)| *value + strength * *glow).collect();
+        Image {
+            width: self.width,
+            height: self.height,
+            data
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn footprint() {
+        let (size, radius) = (21, 6);
+        let mut image = Image::<f64>::new(size, size);
+        image.set_pixel(10, 10, [11.0, 11.0, 11.0]).unwrap();
+        let bloomed = image.bloom(1.0, radius, 0.5);
+
+        // The 10 above the threshold spread as the product of two gaussians
+        let kernel = gaussian::<f64>(radius);
+        let weight = |x: usize| match x as isize - 10 {
+            d if d.abs() <= radius as isize => kernel[(d + radius as isize) as usize],
+            _ => 0.0
+        };
+        let mut sum = 0.0;
+        for y in 0..size {
+            for x in 0..size {
+                let pixel = bloomed.get_pixel(x, y).unwrap();
+                let original = if (x, y) == (10, 10) { 11.0 } else { 0.0 };
+                let expected = original + 0.5 * 10.0 * weight(x) * weight(y);
+                assert!((pixel[0] - expected).abs() < 1e-12);
+                assert_eq!(pixel[0], pixel[2]);
+                sum += pixel[1] - original;
+            }
+        }
+        assert!((sum - 5.0).abs() < 1e-12);
+        // Symmetric, and falling off with the distance
+        assert!((bloomed.get_pixel(7, 10).unwrap()[0] - bloomed.get_pixel(10, 13).unwrap()[0]).abs() < 1e-15);
+        assert!(bloomed.get_pixel(11, 10).unwrap()[0] > bloomed.get_pixel(12, 10).unwrap()[0]);
+    }
+
+    #[test]
+    fn borders() {
+        // As bright in the corners as in the middle
+        let mut image = Image::<f64>::new(9, 7);
+        image.data.iter_mut().for_each(|value| *value = 2.0);
+        let bloomed = image.bloom(1.0, 4, 1.0);
+        for value in bloomed.data.iter() {
+            assert!((value - 3.0).abs() < 1e-12);
+        }
+
+        // A light close to the border glows as much as one in the middle
+        let mut image = Image::<f64>::new(9, 7);
+        image.set_pixel(1, 3, [3.0; 3]).unwrap();
+        image.set_pixel(6, 3, [3.0; 3]).unwrap();
+        let bloomed = image.bloom(1.0, 2, 1.0);
+        let glow = |x: usize, y: usize| bloomed.get_pixel(x, y).unwrap()[0];
+        assert!((glow(0, 3) - glow(5, 3)).abs() < 1e-12);
+        assert!((glow(0, 5) - glow(5, 5)).abs() < 1e-12);
+        assert!((glow(2, 4) - glow(7, 4)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn threshold() {
+        let mut image = Image::<f64>::new(5, 5);
+        image.set_pixel(0, 0, [0.5, 0.9, 0.2]).unwrap();
+        image.set_pixel(4, 4, [f64::NAN, 10.0, 10.0]).unwrap();
+        image.set_pixel(2, 2, [f64::INFINITY; 3]).unwrap();
+        let bloomed = image.bloom(1.0, 2, 1.0);
+        assert_eq!(bloomed.get_pixel(0, 0), Some([0.5, 0.9, 0.2]));
+        assert_eq!(bloomed.get_pixel(1, 1), Some([0.0, 0.0, 0.0]));
+        assert!(bloomed.get_pixel(4, 4).unwrap()[0].is_nan());
+
+        // Without a radius only the brightness above the threshold is added
+        let mut image = Image::<f64>::new(2, 1);
+        image.set_pixel(1, 0, [4.0, 4.0, 4.0]).unwrap();
+        let bloomed = image.bloom(1.0, 0, 1.0);
+        assert_eq!(bloomed.get_pixel(0, 0), Some([0.0, 0.0, 0.0]));
+        assert!((bloomed.get_pixel(1, 0).unwrap()[1] - 7.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::float::Float;
+use crate::camera::Camera;
+
+// How Renderer::render_depth_pass stores the depth of the first hit, its
+// distance from the camera along the viewing direction
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DepthEncoding<T>
+    where T: Float
+{
+    // The depth itself, misses are +inf
+    Distance,
+    // 0 at near and 1 at far, clamped in between. Misses are 1.
+    Normalized { near: T, far: T },
+    // 1 / depth, misses are 0. Changes linearly across a plane in the image.
+    Inverse
+}
+
+impl<T> DepthEncoding<T>
+    where T: Float
+{
+    // Normalized between the near and far planes of the camera
+    pub fn from_camera(camera: &dyn Camera<T>) -> Self {
+        let (near, far) = camera.get_near_far();
+        DepthEncoding::Normalized { near, far }
+    }
+
+    pub fn encode(&self, depth: T) -> T {
+        match *self {
+            DepthEncoding::Distance => depth,
+            DepthEncoding::Normalized { near, far } => {
+                ((depth - near) / (far - near)).max(T::zero()).min(T::one())
+            },
+            DepthEncoding::Inverse => T::one() / depth
+        }
+    }
+}
+
+// How the samples of a pixel are combined
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DepthFilter {
+    // The sample closest to the camera
+    Min,
+    // The mean of the encoded samples. With Distance a pixel with a miss
+    // is +inf, with the other encodings misses are blended in.
+    Average
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        assert_eq!(DepthEncoding::Distance.encode(4.0), 4.0);
+        assert_eq!(DepthEncoding::Distance.encode(f64::INFINITY), f64::INFINITY);
+        let normalized = DepthEncoding::Normalized { near: 2.0, far: 6.0 };
+        assert_eq!(normalized.encode(3.0), 0.25);
+        assert_eq!(normalized.encode(1.0), 0.0);
+        assert_eq!(normalized.encode(f64::INFINITY), 1.0);
+        assert_eq!(DepthEncoding::Inverse.encode(4.0), 0.25);
+        assert_eq!(DepthEncoding::Inverse.encode(f64::INFINITY), 0.0);
+    }
+
+    #[test]
+    fn from_camera() {
+        use crate::camera::perspective::PerspectiveCamera;
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_near(2.0).unwrap();
+        camera.set_far(6.0).unwrap();
+        let encoding = DepthEncoding::from_camera(&camera);
+        assert_eq!(encoding, DepthEncoding::Normalized { near: 2.0, far: 6.0 });
+        assert_eq!(encoding.encode(3.0), 0.25);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::renderer::Image;
+use crate::error::Error;
+
+// Thresholds of Image::overlay_edges. Both are rates of change per unit of
+// screen space, in which the image spans [-1, 1] in both directions, so they
+// don't depend on the resolution: on smooth surfaces the differences between
+// neighbouring pixels shrink with the pixel size, at edges they don't.
+pub struct EdgeParams<T>
+    where T: Float
+{
+    pub color: Vec3<T>,
+    // Change of the normal, i.e. at creases and silhouettes
+    pub normal_threshold: T,
+    // Second derivative of the inverse depth relative to it. Zero on planes,
+    // large where one surface occludes another one.
+    pub depth_threshold: T
+}
+
+impl<T> EdgeParams<T>
+    where T: Float
+{
+    pub fn new(color: Vec3<T>) -> Self {
+        EdgeParams {
+            color,
+            normal_threshold: T::from_f64(20.0),
+            depth_threshold: T::from_f64(200.0)
+        }
+    }
+}
+
+impl<T> Image<T>
+    where T: Float
+{
+    // Draws the edges found in the normals (Renderer::render_normal_pass) and
+    // depths (Renderer::render_depth_pass with DepthEncoding::Distance) of the
+    // image in the color of params, i.e. to check the placement of objects
+    pub fn overlay_edges(&mut self, normals: &Image<T>, depths: &Image<T>, params: &EdgeParams<T>) -> Result<(), Error> {
+        for aov in [normals, depths].iter() {
+            if aov.width != self.width || aov.height != self.height {
+                return Err(Error::ImageSize { width: aov.width, height: aov.height, expected_width: self.width, expected_height: self.height });
+            }
+        }
+
+        let edges = find_edges(normals, depths, params);
+        for (index, edge) in edges.iter().enumerate() {
+            if *edge {
+                self.data[3 * index..3 * index + 3].copy_from_slice(params.color.get_data());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn find_edges<T>(normals: &Image<T>, depths: &Image<T>, params: &EdgeParams<T>) -> Vec<bool>
+    where T: Float
+{
+    let (width, height) = (normals.width, normals.height);
+    let normal = |index: usize| Vec3::from_slice(&normals.data[3 * index..3 * index + 3]);
+    // Misses are infinitely far away
+    let inverse_depth = |index: usize| {
+        let depth = depths.data[3 * index];
+        if depth > T::zero() { T::one() / depth } else { T::zero() }
+    };
+
+    let mut edges = vec![false; width * height];
+    // Along the rows (step 1) and the columns (step width) of the image
+    for &(step, size) in [(1, width), (width, height)].iter() {
+        let footprint = T::from_f64(2.0 / size as f64);
+        for (index, edge) in edges.iter_mut().enumerate() {
+            let position = if step == 1 { index % width } else { index / width };
+            if position + 1 < size {
+                let change = (normal(index + step) - normal(index)).norm() / footprint;
+                *edge |= change > params.normal_threshold;
+            }
+            let center = inverse_depth(index);
+            if position > 0 && position + 1 < size && center > T::zero() {
+                let two = T::from_f64(2.0);
+                let curvature = (inverse_depth(index - step) - two * center + inverse_depth(index + step)).abs();
+                *edge |= curvature / (center * footprint * footprint) > params.depth_threshold;
+            }
+        }
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 64;
+
+    // A WIDTH x 4 image of the given depths and normals
+    fn plane(depth: impl Fn(usize, usize) -> f64, normal: impl Fn(usize, usize) -> [f64; 3]) -> (Image<f64>, Image<f64>) {
+        let mut normals = Image::new(WIDTH, 4);
+        let mut depths = Image::new(WIDTH, 4);
+        for j in 0..4 {
+            for i in 0..WIDTH {
+                let index = 3 * (j * WIDTH + i);
+                normals.data[index..index + 3].copy_from_slice(&normal(i, j));
+                depths.data[index..index + 3].copy_from_slice(&[depth(i, j); 3]);
+            }
+        }
+        (normals, depths)
+    }
+
+    fn edge_columns(normals: &Image<f64>, depths: &Image<f64>) -> Vec<usize> {
+        let mut image = Image::new(WIDTH, 4);
+        let params = EdgeParams::new(Vec3::from_array([1.0, 0.0, 0.0]));
+        image.overlay_edges(normals, depths, &params).unwrap();
+        // Only vertical edges are expected
+        for i in 0..WIDTH {
+            let column: Vec<f64> = (0..4).map(|j| image.data[3 * (j * WIDTH + i)]).collect();
+            assert!(column.iter().all(|value| *value == column[0]));
+        }
+        (0..WIDTH).filter(|i| image.data[3 * i] == 1.0).collect()
+    }
+
+    #[test]
+    fn flat() {
+        let (normals, depths) = plane(|_, _| 5.0, |_, _| [0.0, 0.0, 1.0]);
+        assert!(edge_columns(&normals, &depths).is_empty());
+        // A tilted plane is linear in the inverse depth
+        let (normals, depths) = plane(|i, _| 1.0 / (0.5 + 0.01 * i as f64), |_, _| [0.0, 0.6, 0.8]);
+        assert!(edge_columns(&normals, &depths).is_empty());
+    }
+
+    #[test]
+    fn crease() {
+        let (normals, depths) = plane(|_, _| 5.0, |i, _| if i < 32 { [0.0, 0.0, 1.0] } else { [1.0, 0.0, 0.0] });
+        assert_eq!(edge_columns(&normals, &depths), [31]);
+    }
+
+    #[test]
+    fn occlusion() {
+        // A plane in front of another one, with the same normals
+        let (normals, depths) = plane(|i, _| if i < 32 { 2.0 } else { 8.0 }, |_, _| [0.0, 0.0, 1.0]);
+        assert_eq!(edge_columns(&normals, &depths), [31, 32]);
+        // And in front of the background
+        let (normals, depths) = plane(|i, _| if i < 32 { 2.0 } else { f64::INFINITY }, |i, _| if i < 32 { [0.0, 0.0, 1.0] } else { [0.0; 3] });
+        assert_eq!(edge_columns(&normals, &depths), [31]);
+    }
+
+    #[test]
+    fn size() {
+        let (normals, depths) = plane(|_, _| 5.0, |_, _| [0.0, 0.0, 1.0]);
+        let mut image = Image::new(3, 4);
+        match image.overlay_edges(&normals, &depths, &EdgeParams::new(Vec3::new())) {
+            Err(Error::ImageSize { width: WIDTH, height: 4, expected_width: 3, expected_height: 4 }) => (),
+            _ => panic!("expected an image size error")
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::float::Float;
+use crate::camera::Camera;
+use crate::error::Error;
+
+// Settings of a real camera the rendered radiance is scaled with, taken to
+// be in cd/m^2. The scale is 1 / (1.2 * 2^EV100) with
+// EV100 = log2(N^2 / t * 100 / S), so a gray card of 18% in sunlight (about
+// 5700 cd/m^2) comes out at about 0.19 with sunny 16 (N = 16, t = 1 / S).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Exposure {
+    iso: f64,
+    shutter: f64,
+    f_number: f64,
+    ev_compensation: f64,
+    focal_length: Option<f64>
+}
+
+impl Exposure {
+    // The shutter time in seconds
+    pub fn new(iso: f64, shutter: f64, f_number: f64) -> Result<Self, Error> {
+        let mut exposure = Exposure { iso: 100.0, shutter: 1.0, f_number: 1.0, ev_compensation: 0.0, focal_length: None };
+        exposure.set_exposure(iso, shutter, f_number)?;
+        Ok(exposure)
+    }
+
+    pub fn get_iso(&self) -> f64 {
+        self.iso
+    }
+
+    pub fn get_shutter(&self) -> f64 {
+        self.shutter
+    }
+
+    pub fn get_f_number(&self) -> f64 {
+        self.f_number
+    }
+
+    pub fn set_exposure(&mut self, iso: f64, shutter: f64, f_number: f64) -> Result<(), Error> {
+        let positive = |value: f64| value > 0.0 && value.is_finite();
+        if !positive(iso) {
+            return Err(Error::InvalidCamera("iso"));
+        }
+        if !positive(shutter) {
+            return Err(Error::InvalidCamera("shutter"));
+        }
+        if !positive(f_number) {
+            return Err(Error::InvalidCamera("f-number"));
+        }
+        self.iso = iso;
+        self.shutter = shutter;
+        self.f_number = f_number;
+        Ok(())
+    }
+
+    pub fn get_ev_compensation(&self) -> f64 {
+        self.ev_compensation
+    }
+
+    // Brighter by a factor of 2 per stop, darker for negative stops
+    pub fn set_ev_compensation(&mut self, stops: f64) {
+        self.ev_compensation = stops;
+    }
+
+    pub fn get_linked_aperture(&self) -> Option<f64> {
+        self.focal_length
+    }
+
+    // Linked to the focal length of the lens (in the units of the scene),
+    // the f-number is the one of the aperture of the camera, the depth of
+    // field and the exposure change together. A pinhole camera without an
+    // aperture keeps the f-number of the exposure. Independent with None.
+    pub fn set_linked_aperture(&mut self, focal_length: Option<f64>) -> Result<(), Error> {
+        if let Some(focal_length) = focal_length {
+            if !(focal_length > 0.0 && focal_length.is_finite()) {
+                return Err(Error::InvalidCamera("focal length"));
+            }
+        }
+        self.focal_length = focal_length;
+        Ok(())
+    }
+
+    // The aperture of a camera for the f-number, i.e. to match the depth of
+    // field to the exposure
+    pub fn aperture_for(&self, focal_length: f64) -> f64 {
+        focal_length / self.f_number
+    }
+
+    // The f-number used with the camera, see set_linked_aperture
+    pub fn f_number_for<T>(&self, camera: &dyn Camera<T>) -> f64
+        where T: Float
+    {
+        let aperture = camera.get_aperture().to_f64().unwrap_or(0.0);
+        match self.focal_length {
+            Some(focal_length) if aperture > 0.0 => focal_length / aperture,
+            _ => self.f_number
+        }
+    }
+
+    // The exposure value at ISO 100 without the compensation
+    pub fn ev100(&self, f_number: f64) -> f64 {
+        (f_number * f_number / self.shutter * 100.0 / self.iso).log2()
+    }
+
+    // The factor for the radiance, linear in the ISO and the shutter time
+    pub fn scale(&self, f_number: f64) -> f64 {
+        self.shutter * self.iso / (120.0 * f_number * f_number) * self.ev_compensation.exp2()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::perspective::PerspectiveCamera;
+
+    #[test]
+    fn scale() {
+        let mut exposure = Exposure::new(100.0, 1.0 / 125.0, 16.0).unwrap();
+        assert!((exposure.ev100(16.0) - (256.0f64 * 125.0).log2()).abs() < 1e-12);
+        let scale = exposure.scale(16.0);
+        assert!((scale - 1.0 / (1.2 * 2.0f64.powf(exposure.ev100(16.0)))).abs() < 1e-15);
+        exposure.set_ev_compensation(-1.0);
+        assert_eq!(exposure.scale(16.0), scale / 2.0);
+        assert!(Exposure::new(0.0, 1.0, 1.0).is_err());
+        assert!(Exposure::new(100.0, f64::NAN, 1.0).is_err());
+        assert!(exposure.set_exposure(100.0, 1.0, -2.0).is_err());
+        assert_eq!(exposure.get_f_number(), 16.0);
+    }
+
+    #[test]
+    fn linked_aperture() {
+        let mut exposure = Exposure::new(100.0, 0.01, 8.0).unwrap();
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_aperture(0.5).unwrap();
+        assert_eq!(exposure.f_number_for(&camera), 8.0);
+        exposure.set_linked_aperture(Some(2.0)).unwrap();
+        assert_eq!(exposure.f_number_for(&camera), 4.0);
+        assert_eq!(exposure.aperture_for(2.0), 0.25);
+        // A pinhole keeps the f-number
+        camera.set_aperture(0.0).unwrap();
+        assert_eq!(exposure.f_number_for(&camera), 8.0);
+        assert!(exposure.set_linked_aperture(Some(0.0)).is_err());
+    }
+}
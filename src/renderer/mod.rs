@@ -1,11 +1,43 @@
+use std::fmt;
+use std::ops::Range;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+
 use rand::prelude::*;
+use rand::rngs::SmallRng;
 
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::camera::Camera;
-use crate::scene::Scene;
+use crate::scene::{Scene, SceneStats};
+use crate::actor::{ActorId, ALL_LAYERS};
+use crate::error::{Error, BoundsError};
+use crate::sampling::{self, Sampler};
+
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(feature = "image")]
+mod rgb_image;
+pub mod report;
+pub mod depth;
+pub mod edges;
+pub mod rgba;
+mod bloom;
+pub mod passes;
+pub mod exposure;
+pub mod spectral;
+
+pub use report::{RenderReport, TileReport};
+pub use depth::{DepthEncoding, DepthFilter};
+pub use edges::EdgeParams;
+pub use rgba::ImageRgba;
+pub use passes::{RenderPassFlags, RenderResult};
+pub use exposure::Exposure;
 
+#[derive(Clone)]
 pub struct Image<T>
     where T: Float
 {
@@ -25,6 +57,245 @@ impl<T> Image<T>
             data
         }
     }
+
+    // Index of the first channel of the pixel in data
+    fn pixel_index(&self, x: usize, y: usize) -> usize {
+        debug_assert!(x < self.width && y < self.height, "pixel ({}, {}) is outside of the {}x{} image", x, y, self.width, self.height);
+        3 * (y * self.width + x)
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Option<[T; 3]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = self.pixel_index(x, y);
+        Some([self.data[index], self.data[index + 1], self.data[index + 2]])
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: [T; 3]) -> Result<(), BoundsError> {
+        if x >= self.width || y >= self.height {
+            return Err(BoundsError { x, y, width: self.width, height: self.height });
+        }
+        let index = self.pixel_index(x, y);
+        self.data[index..index + 3].copy_from_slice(&color);
+        Ok(())
+    }
+
+    pub fn convert<U>(&self) -> Image<U>
+        where U: Float
+    {
+        let mut image = Image::<U>::new(self.width, self.height);
+        for (d, x) in image.data.iter_mut().zip(self.data.iter()) {
+            *d = U::from(*x).unwrap();
+        }
+        image
+    }
+
+    pub fn to_f32(&self) -> Image<f32> {
+        self.convert()
+    }
+
+    pub fn to_f64(&self) -> Image<f64> {
+        self.convert()
+    }
+
+    pub fn compute_variance_map(&self, reference: &Image<T>) -> Image<T> {
+        assert_eq!(self.width, reference.width);
+        assert_eq!(self.height, reference.height);
+
+        let mut variance = Image::<T>::new(self.width, self.height);
+        for i in 0..self.data.len() {
+            let delta = self.data[i] - reference.data[i];
+            variance.data[i] = delta * delta;
+        }
+        variance
+    }
+
+    pub fn compute_local_variance(&self, window: usize) -> Image<T> {
+        let mut variance = Image::<T>::new(self.width, self.height);
+        let half = window / 2;
+
+        for j in 0..self.height {
+            // The window is clamped at the image borders
+            let j0 = j.saturating_sub(half);
+            let j1 = usize::min(j + half + 1, self.height);
+            for i in 0..self.width {
+                let i0 = i.saturating_sub(half);
+                let i1 = usize::min(i + half + 1, self.width);
+                let count = T::from((j1 - j0) * (i1 - i0)).unwrap();
+                let index = j * self.width + i;
+
+                for c in 0..3 {
+                    let mut sum = T::zero();
+                    let mut sum2 = T::zero();
+                    for jj in j0..j1 {
+                        for ii in i0..i1 {
+                            let value = self.data[3 * (jj * self.width + ii) + c];
+                            sum = sum + value;
+                            sum2 = sum2 + value * value;
+                        }
+                    }
+                    let mean = sum / count;
+                    let var = sum2 / count - mean * mean;
+                    // Guard against tiny negative values due to round-off
+                    variance.data[3 * index + c] = if var > T::zero() { var } else { T::zero() };
+                }
+            }
+        }
+        variance
+    }
+
+    // Divides all channels by the largest one, i.e. to fit an image of bright
+    // lights into [0, 1] without shifting the colors. An image without a
+    // positive finite channel is left as it is.
+    pub fn normalize(&mut self) {
+        let max = self.data.iter().fold(T::zero(), |max, value| if value.is_finite() { max.max(*value) } else { max });
+        if max > T::zero() {
+            for value in self.data.iter_mut() {
+                *value = *value / max;
+            }
+        }
+    }
+
+    // Scale factor that brings the luminance at the percentile (0 to 100,
+    // nearest rank) of the pixels to the target, i.e. 50 for the median.
+    // Pixels with a NaN or infinite channel don't count. None if there is
+    // no such pixel or the luminance at the percentile isn't positive.
+    pub fn auto_exposure(&self, target_luminance: T, percentile: T) -> Option<T> {
+        let mut luminances: Vec<T> = self.data.chunks(3)
+            .filter(|pixel| pixel.iter().all(|value| value.is_finite()))
+            .map(|pixel| Vec3::from_slice(pixel).luminance())
+            .collect();
+        if luminances.is_empty() {
+            return None;
+        }
+        luminances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = luminances.len();
+        let fraction = percentile.max(T::zero()).min(T::from_f64(100.0)) / T::from_f64(100.0);
+        let rank = (fraction * T::from_f64(n as f64)).ceil().to_usize().unwrap_or(n);
+        let luminance = luminances[rank.max(1).min(n) - 1];
+        if luminance > T::zero() {
+            Some(target_luminance / luminance)
+        } else {
+            None
+        }
+    }
+
+    // Like auto_exposure, also multiplying the image with the exposure
+    pub fn auto_expose(&mut self, target_luminance: T, percentile: T) -> Option<T> {
+        let exposure = self.auto_exposure(target_luminance, percentile)?;
+        for value in self.data.iter_mut() {
+            *value = *value * exposure;
+        }
+        Some(exposure)
+    }
+
+    // Copies the image into this one with its top left corner at (x, y),
+    // the parts outside this image are dropped
+    pub fn blit(&mut self, image: &Image<T>, x: usize, y: usize) {
+        let width = usize::min(image.width, self.width.saturating_sub(x));
+        let height = usize::min(image.height, self.height.saturating_sub(y));
+        for j in 0..height {
+            let src = 3 * j * image.width;
+            let dst = 3 * ((y + j) * self.width + x);
+            self.data[dst..dst + 3 * width].copy_from_slice(&image.data[src..src + 3 * width]);
+        }
+    }
+
+    // Sample mask of 1 inside the rectangle and 0 outside, see
+    // Renderer::set_sample_mask
+    pub fn rectangle_mask(width: usize, height: usize, columns: Range<usize>, rows: Range<usize>) -> Self {
+        let mut mask = Image::<T>::new(width, height);
+        for j in rows.start..usize::min(rows.end, height) {
+            for i in columns.start..usize::min(columns.end, width) {
+                let index = mask.pixel_index(i, j);
+                mask.data[index..index + 3].copy_from_slice(&[T::one(), T::one(), T::one()]);
+            }
+        }
+        mask
+    }
+
+    // Sample mask of 1 where the actor is seen in the ids of
+    // Renderer::render_id_pass, and around it to include its edges
+    pub fn actor_mask(ids: &[Option<ActorId>], width: usize, height: usize, actor_id: ActorId) -> Self {
+        let mut mask = Image::<T>::new(width, height);
+        for j in 0..height {
+            for i in 0..width {
+                if ids[j * width + i] != Some(actor_id) {
+                    continue;
+                }
+                for jj in j.saturating_sub(1)..usize::min(j + 2, height) {
+                    for ii in i.saturating_sub(1)..usize::min(i + 2, width) {
+                        let index = mask.pixel_index(ii, jj);
+                        mask.data[index..index + 3].copy_from_slice(&[T::one(), T::one(), T::one()]);
+                    }
+                }
+            }
+        }
+        mask
+    }
+
+    // Four bytes per pixel with an opaque alpha, the layout of the
+    // ImageData of a HTML canvas
+    pub fn to_rgba(&self, gamma: T) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(4 * self.width * self.height);
+        for pixel in self.to_bytes(gamma).chunks(3) {
+            rgba.extend_from_slice(pixel);
+            rgba.push(255);
+        }
+        rgba
+    }
+
+    // The channels as bytes, with the gamma correction applied and the
+    // colors clamped to [0, 1]
+    fn to_bytes(&self, gamma: T) -> Vec<u8> {
+        let exponent = T::one() / gamma;
+        self.data.chunks(3).flat_map(|pixel| {
+            let mut color = Vec3::from_slice(pixel);
+            color.get_data_mut().iter_mut().for_each(|value| *value = value.max(T::zero()).powf(exponent));
+            color.to_rgb_u8()
+        }).collect()
+    }
+
+    // Plain text PPM (P3), one pixel per line
+    pub fn save_ppm<P>(&self, path: P, gamma: T) -> Result<(), Error>
+        where P: AsRef<Path>
+    {
+        let mut text = format!("P3\n{} {}\n255\n", self.width, self.height);
+        for pixel in self.to_bytes(gamma).chunks(3) {
+            text.push_str(&format!("{} {} {}\n", pixel[0], pixel[1], pixel[2]));
+        }
+
+        let mut file = File::create(path)?;
+        file.write_all(text.as_bytes())?;
+        Ok(())
+    }
+
+    // Binary PPM (P6)
+    pub fn save_ppm_binary<P>(&self, path: P, gamma: T) -> Result<(), Error>
+        where P: AsRef<Path>
+    {
+        let mut bytes = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend(self.to_bytes(gamma));
+
+        let mut file = File::create(path)?;
+        file.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+// Size of the tiles timed by render_with_stats
+const STATS_TILE_SIZE: usize = 16;
+
+// How the light arriving through each pixel is estimated
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Integrator {
+    // Paths from the camera that have to find the lights on their own
+    Path,
+    // Bidirectional paths, the lights of the scene are connected to the
+    // camera paths. Much less noise if the lights are hard to reach, i.e.
+    // through small openings, see Scene::get_bdpt_color.
+    Bdpt
 }
 
 pub struct Renderer {
@@ -36,13 +307,38 @@ pub struct Renderer {
     height: usize,
     sampling: usize,
     reflections: usize,
-    antialiasing: bool
+    min_reflections: usize,
+    antialiasing: bool,
+    layer_mask: u32,
+    seed: Option<u64>,
+    integrator: Integrator,
+    sampler: Sampler,
+    sample_mask: Option<Image<f64>>,
+    transparent_background: bool,
+    exposure: Option<Exposure>,
+    spectral: bool,
+    warm_up_rays: usize,
+    warm_up_stats: Option<SceneStats>
+}
+
+impl<T> fmt::Display for Image<T>
+    where T: Float
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Image({}×{}, T={})", self.width, self.height, std::any::type_name::<T>())
+    }
 }
 
 impl Renderer {
-    pub fn new(x0: usize, x1: usize, y0: usize, y1: usize, width: usize, height: usize, sampling: usize, reflections: usize, antialiasing: bool) -> Self {
+    pub fn new(x0: usize, x1: usize, y0: usize, y1: usize, width: usize, height: usize, sampling: usize, reflections: usize, antialiasing: bool) -> Result<Self, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::EmptyImage { width, height });
+        }
+        if x1 < x0 || y1 < y0 || x1 > width || y1 > height {
+            return Err(Error::InvalidRegion { x0, x1, y0, y1, width, height });
+        }
 
-        Renderer {
+        Ok(Renderer {
             x0,
             x1,
             y0,
@@ -51,51 +347,564 @@ impl Renderer {
             height,
             sampling,
             reflections,
-            antialiasing
+            min_reflections: usize::MAX,
+            antialiasing,
+            layer_mask: ALL_LAYERS,
+            seed: None,
+            integrator: Integrator::Path,
+            sampler: Sampler::Independent,
+            sample_mask: None,
+            transparent_background: false,
+            exposure: None,
+            spectral: false,
+            warm_up_rays: 4096,
+            warm_up_stats: None
+        })
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    // Samples computed by render, i.e. to measure samples per second
+    pub fn sample_count(&self) -> usize {
+        let mut count = 0;
+        for j in self.y0..self.y1 {
+            for i in self.x0..self.x1 {
+                count += usize::max(self.pixel_sampling(i, j), 1);
+            }
         }
+        count
+    }
+
+    pub fn get_max_reflections(&self) -> usize {
+        self.reflections
+    }
+
+    pub fn set_max_reflections(&mut self, reflections: usize) {
+        self.reflections = reflections;
+    }
+
+    pub fn get_min_reflections(&self) -> usize {
+        self.min_reflections
+    }
+
+    // Paths shorter than min_reflections always continue, longer paths are
+    // terminated with Russian roulette. Roulette is disabled by default.
+    pub fn set_min_reflections(&mut self, min_reflections: usize) {
+        self.min_reflections = min_reflections;
+    }
+
+    pub fn get_layer_mask(&self) -> u32 {
+        self.layer_mask
+    }
+
+    // Only actors sharing a layer with the mask are rendered,
+    // i.e. to render parts of the scene in separate passes
+    pub fn set_layer_mask(&mut self, layer_mask: u32) {
+        self.layer_mask = layer_mask;
+    }
+
+    pub fn get_seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    // With a seed every region is rendered with the same random numbers each
+    // time, without one the generators are seeded from a per thread generator
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    pub fn get_integrator(&self) -> Integrator {
+        self.integrator
+    }
+
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+
+    pub fn get_sampler(&self) -> Sampler {
+        self.sampler
+    }
+
+    // Only used with antialiasing, for the position in the pixel and on the lens
+    pub fn set_sampler(&mut self, sampler: Sampler) {
+        self.sampler = sampler;
+    }
+
+    pub fn get_sample_mask(&self) -> Option<&Image<f64>> {
+        self.sample_mask.as_ref()
+    }
+
+    // Scales the samples of each pixel by the luminance of the mask, clamped
+    // to [0, 1], i.e. to render only a part of the image again with many
+    // samples. Pixels at 0 are skipped by render_masked and get a single
+    // sample otherwise. The mask has the size of the whole image.
+    pub fn set_sample_mask<T>(&mut self, mask: Option<Image<T>>) -> Result<(), Error>
+        where T: Float
+    {
+        if let Some(mask) = &mask {
+            if mask.width != self.width || mask.height != self.height {
+                return Err(Error::ImageSize { width: mask.width, height: mask.height, expected_width: self.width, expected_height: self.height });
+            }
+        }
+        self.sample_mask = mask.map(|mask| mask.convert());
+        Ok(())
+    }
+
+    pub fn get_transparent_background(&self) -> bool {
+        self.transparent_background
+    }
+
+    // Camera rays that hit no actor add neither the background nor alpha,
+    // see render_rgba. The colors of the other renders are left black there.
+    pub fn set_transparent_background(&mut self, transparent_background: bool) {
+        self.transparent_background = transparent_background;
+    }
+
+    pub fn get_exposure(&self) -> Option<&Exposure> {
+        self.exposure.as_ref()
+    }
+
+    // Scales the colors of render and the beauty pass of render_passes,
+    // before any post-processing. Without an exposure they are left as is.
+    pub fn set_exposure(&mut self, exposure: Option<Exposure>) {
+        self.exposure = exposure;
+    }
+
+    pub fn get_spectral(&self) -> bool {
+        self.spectral
+    }
+
+    // Each path carries a single wavelength, for materials that disperse
+    // light. Only the path integrator traces wavelengths.
+    pub fn set_spectral(&mut self, spectral: bool) {
+        self.spectral = spectral;
+    }
+
+    // The wavelength of a path starting with the ray and the weight of its
+    // radiance, None without spectral rendering
+    fn sample_wavelength<T>(&self, ray: &mut Ray<T>, rng: &mut dyn RngCore) -> Option<Vec3<T>>
+        where T: Float
+    {
+        if !self.spectral || self.integrator != Integrator::Path {
+            return None;
+        }
+        let (wavelength, weight) = spectral::sample_wavelength(rng);
+        ray.set_wavelength(Some(wavelength));
+        Some(weight)
+    }
+
+    pub fn get_warm_up_rays(&self) -> usize {
+        self.warm_up_rays
+    }
+
+    pub fn set_warm_up_rays(&mut self, warm_up_rays: usize) {
+        self.warm_up_rays = warm_up_rays;
+    }
+
+    // The stats of the last warm_up_scene
+    pub fn get_warm_up_stats(&self) -> Option<&SceneStats> {
+        self.warm_up_stats.as_ref()
+    }
+
+    // Traces the warm up rays through the scene (see Scene::warm_up) before
+    // a long render, i.e. to find NaNs in the geometry or the materials
+    // within moments instead of in the finished image. The scene is borrowed
+    // mutably for trees that adapt to the rays, none of the current ones do.
+    pub fn warm_up_scene<T>(&mut self, scene: &mut Scene<T>) -> Result<SceneStats, Error>
+        where T: Float
+    {
+        let mut rng = self.create_rng(usize::MAX - 1, usize::MAX - 1);
+        let stats = scene.warm_up(self.warm_up_rays, self.layer_mask, &mut rng)?;
+        self.warm_up_stats = Some(stats);
+        Ok(stats)
+    }
+
+    fn exposure_scale<T>(&self, camera: &dyn Camera<T>) -> T
+        where T: Float
+    {
+        match &self.exposure {
+            Some(exposure) => T::from_f64(exposure.scale(exposure.f_number_for(camera))),
+            None => T::one()
+        }
+    }
+
+    // Samples of the pixel after the sample mask, 0 if it's skipped
+    fn pixel_sampling(&self, i: usize, j: usize) -> usize {
+        let sampling = usize::max(self.sampling, 1);
+        match &self.sample_mask {
+            Some(mask) => {
+                let index = mask.pixel_index(i, j);
+                let weight = Vec3::from_slice(&mask.data[index..index + 3]).luminance().clamp(0.0, 1.0);
+                (weight * sampling as f64).round() as usize
+            },
+            None => sampling
+        }
+    }
+
+    fn create_rng(&self, x: usize, y: usize) -> SmallRng {
+        let seed = match self.seed {
+            // Different for each position, i.e. each tile of an image
+            Some(seed) => seed ^ ((x as u64) << 32 | y as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15),
+            None => sampling::with_thread_rng(|rng| rng.gen())
+        };
+        SmallRng::seed_from_u64(seed)
+    }
+
+    // Random numbers shared by the pixels, by all the tiles of an image if
+    // there is a seed, i.e. to shift the mask of the blue noise sampler
+    fn create_shift(&self) -> [f64; 4] {
+        let mut rng = self.create_rng(usize::MAX, usize::MAX);
+        [rng.gen(), rng.gen(), rng.gen(), rng.gen()]
     }
 
     pub fn render_pixel<T>(&self, i: usize, j: usize, scene: &Scene<T>, camera: &dyn Camera<T>) -> Vec3<T>
         where T: Float
     {
-        let two = T::from(2.0).unwrap();
+        let mut rng = self.create_rng(i, j);
+        self.sample_pixel(i, j, scene, camera, &mut rng, &self.create_shift(), None, None)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn sample_pixel<T>(&self, i: usize, j: usize, scene: &Scene<T>, camera: &dyn Camera<T>, rng: &mut dyn RngCore, shift: &[f64; 4], ray_counts: Option<&mut Vec<u64>>, stats: Option<&mut SceneStats>) -> Vec3<T>
+        where T: Float
+    {
+        self.sample_pixel_coverage(i, j, scene, camera, rng, shift, ray_counts, stats).0
+    }
+
+    // The color of the pixel and the part of its samples that hit an actor,
+    // 1 unless the background is transparent
+    #[allow(clippy::too_many_arguments)]
+    fn sample_pixel_coverage<T>(&self, i: usize, j: usize, scene: &Scene<T>, camera: &dyn Camera<T>, rng: &mut dyn RngCore, shift: &[f64; 4], mut ray_counts: Option<&mut Vec<u64>>, mut stats: Option<&mut SceneStats>) -> (Vec3<T>, T)
+        where T: Float
+    {
         let mut color = Vec3::<T>::new();
+        let mut covered = 0;
+
+        let sampling = usize::max(self.pixel_sampling(i, j), 1);
+        // Size of a pixel in screen coordinates
+        let scale = (T::from_f64(2.0 / self.width as f64), T::from_f64(2.0 / self.height as f64));
 
-        let sampling = match self.sampling {
-            0 => 1,
-            _ => self.sampling
+        // Independent samples are drawn one by one while tracing
+        let samples = match (self.sampler, self.antialiasing) {
+            (Sampler::Independent, _) | (_, false) => None,
+            (sampler, true) => Some(sampler.pixel_samples((i, j), shift, sampling, rng))
+        };
+        let next_ray = |k: usize, rng: &mut dyn RngCore| match &samples {
+            Some(samples) => self.get_sampled_ray(i, j, camera, scale, &samples[k]),
+            None => self.get_ray(i, j, camera, scale, rng)
         };
 
-        match self.antialiasing {
-            false => {
-                let ray = self.get_ray(i, j, camera, two);
-                for _k in 0..sampling {
-                    color = color + scene.get_color(&ray, 0, self.reflections);
+        // If antialiasing is disabled all samples share the same ray
+        let mut ray = next_ray(0, rng);
+        for k in 0..sampling {
+            if self.antialiasing && k > 0 {
+                ray = next_ray(k, rng);
+            }
+            if self.transparent_background && scene.cast_layer_ray(&ray, self.layer_mask).is_none() {
+                continue;
+            }
+            covered += 1;
+            let weight = self.sample_wavelength(&mut ray, rng);
+            // Only the path integrator counts its rays per depth
+            let sample = match (self.integrator, ray_counts.as_mut(), stats.as_deref_mut()) {
+                (Integrator::Bdpt, _, Some(stats)) => scene.get_bdpt_color_stats(&ray, self.reflections, self.layer_mask, rng, stats),
+                (Integrator::Bdpt, _, None) => scene.get_bdpt_color(&ray, self.reflections, self.layer_mask, rng),
+                (Integrator::Path, Some(counts), _) => scene.get_layer_color_counted(&ray, self.min_reflections, self.reflections, self.layer_mask, rng, counts),
+                (Integrator::Path, None, Some(stats)) => scene.get_layer_color_stats(&ray, self.min_reflections, self.reflections, self.layer_mask, rng, stats),
+                (Integrator::Path, None, None) => scene.get_layer_color(&ray, 0, self.min_reflections, self.reflections, self.layer_mask, rng)
+            };
+            color = color + match weight {
+                Some(weight) => sample * weight,
+                None => sample
+            };
+        }
+
+        let sampling = T::from_f64(sampling as f64);
+        (color / sampling * self.exposure_scale(camera), T::from_f64(covered as f64) / sampling)
+    }
+
+    pub fn render<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>) -> Image<T>
+        where T: Float
+    {
+        self.render_region(self.x0, self.x1, self.y0, self.y1, scene, camera)
+    }
+
+    // Renders tile (tile_x, tile_y) of a grid of tile_w x tile_h tiles covering
+    // the image area of the renderer. Tiles at the right and bottom border
+    // are cropped, a tile outside the area is empty.
+    pub fn render_tile<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>, tile_x: usize, tile_y: usize, tile_w: usize, tile_h: usize) -> Image<T>
+        where T: Float
+    {
+        let x0 = usize::min(self.x0 + tile_x * tile_w, self.x1);
+        let y0 = usize::min(self.y0 + tile_y * tile_h, self.y1);
+        let x1 = usize::min(x0 + tile_w, self.x1);
+        let y1 = usize::min(y0 + tile_h, self.y1);
+        self.render_region(x0, x1, y0, y1, scene, camera)
+    }
+
+    // Like render, with the part of the samples of each pixel that hit an
+    // actor as alpha. Without a transparent background the alpha is 1.
+    pub fn render_rgba<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>) -> ImageRgba<T>
+        where T: Float
+    {
+        let shift = self.create_shift();
+        let mut alpha = Vec::with_capacity((self.x1 - self.x0) * (self.y1 - self.y0));
+        let color = self.render_region_with(self.x0, self.x1, self.y0, self.y1, |i, j, rng| {
+            let (color, coverage) = self.sample_pixel_coverage(i, j, scene, camera, rng, &shift, None, None);
+            alpha.push(coverage);
+            color
+        });
+        ImageRgba::from_rgb(&color, &alpha)
+    }
+
+    // Renders the given rows of the image area in full width, the rows outside
+    // of the area are dropped. Lets a caller without threads (i.e. a web page)
+    // render an image in chunks and do other work in between.
+    pub fn render_rows<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>, rows: Range<usize>) -> Image<T>
+        where T: Float
+    {
+        let y0 = usize::min(usize::max(rows.start, self.y0), self.y1);
+        let y1 = usize::min(usize::max(rows.end, y0), self.y1);
+        self.render_region(self.x0, self.x1, y0, y1, scene, camera)
+    }
+
+    // Like render, the pixels skipped by the sample mask keep their color
+    // in the previous image of the region, i.e. one accumulated so far
+    pub fn render_masked<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>, previous: &Image<T>) -> Result<Image<T>, Error>
+        where T: Float
+    {
+        let (width, height) = (self.x1 - self.x0, self.y1 - self.y0);
+        if previous.width != width || previous.height != height {
+            return Err(Error::ImageSize { width: previous.width, height: previous.height, expected_width: width, expected_height: height });
+        }
+        let shift = self.create_shift();
+        Ok(self.render_region_with(self.x0, self.x1, self.y0, self.y1, |i, j, rng| {
+            if self.pixel_sampling(i, j) > 0 {
+                return self.sample_pixel(i, j, scene, camera, rng, &shift, None, None);
+            }
+            let index = previous.pixel_index(i - self.x0, j - self.y0);
+            Vec3::from_slice(&previous.data[index..index + 3])
+        }))
+    }
+
+    // Unlit base color of the surfaces seen by the camera, averaged over the
+    // samples of each pixel. A guide for denoising or to bake albedo maps.
+    pub fn render_albedo_pass<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>) -> Image<T>
+        where T: Float
+    {
+        let sampling = usize::max(self.sampling, 1);
+        let scale = (T::from_f64(2.0 / self.width as f64), T::from_f64(2.0 / self.height as f64));
+        self.render_region_with(self.x0, self.x1, self.y0, self.y1, |i, j, rng| {
+            let mut color = Vec3::<T>::new();
+            for _k in 0..sampling {
+                let ray = self.get_ray(i, j, camera, scale, rng);
+                color = color + scene.get_layer_albedo(&ray, self.layer_mask);
+            }
+            color / T::from_f64(sampling as f64)
+        })
+    }
+
+    // 1 where the first surface seen by the camera is lit by a point light at
+    // light_position, 0 where it's in the shadow, averaged over the samples.
+    // Pixels without a surface are lit.
+    pub fn render_shadow_pass<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>, light_position: &Vec3<T>) -> Image<T>
+        where T: Float
+    {
+        let sampling = usize::max(self.sampling, 1);
+        let scale = (T::from_f64(2.0 / self.width as f64), T::from_f64(2.0 / self.height as f64));
+        self.render_region_with(self.x0, self.x1, self.y0, self.y1, |i, j, rng| {
+            let mut lit = T::zero();
+            for _k in 0..sampling {
+                let ray = self.get_ray(i, j, camera, scale, rng);
+                let shadowed = match scene.cast_layer_ray(&ray, self.layer_mask) {
+                    Some(scene_hit) => scene.is_shadowed(&scene_hit.hit, light_position, self.layer_mask),
+                    None => false
+                };
+                if !shadowed {
+                    lit = lit + T::one();
                 }
-            },
-            true => {
+            }
+            lit = lit / T::from_f64(sampling as f64);
+            Vec3::from_array([lit, lit, lit])
+        })
+    }
+
+    // Normal of the first surface seen by the camera, facing the camera and
+    // averaged over the samples. Pixels without a surface are 0.
+    pub fn render_normal_pass<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>) -> Image<T>
+        where T: Float
+    {
+        let sampling = usize::max(self.sampling, 1);
+        let scale = (T::from_f64(2.0 / self.width as f64), T::from_f64(2.0 / self.height as f64));
+        self.render_region_with(self.x0, self.x1, self.y0, self.y1, |i, j, rng| {
+            let mut normal = Vec3::<T>::new();
+            for _k in 0..sampling {
+                let ray = self.get_ray(i, j, camera, scale, rng);
+                if let Some(scene_hit) = scene.cast_layer_ray(&ray, self.layer_mask) {
+                    normal = normal + &scene_hit.hit.normal;
+                }
+            }
+            normal / T::from_f64(sampling as f64)
+        })
+    }
+
+    // World space position of the first surface seen by the camera, the
+    // nearest one of the samples (averaged positions would be between
+    // surfaces at silhouettes). Pixels without a surface are T::max_value(),
+    // i.e. for reprojection or to extract a point cloud.
+    pub fn render_position_pass<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>) -> Image<T>
+        where T: Float
+    {
+        let sampling = usize::max(self.sampling, 1);
+        let scale = (T::from_f64(2.0 / self.width as f64), T::from_f64(2.0 / self.height as f64));
+        self.render_region_with(self.x0, self.x1, self.y0, self.y1, |i, j, rng| {
+            let mut nearest = T::infinity();
+            let mut position = Vec3::from_array([T::max_value(), T::max_value(), T::max_value()]);
+            for _k in 0..sampling {
+                let ray = self.get_ray(i, j, camera, scale, rng);
+                if let Some(scene_hit) = scene.cast_layer_ray(&ray, self.layer_mask) {
+                    let distance = (&scene_hit.hit.point - ray.get_origin()).norm();
+                    if distance < nearest {
+                        nearest = distance;
+                        position = scene_hit.hit.point;
+                    }
+                }
+            }
+            position
+        })
+    }
+
+    // Actor seen by the most samples of each pixel, row by row like the
+    // pixels of an image of the region, None where the samples miss
+    pub fn render_id_pass<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>) -> Vec<Option<ActorId>>
+        where T: Float
+    {
+        let sampling = usize::max(self.sampling, 1);
+        let scale = (T::from_f64(2.0 / self.width as f64), T::from_f64(2.0 / self.height as f64));
+        let mut rng = self.create_rng(self.x0, self.y0);
+        let mut ids = Vec::with_capacity((self.x1 - self.x0) * (self.y1 - self.y0));
+        for j in self.y0..self.y1 {
+            for i in self.x0..self.x1 {
+                let mut counts: Vec<(Option<ActorId>, usize)> = vec![];
                 for _k in 0..sampling {
-                    let ray = self.get_ray(i, j, camera, two);
-                    color = color + scene.get_color(&ray, 0, self.reflections);
+                    let ray = self.get_ray(i, j, camera, scale, &mut rng);
+                    let id = scene.cast_layer_ray(&ray, self.layer_mask).map(|scene_hit| scene_hit.actor_id);
+                    match counts.iter_mut().find(|(seen, _)| *seen == id) {
+                        Some((_, count)) => *count += 1,
+                        None => counts.push((id, 1))
+                    }
+                }
+                let mut best = (None, 0);
+                for (id, count) in counts.iter() {
+                    if *count > best.1 {
+                        best = (*id, *count);
+                    }
                 }
+                ids.push(best.0);
             }
         }
+        ids
+    }
 
-        let sampling = T::from(sampling).unwrap();
-        color = color / sampling;
+    // Depth of the first surface seen by the camera, see DepthEncoding for
+    // the values stored and for the misses
+    pub fn render_depth_pass<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>, encoding: DepthEncoding<T>, filter: DepthFilter) -> Image<T>
+        where T: Float
+    {
+        let sampling = usize::max(self.sampling, 1);
+        let scale = (T::from_f64(2.0 / self.width as f64), T::from_f64(2.0 / self.height as f64));
+        let mut direction = Vec3::from_slice(camera.get_direction().get_data());
+        direction.normalize();
+        self.render_region_with(self.x0, self.x1, self.y0, self.y1, |i, j, rng| {
+            let mut nearest = T::infinity();
+            let mut sum = T::zero();
+            for _k in 0..sampling {
+                let ray = self.get_ray(i, j, camera, scale, rng);
+                let depth = match scene.cast_layer_ray(&ray, self.layer_mask) {
+                    Some(scene_hit) => (&scene_hit.hit.point - camera.get_position()).dot(&direction),
+                    None => T::infinity()
+                };
+                nearest = nearest.min(depth);
+                sum = sum + encoding.encode(depth);
+            }
+            let value = match filter {
+                DepthFilter::Min => encoding.encode(nearest),
+                DepthFilter::Average => sum / T::from_f64(sampling as f64)
+            };
+            Vec3::from_array([value, value, value])
+        })
+    }
 
-        color
+    fn render_region<T>(&self, x0: usize, x1: usize, y0: usize, y1: usize, scene: &Scene<T>, camera: &dyn Camera<T>) -> Image<T>
+        where T: Float
+    {
+        let shift = self.create_shift();
+        self.render_region_with(x0, x1, y0, y1, |i, j, rng| self.sample_pixel(i, j, scene, camera, rng, &shift, None, None))
     }
 
-    pub fn render<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>) -> Image<T>
+    // Renders like render, in tiles of STATS_TILE_SIZE pixels, and reports
+    // the time of each tile and the number of rays traced at each depth.
+    // Needs a clock, which wasm32-unknown-unknown doesn't have.
+    pub fn render_with_stats<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>) -> (Image<T>, RenderReport)
+        where T: Float
+    {
+        let mut report = RenderReport::new();
+        let mut image = Image::<T>::new(self.x1 - self.x0, self.y1 - self.y0);
+        let shift = self.create_shift();
+        let start = Instant::now();
+        for y0 in (self.y0..self.y1).step_by(STATS_TILE_SIZE) {
+            for x0 in (self.x0..self.x1).step_by(STATS_TILE_SIZE) {
+                let x1 = usize::min(x0 + STATS_TILE_SIZE, self.x1);
+                let y1 = usize::min(y0 + STATS_TILE_SIZE, self.y1);
+                let tile_start = Instant::now();
+                let tile = self.render_region_with(x0, x1, y0, y1, |i, j, rng| {
+                    self.sample_pixel(i, j, scene, camera, rng, &shift, Some(&mut report.rays_per_depth), None)
+                });
+                report.tiles.push(TileReport { x0, y0, width: x1 - x0, height: y1 - y0, time: tile_start.elapsed() });
+                image.blit(&tile, x0 - self.x0, y0 - self.y0);
+            }
+        }
+        report.render_time = start.elapsed();
+        report.tree_builds = scene.get_tree_builds();
+        report.tree_build_time = scene.get_tree_build_time();
+        (image, report)
+    }
+
+    // Like render, also counting the rays and the work of the tree to find
+    // their hits. The render time is only measured if the scene has timing
+    // enabled, the clock isn't available everywhere.
+    pub fn render_verbose<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>) -> (Image<T>, SceneStats)
         where T: Float
     {
-        let img_width = self.x1 - self.x0;
-        let img_height = self.y1 - self.y0;
+        let mut stats = SceneStats::default();
+        let shift = self.create_shift();
+        let start = if scene.get_timing() { Some(Instant::now()) } else { None };
+        let image = self.render_region_with(self.x0, self.x1, self.y0, self.y1, |i, j, rng| {
+            self.sample_pixel(i, j, scene, camera, rng, &shift, None, Some(&mut stats))
+        });
+        if let Some(start) = start {
+            stats.render_time_ms = start.elapsed().as_millis() as u64;
+        }
+        (image, stats)
+    }
+
+    fn render_region_with<T, F>(&self, x0: usize, x1: usize, y0: usize, y1: usize, mut sample: F) -> Image<T>
+        where T: Float, F: FnMut(usize, usize, &mut dyn RngCore) -> Vec3<T>
+    {
+        let img_width = x1 - x0;
+        let img_height = y1 - y0;
         let mut image = Image::<T>::new(img_width, img_height);
+        // A single generator for the whole region instead of one per pixel
+        let mut rng = self.create_rng(x0, y0);
         for j in 0..img_height {
             for i in 0..img_width {
-                let color = self.render_pixel(self.x0 + i, self.y0 + j, scene, camera);
+                let color = sample(x0 + i, y0 + j, &mut rng);
                 let index = j * img_width + i;
                 image.data[3 * index] = color.get_data()[0];
                 image.data[3 * index + 1] = color.get_data()[1];
@@ -105,26 +914,678 @@ impl Renderer {
         image
     }
 
-    fn get_ray<T>(&self, i: usize, j: usize, camera: &dyn Camera<T>, _two: T) -> Ray<T>
+    fn get_ray<T>(&self, i: usize, j: usize, camera: &dyn Camera<T>, scale: (T, T), rng: &mut dyn RngCore) -> Ray<T>
         where T: Float
     {
-        let two = T::from(2.0).unwrap();
-
-        match self.antialiasing {
+        let (i, j) = match self.antialiasing {
             // If antialiasing is disabled, the ray always hits the pixel in the same position
-            false => {
-                let v = two * (T::from(j).unwrap() / T::from(self.height).unwrap()) - T::one();
-                let u = two * (T::from(i).unwrap() / T::from(self.width).unwrap()) - T::one();
-                camera.get_ray(u, v)
-            },
+            false => (T::from_f64(i as f64), T::from_f64(j as f64)),
             // If antializasing is enabled, the ray is randomly chosen in the vicinity of the pixel
-            true => {
-                let i : f64 = (i as f64) + random::<f64>();
-                let j : f64 = (j as f64) + random::<f64>();
-                let v = two * (T::from(j).unwrap() / T::from(self.height).unwrap()) - T::one();
-                let u = two * (T::from(i).unwrap() / T::from(self.width).unwrap()) - T::one();
-                camera.get_ray(u, v)
+            true => (T::from_f64(i as f64 + rng.gen::<f64>()), T::from_f64(j as f64 + rng.gen::<f64>()))
+        };
+        let mut ray = camera.get_ray_with(i * scale.0 - T::one(), j * scale.1 - T::one(), rng);
+        ray.set_spread(Some(Renderer::pixel_spread(camera, scale)));
+        ray
+    }
+
+    fn get_sampled_ray<T>(&self, i: usize, j: usize, camera: &dyn Camera<T>, scale: (T, T), sample: &[f64; 4]) -> Ray<T>
+        where T: Float
+    {
+        let i = T::from_f64(i as f64 + sample[0]);
+        let j = T::from_f64(j as f64 + sample[1]);
+        let mut ray = camera.get_ray_at(i * scale.0 - T::one(), j * scale.1 - T::one(), (T::from_f64(sample[2]), T::from_f64(sample[3])));
+        ray.set_spread(Some(Renderer::pixel_spread(camera, scale)));
+        ray
+    }
+
+    // The height of a pixel seen from the camera at a distance of 1, the
+    // spread of its rays for the textures to filter over
+    fn pixel_spread<T>(camera: &dyn Camera<T>, scale: (T, T)) -> T
+        where T: Float
+    {
+        (camera.get_fov() * T::from_f64(0.5)).tan() * scale.1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Actor, ActorBuilder};
+    use crate::hitable::Hitable;
+    use crate::hitable::primitive::{Sphere, Rectangle, ConstantMedium};
+    use crate::constants::Axis;
+    use crate::hitable::transform::Translation;
+    use crate::camera::perspective::PerspectiveCamera;
+    use crate::material::plain::PlainMaterial;
+    use crate::material::lambertian::LambertianMaterial;
+    use crate::material::isotropic::IsotropicMaterial;
+    use crate::material::dielectric::{DielectricMaterial, Dispersion};
+    use crate::texture::checker::CheckerTexture;
+    use crate::texture::uniform::UniformTexture;
+    use crate::tree::TreeType;
+
+    #[test]
+    fn convert() {
+        let mut image = Image::<f64>::new(2, 2);
+        image.data[4] = 0.1;
+        let converted = image.to_f32();
+        assert_eq!(converted.width, 2);
+        assert_eq!(converted.height, 2);
+        assert_eq!(converted.data[4], 0.1f32);
+        let back = converted.to_f64();
+        assert_eq!(back.data[4], 0.1f32 as f64);
+        assert_eq!(back.data[0], 0.0);
+        assert_eq!(image.to_string(), "Image(2×2, T=f64)");
+        assert_eq!(converted.to_string(), "Image(2×2, T=f32)");
+    }
+
+    #[test]
+    fn variance_map() {
+        let mut image = Image::<f64>::new(4, 3);
+        let reference = Image::<f64>::new(4, 3);
+        image.data[0] = 2.0;
+        image.data[5] = -3.0;
+        let variance = image.compute_variance_map(&reference);
+        assert_eq!(variance.width, 4);
+        assert_eq!(variance.height, 3);
+        assert_eq!(variance.data[0], 4.0);
+        assert_eq!(variance.data[5], 9.0);
+        assert_eq!(variance.data[1], 0.0);
+    }
+
+    #[test]
+    fn local_variance() {
+        let mut image = Image::<f64>::new(5, 4);
+        for i in 0..image.width * image.height {
+            image.data[3 * i] = 0.25;
+            image.data[3 * i + 1] = 0.5;
+            image.data[3 * i + 2] = 0.75;
+        }
+        let variance = image.compute_local_variance(3);
+        for value in variance.data.iter() {
+            assert_eq!(*value, 0.0);
+        }
+
+        // A single bright pixel only affects its neighborhood
+        image.data[0] = 1.0;
+        let variance = image.compute_local_variance(3);
+        assert!(variance.data[0] > 0.0);
+        assert!(variance.data[3] > 0.0);
+        assert_eq!(variance.data[3 * 2], 0.0);
+        assert_eq!(variance.data[1], 0.0);
+    }
+
+    #[test]
+    fn save_ppm_binary() {
+        let mut image = Image::<f64>::new(2, 1);
+        image.data.copy_from_slice(&[0.0, 0.25, 1.0, 2.0, -1.0, 0.5]);
+        let path = std::env::temp_dir().join(format!("save_ppm_binary_{}.ppm", std::process::id()));
+        image.save_ppm_binary(&path, 2.0).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let header = b"P6\n2 1\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(&bytes[header.len()..], [0, 127, 255, 255, 0, 180]);
+    }
+
+    #[test]
+    fn save_ppm() {
+        let mut image = Image::<f64>::new(2, 1);
+        image.data.copy_from_slice(&[0.0, 0.25, 1.0, 2.0, -1.0, 0.5]);
+        let path = std::env::temp_dir().join(format!("save_ppm_{}.ppm", std::process::id()));
+        image.save_ppm(&path, 2.0).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(text, "P3\n2 1\n255\n0 127 255\n255 0 180\n");
+    }
+
+    #[test]
+    fn save_ppm_binary_error() {
+        let image = Image::<f64>::new(2, 1);
+        let path = std::env::temp_dir().join("missing_directory").join("image.ppm");
+        match image.save_ppm_binary(&path, 2.0) {
+            Err(Error::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::NotFound),
+            _ => panic!("expected an io error")
+        }
+    }
+
+    #[test]
+    fn to_rgba() {
+        let mut image = Image::<f64>::new(2, 1);
+        image.data.copy_from_slice(&[1.0, 0.25, 0.0, 2.0, -1.0, 1.0]);
+        assert_eq!(image.to_rgba(1.0), [255, 63, 0, 255, 255, 0, 255, 255]);
+        assert_eq!(image.to_rgba(2.0)[1], 127);
+    }
+
+    #[test]
+    fn normalize() {
+        let mut image = Image::<f64>::new(2, 1);
+        image.data.copy_from_slice(&[2.0, 1.0, 0.5, 4.0, 0.0, 1.0]);
+        image.normalize();
+        assert_eq!(image.data, [0.5, 0.25, 0.125, 1.0, 0.0, 0.25]);
+        let mut image = Image::<f64>::new(2, 1);
+        image.normalize();
+        assert_eq!(image.data, [0.0; 6]);
+    }
+
+    #[test]
+    fn auto_exposure() {
+        // Grey pixels of luminance 0.1 to 1.0, in no particular order
+        let mut image = Image::<f64>::new(5, 2);
+        for (i, value) in [0.3, 1.0, 0.1, 0.7, 0.5, 0.2, 0.9, 0.4, 0.8, 0.6].iter().enumerate() {
+            image.set_pixel(i % 5, i / 5, [*value; 3]).unwrap();
+        }
+        let close = |exposure: Option<f64>, expected: f64| (exposure.unwrap() - expected).abs() < 1e-12;
+        assert!(close(image.auto_exposure(0.18, 50.0), 0.18 / 0.5));
+        assert!(close(image.auto_exposure(0.18, 90.0), 0.18 / 0.9));
+        assert!(close(image.auto_exposure(1.0, 100.0), 1.0));
+        assert!(close(image.auto_exposure(1.0, 0.0), 10.0));
+
+        // Broken pixels are left out, a bright light shifts the median by one
+        image.set_pixel(0, 0, [f64::NAN, 0.0, 0.0]).unwrap();
+        image.set_pixel(1, 0, [f64::INFINITY; 3]).unwrap();
+        assert!(close(image.auto_exposure(0.18, 50.0), 0.18 / 0.5));
+        image.set_pixel(2, 0, [50.0; 3]).unwrap();
+        assert!(close(image.auto_exposure(0.18, 50.0), 0.18 / 0.6));
+
+        let mut image = Image::<f64>::new(2, 1);
+        image.data.copy_from_slice(&[0.0, 0.0, 0.0, 2.0, 4.0, 2.0]);
+        assert_eq!(image.auto_exposure(0.5, 50.0), None);
+        assert!(close(image.auto_expose(0.5, 90.0), 0.5 / 3.4304));
+        assert!((Vec3::from_slice(&image.data[3..]).luminance() - 0.5).abs() < 1e-12);
+        assert_eq!(Image::<f64>::new(0, 0).auto_exposure(0.5, 50.0), None);
+    }
+
+    #[test]
+    fn pixels() {
+        let mut image = Image::<f64>::new(3, 2);
+        image.set_pixel(2, 1, [1.0, 2.0, 3.0]).unwrap();
+        assert_eq!(image.get_pixel(2, 1), Some([1.0, 2.0, 3.0]));
+        assert_eq!(image.data[15..18], [1.0, 2.0, 3.0]);
+        assert_eq!(image.get_pixel(3, 0), None);
+        assert_eq!(image.get_pixel(0, 2), None);
+        assert_eq!(image.set_pixel(3, 1, [1.0; 3]), Err(BoundsError { x: 3, y: 1, width: 3, height: 2 }));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "pixel (0, 2) is outside of the 3x2 image")]
+    fn pixel_index() {
+        Image::<f64>::new(3, 2).pixel_index(0, 2);
+    }
+
+    #[test]
+    fn blit() {
+        let mut image = Image::<f64>::new(3, 2);
+        let mut tile = Image::<f64>::new(2, 2);
+        for (i, value) in tile.data.iter_mut().enumerate() {
+            *value = i as f64 + 1.0;
+        }
+        // Only the first column of the tile fits
+        image.blit(&tile, 2, 0);
+        assert_eq!(image.data[6..9], [1.0, 2.0, 3.0]);
+        assert_eq!(image.data[15..18], [7.0, 8.0, 9.0]);
+        assert_eq!(image.data[0..6], [0.0; 6]);
+        assert_eq!(image.data[9..15], [0.0; 6]);
+    }
+
+    #[test]
+    fn render_tile() {
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.2, 0.3, 0.4]));
+        let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::new(1.0));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -3.0])));
+        let odd = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.0, 0.0])));
+        let even = Box::new(UniformTexture::new(Vec3::from_array([0.0, 1.0, 0.0])));
+        let texture = Box::new(CheckerTexture::new(odd, even));
+        scene.add_actor(Actor::new(hitable, Box::new(PlainMaterial::new(texture))));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 0.0]);
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+
+        let renderer = Renderer::new(0, 10, 0, 7, 10, 7, 1, 4, false).unwrap();
+        let reference = renderer.render(&scene, &camera);
+
+        // 4 x 3 pixel tiles, cropped at the border
+        let mut image = Image::<f64>::new(10, 7);
+        for tile_y in 0..3 {
+            for tile_x in 0..3 {
+                let tile = renderer.render_tile(&scene, &camera, tile_x, tile_y, 4, 3);
+                assert_eq!(tile.width, if tile_x == 2 { 2 } else { 4 });
+                assert_eq!(tile.height, if tile_y == 2 { 1 } else { 3 });
+                image.blit(&tile, 4 * tile_x, 3 * tile_y);
+            }
+        }
+        assert_eq!(image.data, reference.data);
+
+        let tile = renderer.render_tile(&scene, &camera, 3, 0, 4, 3);
+        assert_eq!(tile.width, 0);
+    }
+
+    #[test]
+    fn render_rows() {
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.2, 0.3, 0.4]));
+        let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::new(1.0));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -3.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.0, 0.0])));
+        scene.add_actor(Actor::new(hitable, Box::new(PlainMaterial::new(texture))));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 0.0]);
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+
+        let renderer = Renderer::new(2, 8, 1, 7, 10, 7, 1, 4, false).unwrap();
+        let reference = renderer.render(&scene, &camera);
+        let mut image = Image::<f64>::new(6, 6);
+        for y in (0..7).step_by(4) {
+            let rows = renderer.render_rows(&scene, &camera, y..y + 4);
+            assert_eq!(rows.width, 6);
+            image.blit(&rows, 0, usize::max(y, 1) - 1);
+        }
+        assert_eq!(image.data, reference.data);
+        assert_eq!(renderer.render_rows(&scene, &camera, 7..9).height, 0);
+    }
+
+    #[test]
+    fn seed() {
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.8, 0.9, 1.0]));
+        let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::new(1.0));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -3.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        scene.add_actor(Actor::new(hitable, Box::new(LambertianMaterial::new(texture, 0.5))));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 0.0]);
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+        camera.set_aperture(0.1).unwrap();
+
+        let mut renderer = Renderer::new(0, 8, 0, 8, 8, 8, 4, 4, true).unwrap();
+        assert_ne!(renderer.render(&scene, &camera).data, renderer.render(&scene, &camera).data);
+        renderer.set_seed(Some(42));
+        assert_eq!(renderer.get_seed(), Some(42));
+        let image = renderer.render(&scene, &camera);
+        assert_eq!(image.data, renderer.render(&scene, &camera).data);
+        renderer.set_seed(Some(43));
+        assert_ne!(image.data, renderer.render(&scene, &camera).data);
+    }
+
+    #[test]
+    fn seed_fog() {
+        // The distances into a medium are random as well
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.8, 0.9, 1.0]));
+        let medium = ConstantMedium::new(Box::new(Sphere::new(1.0)), 0.8);
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.9, 0.9, 0.9])));
+        scene.add_actor(ActorBuilder::new(medium).at([0.0, 0.0, -3.0]).material(IsotropicMaterial::new(texture)));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+
+        let mut renderer = Renderer::new(0, 8, 0, 8, 8, 8, 4, 8, true).unwrap();
+        renderer.set_seed(Some(42));
+        let image = renderer.render(&scene, &camera);
+        assert_eq!(image.data, renderer.render(&scene, &camera).data);
+        renderer.set_seed(Some(43));
+        assert_ne!(image.data, renderer.render(&scene, &camera).data);
+    }
+
+    #[test]
+    fn exposure() {
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.8, 0.9, 1.0]));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        scene.add_actor(Actor::sphere(1.0).at([0.0, 0.0, -3.0]).material(LambertianMaterial::new(texture, 0.5)));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+        camera.set_aperture(0.1).unwrap();
+
+        let mut renderer = Renderer::new(0, 8, 0, 8, 8, 8, 4, 4, true).unwrap();
+        renderer.set_seed(Some(7));
+        let mean = |image: &Image<f64>| image.data.chunks(3).map(|pixel| Vec3::from_slice(pixel).luminance()).sum::<f64>();
+        let plain = renderer.render(&scene, &camera);
+        let mut exposure = Exposure::new(100.0, 1.0 / 60.0, 2.8).unwrap();
+        renderer.set_exposure(Some(exposure));
+        let base = mean(&renderer.render(&scene, &camera));
+        assert!((base / mean(&plain) - exposure.scale(2.8)).abs() < 1e-12);
+
+        // Doubling the ISO or the shutter time, or one stop more, is
+        // exactly twice as bright
+        exposure.set_exposure(200.0, 1.0 / 60.0, 2.8).unwrap();
+        renderer.set_exposure(Some(exposure));
+        assert_eq!(mean(&renderer.render(&scene, &camera)), 2.0 * base);
+        exposure.set_exposure(100.0, 1.0 / 30.0, 2.8).unwrap();
+        renderer.set_exposure(Some(exposure));
+        assert_eq!(mean(&renderer.render(&scene, &camera)), 2.0 * base);
+        exposure.set_exposure(100.0, 1.0 / 60.0, 2.8).unwrap();
+        exposure.set_ev_compensation(1.0);
+        renderer.set_exposure(Some(exposure));
+        assert_eq!(mean(&renderer.render(&scene, &camera)), 2.0 * base);
+
+        // An aperture twice as wide lets in four times the light
+        exposure.set_ev_compensation(0.0);
+        exposure.set_linked_aperture(Some(0.28)).unwrap();
+        renderer.set_exposure(Some(exposure));
+        assert!((mean(&renderer.render(&scene, &camera)) / base - 1.0).abs() < 1e-12);
+        camera.set_aperture(0.2).unwrap();
+        assert!((renderer.exposure_scale(&camera) / exposure.scale(2.8) - 4.0).abs() < 1e-12);
+        camera.set_aperture(0.1).unwrap();
+        renderer.set_exposure(None);
+        assert_eq!(renderer.render(&scene, &camera).data, plain.data);
+    }
+
+    #[test]
+    fn spectral() {
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.8, 0.9, 1.0]));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.9, 0.5, 0.2])));
+        scene.add_actor(Actor::sphere(1.0).at([0.5, 0.0, -4.0]).material(LambertianMaterial::new(texture, 0.8)));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+        let mut glass = DielectricMaterial::new(texture, 1.5);
+        glass.set_dispersion(Some(Dispersion::bk7()));
+        scene.add_actor(Actor::sphere(0.5).at([-0.5, 0.0, -2.5]).material(glass));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+
+        let mut renderer = Renderer::new(0, 8, 0, 8, 8, 8, 1024, 4, true).unwrap();
+        renderer.set_seed(Some(3));
+        let rgb = renderer.render(&scene, &camera);
+        renderer.set_spectral(true);
+        assert!(renderer.get_spectral());
+        let spectral = renderer.render(&scene, &camera);
+
+        // The weights of the wavelengths average to white, barely dispersive
+        // glass gives the same colors within the noise
+        let mean = |image: &Image<f64>, k: usize| image.data.iter().skip(k).step_by(3).sum::<f64>() / 64.0;
+        for k in 0..3 {
+            assert!((mean(&spectral, k) / mean(&rgb, k) - 1.0).abs() < 0.03);
+        }
+        assert!(spectral.data != rgb.data);
+    }
+
+    #[test]
+    fn warm_up_scene() {
+        let mut scene = Scene::<f64>::new();
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        scene.add_actor(Actor::sphere(1.0).at([0.0, 0.0, -3.0]).material(LambertianMaterial::new(texture, 0.5)));
+        scene.add_actor(Actor::sphere(1.0).at([0.0, 0.0, 3.0]).lambertian([0.5, 0.5, 0.5], 0.5));
+        scene.set_tree_type(TreeType::Binary);
+
+        let mut renderer = Renderer::new(0, 8, 0, 8, 8, 8, 1, 3, false).unwrap();
+        renderer.set_seed(Some(1));
+        renderer.set_warm_up_rays(1000);
+        let stats = renderer.warm_up_scene(&mut scene).unwrap();
+        assert_eq!(stats.primary_rays, 1000);
+        assert_eq!(renderer.get_warm_up_stats(), Some(&stats));
+        assert!(stats.hits > 100 && stats.hits < 1000);
+        assert!(stats.intersection_tests >= stats.hits);
+
+        // A material with a NaN color is found
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([f64::NAN, 0.5, 0.5])));
+        scene.set_actor_material(1, Box::new(LambertianMaterial::new(texture, 0.5)));
+        match renderer.warm_up_scene(&mut scene) {
+            Err(Error::InvalidScene(reason)) => assert!(reason.contains("material")),
+            _ => panic!("expected an invalid scene")
+        }
+    }
+
+    #[test]
+    fn render_with_stats() {
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.2, 0.3, 0.4]));
+        let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::new(1.0));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -3.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        scene.add_actor(Actor::new(hitable, Box::new(LambertianMaterial::new(texture, 0.5))));
+        scene.set_tree_type(TreeType::Binary);
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 0.0]);
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+
+        // The plain render is the same apart from the random bounces
+        let renderer = Renderer::new(0, 20, 0, 18, 20, 18, 2, 3, false).unwrap();
+        let (image, report) = renderer.render_with_stats(&scene, &camera);
+        let reference = renderer.render(&scene, &camera);
+        assert_eq!(image.data[0..3], reference.data[0..3]);
+
+        assert_eq!(report.tree_builds, 1);
+        // 16 x 16 pixel tiles, cropped at the border
+        assert_eq!(report.tiles.len(), 4);
+        assert_eq!((report.tiles[3].x0, report.tiles[3].y0, report.tiles[3].width, report.tiles[3].height), (16, 16, 4, 2));
+        // Two samples of each pixel, the ones hitting the sphere bounce
+        assert_eq!(report.rays_per_depth[0], 2 * 20 * 18);
+        assert!(report.rays_per_depth[1] > 0 && report.rays_per_depth[1] < report.rays_per_depth[0]);
+        assert!(report.rays_per_depth.len() <= 4);
+    }
+
+    #[test]
+    fn render_verbose() {
+        let mut scene = Scene::<f64>::new();
+        let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::new(1.0));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -3.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        scene.add_actor(Actor::new(hitable, Box::new(LambertianMaterial::new(texture, 0.5))));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 0.0]);
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+
+        // The same image as without stats
+        let mut renderer = Renderer::new(0, 10, 0, 8, 10, 8, 2, 3, true).unwrap();
+        renderer.set_seed(Some(1));
+        let (image, stats) = renderer.render_verbose(&scene, &camera);
+        assert_eq!(image.data, renderer.render(&scene, &camera).data);
+        assert_eq!(stats.primary_rays, 2 * 10 * 8);
+        assert!(stats.hits > 0 && stats.hits < stats.primary_rays + stats.bounce_rays);
+        assert_eq!(stats.intersection_tests, stats.primary_rays + stats.bounce_rays);
+        assert_eq!(stats.shadow_rays, 0);
+        // The clock is only read with timing enabled
+        assert_eq!(stats.render_time_ms, 0);
+
+        // Only bidirectional paths test the visibility of the lights
+        let light: Box<dyn Hitable<f64>> = Box::new(Sphere::new(0.5));
+        let light = Box::new(Translation::new(light, Vec3::from_array([0.0, 2.0, -3.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+        let light = scene.add_actor(Actor::new(light, Box::new(PlainMaterial::new(texture))));
+        scene.add_light(light);
+        renderer.set_integrator(Integrator::Bdpt);
+        let (_, stats) = renderer.render_verbose(&scene, &camera);
+        assert_eq!(stats.primary_rays, 2 * 10 * 8);
+        assert!(stats.shadow_rays > 0);
+    }
+
+    #[test]
+    fn shadow_pass() {
+        // A small sphere above a large floor sphere, lit from straight above
+        let mut scene = Scene::<f64>::new();
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        let hitable = Box::new(Translation::new(Box::new(Sphere::new(100.0)), Vec3::from_array([0.0, -101.0, -5.0])));
+        scene.add_actor(Actor::new(hitable, Box::new(LambertianMaterial::new(texture, 0.5))));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        let hitable = Box::new(Translation::new(Box::new(Sphere::new(0.5)), Vec3::from_array([0.0, 0.0, -5.0])));
+        scene.add_actor(Actor::new(hitable, Box::new(LambertianMaterial::new(texture, 0.5))));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 0.0]);
+        camera.set_direction(&[0.0, -0.1, -1.0]);
+        camera.set_fov(std::f64::consts::PI / 6.0).unwrap();
+
+        let renderer = Renderer::new(0, 31, 0, 31, 31, 31, 1, 1, false).unwrap();
+        let light = Vec3::from_array([0.0, 10.0, -5.0]);
+        let image = renderer.render_shadow_pass(&scene, &camera, &light);
+        for value in image.data.iter() {
+            assert!(*value == 0.0 || *value == 1.0);
+        }
+        // The top of the small sphere is lit, the floor right below it isn't,
+        // the floor further away is
+        let lit = |point: [f64; 3]| {
+            let (r, s) = camera.project(&Vec3::from_array(point)).unwrap();
+            let i = ((r + 1.0) * 15.5) as usize;
+            let j = ((s + 1.0) * 15.5) as usize;
+            image.data[3 * (j * 31 + i)]
+        };
+        assert_eq!(lit([0.0, 0.45, -5.0]), 1.0);
+        assert_eq!(lit([0.0, -1.0, -5.0]), 0.0);
+        assert_eq!(lit([1.0, -1.0, -5.0]), 1.0);
+        // Without the light being blocked everything is lit
+        let light = Vec3::from_array([0.0, 0.0, 0.0]);
+        let image = renderer.render_shadow_pass(&scene, &camera, &light);
+        assert!(image.data.iter().all(|value| *value == 1.0));
+    }
+
+    #[test]
+    fn albedo_pass() {
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.2, 0.3, 0.4]));
+        let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::new(1.0));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -3.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.5, 0.25])));
+        scene.add_actor(Actor::new(hitable, Box::new(LambertianMaterial::new(texture, 0.5))));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 0.0]);
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+
+        // Without any lighting the sphere has its dimmed texture color everywhere
+        let renderer = Renderer::new(0, 9, 0, 9, 9, 9, 4, 4, false).unwrap();
+        let image = renderer.render_albedo_pass(&scene, &camera);
+        let center = 3 * (4 * 9 + 4);
+        assert_eq!(image.data[center..center + 3], [0.5, 0.25, 0.125]);
+        assert_eq!(image.data[0..3], [0.2, 0.3, 0.4]);
+    }
+
+    #[test]
+    fn position_pass() {
+        let mut scene = Scene::<f64>::new();
+        let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::new(1.0));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -3.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        scene.add_actor(Actor::new(hitable, Box::new(PlainMaterial::new(texture))));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 0.0]);
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+
+        let renderer = Renderer::new(0, 9, 0, 9, 9, 9, 4, 4, false).unwrap();
+        let image = renderer.render_position_pass(&scene, &camera);
+        // On the front of the sphere in the center, the sentinel for the misses
+        let center = 3 * (4 * 9 + 4);
+        let position = Vec3::from_slice(&image.data[center..center + 3]);
+        assert!(((&position - &Vec3::from_array([0.0, 0.0, -3.0])).norm() - 1.0).abs() < 1e-9);
+        assert!(position.get_data()[2] > -3.0);
+        assert_eq!(image.data[0..3], [f64::MAX, f64::MAX, f64::MAX]);
+    }
+
+    #[test]
+    fn depth_pass() {
+        let mut scene = Scene::<f64>::new();
+        let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::new(1.0));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -4.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        scene.add_actor(Actor::new(hitable, Box::new(PlainMaterial::new(texture))));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 0.0]);
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+        camera.set_fov(std::f64::consts::PI / 4.0).unwrap();
+
+        // Pixel 10 of 20 is the center of the image without antialiasing
+        let renderer = Renderer::new(0, 20, 0, 20, 20, 20, 1, 1, false).unwrap();
+        let image = renderer.render_depth_pass(&scene, &camera, DepthEncoding::Distance, DepthFilter::Min);
+        let depth = |i: usize, j: usize| image.data[3 * (j * 20 + i)];
+        assert!((depth(10, 10) - 3.0).abs() < 1e-12);
+        // Until the sphere is missed
+        let increasing = |inner: f64, outer: f64| outer > inner || outer == f64::INFINITY;
+        for k in 1..10 {
+            assert!(increasing(depth(10 + k - 1, 10), depth(10 + k, 10)));
+            assert!(increasing(depth(10 - k + 1, 10), depth(10 - k, 10)));
+            assert!(increasing(depth(10, 10 + k - 1), depth(10, 10 + k)));
+        }
+        assert!(depth(13, 10).is_finite());
+        assert_eq!(depth(0, 0), f64::INFINITY);
+
+        // Inverse depth on a floor in front of the camera
+        let mut scene = Scene::<f64>::new();
+        let hitable: Box<dyn Hitable<f64>> = Box::new(Rectangle::new(100.0, Axis::X, 100.0, Axis::Z));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, -1.0, 0.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        scene.add_actor(Actor::new(hitable, Box::new(PlainMaterial::new(texture))));
+        let image = renderer.render_depth_pass(&scene, &camera, DepthEncoding::Inverse, DepthFilter::Min);
+        let column: Vec<f64> = (0..20).map(|j| image.data[3 * (j * 20 + 5)]).filter(|d| *d > 0.0).collect();
+        assert!(column.len() > 5);
+        for window in column.windows(3) {
+            assert!(((window[2] - window[1]) - (window[1] - window[0])).abs() < 1e-9);
+        }
+
+        // Averaging blends in the misses, the nearest sample doesn't
+        let renderer = Renderer::new(0, 20, 0, 20, 20, 20, 32, 1, true).unwrap();
+        let encoding = DepthEncoding::Normalized { near: 0.0, far: 100.0 };
+        let min = renderer.render_depth_pass(&scene, &camera, encoding, DepthFilter::Min);
+        let average = renderer.render_depth_pass(&scene, &camera, encoding, DepthFilter::Average);
+        assert!(min.data.iter().zip(average.data.iter()).all(|(a, b)| a <= b));
+    }
+
+    #[test]
+    fn invalid() {
+        match Renderer::new(0, 0, 0, 4, 0, 4, 1, 4, false) {
+            Err(Error::EmptyImage { width: 0, height: 4 }) => (),
+            _ => panic!("expected an empty image error")
+        }
+        match Renderer::new(3, 2, 0, 4, 4, 4, 1, 4, false) {
+            Err(Error::InvalidRegion { x0: 3, x1: 2, .. }) => (),
+            _ => panic!("expected an invalid region error")
+        }
+        assert!(Renderer::new(0, 4, 0, 5, 4, 4, 1, 4, false).is_err());
+        assert!(Renderer::new(4, 4, 0, 4, 4, 4, 1, 4, false).is_ok());
+    }
+
+    #[test]
+    fn sample_count() {
+        assert_eq!(Renderer::new(0, 10, 0, 7, 10, 7, 16, 4, true).unwrap().sample_count(), 16 * 10 * 7);
+        // Only the region, at least one sample per pixel
+        assert_eq!(Renderer::new(2, 5, 1, 3, 10, 7, 16, 4, true).unwrap().sample_count(), 16 * 3 * 2);
+        assert_eq!(Renderer::new(0, 10, 0, 7, 10, 7, 0, 4, true).unwrap().sample_count(), 10 * 7);
+    }
+
+    #[test]
+    fn sample_mask() {
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.2, 0.3, 0.4]));
+        let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::new(1.0));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -3.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.0, 0.0])));
+        let sphere = scene.add_actor(Actor::new(hitable, Box::new(PlainMaterial::new(texture))));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 0.0]);
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+
+        let mut renderer = Renderer::new(0, 10, 0, 8, 10, 8, 4, 2, true).unwrap();
+        assert!(renderer.set_sample_mask(Some(Image::<f64>::new(8, 10))).is_err());
+        renderer.set_sample_mask(Some(Image::<f64>::rectangle_mask(10, 8, 2..5, 3..7))).unwrap();
+        assert_eq!(renderer.sample_count(), 4 * 3 * 4 + (80 - 3 * 4));
+
+        // Only the rectangle is rendered over the previous image
+        let mut previous = Image::<f64>::new(10, 8);
+        for value in previous.data.iter_mut() {
+            *value = -1.0;
+        }
+        let image = renderer.render_masked(&scene, &camera, &previous).unwrap();
+        for j in 0..8 {
+            for i in 0..10 {
+                let inside = (2..5).contains(&i) && (3..7).contains(&j);
+                assert_eq!(image.data[3 * (j * 10 + i)] >= 0.0, inside);
+            }
+        }
+        assert!(renderer.render_masked(&scene, &camera, &Image::new(10, 7)).is_err());
+
+        // The mask of the sphere covers it and its edge
+        renderer.set_sample_mask::<f64>(None).unwrap();
+        let ids = renderer.render_id_pass(&scene, &camera);
+        assert_eq!(ids[4 * 10 + 5], Some(sphere));
+        assert_eq!(ids[0], None);
+        let mask = Image::<f64>::actor_mask(&ids, 10, 8, sphere);
+        renderer.set_sample_mask(Some(mask)).unwrap();
+        let image = renderer.render_masked(&scene, &camera, &previous).unwrap();
+        for (index, id) in ids.iter().enumerate() {
+            if id.is_some() {
+                assert!(image.data[3 * index] >= 0.0);
             }
         }
+        assert_eq!(image.data[0], -1.0);
     }
 }
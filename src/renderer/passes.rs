@@ -0,0 +1,212 @@
+use std::ops::{BitOr, BitOrAssign};
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::camera::Camera;
+use crate::scene::Scene;
+use crate::renderer::{Image, Integrator, Renderer};
+
+// Which passes Renderer::render_passes fills in, combined with |
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RenderPassFlags(u32);
+
+impl RenderPassFlags {
+    // The colors, like Renderer::render
+    pub const BEAUTY: RenderPassFlags = RenderPassFlags(1);
+    // Like Renderer::render_normal_pass
+    pub const NORMAL: RenderPassFlags = RenderPassFlags(1 << 1);
+    // Like Renderer::render_depth_pass with Distance and Min
+    pub const DEPTH: RenderPassFlags = RenderPassFlags(1 << 2);
+    // Like Renderer::render_albedo_pass
+    pub const ALBEDO: RenderPassFlags = RenderPassFlags(1 << 3);
+    // The part of the samples whose surface sees a light of the scene,
+    // see Scene::sees_light
+    pub const SHADOW_MASK: RenderPassFlags = RenderPassFlags(1 << 4);
+
+    pub const fn empty() -> Self {
+        RenderPassFlags(0)
+    }
+
+    pub const fn all() -> Self {
+        RenderPassFlags(0b11111)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(&self, other: RenderPassFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for RenderPassFlags {
+    type Output = RenderPassFlags;
+
+    fn bitor(self, other: RenderPassFlags) -> RenderPassFlags {
+        RenderPassFlags(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for RenderPassFlags {
+    fn bitor_assign(&mut self, other: RenderPassFlags) {
+        self.0 |= other.0;
+    }
+}
+
+// The passes asked for, None for the others
+pub struct RenderResult<T>
+    where T: Float
+{
+    pub beauty: Option<Image<T>>,
+    pub normal: Option<Image<T>>,
+    pub depth: Option<Image<T>>,
+    pub albedo: Option<Image<T>>,
+    pub shadow_mask: Option<Image<T>>
+}
+
+impl Renderer {
+    // Renders several passes at once, the first hit of each camera ray is
+    // found once for all of them and the beauty pass traces its path along
+    // the same ray. With the independent sampler and without a sample mask
+    // the beauty pass is the image render gives.
+    pub fn render_passes<T>(&self, scene: &Scene<T>, camera: &dyn Camera<T>, passes: RenderPassFlags) -> RenderResult<T>
+        where T: Float
+    {
+        let (width, height) = (self.x1 - self.x0, self.y1 - self.y0);
+        let sampling = usize::max(self.sampling, 1);
+        let scale = (T::from_f64(2.0 / self.width as f64), T::from_f64(2.0 / self.height as f64));
+        let mut direction = Vec3::from_slice(camera.get_direction().get_data());
+        direction.normalize();
+        let pass = |flag: RenderPassFlags| if passes.contains(flag) { Some(Image::<T>::new(width, height)) } else { None };
+        let mut result = RenderResult {
+            beauty: pass(RenderPassFlags::BEAUTY),
+            normal: pass(RenderPassFlags::NORMAL),
+            depth: pass(RenderPassFlags::DEPTH),
+            albedo: pass(RenderPassFlags::ALBEDO),
+            shadow_mask: pass(RenderPassFlags::SHADOW_MASK)
+        };
+
+        let exposure = self.exposure_scale(camera);
+        let mut rng = self.create_rng(self.x0, self.y0);
+        for j in 0..height {
+            for i in 0..width {
+                let (mut color, mut normal, mut albedo) = (Vec3::<T>::new(), Vec3::<T>::new(), Vec3::<T>::new());
+                let mut nearest = T::infinity();
+                let mut lit = T::zero();
+                for _k in 0..sampling {
+                    let mut ray = self.get_ray(self.x0 + i, self.y0 + j, camera, scale, &mut rng);
+                    let scene_hit = scene.cast_layer_ray(&ray, self.layer_mask);
+                    match &scene_hit {
+                        Some(scene_hit) => {
+                            normal = normal + &scene_hit.hit.normal;
+                            if result.albedo.is_some() {
+                                albedo = albedo + scene_hit.get_material().get_base_color(&scene_hit.hit);
+                            }
+                            nearest = nearest.min((&scene_hit.hit.point - camera.get_position()).dot(&direction));
+                            if result.shadow_mask.is_some() && scene.sees_light(scene_hit, self.layer_mask) {
+                                lit = lit + T::one();
+                            }
+                        },
+                        None => {
+                            if result.albedo.is_some() {
+                                albedo = albedo + scene.get_background_radiance(ray.get_direction());
+                            }
+                            lit = lit + T::one();
+                        }
+                    }
+                    if result.beauty.is_none() || (self.transparent_background && scene_hit.is_none()) {
+                        continue;
+                    }
+                    let weight = self.sample_wavelength(&mut ray, &mut rng);
+                    let sample = match self.integrator {
+                        Integrator::Bdpt => scene.get_bdpt_color(&ray, self.reflections, self.layer_mask, &mut rng),
+                        Integrator::Path => scene.get_layer_color(&ray, 0, self.min_reflections, self.reflections, self.layer_mask, &mut rng)
+                    };
+                    color = color + match weight {
+                        Some(weight) => sample * weight,
+                        None => sample
+                    };
+                }
+
+                let sampling = T::from_f64(sampling as f64);
+                let values = [
+                    (&mut result.beauty, color / sampling * exposure),
+                    (&mut result.normal, normal / sampling),
+                    (&mut result.depth, Vec3::from_array([nearest; 3])),
+                    (&mut result.albedo, albedo / sampling),
+                    (&mut result.shadow_mask, Vec3::from_array([lit / sampling; 3]))
+                ];
+                for (image, value) in values {
+                    if let Some(image) = image.as_mut() {
+                        image.set_pixel(i, j, value.to_array()).unwrap();
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::Actor;
+    use crate::camera::perspective::PerspectiveCamera;
+    use crate::renderer::{DepthEncoding, DepthFilter};
+
+    #[test]
+    fn flags() {
+        let passes = RenderPassFlags::NORMAL | RenderPassFlags::DEPTH;
+        assert!(passes.contains(RenderPassFlags::NORMAL));
+        assert!(!passes.contains(RenderPassFlags::BEAUTY));
+        assert!(!passes.contains(RenderPassFlags::all()));
+        assert!(RenderPassFlags::empty().is_empty());
+        let mut all = RenderPassFlags::BEAUTY | RenderPassFlags::ALBEDO;
+        all |= passes | RenderPassFlags::SHADOW_MASK;
+        assert_eq!(all, RenderPassFlags::all());
+    }
+
+    #[test]
+    fn render_passes() {
+        // A small sphere above a large floor sphere, lit by a sphere above
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.1, 0.2, 0.3]));
+        scene.add_actor(Actor::sphere(100.0).at([0.0, -101.0, -5.0]).lambertian([0.5, 0.5, 0.5], 0.5));
+        scene.add_actor(Actor::sphere(0.5).at([0.0, 0.0, -5.0]).lambertian([1.0, 0.5, 0.25], 1.0));
+        let light = scene.add_actor(Actor::sphere(0.5).at([0.0, 10.0, -5.0]).emissive([4.0, 4.0, 4.0]));
+        scene.add_light(light);
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_direction(&[0.0, -0.1, -1.0]);
+        camera.set_fov(std::f64::consts::PI / 6.0).unwrap();
+
+        let mut renderer = Renderer::new(0, 15, 0, 15, 15, 15, 2, 3, false).unwrap();
+        renderer.set_seed(Some(5));
+        let result = renderer.render_passes(&scene, &camera, RenderPassFlags::all());
+        assert_eq!(result.beauty.unwrap().data, renderer.render(&scene, &camera).data);
+        assert_eq!(result.normal.unwrap().data, renderer.render_normal_pass(&scene, &camera).data);
+        assert_eq!(result.albedo.unwrap().data, renderer.render_albedo_pass(&scene, &camera).data);
+        let depth = renderer.render_depth_pass(&scene, &camera, DepthEncoding::Distance, DepthFilter::Min);
+        assert_eq!(result.depth.unwrap().data, depth.data);
+
+        // The top of the small sphere is lit, the floor right
+        // below the small sphere isn't, the floor further away is
+        let shadow = result.shadow_mask.unwrap();
+        let lit = |point: [f64; 3]| {
+            let (r, s) = camera.project(&Vec3::from_array(point)).unwrap();
+            shadow.get_pixel(((r + 1.0) * 7.5) as usize, ((s + 1.0) * 7.5) as usize).unwrap()[0]
+        };
+        assert_eq!(lit([0.0, 0.45, -5.0]), 1.0);
+        assert_eq!(lit([0.0, -1.0, -5.0]), 0.0);
+        assert_eq!(lit([1.0, -1.0, -5.0]), 1.0);
+        assert!(shadow.data.iter().all(|value| *value == 0.0 || *value == 1.0));
+
+        let result = renderer.render_passes(&scene, &camera, RenderPassFlags::DEPTH);
+        assert!(result.beauty.is_none() && result.normal.is_none() && result.albedo.is_none() && result.shadow_mask.is_none());
+        assert_eq!(result.depth.unwrap().width, 15);
+    }
+}
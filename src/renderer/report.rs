@@ -0,0 +1,99 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+pub struct TileReport {
+    pub x0: usize,
+    pub y0: usize,
+    pub width: usize,
+    pub height: usize,
+    pub time: Duration
+}
+
+// Where the time of a render went, filled by Renderer::render_with_stats.
+// Other phases, i.e. encoding the image, can be timed with time.
+#[derive(Default)]
+pub struct RenderReport {
+    // Of the scene so far, the time only if the scene has timing enabled
+    pub tree_builds: usize,
+    pub tree_build_time: Duration,
+    pub render_time: Duration,
+    pub tiles: Vec<TileReport>,
+    // Number of rays traced at each depth, camera rays at depth 0
+    pub rays_per_depth: Vec<u64>,
+    pub phases: Vec<(&'static str, Duration)>
+}
+
+impl RenderReport {
+    pub fn new() -> Self {
+        RenderReport::default()
+    }
+
+    // Runs f and records its duration as the given phase
+    pub fn time<R, F>(&mut self, phase: &'static str, f: F) -> R
+        where F: FnOnce() -> R
+    {
+        let start = Instant::now();
+        let result = f();
+        self.phases.push((phase, start.elapsed()));
+        result
+    }
+
+    pub fn get_ray_count(&self) -> u64 {
+        self.rays_per_depth.iter().sum()
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// A human readable table of the phases and the rays
+impl fmt::Display for RenderReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<20}{:>14}", "phase", "time [ms]")?;
+        writeln!(f, "{:<20}{:>14.3}", format!("tree build ({})", self.tree_builds), millis(self.tree_build_time))?;
+        writeln!(f, "{:<20}{:>14.3}", "render", millis(self.render_time))?;
+        if let (Some(min), Some(max)) = (self.tiles.iter().map(|t| t.time).min(), self.tiles.iter().map(|t| t.time).max()) {
+            writeln!(f, "{:<20}{:>14.3}", format!("  fastest of {} tiles", self.tiles.len()), millis(min))?;
+            writeln!(f, "{:<20}{:>14.3}", "  slowest tile", millis(max))?;
+        }
+        for (phase, time) in self.phases.iter() {
+            writeln!(f, "{:<20}{:>14.3}", phase, millis(*time))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{:<20}{:>14}", "depth", "rays")?;
+        for (depth, rays) in self.rays_per_depth.iter().enumerate() {
+            writeln!(f, "{:<20}{:>14}", depth, rays)?;
+        }
+        write!(f, "{:<20}{:>14}", "total", self.get_ray_count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let mut report = RenderReport::new();
+        report.tree_builds = 1;
+        report.render_time = Duration::from_millis(12);
+        report.tiles.push(TileReport { x0: 0, y0: 0, width: 4, height: 4, time: Duration::from_millis(5) });
+        report.tiles.push(TileReport { x0: 4, y0: 0, width: 4, height: 4, time: Duration::from_millis(7) });
+        report.rays_per_depth = vec![16, 9];
+        let value = report.time("encode", || 42);
+        assert_eq!(value, 42);
+        assert_eq!(report.phases[0].0, "encode");
+        assert_eq!(report.get_ray_count(), 25);
+
+        // Compare the lines with single spaces, the widths aren't fixed
+        let lines: Vec<String> = report.to_string().lines().map(|line| line.split_whitespace().collect::<Vec<_>>().join(" ")).collect();
+        assert_eq!(lines[0], "phase time [ms]");
+        assert_eq!(lines[1], "tree build (1) 0.000");
+        assert_eq!(lines[2], "render 12.000");
+        assert_eq!(lines[3], "fastest of 2 tiles 5.000");
+        assert_eq!(lines[4], "slowest tile 7.000");
+        assert!(lines[5].starts_with("encode "));
+        assert_eq!(lines[6..], ["", "depth rays", "0 16", "1 9", "total 25"]);
+    }
+}
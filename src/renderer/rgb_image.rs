@@ -0,0 +1,126 @@
+use std::path::Path;
+
+use image::{ImageFormat, Rgb, Rgb32FImage, RgbImage, RgbaImage};
+
+use crate::float::Float;
+use crate::renderer::{Image, ImageRgba};
+use crate::error::Error;
+
+// Conversions from and to the image crate. The float images keep the linear
+// colors, the byte images are gamma encoded like the PPM files.
+
+impl<T> From<&Image<T>> for Rgb32FImage
+    where T: Float
+{
+    fn from(image: &Image<T>) -> Self {
+        let data = image.data.iter().map(|value| value.to_f32().unwrap_or(0.0)).collect();
+        Rgb32FImage::from_raw(image.width as u32, image.height as u32, data).unwrap()
+    }
+}
+
+impl<T> Image<T>
+    where T: Float
+{
+    pub fn to_rgb_image(&self, gamma: T) -> RgbImage {
+        RgbImage::from_raw(self.width as u32, self.height as u32, self.to_bytes(gamma)).unwrap()
+    }
+
+    // The inverse of to_rgb_image, i.e. a photo to use as a texture or as the
+    // reference of a render. Each byte stands for the middle of the range of
+    // colors that to_rgb_image rounds down to it.
+    pub fn from_rgb_image(rgb_image: &RgbImage, gamma: T) -> Self {
+        let max = T::from_f64(255.0);
+        let half = T::from_f64(0.5);
+        let data = rgb_image.pixels().flat_map(|Rgb(pixel)| pixel.iter()).map(|byte| {
+            ((T::from_f64(f64::from(*byte)) + half) / max).min(T::one()).powf(gamma)
+        }).collect();
+        Image {
+            width: rgb_image.width() as usize,
+            height: rgb_image.height() as usize,
+            data
+        }
+    }
+}
+
+impl<T> ImageRgba<T>
+    where T: Float
+{
+    pub fn to_rgba_image(&self, gamma: T) -> RgbaImage {
+        RgbaImage::from_raw(self.width as u32, self.height as u32, self.to_bytes(gamma)).unwrap()
+    }
+
+    // 8 bit RGBA PNG
+    pub fn save_png<P>(&self, path: P, gamma: T) -> Result<(), Error>
+        where P: AsRef<Path>
+    {
+        if self.width == 0 || self.height == 0 {
+            return Err(Error::EmptyImage { width: self.width, height: self.height });
+        }
+        self.to_rgba_image(gamma).save_with_format(path, ImageFormat::Png)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::Renderer;
+    use crate::scenes;
+
+    #[test]
+    fn float() {
+        let mut image = Image::<f64>::new(3, 2);
+        image.set_pixel(2, 1, [0.25, 1.5, -1.0]).unwrap();
+        let rgb = Rgb32FImage::from(&image);
+        assert_eq!(rgb.dimensions(), (3, 2));
+        assert_eq!(rgb.get_pixel(2, 1), &Rgb([0.25, 1.5, -1.0]));
+        assert_eq!(rgb.get_pixel(0, 0), &Rgb([0.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn bytes() {
+        let mut image = Image::<f64>::new(2, 1);
+        image.set_pixel(0, 0, [0.0, 0.5, 1.0]).unwrap();
+        image.set_pixel(1, 0, [0.2, 2.0, -0.5]).unwrap();
+        let rgb = image.to_rgb_image(2.2);
+        assert_eq!(rgb.as_raw(), &image.to_rgba(2.2).iter().enumerate().filter(|(i, _)| i % 4 != 3).map(|(_, b)| *b).collect::<Vec<u8>>());
+
+        // Back within a step of the bytes
+        let back = Image::<f64>::from_rgb_image(&rgb, 2.2);
+        for (value, original) in back.data.iter().zip(image.data.iter()) {
+            let original = original.max(0.0).min(1.0);
+            assert!((value.powf(1.0 / 2.2) - original.powf(1.0 / 2.2)).abs() <= 1.0 / 255.0);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let (width, height) = (48, 32);
+        let (scene, camera) = scenes::cube_scene(width, height).unwrap();
+        let mut renderer = Renderer::new(0, width, 0, height, width, height, 4, 4, true).unwrap();
+        renderer.set_seed(Some(1));
+        let mut image = renderer.render(&scene, &camera);
+        for value in image.data.iter_mut() {
+            *value = value.max(0.0).min(1.0);
+        }
+
+        let back = Image::from_rgb_image(&image.to_rgb_image(2.2), 2.2);
+        let mse = back.data.iter().zip(image.data.iter()).map(|(a, b)| (a - b) * (a - b)).sum::<f64>() / image.data.len() as f64;
+        let psnr = -10.0 * mse.log10();
+        assert!(psnr > 50.0, "{}", psnr);
+    }
+
+    #[test]
+    fn png() {
+        let mut color = Image::<f64>::new(3, 2);
+        color.data.iter_mut().enumerate().for_each(|(i, value)| *value = i as f64 / 40.0);
+        let image = ImageRgba::from_rgb(&color, &[1.0, 0.9, 0.5, 0.25, 0.1, 0.0]);
+        let path = std::env::temp_dir().join(format!("png_{}.png", std::process::id()));
+        image.save_png(&path, 2.2).unwrap();
+        let decoded = image::open(&path).unwrap().to_rgba8();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(decoded, image.to_rgba_image(2.2));
+
+        assert!(ImageRgba::<f64>::new(0, 3).save_png(&path, 1.0).is_err());
+    }
+}
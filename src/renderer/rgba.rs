@@ -0,0 +1,132 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::renderer::Image;
+
+// Colors with an alpha channel, i.e. rendered over a transparent background
+// to composite them over other pictures. The colors are premultiplied by the
+// alpha: a pixel half covered by a white actor is [0.5, 0.5, 0.5, 0.5].
+pub struct ImageRgba<T>
+    where T: Float
+{
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<T>
+}
+
+impl<T> ImageRgba<T>
+    where T: Float
+{
+    pub fn new(width: usize, height: usize) -> Self {
+        ImageRgba {
+            width,
+            height,
+            data: vec![T::zero(); 4 * width * height]
+        }
+    }
+
+    // The colors, already premultiplied, with one alpha value per pixel
+    pub fn from_rgb(color: &Image<T>, alpha: &[T]) -> Self {
+        assert_eq!(alpha.len(), color.width * color.height, "one alpha value per pixel");
+        let mut data = Vec::with_capacity(4 * alpha.len());
+        for (pixel, alpha) in color.data.chunks(3).zip(alpha.iter()) {
+            data.extend_from_slice(pixel);
+            data.push(*alpha);
+        }
+        ImageRgba {
+            width: color.width,
+            height: color.height,
+            data
+        }
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Option<[T; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = 4 * (y * self.width + x);
+        Some([self.data[index], self.data[index + 1], self.data[index + 2], self.data[index + 3]])
+    }
+
+    // Drops the alpha, which leaves the image over a black background. Use
+    // this to save a PPM, which has no alpha channel.
+    pub fn to_rgb(&self) -> Image<T> {
+        let data = self.data.chunks(4).flat_map(|pixel| pixel[..3].iter().copied()).collect();
+        Image {
+            width: self.width,
+            height: self.height,
+            data
+        }
+    }
+
+    // Four bytes per pixel with the alpha not premultiplied, the colors with
+    // the gamma correction applied and clamped to [0, 1] like Image::to_rgba
+    pub fn to_bytes(&self, gamma: T) -> Vec<u8> {
+        let exponent = T::one() / gamma;
+        let max = T::from_f64(255.0);
+        let byte = |value: T| (value.max(T::zero()).min(T::one()) * max).to_u8().unwrap_or(0);
+        let mut bytes = Vec::with_capacity(self.data.len());
+        for pixel in self.data.chunks(4) {
+            let alpha = pixel[3];
+            let mut color = Vec3::from_slice(pixel);
+            for value in color.get_data_mut().iter_mut() {
+                let straight = if alpha > T::zero() { *value / alpha } else { T::zero() };
+                *value = straight.max(T::zero()).min(T::one()).powf(exponent);
+            }
+            bytes.extend_from_slice(&color.to_rgb_u8());
+            bytes.push(byte(alpha));
+        }
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::Actor;
+    use crate::camera::Camera;
+    use crate::camera::perspective::PerspectiveCamera;
+    use crate::renderer::Renderer;
+    use crate::scene::Scene;
+
+    #[test]
+    fn bytes() {
+        let mut color = Image::<f64>::new(3, 1);
+        color.data.copy_from_slice(&[0.5, 0.25, 0.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0]);
+        let image = ImageRgba::from_rgb(&color, &[0.5, 1.0, 0.0]);
+        assert_eq!(image.get_pixel(0, 0), Some([0.5, 0.25, 0.0, 0.5]));
+        assert_eq!(image.to_bytes(1.0), [255, 127, 0, 127, 255, 255, 255, 255, 0, 0, 0, 0]);
+        assert_eq!(image.to_rgb().data, color.data);
+    }
+
+    #[test]
+    fn transparent_background() {
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.2, 0.4, 0.6]));
+        scene.add_actor(Actor::sphere(1.0).at([0.0, 0.0, -4.0]).emissive([1.0, 1.0, 1.0]));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_fov(0.5).unwrap();
+        let size = 32;
+        let mut renderer = Renderer::new(0, size, 0, size, size, size, 64, 2, true).unwrap();
+        renderer.set_seed(Some(3));
+
+        // The background is opaque by default
+        let image = renderer.render_rgba(&scene, &camera);
+        assert!(image.data.chunks(4).all(|pixel| pixel[3] == 1.0));
+        let corner = image.get_pixel(0, 0).unwrap();
+        assert!((corner[2] - 0.6).abs() < 1e-12);
+
+        renderer.set_transparent_background(true);
+        let image = renderer.render_rgba(&scene, &camera);
+        let center = image.get_pixel(size / 2, size / 2).unwrap();
+        assert_eq!(center, [1.0, 1.0, 1.0, 1.0]);
+        for (x, y) in [(0, 0), (size - 1, 0), (0, size - 1), (size - 1, size - 1)].iter() {
+            assert_eq!(image.get_pixel(*x, *y), Some([0.0; 4]));
+        }
+        // Partly covered pixels along the silhouette, premultiplied
+        let ring: Vec<[f64; 4]> = image.data.chunks(4).map(|p| [p[0], p[1], p[2], p[3]]).filter(|p| p[3] > 0.0 && p[3] < 1.0).collect();
+        assert!(ring.len() > 20);
+        assert!(ring.iter().all(|p| p[0] == p[3]));
+        // The same colors as render, without the background
+        assert_eq!(renderer.render(&scene, &camera).data, image.to_rgb().data);
+    }
+}
@@ -0,0 +1,189 @@
+use std::mem::{size_of, size_of_val};
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+use serde_bytes::ByteBuf;
+
+use crate::float::Float;
+use crate::renderer::Image;
+
+// Images are serialized as their dimensions followed by the pixel data
+// packed as little endian bytes, which keeps binary formats compact
+// instead of storing a long list of floats.
+
+#[derive(Serialize)]
+struct RawImageRef<'a> {
+    width: usize,
+    height: usize,
+    #[serde(with = "serde_bytes")]
+    data: &'a [u8]
+}
+
+#[derive(Deserialize)]
+struct RawImage {
+    width: usize,
+    height: usize,
+    data: ByteBuf
+}
+
+fn encode<T>(data: &[T]) -> Vec<u8>
+    where T: Float
+{
+    let mut bytes = Vec::with_capacity(size_of_val(data));
+    for value in data.iter() {
+        if size_of::<T>() == size_of::<f32>() {
+            bytes.extend_from_slice(&value.to_f32().unwrap().to_le_bytes());
+        } else {
+            bytes.extend_from_slice(&value.to_f64().unwrap().to_le_bytes());
+        }
+    }
+    bytes
+}
+
+fn decode<T>(bytes: &[u8]) -> Vec<T>
+    where T: Float
+{
+    let mut data = Vec::with_capacity(bytes.len() / size_of::<T>());
+    for chunk in bytes.chunks_exact(size_of::<T>()) {
+        let value = if size_of::<T>() == size_of::<f32>() {
+            let mut buffer = [0u8; 4];
+            buffer.copy_from_slice(chunk);
+            T::from(f32::from_le_bytes(buffer)).unwrap()
+        } else {
+            let mut buffer = [0u8; 8];
+            buffer.copy_from_slice(chunk);
+            T::from(f64::from_le_bytes(buffer)).unwrap()
+        };
+        data.push(value);
+    }
+    data
+}
+
+impl<T> Serialize for Image<T>
+    where T: Float
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let data = encode(&self.data);
+        let raw = RawImageRef {
+            width: self.width,
+            height: self.height,
+            data: &data
+        };
+        raw.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Image<T>
+    where T: Float
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        let raw = RawImage::deserialize(deserializer)?;
+        let expected = raw.width.checked_mul(raw.height)
+            .and_then(|n| n.checked_mul(3 * size_of::<T>()));
+
+        if expected != Some(raw.data.len()) {
+            let expected = match expected {
+                Some(n) => n.to_string(),
+                None => String::from("overflow")
+            };
+            return Err(D::Error::custom(format!(
+                "image data has {} bytes, expected {} for a {}x{} image",
+                raw.data.len(), expected, raw.width, raw.height
+            )));
+        }
+
+        Ok(Image {
+            width: raw.width,
+            height: raw.height,
+            data: decode(&raw.data)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vec3;
+    use crate::constants::Axis;
+
+    fn test_image<T>() -> Image<T>
+        where T: Float
+    {
+        let mut image = Image::<T>::new(3, 2);
+        for i in 0..image.data.len() {
+            image.data[i] = T::from(i).unwrap() / T::from(7.0).unwrap();
+        }
+        image
+    }
+
+    #[test]
+    fn vector() {
+        let v = Vec3::<f64>::from_array([1.5, -2.0, 0.1]);
+        let text = ron::to_string(&v).unwrap();
+        assert_eq!(text, "(1.5,-2.0,0.1)");
+        let w: Vec3<f64> = ron::from_str(&text).unwrap();
+        assert_eq!(w.get_data(), v.get_data());
+
+        let bytes = bincode::serialize(&v).unwrap();
+        let w: Vec3<f64> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(w.get_data(), v.get_data());
+    }
+
+    #[test]
+    fn axis() {
+        for axis in [Axis::X, Axis::Y, Axis::Z].iter() {
+            let text = ron::to_string(axis).unwrap();
+            let other: Axis = ron::from_str(&text).unwrap();
+            assert_eq!(other as usize, *axis as usize);
+
+            let bytes = bincode::serialize(axis).unwrap();
+            let other: Axis = bincode::deserialize(&bytes).unwrap();
+            assert_eq!(other as usize, *axis as usize);
+        }
+    }
+
+    #[test]
+    fn image() {
+        let image = test_image::<f64>();
+        let text = ron::to_string(&image).unwrap();
+        let other: Image<f64> = ron::from_str(&text).unwrap();
+        assert_eq!(other.width, image.width);
+        assert_eq!(other.height, image.height);
+        assert_eq!(other.data, image.data);
+
+        let bytes = bincode::serialize(&image).unwrap();
+        // Dimensions, length prefix, and the raw data
+        assert_eq!(bytes.len(), 3 * 8 + 8 * image.data.len());
+        let other: Image<f64> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(other.data, image.data);
+
+        let image = test_image::<f32>();
+        let bytes = bincode::serialize(&image).unwrap();
+        let other: Image<f32> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(other.data, image.data);
+    }
+
+    #[test]
+    fn image_mismatch() {
+        let mut image = test_image::<f64>();
+        image.width = 4;
+        let text = ron::to_string(&image).unwrap();
+        assert!(ron::from_str::<Image<f64>>(&text).is_err());
+
+        let bytes = bincode::serialize(&test_image::<f32>()).unwrap();
+        assert!(bincode::deserialize::<Image<f64>>(&bytes).is_err());
+
+        let mut image = test_image::<f64>();
+        image.width = usize::MAX;
+        image.height = usize::MAX;
+        let text = ron::to_string(&image).unwrap();
+        match ron::from_str::<Image<f64>>(&text) {
+            Ok(_) => panic!("oversized image should not deserialize"),
+            Err(error) => assert!(error.to_string().contains("overflow"))
+        }
+    }
+}
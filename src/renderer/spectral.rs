@@ -0,0 +1,82 @@
+use rand::RngCore;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::sampling::uniform;
+
+// The visible range the wavelengths are drawn from, in nm
+pub const MIN_WAVELENGTH: f64 = 380.0;
+pub const MAX_WAVELENGTH: f64 = 780.0;
+
+// Mean of the linear sRGB of the matching functions over the visible range
+const MEAN_RGB: [f64; 3] = [0.320906714, 0.253871590, 0.242623889];
+
+fn gaussian(wavelength: f64, mean: f64, below: f64, above: f64) -> f64 {
+    let sigma = if wavelength < mean { below } else { above };
+    let x = (wavelength - mean) / sigma;
+    (-0.5 * x * x).exp()
+}
+
+// The CIE 1931 color matching functions, in the multi-lobe Gaussian fit of
+// Wyman, Sloan and Shirley
+pub fn cie_xyz(wavelength: f64) -> [f64; 3] {
+    let l = wavelength;
+    let x = 1.056 * gaussian(l, 599.8, 37.9, 31.0) + 0.362 * gaussian(l, 442.0, 16.0, 26.7) - 0.065 * gaussian(l, 501.1, 20.4, 26.2);
+    let y = 0.821 * gaussian(l, 568.8, 46.9, 40.5) + 0.286 * gaussian(l, 530.9, 16.3, 31.1);
+    let z = 1.217 * gaussian(l, 437.0, 11.8, 36.0) + 0.681 * gaussian(l, 459.0, 26.0, 13.8);
+    [x, y, z]
+}
+
+// The linear sRGB of light of a single wavelength, scaled so that white
+// light (the same at every wavelength) averages to 1 in every channel.
+// Channels outside of the sRGB gamut are negative.
+pub fn wavelength_to_rgb(wavelength: f64) -> [f64; 3] {
+    let [x, y, z] = cie_xyz(wavelength);
+    let rgb = [
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z
+    ];
+    [rgb[0] / MEAN_RGB[0], rgb[1] / MEAN_RGB[1], rgb[2] / MEAN_RGB[2]]
+}
+
+// A wavelength drawn uniformly from the visible range and the weight of the
+// radiance of a path carrying it. Without dispersion the weights average to
+// 1, the RGB image is the same (but noisier).
+pub fn sample_wavelength<T>(rng: &mut dyn RngCore) -> (T, Vec3<T>)
+    where T: Float
+{
+    let u = uniform::<f64, dyn RngCore>(rng);
+    let wavelength = MIN_WAVELENGTH + u * (MAX_WAVELENGTH - MIN_WAVELENGTH);
+    let rgb = wavelength_to_rgb(wavelength);
+    (T::from_f64(wavelength), Vec3::from_array([T::from_f64(rgb[0]), T::from_f64(rgb[1]), T::from_f64(rgb[2])]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wavelength_to_rgb() {
+        let n = 4000;
+        let mut mean = [0.0; 3];
+        for i in 0..n {
+            let wavelength = MIN_WAVELENGTH + (MAX_WAVELENGTH - MIN_WAVELENGTH) * (i as f64 + 0.5) / n as f64;
+            let rgb = super::wavelength_to_rgb(wavelength);
+            for k in 0..3 {
+                mean[k] += rgb[k] / n as f64;
+            }
+        }
+        for k in 0..3 {
+            assert!((mean[k] - 1.0).abs() < 1e-6);
+        }
+
+        let dominant = |wavelength: f64| {
+            let rgb = super::wavelength_to_rgb(wavelength);
+            (0..3).fold(0, |best, k| if rgb[k] > rgb[best] { k } else { best })
+        };
+        assert_eq!(dominant(450.0), 2);
+        assert_eq!(dominant(530.0), 1);
+        assert_eq!(dominant(610.0), 0);
+    }
+}
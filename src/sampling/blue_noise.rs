@@ -0,0 +1,170 @@
+use std::sync::OnceLock;
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+
+// Side of the tile of the mask, repeated over the image
+pub const SIZE: usize = 64;
+
+// Width of the gaussian that measures how crowded a texel is
+const SIGMA: f64 = 1.5;
+
+static MASK: OnceLock<Vec<f64>> = OnceLock::new();
+
+// Threshold in [0, 1) of each texel, row by row: any fraction of the texels
+// with the lowest thresholds is spread as evenly as possible
+pub fn mask() -> &'static [f64] {
+    MASK.get_or_init(|| {
+        let ranks = void_and_cluster(SIZE, SIGMA);
+        let n = (SIZE * SIZE) as f64;
+        ranks.iter().map(|rank| (*rank as f64 + 0.5) / n).collect()
+    })
+}
+
+// How crowded each texel is by the texels that are set, updated as they
+// are set and cleared
+struct Energy {
+    size: usize,
+    // Gaussian of each offset, wrapping around the tile
+    kernel: Vec<f64>,
+    values: Vec<f64>
+}
+
+impl Energy {
+    fn new(size: usize, sigma: f64) -> Self {
+        let mut kernel = vec![0.0; size * size];
+        for y in 0..size {
+            for x in 0..size {
+                let dx = usize::min(x, size - x) as f64;
+                let dy = usize::min(y, size - y) as f64;
+                kernel[y * size + x] = (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp();
+            }
+        }
+        Energy {
+            size,
+            kernel,
+            values: vec![0.0; size * size]
+        }
+    }
+
+    fn update(&mut self, index: usize, sign: f64) {
+        let size = self.size;
+        let (x0, y0) = (index % size, index / size);
+        for y in 0..size {
+            let dy = (y + size - y0) % size;
+            for x in 0..size {
+                let dx = (x + size - x0) % size;
+                self.values[y * size + x] += sign * self.kernel[dy * size + dx];
+            }
+        }
+    }
+
+    // The most crowded texel that is set, or the emptiest that isn't
+    fn extreme(&self, set: &[bool], wanted: bool) -> usize {
+        let mut best = 0;
+        let mut best_value = if wanted { f64::MIN } else { f64::MAX };
+        for (index, value) in self.values.iter().enumerate() {
+            if set[index] != wanted {
+                continue;
+            }
+            if (wanted && *value > best_value) || (!wanted && *value < best_value) {
+                best = index;
+                best_value = *value;
+            }
+        }
+        best
+    }
+}
+
+// Rank of each texel by Ulichney's void and cluster method
+fn void_and_cluster(size: usize, sigma: f64) -> Vec<usize> {
+    let n = size * size;
+    let mut rng = SmallRng::seed_from_u64(0x5eed);
+    let mut energy = Energy::new(size, sigma);
+
+    // Random starting pattern, made even by moving the texel of the
+    // tightest cluster into the largest void until it stays
+    let mut set = vec![false; n];
+    let ones = n / 10;
+    let mut count = 0;
+    while count < ones {
+        let index = rng.gen_range(0, n);
+        if !set[index] {
+            set[index] = true;
+            energy.update(index, 1.0);
+            count += 1;
+        }
+    }
+    loop {
+        let cluster = energy.extreme(&set, true);
+        set[cluster] = false;
+        energy.update(cluster, -1.0);
+        let void = energy.extreme(&set, false);
+        set[void] = true;
+        energy.update(void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    // Below the starting pattern the texels are taken out cluster by cluster,
+    // above it filled in void by void
+    let mut ranks = vec![0; n];
+    let prototype = set.clone();
+    let prototype_energy = energy.values.clone();
+    for rank in (0..ones).rev() {
+        let cluster = energy.extreme(&set, true);
+        set[cluster] = false;
+        energy.update(cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+    set = prototype;
+    energy.values = prototype_energy;
+    for rank in ones..n {
+        let void = energy.extreme(&set, false);
+        set[void] = true;
+        energy.update(void, 1.0);
+        ranks[void] = rank;
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Mean square of the values averaged over blocks of 4 x 4 texels, little
+    // for blue noise as neighbouring texels make up for each other
+    fn low_frequencies(values: &[f64]) -> f64 {
+        let blocks = SIZE / 4;
+        let mut sum = 0.0;
+        for by in 0..blocks {
+            for bx in 0..blocks {
+                let mut block = 0.0;
+                for y in 0..4 {
+                    for x in 0..4 {
+                        block += values[(4 * by + y) * SIZE + 4 * bx + x] - 0.5;
+                    }
+                }
+                sum += (block / 16.0) * (block / 16.0);
+            }
+        }
+        sum / (blocks * blocks) as f64
+    }
+
+    #[test]
+    fn mask() {
+        let mask = super::mask();
+        assert_eq!(mask.len(), SIZE * SIZE);
+        // Each threshold once
+        let mut sorted = mask.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (rank, value) in sorted.iter().enumerate() {
+            assert_eq!(*value, (rank as f64 + 0.5) / (SIZE * SIZE) as f64);
+        }
+
+        let mut rng = SmallRng::seed_from_u64(1);
+        let white: Vec<f64> = (0..SIZE * SIZE).map(|_| rng.gen()).collect();
+        assert!(low_frequencies(mask) < 0.25 * low_frequencies(&white));
+    }
+}
@@ -0,0 +1,456 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use rand::rngs::SmallRng;
+
+use crate::float::Float;
+use crate::vector::{Vec3, Onb};
+
+mod blue_noise;
+
+pub fn uniform<T, R>(rng: &mut R) -> T
+    where T: Float, R: Rng + ?Sized
+{
+    T::from_f64(rng.gen::<f64>())
+}
+
+// Uniformly distributed point inside the unit sphere
+pub fn unit_sphere<T, R>(rng: &mut R) -> Vec3<T>
+    where T: Float, R: Rng + ?Sized
+{
+    let two = T::from_f64(2.0);
+
+    loop {
+        let x = uniform::<T, R>(rng) * two - T::one();
+        let y = uniform::<T, R>(rng) * two - T::one();
+        let z = uniform::<T, R>(rng) * two - T::one();
+
+        if x * x + y * y + z * z < T::one() {
+            return Vec3::from_array([x, y, z]);
+        }
+    }
+}
+
+// Uniformly distributed point on the surface of the unit sphere
+pub fn unit_sphere_surface<T, R>(rng: &mut R) -> Vec3<T>
+    where T: Float, R: Rng + ?Sized
+{
+    let two = T::from_f64(2.0);
+    let two_pi = T::from_f64(2.0 * std::f64::consts::PI);
+    let z = uniform::<T, R>(rng) * two - T::one();
+    let phi = two_pi * uniform::<T, R>(rng);
+    let r = (T::one() - z * z).sqrt();
+
+    Vec3::from_array([phi.cos() * r, phi.sin() * r, z])
+}
+
+// Uniformly distributed direction in the hemisphere around the normal
+pub fn unit_hemisphere<T, R>(rng: &mut R, normal: &Vec3<T>) -> Vec3<T>
+    where T: Float, R: Rng + ?Sized
+{
+    let tiny = T::from_f64(1e-12);
+
+    loop {
+        let mut direction = unit_sphere::<T, R>(rng);
+        if direction.is_near_zero(tiny) {
+            continue;
+        }
+        direction.normalize();
+        if direction.dot(normal) < T::zero() {
+            direction = direction * (-T::one());
+        }
+        return direction;
+    }
+}
+
+// Direction in the hemisphere around the normal, distributed
+// proportionally to the cosine of the angle with the normal
+pub fn cosine_hemisphere<T, R>(rng: &mut R, normal: &Vec3<T>) -> Vec3<T>
+    where T: Float, R: Rng + ?Sized
+{
+    let two_pi = T::from_f64(2.0 * std::f64::consts::PI);
+    let r1 = uniform::<T, R>(rng);
+    let r2 = uniform::<T, R>(rng);
+    let phi = two_pi * r1;
+    let r = r2.sqrt();
+
+    let local = Vec3::from_array([phi.cos() * r, phi.sin() * r, (T::one() - r2).sqrt()]);
+    Onb::from_w(normal).local_to_world(&local)
+}
+
+// Uniformly distributed point inside the unit disk on the XY plane
+pub fn unit_disk<T, R>(rng: &mut R) -> Vec3<T>
+    where T: Float, R: Rng + ?Sized
+{
+    let two = T::from_f64(2.0);
+
+    loop {
+        let x = uniform::<T, R>(rng) * two - T::one();
+        let y = uniform::<T, R>(rng) * two - T::one();
+
+        if x * x + y * y < T::one() {
+            return Vec3::from_array([x, y, T::zero()]);
+        }
+    }
+}
+
+// Uniformly distributed barycentric coordinates (u, v) on a triangle,
+// the third coordinate being 1 - u - v
+pub fn triangle<T, R>(rng: &mut R) -> (T, T)
+    where T: Float, R: Rng + ?Sized
+{
+    let r1 = uniform::<T, R>(rng).sqrt();
+    let r2 = uniform::<T, R>(rng);
+    (T::one() - r1, r2 * r1)
+}
+
+// Point inside the unit disk on the XY plane for a point in the unit square,
+// keeping the distances between points like the square, see Shirley & Chiu,
+// "A Low Distortion Map Between Disk and Square"
+pub fn concentric_disk<T>(u: T, v: T) -> Vec3<T>
+    where T: Float
+{
+    let two = T::from_f64(2.0);
+    let quarter_pi = T::from_f64(0.25 * std::f64::consts::PI);
+    let x = u * two - T::one();
+    let y = v * two - T::one();
+    if x == T::zero() && y == T::zero() {
+        return Vec3::new();
+    }
+    let (r, phi) = if x.abs() > y.abs() {
+        (x, quarter_pi * (y / x))
+    } else {
+        (y, quarter_pi * (two - x / y))
+    };
+    Vec3::from_array([r * phi.cos(), r * phi.sin(), T::zero()])
+}
+
+// The digits of the index in the base mirrored behind the point, i.e.
+// 1, 2, 3, 4 in base 2 give 0.5, 0.25, 0.75, 0.125
+pub fn radical_inverse(base: u64, index: u64) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let mut index = index;
+    let mut factor = inverse_base;
+    let mut result = 0.0;
+    while index > 0 {
+        result += (index % base) as f64 * factor;
+        index /= base;
+        factor *= inverse_base;
+    }
+    result
+}
+
+// How the samples of a pixel are spread over the pixel and the lens
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Sampler {
+    // Each sample on its own
+    Independent,
+    // One sample in each cell of a grid, the cells are shuffled between
+    // the pixel and the lens
+    Stratified,
+    // The Halton sequence, shifted by a random amount in each pixel
+    Halton,
+    // From a blue noise mask, so neighbouring pixels have different offsets
+    // and their errors at edges and out of focus make up for each other with
+    // few samples. The samples of a pixel step through the thresholds.
+    BlueNoise
+}
+
+impl Sampler {
+    // The position in the pixel and on the lens of n samples of a pixel,
+    // all four numbers in [0, 1). The shift is random, but the same for all
+    // the pixels of an image.
+    pub fn pixel_samples<R>(&self, pixel: (usize, usize), shift: &[f64; 4], n: usize, rng: &mut R) -> Vec<[f64; 4]>
+        where R: Rng + ?Sized
+    {
+        match self {
+            Sampler::Independent => (0..n).map(|_| [rng.gen(), rng.gen(), rng.gen(), rng.gen()]).collect(),
+            Sampler::Stratified => {
+                let pixel = Sampler::stratified(n, rng);
+                let lens = Sampler::stratified(n, rng);
+                pixel.iter().zip(lens.iter()).map(|(p, l)| [p.0, p.1, l.0, l.1]).collect()
+            },
+            Sampler::Halton => {
+                // Cranley-Patterson rotation: without it all pixels would
+                // repeat the same pattern
+                let shift: [f64; 4] = [rng.gen(), rng.gen(), rng.gen(), rng.gen()];
+                let bases = [2, 3, 5, 7];
+                (0..n).map(|k| {
+                    let mut sample = [0.0; 4];
+                    for (d, value) in sample.iter_mut().enumerate() {
+                        *value = (radical_inverse(bases[d], k as u64) + shift[d]).fract();
+                    }
+                    sample
+                }).collect()
+            },
+            Sampler::BlueNoise => {
+                let mask = blue_noise::mask();
+                let size = blue_noise::SIZE;
+                // Other parts of the mask for the other numbers
+                let offsets = [(0, 0), (size / 2, size / 4), (size / 4, size / 2), (3 * size / 4, 3 * size / 4)];
+                let golden_ratio = 0.5 * (5.0_f64.sqrt() - 1.0);
+                (0..n).map(|k| {
+                    let mut sample = [0.0; 4];
+                    for (d, value) in sample.iter_mut().enumerate() {
+                        let x = (pixel.0 + offsets[d].0) % size;
+                        let y = (pixel.1 + offsets[d].1) % size;
+                        *value = (mask[y * size + x] + shift[d] + golden_ratio * k as f64).fract();
+                    }
+                    sample
+                }).collect()
+            }
+        }
+    }
+
+    // Points jittered in n random cells of the smallest square grid with at
+    // least n cells, so each cell is as likely as the others
+    fn stratified<R>(n: usize, rng: &mut R) -> Vec<(f64, f64)>
+        where R: Rng + ?Sized
+    {
+        let cells = (n as f64).sqrt().ceil() as usize;
+        let mut indices: Vec<usize> = (0..cells * cells).collect();
+        indices.shuffle(rng);
+        indices.iter().take(n).map(|index| {
+            let x = (index % cells) as f64 + rng.gen::<f64>();
+            let y = (index / cells) as f64 + rng.gen::<f64>();
+            (x / cells as f64, y / cells as f64)
+        }).collect()
+    }
+}
+
+// Seeds of the per thread generators, counted up instead of drawn from the
+// OS, which isn't available everywhere (i.e. wasm32-unknown-unknown)
+static THREAD_SEED: AtomicU64 = AtomicU64::new(0x2545_f491_4f6c_dd1d);
+
+thread_local! {
+    static THREAD_RNG: RefCell<SmallRng> = RefCell::new(SmallRng::seed_from_u64(THREAD_SEED.fetch_add(1, Ordering::Relaxed)));
+}
+
+// For the places without a generator passed in, i.e. Hitable::hit
+pub(crate) fn with_thread_rng<F, R>(f: F) -> R
+    where F: FnOnce(&mut SmallRng) -> R
+{
+    THREAD_RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+// The scene restarts it from the generator of each path it traces, so the
+// media hit along the path are as reproducible as the rest of it
+pub(crate) fn seed_thread_rng(seed: u64) {
+    THREAD_RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(seed));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    const N: usize = 100000;
+    const BINS: usize = 10;
+    // 99.9th percentile of the chi-square distribution with 9 degrees of freedom
+    const CHI_SQUARE_CRITICAL: f64 = 27.88;
+
+    fn rng() -> StdRng {
+        StdRng::seed_from_u64(12345)
+    }
+
+    // Chi-square statistic of values expected to be uniform in [0, 1)
+    fn chi_square(values: &[f64]) -> f64 {
+        let mut counts = [0usize; BINS];
+        for value in values.iter() {
+            let bin = usize::min((value * BINS as f64) as usize, BINS - 1);
+            counts[bin] += 1;
+        }
+        let expected = values.len() as f64 / BINS as f64;
+        let mut chi = 0.0;
+        for count in counts.iter() {
+            let delta = *count as f64 - expected;
+            chi += delta * delta / expected;
+        }
+        chi
+    }
+
+    fn mean(points: &[Vec3<f64>]) -> Vec3<f64> {
+        let mut sum = Vec3::new();
+        for point in points.iter() {
+            sum = sum + point;
+        }
+        sum / points.len() as f64
+    }
+
+    #[test]
+    fn unit_sphere() {
+        let mut rng = rng();
+        let points: Vec<Vec3<f64>> = (0..N).map(|_| super::unit_sphere(&mut rng)).collect();
+        for point in points.iter() {
+            assert!(point.norm() < 1.0);
+        }
+        assert!(mean(&points).norm() < 0.01);
+
+        // The cube of the radius of a uniform point in a ball is uniform
+        let radii: Vec<f64> = points.iter().map(|p| p.norm().powi(3)).collect();
+        assert!(chi_square(&radii) < CHI_SQUARE_CRITICAL);
+        // Each coordinate is (1 - x^2) distributed, so z alone isn't uniform,
+        // but the azimuth is
+        let angles: Vec<f64> = points.iter().map(|p| {
+            let d = p.get_data();
+            (d[1].atan2(d[0]) + std::f64::consts::PI) / (2.0 * std::f64::consts::PI)
+        }).collect();
+        assert!(chi_square(&angles) < CHI_SQUARE_CRITICAL);
+    }
+
+    #[test]
+    fn unit_sphere_surface() {
+        let mut rng = rng();
+        let points: Vec<Vec3<f64>> = (0..N).map(|_| super::unit_sphere_surface(&mut rng)).collect();
+        for point in points.iter() {
+            assert!((point.norm() - 1.0).abs() < 1e-12);
+        }
+        assert!(mean(&points).norm() < 0.01);
+
+        // Archimedes: each coordinate of a uniform point on the sphere is uniform
+        let heights: Vec<f64> = points.iter().map(|p| (p.get_data()[0] + 1.0) * 0.5).collect();
+        assert!(chi_square(&heights) < CHI_SQUARE_CRITICAL);
+        let heights: Vec<f64> = points.iter().map(|p| (p.get_data()[2] + 1.0) * 0.5).collect();
+        assert!(chi_square(&heights) < CHI_SQUARE_CRITICAL);
+    }
+
+    #[test]
+    fn unit_hemisphere() {
+        let mut rng = rng();
+        let mut normal = Vec3::from_array([1.0, -2.0, 0.5]);
+        normal.normalize();
+        let points: Vec<Vec3<f64>> = (0..N).map(|_| super::unit_hemisphere(&mut rng, &normal)).collect();
+        for point in points.iter() {
+            assert!((point.norm() - 1.0).abs() < 1e-12);
+            assert!(point.dot(&normal) >= 0.0);
+        }
+        // The mean direction is half the normal
+        assert!((mean(&points) - &normal * 0.5).norm() < 0.01);
+
+        // The cosine with the normal is uniform
+        let cosines: Vec<f64> = points.iter().map(|p| p.dot(&normal)).collect();
+        assert!(chi_square(&cosines) < CHI_SQUARE_CRITICAL);
+    }
+
+    #[test]
+    fn cosine_hemisphere() {
+        let mut rng = rng();
+        let mut normal = Vec3::from_array([0.0, 0.0, -1.0]);
+        normal.normalize();
+        let points: Vec<Vec3<f64>> = (0..N).map(|_| super::cosine_hemisphere(&mut rng, &normal)).collect();
+        for point in points.iter() {
+            assert!((point.norm() - 1.0).abs() < 1e-12);
+            assert!(point.dot(&normal) >= 0.0);
+        }
+        // The mean direction is 2/3 of the normal
+        assert!((mean(&points) - &normal * (2.0 / 3.0)).norm() < 0.01);
+
+        // The squared cosine with the normal is uniform
+        let cosines: Vec<f64> = points.iter().map(|p| p.dot(&normal).powi(2)).collect();
+        assert!(chi_square(&cosines) < CHI_SQUARE_CRITICAL);
+    }
+
+    #[test]
+    fn unit_disk() {
+        let mut rng = rng();
+        let points: Vec<Vec3<f64>> = (0..N).map(|_| super::unit_disk(&mut rng)).collect();
+        for point in points.iter() {
+            assert!(point.norm() < 1.0);
+            assert_eq!(point.get_data()[2], 0.0);
+        }
+        assert!(mean(&points).norm() < 0.01);
+
+        // The squared radius is uniform
+        let radii: Vec<f64> = points.iter().map(|p| p.dot(p)).collect();
+        assert!(chi_square(&radii) < CHI_SQUARE_CRITICAL);
+        let angles: Vec<f64> = points.iter().map(|p| {
+            let d = p.get_data();
+            (d[1].atan2(d[0]) + std::f64::consts::PI) / (2.0 * std::f64::consts::PI)
+        }).collect();
+        assert!(chi_square(&angles) < CHI_SQUARE_CRITICAL);
+    }
+
+    #[test]
+    fn triangle() {
+        let mut rng = rng();
+        let points: Vec<(f64, f64)> = (0..N).map(|_| super::triangle(&mut rng)).collect();
+        let mut mean_u = 0.0;
+        let mut mean_v = 0.0;
+        for (u, v) in points.iter() {
+            assert!(*u >= 0.0 && *v >= 0.0 && u + v <= 1.0);
+            mean_u += u;
+            mean_v += v;
+        }
+        // The centroid of the triangle
+        assert!((mean_u / N as f64 - 1.0 / 3.0).abs() < 0.01);
+        assert!((mean_v / N as f64 - 1.0 / 3.0).abs() < 0.01);
+
+        // The area below each barycentric isoline grows quadratically
+        let w: Vec<f64> = points.iter().map(|(u, v)| 1.0 - (u + v) * (u + v)).collect();
+        assert!(chi_square(&w) < CHI_SQUARE_CRITICAL);
+    }
+
+    #[test]
+    fn concentric_disk() {
+        let mut rng = rng();
+        let points: Vec<Vec3<f64>> = (0..N).map(|_| super::concentric_disk(rng.gen::<f64>(), rng.gen::<f64>())).collect();
+        for point in points.iter() {
+            assert!(point.norm() <= 1.0);
+        }
+        let radii: Vec<f64> = points.iter().map(|p| p.dot(p)).collect();
+        assert!(chi_square(&radii) < CHI_SQUARE_CRITICAL);
+        // The corners of the square go to the rim
+        assert!((super::concentric_disk(1.0_f64, 1.0).norm() - 1.0).abs() < 1e-12);
+        assert_eq!(super::concentric_disk(0.5_f64, 0.5).get_data(), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn radical_inverse() {
+        let values: Vec<f64> = (0..5).map(|i| super::radical_inverse(2, i)).collect();
+        assert_eq!(values, [0.0, 0.5, 0.25, 0.75, 0.125]);
+        assert!((super::radical_inverse(3, 5) - 7.0 / 9.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sampler() {
+        let mut rng = rng();
+        for sampler in [Sampler::Independent, Sampler::Stratified, Sampler::Halton, Sampler::BlueNoise].iter() {
+            let samples = sampler.pixel_samples((0, 0), &[0.0; 4], 10, &mut rng);
+            assert_eq!(samples.len(), 10);
+            assert!(samples.iter().all(|sample| sample.iter().all(|x| (0.0..1.0).contains(x))));
+        }
+
+        // A single sample in each quarter of the pixel and the lens
+        let samples = Sampler::Stratified.pixel_samples((0, 0), &[0.0; 4], 4, &mut rng);
+        for d in [0, 2].iter() {
+            let mut quarters: Vec<usize> = samples.iter().map(|s| (2.0 * s[*d]) as usize + 2 * (2.0 * s[d + 1]) as usize).collect();
+            quarters.sort_unstable();
+            assert_eq!(quarters, [0, 1, 2, 3]);
+        }
+
+        // Halton samples of each pixel cover the pixel evenly, but the pixels differ
+        let a = Sampler::Halton.pixel_samples((0, 0), &[0.0; 4], 64, &mut rng);
+        let b = Sampler::Halton.pixel_samples((0, 0), &[0.0; 4], 64, &mut rng);
+        assert_ne!(a[0], b[0]);
+        for d in 0..4 {
+            let values: Vec<f64> = a.iter().map(|s| s[d]).collect();
+            let mut counts = [0; 8];
+            for value in values.iter() {
+                counts[usize::min((value * 8.0) as usize, 7)] += 1;
+            }
+            assert!(counts.iter().all(|count| (6..=10).contains(count)));
+        }
+
+        // Over a tile of the mask each blue noise sample takes every threshold once
+        let shift = [0.3, 0.6, 0.1, 0.9];
+        let values: Vec<f64> = (0..64 * 64).map(|p| Sampler::BlueNoise.pixel_samples((p % 64, p / 64), &shift, 2, &mut rng)[1][2]).collect();
+        let mut counts = [0; 8];
+        for value in values.iter() {
+            counts[usize::min((value * 8.0) as usize, 7)] += 1;
+        }
+        assert!(counts.iter().all(|count| (511..=513).contains(count)));
+    }
+}
@@ -0,0 +1,454 @@
+use rand::RngCore;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::actor::{Actor, ActorFlags};
+use crate::interval::Interval;
+use crate::sampling::{self, cosine_hemisphere};
+use crate::scene::{Scene, SceneStats};
+
+// Longest light subpath, including the point on the light
+const MAX_LIGHT_VERTICES: usize = 3;
+
+enum VertexKind<'a, T>
+    where T: Float
+{
+    Camera,
+    Light,
+    Surface(&'a Actor<T>, Hit<T>)
+}
+
+struct Vertex<'a, T>
+    where T: Float
+{
+    kind: VertexKind<'a, T>,
+    point: Vec3<T>,
+    // Unit geometric normal on the side the subpath arrived from, the
+    // emitting side for the light, none for the camera
+    normal: Option<Vec3<T>>,
+    // Light carried by the subpath up to the vertex, without its brdf
+    beta: Vec3<T>,
+    // Radiance of an emitter the camera subpath ended at, and if it's one
+    // of the lights the light subpaths start from
+    emission: Option<(Vec3<T>, bool)>,
+    // Mirrors and glass, which can't be connected to
+    delta: bool,
+    // Density of the vertex per area when sampled from the previous vertex
+    // of its own subpath, and when sampled from the other direction
+    pdf_fwd: T,
+    pdf_rev: T
+}
+
+impl<'a, T> Vertex<'a, T>
+    where T: Float
+{
+    fn connectible(&self) -> bool {
+        !self.delta && self.emission.is_none()
+    }
+
+    // Cosine of the angle between the normal and the direction, 1 without normal
+    fn cos(&self, direction: &Vec3<T>) -> T {
+        match &self.normal {
+            Some(normal) => normal.dot(direction).abs(),
+            None => T::one()
+        }
+    }
+
+    // Density per solid angle of sampling the direction towards the point,
+    // as a light if emitting, otherwise as a surface
+    fn pdf_dir(&self, point: &Vec3<T>, emitting: bool) -> T {
+        if self.delta {
+            return T::zero();
+        }
+        let direction = direction(&self.point, point);
+        let pi = T::from_f64(std::f64::consts::PI);
+        match (&self.kind, emitting) {
+            (VertexKind::Light, _) | (_, true) => self.cos(&direction) / (pi + pi),
+            (VertexKind::Surface(..), false) => self.cos(&direction) / pi,
+            (VertexKind::Camera, false) => T::zero()
+        }
+    }
+
+    // Density per area of sampling the other vertex from this one
+    fn pdf_area(&self, other: &Vertex<'_, T>, emitting: bool) -> T {
+        convert_density(self.pdf_dir(&other.point, emitting), &self.point, &other.point, other.normal.as_ref())
+    }
+
+    fn brdf(&self, to_light: &Vec3<T>, to_camera: &Vec3<T>) -> Vec3<T> {
+        match (&self.kind, &self.normal) {
+            (VertexKind::Surface(actor, hit), Some(normal)) if !self.delta => surface_brdf(actor, hit, normal, to_light, to_camera),
+            _ => Vec3::new()
+        }
+    }
+}
+
+// Share of the light arriving from the direction to_light that leaves
+// towards to_camera. Only reflections, both need to be on the same side.
+fn surface_brdf<T>(actor: &Actor<T>, hit: &Hit<T>, normal: &Vec3<T>, to_light: &Vec3<T>, to_camera: &Vec3<T>) -> Vec3<T>
+    where T: Float
+{
+    let cos_light = normal.dot(to_light);
+    let cos_camera = normal.dot(to_camera);
+    if cos_light * cos_camera <= T::zero() {
+        return Vec3::new();
+    }
+    let flip = cos_camera < T::zero();
+    let side = Hit {
+        point: Vec3::from_slice(hit.point.get_data()),
+        normal: if flip { normal * (-T::one()) } else { Vec3::from_slice(normal.get_data()) },
+        t: hit.t,
+        u: hit.u,
+        v: hit.v,
+        front_face: hit.front_face != flip,
+        barycentric: hit.barycentric,
+        face: hit.face,
+        footprint: hit.footprint
+    };
+    actor.get_material().brdf(&side, &(to_light * (-T::one())))
+}
+
+fn direction<T>(from: &Vec3<T>, to: &Vec3<T>) -> Vec3<T>
+    where T: Float
+{
+    let mut direction = to - from;
+    direction.normalize();
+    direction
+}
+
+// Density per solid angle seen from one point to density per area at the
+// other, a surface with the normal if there's one
+fn convert_density<T>(pdf: T, from: &Vec3<T>, to: &Vec3<T>, normal: Option<&Vec3<T>>) -> T
+    where T: Float
+{
+    let offset = to - from;
+    let distance2 = offset.dot(&offset);
+    if distance2 <= T::zero() {
+        return T::zero();
+    }
+    let cos = match normal {
+        Some(normal) => normal.dot(&offset).abs() / distance2.sqrt(),
+        None => T::one()
+    };
+    pdf * cos / distance2
+}
+
+// Densities of zero belong to mirrors and glass, which cancel out
+fn remap<T>(pdf: T) -> T
+    where T: Float
+{
+    if pdf == T::zero() { T::one() } else { pdf }
+}
+
+impl<T> Scene<T>
+    where T: Float
+{
+    // Bidirectional path tracing: for each camera ray a subpath from the
+    // camera and one from the lights (added with add_light) are traced and
+    // each pair of their vertices is connected, all combined with multiple
+    // importance sampling (balance heuristic). Only the light subpaths are
+    // restricted: they're at most 3 vertices long and aren't connected to
+    // the camera directly. Mirrors and glass are treated as perfectly
+    // specular, fuzzy metals included, and media as diffuse surfaces.
+    // Unlike get_layer_color, paths that reach max_reflection add nothing
+    // and there's no Russian roulette.
+    pub fn get_bdpt_color(&self, ray: &Ray<T>, max_reflection: usize, layer_mask: u32, rng: &mut dyn RngCore) -> Vec3<T> {
+        self.bdpt_color(ray, max_reflection, layer_mask, rng, None)
+    }
+
+    // Like get_bdpt_color, adding the rays traced and the work of the tree to stats
+    pub fn get_bdpt_color_stats(&self, ray: &Ray<T>, max_reflection: usize, layer_mask: u32, rng: &mut dyn RngCore, stats: &mut SceneStats) -> Vec3<T> {
+        self.bdpt_color(ray, max_reflection, layer_mask, rng, Some(stats))
+    }
+
+    fn bdpt_color(&self, ray: &Ray<T>, max_reflection: usize, layer_mask: u32, rng: &mut dyn RngCore, mut stats: Option<&mut SceneStats>) -> Vec3<T> {
+        sampling::seed_thread_rng(rng.next_u64());
+        let mut camera = vec!(Vertex {
+            kind: VertexKind::Camera,
+            point: Vec3::from_slice(ray.get_origin().get_data()),
+            normal: None,
+            beta: Vec3::from_array([T::one(), T::one(), T::one()]),
+            emission: None,
+            delta: false,
+            pdf_fwd: T::one(),
+            pdf_rev: T::zero()
+        });
+        let camera_ray = Ray::from_vec(Vec3::from_slice(ray.get_origin().get_data()), Vec3::from_slice(ray.get_direction().get_data()));
+        let beta = Vec3::from_array([T::one(), T::one(), T::one()]);
+        // The background is only found by the camera subpaths
+        let mut color = self.random_walk(camera_ray, beta, T::one(), max_reflection + 1, true, layer_mask, rng, &mut camera, stats.as_deref_mut());
+
+        let (areas, total_area) = self.light_areas();
+        let mut light = vec!();
+        if total_area > T::zero() {
+            if let Some((hit, radiance)) = self.sample_light(&areas, total_area, rng) {
+                let mut normal = Vec3::from_slice(hit.normal.get_data());
+                normal.normalize();
+                let direction = cosine_hemisphere::<T, dyn RngCore>(rng, &normal);
+                let cos = normal.dot(&direction);
+                let pi = T::from_f64(std::f64::consts::PI);
+                let ray = self.offset_ray(&hit, Ray::from_vec(Vec3::from_slice(hit.point.get_data()), direction));
+                light.push(Vertex {
+                    kind: VertexKind::Light,
+                    point: hit.point,
+                    normal: Some(normal),
+                    beta: &radiance * total_area,
+                    emission: None,
+                    delta: false,
+                    pdf_fwd: T::one() / total_area,
+                    pdf_rev: T::zero()
+                });
+                // Either side, then a cosine weighted direction
+                let pdf_dir = cos / (pi + pi);
+                let beta = &light[0].beta * (cos / pdf_dir);
+                self.random_walk(ray, beta, pdf_dir, MAX_LIGHT_VERTICES - 1, false, layer_mask, rng, &mut light, stats.as_deref_mut());
+            }
+        }
+
+        for t in 2..=camera.len() {
+            for s in 0..=light.len() {
+                if s + t - 2 > max_reflection {
+                    break;
+                }
+                let contribution = self.connect(&camera, &light, s, t, total_area, layer_mask, stats.as_deref_mut());
+                color = color + contribution;
+            }
+        }
+        color
+    }
+
+    // Adds the vertices of a subpath starting with the ray to vertices, until
+    // max_vertices surface vertices or an emitter are reached. Returns the
+    // background times beta if a camera subpath leaves the scene.
+    #[allow(clippy::too_many_arguments)]
+    fn random_walk<'a>(&'a self, ray: Ray<T>, beta: Vec3<T>, pdf_dir: T, max_vertices: usize, from_camera: bool, layer_mask: u32, rng: &mut dyn RngCore, vertices: &mut Vec<Vertex<'a, T>>, mut stats: Option<&mut SceneStats>) -> Vec3<T> {
+        let mut ray = ray;
+        let mut beta = beta;
+        let mut pdf_dir = pdf_dir;
+        let mut media = vec!();
+        let pi = T::from_f64(std::f64::consts::PI);
+
+        while vertices.len() <= max_vertices {
+            if let Some(stats) = stats.as_deref_mut() {
+                if from_camera && vertices.len() == 1 {
+                    stats.primary_rays += 1;
+                } else {
+                    stats.bounce_rays += 1;
+                }
+            }
+            let seen_by = if from_camera && vertices.len() == 1 { ActorFlags::VISIBLE_TO_CAMERA } else { ActorFlags::VISIBLE_IN_REFLECTIONS };
+            let (actor, hit) = match self.get_hit_stats(&ray, &self.ray_interval(), layer_mask, seen_by, stats.as_deref_mut()) {
+                Some(current_hit) => current_hit,
+                None => {
+                    if from_camera {
+                        return beta * self.get_background_radiance(ray.get_direction());
+                    }
+                    return Vec3::new();
+                }
+            };
+            let mut normal = Vec3::from_slice(hit.normal.get_data());
+            normal.normalize();
+            let n_outside = Scene::outside_index(&media, &hit);
+            let (scatter, specular) = {
+                let material = actor.get_material();
+                (material.scatter_in_medium(&ray, &hit, n_outside, rng), material.is_specular())
+            };
+
+            let previous = vertices.len() - 1;
+            let point = Vec3::from_slice(hit.point.get_data());
+            let pdf_fwd = convert_density(pdf_dir, &vertices[previous].point, &point, Some(&normal));
+            let vertex_beta = Vec3::from_slice(beta.get_data());
+
+            let ray_out = match scatter.scattered {
+                Some(ray_out) => ray_out,
+                None => {
+                    // Light subpaths end at emitters, they don't reflect
+                    if from_camera {
+                        let actor_id = self.actor_ids[&(actor as *const Actor<T> as usize)];
+                        vertices.push(Vertex {
+                            kind: VertexKind::Surface(actor, hit),
+                            point,
+                            normal: Some(normal),
+                            beta: vertex_beta,
+                            emission: Some((scatter.attenuation, self.lights.contains(&actor_id))),
+                            delta: specular,
+                            pdf_fwd,
+                            pdf_rev: T::zero()
+                        });
+                    }
+                    return Vec3::new();
+                }
+            };
+
+            let (next_ray, pdf_rev_dir) = if specular {
+                if let Some(n) = Scene::crossed_index(actor, &hit, &ray_out) {
+                    if hit.front_face {
+                        media.push(n);
+                    } else {
+                        media.pop();
+                    }
+                }
+                beta = beta * scatter.attenuation;
+                pdf_dir = T::zero();
+                (self.offset_ray(&hit, ray_out), T::zero())
+            } else {
+                // Cosine weighted on the side the subpath arrived from, the
+                // scattered ray of the material is only used for mirrors and glass
+                let incoming = direction(&point, &vertices[previous].point);
+                let outgoing = cosine_hemisphere::<T, dyn RngCore>(rng, &normal);
+                let cos = normal.dot(&outgoing);
+                let brdf = if from_camera {
+                    surface_brdf(actor, &hit, &normal, &outgoing, &incoming)
+                } else {
+                    surface_brdf(actor, &hit, &normal, &incoming, &outgoing)
+                };
+                pdf_dir = cos / pi;
+                beta = match pdf_dir > T::zero() {
+                    true => beta * brdf * (cos / pdf_dir),
+                    false => Vec3::new()
+                };
+                let ray_out = Ray::from_vec(Vec3::from_slice(point.get_data()), outgoing);
+                (self.offset_ray(&hit, ray_out), normal.dot(&incoming).abs() / pi)
+            };
+            vertices[previous].pdf_rev = convert_density(pdf_rev_dir, &point, &vertices[previous].point, vertices[previous].normal.as_ref());
+            vertices.push(Vertex {
+                kind: VertexKind::Surface(actor, hit),
+                point,
+                normal: Some(normal),
+                beta: vertex_beta,
+                emission: None,
+                delta: specular,
+                pdf_fwd,
+                pdf_rev: T::zero()
+            });
+
+            if beta.get_data().iter().all(|x| *x <= T::zero()) {
+                break;
+            }
+            ray = next_ray;
+        }
+        Vec3::new()
+    }
+
+    // Light of the path made of the first s light and t camera vertices,
+    // weighted with multiple importance sampling
+    #[allow(clippy::too_many_arguments)]
+    fn connect(&self, camera: &[Vertex<'_, T>], light: &[Vertex<'_, T>], s: usize, t: usize, total_area: T, layer_mask: u32, stats: Option<&mut SceneStats>) -> Vec3<T> {
+        let pt = &camera[t - 1];
+        let contribution = if s == 0 {
+            match &pt.emission {
+                Some((radiance, _)) => &pt.beta * radiance,
+                None => return Vec3::new()
+            }
+        } else {
+            let qs = &light[s - 1];
+            if !pt.connectible() || qs.delta {
+                return Vec3::new();
+            }
+            let to_light = direction(&pt.point, &qs.point);
+            let to_camera = direction(&pt.point, &camera[t - 2].point);
+            let brdf_camera = pt.brdf(&to_light, &to_camera);
+            // Diffuse emitters send the same radiance to all directions
+            let brdf_light = match s {
+                1 => Vec3::from_array([T::one(), T::one(), T::one()]),
+                _ => {
+                    let back = to_light * (-T::one());
+                    qs.brdf(&direction(&qs.point, &light[s - 2].point), &back)
+                }
+            };
+            let offset = &qs.point - &pt.point;
+            let distance2 = offset.dot(&offset);
+            let to_qs = &offset / distance2.sqrt();
+            let g = pt.cos(&to_qs) * qs.cos(&to_qs) / distance2;
+            let contribution = &pt.beta * brdf_camera * brdf_light * &qs.beta * g;
+            if contribution.get_data().iter().all(|x| *x <= T::zero()) || !self.unoccluded(pt, qs, layer_mask, stats) {
+                return Vec3::new();
+            }
+            contribution
+        };
+        contribution * Scene::mis_weight(camera, light, s, t, total_area)
+    }
+
+    // True if nothing is between the surface vertex and the other one
+    fn unoccluded(&self, vertex: &Vertex<'_, T>, other: &Vertex<'_, T>, layer_mask: u32, mut stats: Option<&mut SceneStats>) -> bool {
+        let hit = match &vertex.kind {
+            VertexKind::Surface(_, hit) => hit,
+            _ => return false
+        };
+        let offset = &other.point - &vertex.point;
+        let distance = offset.norm();
+        let ray = self.offset_ray(hit, Ray::from_vec(Vec3::from_slice(vertex.point.get_data()), &offset / distance));
+        // Stop short of the surface of the other vertex
+        let interval = Interval::new(self.ray_interval().min, distance * T::from_f64(1.0 - 1e-4));
+        if let Some(stats) = stats.as_deref_mut() {
+            stats.shadow_rays += 1;
+        }
+        self.get_hit_stats(&ray, &interval, layer_mask, ActorFlags::CASTS_SHADOWS, stats).is_none()
+    }
+
+    // Balance heuristic: the density of the strategy divided by the sum over
+    // all strategies that could have sampled the same path, computed with
+    // the ratios of the densities of neighbouring strategies
+    fn mis_weight(camera: &[Vertex<'_, T>], light: &[Vertex<'_, T>], s: usize, t: usize, total_area: T) -> T {
+        if s + t == 2 {
+            return T::one();
+        }
+        let pt = &camera[t - 1];
+        let pt_minus = &camera[t - 2];
+        // Only the lights can be sampled by the light subpaths
+        if s == 0 && !matches!(pt.emission, Some((_, true))) {
+            return T::one();
+        }
+
+        // The reverse densities of the connected vertices and their
+        // neighbours change, the connection is sampled in another direction
+        let (pt_rev, pt_minus_rev) = match s {
+            0 => (T::one() / total_area, pt.pdf_area(pt_minus, true)),
+            _ => (light[s - 1].pdf_area(pt, false), pt.pdf_area(pt_minus, false))
+        };
+        let (qs_rev, qs_minus_rev) = match s {
+            0 => (T::zero(), T::zero()),
+            1 => (pt.pdf_area(&light[0], false), T::zero()),
+            _ => (pt.pdf_area(&light[s - 1], false), light[s - 1].pdf_area(&light[s - 2], false))
+        };
+
+        let mut sum = T::zero();
+        // Moving the connection towards the camera, down to two camera
+        // vertices since the light subpaths aren't connected to the camera
+        let mut ratio = T::one();
+        for i in (2..t).rev() {
+            if s + t - i > MAX_LIGHT_VERTICES {
+                break;
+            }
+            let pdf_rev = if i == t - 1 {
+                pt_rev
+            } else if i == t - 2 {
+                pt_minus_rev
+            } else {
+                camera[i].pdf_rev
+            };
+            ratio = ratio * remap(pdf_rev) / remap(camera[i].pdf_fwd);
+            if !camera[i].delta && !camera[i - 1].delta {
+                sum = sum + ratio;
+            }
+        }
+        // Moving the connection towards the light
+        let mut ratio = T::one();
+        for i in (0..s).rev() {
+            let pdf_rev = if i == s - 1 {
+                qs_rev
+            } else if i + 2 == s {
+                qs_minus_rev
+            } else {
+                light[i].pdf_rev
+            };
+            ratio = ratio * remap(pdf_rev) / remap(light[i].pdf_fwd);
+            let delta_before = i > 0 && light[i - 1].delta;
+            if !light[i].delta && !delta_before {
+                sum = sum + ratio;
+            }
+        }
+        T::one() / (T::one() + sum)
+    }
+}
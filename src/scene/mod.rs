@@ -1,24 +1,145 @@
-use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use arc_swap::Guard;
+use rand::RngCore;
 
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
-use crate::actor::Actor;
+use crate::hit::Hit;
+use crate::hitable::Hitable;
+use crate::hitable::primitive::Rectangle;
+use crate::hitable::transform::Translation;
+use crate::actor::{Actor, ActorId, ActorFlags, ALL_LAYERS};
+use crate::material::Material;
 use crate::boundingbox::BoundingBox;
-use crate::tree::{Tree, TreeType};
+use crate::interval::Interval;
+use crate::tree::{Tree, TreeType, TraversalCounts};
 use crate::tree::linear::LinearTree;
 use crate::tree::binary::BinaryTree;
 use crate::tree::oct::Octree;
+#[cfg(feature = "embree")]
+use crate::tree::embree::EmbreeTree;
+use crate::sampling::{self, uniform, cosine_hemisphere};
+use crate::error::Error;
+use crate::photon::{Photon, PhotonMap};
+use crate::environment::Environment;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
+use crate::renderer::spectral;
+
+mod bdpt;
+
+// How rays leaving a surface avoid hitting it again due to round-off
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SelfIntersectPolicy {
+    // Ignore hits closer than the ray epsilon
+    Epsilon,
+    // Move the origin of scattered rays away from the surface, along the
+    // geometric normal, by an amount that grows with the distance to the
+    // scene origin. The ray epsilon is not used.
+    NormalOffset,
+    // Both of the above
+    Both
+}
+
+// Result of a ray query, the hit together with the actor that was hit
+pub struct SceneHit<'a, T>
+    where T: Float
+{
+    pub hit: Hit<T>,
+    pub actor_id: ActorId,
+    pub actor: &'a Actor<T>
+}
+
+impl<'a, T> SceneHit<'a, T>
+    where T: Float
+{
+    pub fn get_material(&self) -> Guard<Arc<Box<dyn Material<T>>>> {
+        self.actor.get_material()
+    }
+}
+
+// Rays traced and the work of the tree to find their hits, i.e. to compare
+// tree types. Filled by Renderer::render_verbose.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SceneStats {
+    pub primary_rays: u64,
+    // Tests if two points see each other (bidirectional paths) or if a
+    // point sees the environment
+    pub shadow_rays: u64,
+    // Rays leaving surfaces and lights
+    pub bounce_rays: u64,
+    pub bvh_traversal_steps: u64,
+    pub intersection_tests: u64,
+    pub triangle_tests: u64,
+    pub hits: u64,
+    // 0 unless the scene has timing enabled
+    pub render_time_ms: u64
+}
 
+impl fmt::Display for SceneStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<20}{:>14}", "primary rays", self.primary_rays)?;
+        writeln!(f, "{:<20}{:>14}", "shadow rays", self.shadow_rays)?;
+        writeln!(f, "{:<20}{:>14}", "bounce rays", self.bounce_rays)?;
+        writeln!(f, "{:<20}{:>14}", "traversal steps", self.bvh_traversal_steps)?;
+        writeln!(f, "{:<20}{:>14}", "intersection tests", self.intersection_tests)?;
+        writeln!(f, "{:<20}{:>14}", "triangle tests", self.triangle_tests)?;
+        writeln!(f, "{:<20}{:>14}", "hits", self.hits)?;
+        write!(f, "{:<20}{:>14}", "render time [ms]", self.render_time_ms)
+    }
+}
+
+// What a path carries along while it's traced
+struct PathState<'a, T>
+    where T: Float
+{
+    layer_mask: u32,
+    media: Vec<T>,
+    rng: &'a mut dyn RngCore,
+    // Number of rays traced at each depth, only if requested
+    ray_counts: Option<&'a mut Vec<u64>>,
+    stats: Option<&'a mut SceneStats>,
+    // Caustics are gathered at diffuse surfaces, a path that reaches a light
+    // through specular surfaces after a diffuse one mustn't count it again
+    after_diffuse: bool,
+    caustic_path: bool,
+    // Density of the direction of the ray if the environment was also
+    // sampled directly at its origin
+    environment_pdf: Option<T>
+}
 
 pub struct Scene<T>
     where T: Float
 {
-    actors: Vec<Rc<Actor<T>>>,
+    actors: Vec<Arc<Actor<T>>>,
+    // The trees only know about actors, not about their position in the scene,
+    // so the ids are looked up by the address of the actor
+    actor_ids: HashMap<usize, ActorId>,
     background: Vec3<T>,
     bounds: BoundingBox<T>,
     tree: Box<dyn Tree<T>>,
-    tree_type: TreeType
+    tree_type: TreeType,
+    ray_epsilon: T,
+    time: T,
+    self_intersect_policy: SelfIntersectPolicy,
+    tree_builds: usize,
+    // Only measured with timing enabled, the clock isn't available everywhere
+    timing: bool,
+    tree_build_time: Duration,
+    // Emitting actors the photons of the caustic pre-pass start from
+    lights: Vec<ActorId>,
+    caustics: Option<PhotonMap<T>>,
+    environment: Option<Environment<T>>,
+    // Openings the environment is sampled through, not rendered
+    portals: Vec<Box<dyn Hitable<T>>>,
+    // Rays traced while rendering since the last reset, counted even if
+    // no stats are asked for
+    ray_count: AtomicU64
 }
 
 impl<T> Scene<T>
@@ -27,54 +148,806 @@ impl<T> Scene<T>
     pub fn new() -> Self {
         Scene {
             actors: vec!(),
+            actor_ids: HashMap::new(),
             background: Vec3::<T>::new(),
             bounds: BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new()),
             tree: Box::new(LinearTree::new()),
-            tree_type: TreeType::Linear
+            tree_type: TreeType::Linear,
+            ray_epsilon: T::from_f64(0.000000001),
+            time: T::zero(),
+            self_intersect_policy: SelfIntersectPolicy::Epsilon,
+            tree_builds: 0,
+            timing: false,
+            tree_build_time: Duration::from_secs(0),
+            lights: vec!(),
+            caustics: None,
+            environment: None,
+            portals: vec!(),
+            ray_count: AtomicU64::new(0)
         }
     }
 
+    pub fn get_background(&self) -> &Vec3<T> {
+        &self.background
+    }
+
     pub fn set_background(&mut self, background: Vec3<T>) {
         self.background = background;
     }
 
-    pub fn add_actor(&mut self, actor: Actor<T>) {
-        let _expanded = self.bounds.expand(&actor.hitable.get_bounds());
-        let actor = Rc::new(actor);
-        self.actors.push(Rc::clone(&actor));
-        let success = self.tree.add_actor(actor);
+    pub fn get_environment(&self) -> Option<&Environment<T>> {
+        self.environment.as_ref()
+    }
+
+    // Light from far away in place of the background, sampled directly at
+    // diffuse surfaces by get_color
+    pub fn set_environment(&mut self, environment: Option<Environment<T>>) {
+        self.environment = environment;
+    }
+
+    // A rectangle in an opening of the scene towards the environment, i.e.
+    // a window of a room, placed by the translation. Portals aren't seen,
+    // but diffuse surfaces sample the environment through them instead of
+    // in all directions, so a small window is found by far more paths. The
+    // light of the environment has to pass through one of the portals.
+    pub fn add_portal(&mut self, rectangle: Rectangle<T>, translation: Vec3<T>) {
+        self.portals.push(Box::new(Translation::new(Box::new(rectangle), translation)));
+    }
+
+    pub fn get_portals(&self) -> &[Box<dyn Hitable<T>>] {
+        &self.portals
+    }
+
+    pub fn clear_portals(&mut self) {
+        self.portals.clear();
+    }
+
+    // A direction from the point towards the environment and its density per
+    // solid angle, through a point uniform over the area of the portals if
+    // there are any
+    fn sample_environment_direction(&self, environment: &Environment<T>, point: &Vec3<T>, rng: &mut dyn RngCore) -> (Vec3<T>, T) {
+        if self.portals.is_empty() {
+            return environment.sample_direction(rng);
+        }
+        let total_area = self.portals.iter().fold(T::zero(), |sum, portal| sum + portal.surface_area());
+        let mut area = uniform::<T, dyn RngCore>(rng) * total_area;
+        let mut portal = &self.portals[self.portals.len() - 1];
+        for candidate in self.portals.iter() {
+            area = area - candidate.surface_area();
+            if area < T::zero() {
+                portal = candidate;
+                break;
+            }
+        }
+        let sample = match portal.sample_surface(rng) {
+            Some(sample) => sample,
+            None => return (Vec3::new(), T::zero())
+        };
+        let mut direction = &sample.point - point;
+        if direction.dot(&direction) == T::zero() {
+            return (direction, T::zero());
+        }
+        direction.normalize();
+        // Portals behind each other add to the density
+        let pdf = self.environment_direction_pdf(environment, point, &direction);
+        (direction, pdf)
+    }
+
+    // Density of sample_environment_direction returning the direction, over
+    // all the portals the direction passes through
+    fn environment_direction_pdf(&self, environment: &Environment<T>, point: &Vec3<T>, direction: &Vec3<T>) -> T {
+        if self.portals.is_empty() {
+            return environment.pdf(direction);
+        }
+        let total_area = self.portals.iter().fold(T::zero(), |sum, portal| sum + portal.surface_area());
+        let mut direction = Vec3::from_slice(direction.get_data());
+        direction.normalize();
+        let ray = Ray::from_vec(Vec3::from_slice(point.get_data()), direction);
+        self.portals.iter().fold(T::zero(), |pdf, portal| {
+            match portal.hit(&ray, T::zero(), T::infinity()) {
+                Some(hit) => {
+                    let cos = ray.get_direction().dot(&hit.normal).abs() / hit.normal.norm();
+                    if cos > T::zero() { pdf + hit.t * hit.t / (cos * total_area) } else { pdf }
+                },
+                None => pdf
+            }
+        })
+    }
+
+    // Light arriving along rays that don't hit anything
+    pub(crate) fn get_background_radiance(&self, direction: &Vec3<T>) -> Vec3<T> {
+        match &self.environment {
+            Some(environment) => environment.get_radiance(direction),
+            None => Vec3::from_slice(self.background.get_data())
+        }
+    }
 
-        if !success {
+    pub fn add_actor(&mut self, actor: Actor<T>) -> ActorId {
+        if let Some(bounds) = actor.hitable.bounding_box() {
+            let _expanded = self.bounds.expand(&bounds);
+        }
+        let actor = Arc::new(actor);
+        let actor_id = self.actors.len();
+        self.actor_ids.insert(Arc::as_ptr(&actor) as usize, actor_id);
+        self.actors.push(Arc::clone(&actor));
+        // Invisible actors are left out of the tree
+        if actor.is_visible() && !self.tree.add_actor(actor) {
             self.rebuild_tree();
         }
+        actor_id
+    }
+
+    pub fn set_actor_visible(&mut self, actor_id: ActorId, visible: bool) {
+        if self.actors[actor_id].is_visible() == visible {
+            return;
+        }
+        // The tree shares the actors, drop it to be able to change them
+        self.tree = Box::new(LinearTree::new());
+        Arc::get_mut(&mut self.actors[actor_id]).unwrap().set_visible(visible);
+        self.rebuild_tree();
+    }
+
+    // Which rays see the actor, takes effect for the rays cast afterwards
+    // without rebuilding the tree
+    pub fn set_actor_flags(&self, actor_id: ActorId, flags: ActorFlags) {
+        self.actors[actor_id].set_flags(flags);
+    }
+
+    // Without rebuilding the tree, and not in the middle of a render
+    pub fn set_actor_material(&mut self, actor_id: ActorId, material: Box<dyn Material<T>>) {
+        self.actors[actor_id].set_material(material);
+    }
+
+    // Registers an emitting actor as a light, the caustic pre-pass only
+    // starts photons from lights
+    pub fn add_light(&mut self, actor_id: ActorId) {
+        if !self.lights.contains(&actor_id) {
+            self.lights.push(actor_id);
+        }
     }
 
-    pub fn get_color(&self, ray: &Ray<T>, reflection: usize, max_reflection: usize) -> Vec3<T> {
-        let current_hit = self.tree.get_hit(ray, T::from(0.000000001).unwrap(), T::from(10000000000.0).unwrap());
+    pub fn get_lights(&self) -> &[ActorId] {
+        &self.lights
+    }
+
+    // Pre-allocates the space for n actors in total in the scene, the trees
+    // are built from their nodes and allocate as they go
+    pub fn set_max_actors(&mut self, n: usize) {
+        let additional = n.saturating_sub(self.actors.len());
+        self.actors.reserve(additional);
+        self.actor_ids.reserve(additional);
+    }
+
+    pub fn get_actor(&self, actor_id: ActorId) -> Option<&Actor<T>> {
+        self.actors.get(actor_id).map(|actor| actor.as_ref())
+    }
+
+    // Closest hit along the ray and the actor that was hit
+    pub fn cast_ray(&self, ray: &Ray<T>) -> Option<SceneHit<'_, T>> {
+        self.cast_layer_ray(ray, ALL_LAYERS)
+    }
+
+    // Like a camera ray, actors hidden from the camera aren't hit
+    pub fn cast_layer_ray(&self, ray: &Ray<T>, layer_mask: u32) -> Option<SceneHit<'_, T>> {
+        let (actor, hit) = self.get_flagged_hit(ray, &self.ray_interval(), layer_mask, ActorFlags::VISIBLE_TO_CAMERA, &mut TraversalCounts::default())?;
+        let actor_id = self.actor_ids[&(actor as *const Actor<T> as usize)];
+        Some(SceneHit { hit, actor_id, actor })
+    }
+
+    pub fn get_color(&self, ray: &Ray<T>, reflection: usize, min_reflection: usize, max_reflection: usize, rng: &mut dyn RngCore) -> Vec3<T> {
+        self.get_layer_color(ray, reflection, min_reflection, max_reflection, ALL_LAYERS, rng)
+    }
+
+    // Like get_color, but only actors in one of the layers of the mask are seen
+    pub fn get_layer_color(&self, ray: &Ray<T>, reflection: usize, min_reflection: usize, max_reflection: usize, layer_mask: u32, rng: &mut dyn RngCore) -> Vec3<T> {
+        sampling::seed_thread_rng(rng.next_u64());
+        let mut path = PathState { layer_mask, media: vec!(), rng, ray_counts: None, stats: None, after_diffuse: false, caustic_path: false, environment_pdf: None };
+        self.trace(ray, reflection, min_reflection, max_reflection, &mut path)
+    }
+
+    // Like get_layer_color for a camera ray, also counting the rays traced
+    // at each depth into ray_counts
+    pub fn get_layer_color_counted(&self, ray: &Ray<T>, min_reflection: usize, max_reflection: usize, layer_mask: u32, rng: &mut dyn RngCore, ray_counts: &mut Vec<u64>) -> Vec3<T> {
+        sampling::seed_thread_rng(rng.next_u64());
+        let mut path = PathState { layer_mask, media: vec!(), rng, ray_counts: Some(ray_counts), stats: None, after_diffuse: false, caustic_path: false, environment_pdf: None };
+        self.trace(ray, 0, min_reflection, max_reflection, &mut path)
+    }
+
+    // Like get_layer_color for a camera ray, adding the rays traced and
+    // the work of the tree to stats
+    pub fn get_layer_color_stats(&self, ray: &Ray<T>, min_reflection: usize, max_reflection: usize, layer_mask: u32, rng: &mut dyn RngCore, stats: &mut SceneStats) -> Vec3<T> {
+        sampling::seed_thread_rng(rng.next_u64());
+        let mut path = PathState { layer_mask, media: vec!(), rng, ray_counts: None, stats: Some(stats), after_diffuse: false, caustic_path: false, environment_pdf: None };
+        self.trace(ray, 0, min_reflection, max_reflection, &mut path)
+    }
+
+    // The media stack holds the refractive indices of the dielectrics the path
+    // is currently inside of, the innermost one last (vacuum if it's empty).
+    // Entering a dielectric pushes its index, leaving it pops the top entry.
+    // Media are expected to be nested: if dielectrics overlap, the last one
+    // entered wins until the path leaves any of them.
+    fn trace(&self, ray: &Ray<T>, reflection: usize, min_reflection: usize, max_reflection: usize, path: &mut PathState<'_, T>) -> Vec3<T> {
+        if let Some(counts) = path.ray_counts.as_mut() {
+            if counts.len() <= reflection {
+                counts.resize(reflection + 1, 0);
+            }
+            counts[reflection] += 1;
+        }
+        if let Some(stats) = path.stats.as_deref_mut() {
+            if reflection == 0 {
+                stats.primary_rays += 1;
+            } else {
+                stats.bounce_rays += 1;
+            }
+        }
+        let seen_by = if reflection == 0 { ActorFlags::VISIBLE_TO_CAMERA } else { ActorFlags::VISIBLE_IN_REFLECTIONS };
+        let current_hit = self.get_hit_stats(ray, &self.ray_interval(), path.layer_mask, seen_by, path.stats.as_deref_mut());
+        let environment_pdf = path.environment_pdf.take();
 
         match current_hit {
             Some((actor, hit)) => {
-                // let actor = &self.actors[actor_idx];
-                let scatter = actor.material.scatter(ray, &hit);
+                // Passing a portal counts as a reflection, to end the paths
+                // between two portals facing each other
+                if let Some(ray_out) = actor.hitable.teleport(ray, &hit.point) {
+                    if reflection >= max_reflection {
+                        return Vec3::<T>::new();
+                    }
+                    return self.trace(&ray_out, reflection + 1, min_reflection, max_reflection, path);
+                }
+                let n_outside = Scene::outside_index(&path.media, &hit);
+                let sample = match (&self.environment, reflection < max_reflection) {
+                    (Some(environment), true) => Some(self.sample_environment_direction(environment, &hit.point, path.rng)),
+                    _ => None
+                };
+                // The material is locked only here, not while tracing further
+                let (scatter, specular, caustic, scatter_pdf, direct) = {
+                    let material = actor.get_material();
+                    let scatter = material.scatter_in_medium(ray, &hit, n_outside, path.rng);
+                    let specular = material.is_specular();
+                    let caustic = match (&self.caustics, specular, &scatter.scattered) {
+                        (Some(caustics), false, Some(_)) => caustics.estimate_radiance(&hit, |incoming| material.brdf(&hit, incoming)),
+                        _ => Vec3::<T>::new()
+                    };
+                    // The brdf towards the sampled direction and both densities of it
+                    let (scatter_pdf, direct) = match (sample, &scatter.scattered, specular) {
+                        (Some((direction, pdf)), Some(ray_out), false) => {
+                            match (material.scatter_pdf(&hit, ray_out.get_direction()), material.scatter_pdf(&hit, &direction)) {
+                                (Some(scatter_pdf), Some(direction_pdf)) => {
+                                    let brdf = material.brdf(&hit, &(&direction * (-T::one())));
+                                    (Some(scatter_pdf), Some((direction, pdf, direction_pdf, brdf)))
+                                },
+                                _ => (None, None)
+                            }
+                        },
+                        _ => (None, None)
+                    };
+                    (scatter, specular, caustic, scatter_pdf, direct)
+                };
+                // Light of the environment sampled directly, weighted against
+                // the scattered ray finding it (power heuristic)
+                let caustic = match direct {
+                    Some((direction, pdf, direction_pdf, brdf)) => caustic + self.sample_environment(&hit, direction, pdf, direction_pdf, brdf, path),
+                    None => caustic
+                };
                 let attenuation = Vec3::<T>::from_slice(scatter.attenuation.get_data());
                 let scattered_ray = scatter.scattered;
                 match scattered_ray {
                     Some(ray_out) => {
+                        if specular {
+                            path.caustic_path = path.after_diffuse;
+                        } else {
+                            path.after_diffuse = true;
+                            path.caustic_path = false;
+                        }
                         if reflection < max_reflection {
-                            return attenuation * self.get_color(&ray_out, reflection + 1, max_reflection);
+                            // Past the minimum depth, terminate paths with Russian roulette
+                            // and boost the survivors to keep the estimate unbiased.
+                            let mut attenuation = attenuation;
+                            if reflection >= min_reflection {
+                                let survival = Scene::<T>::survival_probability(&attenuation);
+                                if uniform::<T, dyn RngCore>(path.rng) >= survival {
+                                    return caustic;
+                                }
+                                attenuation = attenuation / survival;
+                            }
+                            let mut ray_out = self.offset_ray(&hit, ray_out);
+                            ray_out.set_wavelength(ray.get_wavelength());
+                            path.environment_pdf = scatter_pdf;
+
+                            let n = match Scene::crossed_index(actor, &hit, &ray_out) {
+                                Some(n) => n,
+                                None => {
+                                    return caustic + attenuation * self.trace(&ray_out, reflection + 1, min_reflection, max_reflection, path);
+                                }
+                            };
+                            if hit.front_face {
+                                path.media.push(n);
+                                let color = self.trace(&ray_out, reflection + 1, min_reflection, max_reflection, path);
+                                path.media.pop();
+                                return caustic + attenuation * color;
+                            }
+                            let left = path.media.pop();
+                            let color = self.trace(&ray_out, reflection + 1, min_reflection, max_reflection, path);
+                            if let Some(left) = left {
+                                path.media.push(left);
+                            }
+                            return caustic + attenuation * color;
                         } else {
-                            return attenuation;
+                            return caustic + attenuation;
                         }
                     },
                     None => {
+                        // Already in the photon map
+                        if path.caustic_path && self.caustics.is_some() {
+                            return Vec3::<T>::new();
+                        }
                         return attenuation;
                     }
                 }
             },
             None => {
-                return Vec3::<T>::from_slice(self.background.get_data());
+                let radiance = self.get_background_radiance(ray.get_direction());
+                match (&self.environment, environment_pdf) {
+                    (Some(environment), Some(scatter_pdf)) => {
+                        let pdf = self.environment_direction_pdf(environment, ray.get_origin(), ray.get_direction());
+                        radiance * (scatter_pdf * scatter_pdf / (scatter_pdf * scatter_pdf + pdf * pdf))
+                    },
+                    _ => radiance
+                }
+            }
+        }
+    }
+
+    fn sample_environment(&self, hit: &Hit<T>, direction: Vec3<T>, pdf: T, scatter_pdf: T, brdf: Vec3<T>, path: &mut PathState<'_, T>) -> Vec3<T> {
+        let cos = direction.dot(&hit.normal) / hit.normal.norm();
+        if cos <= T::zero() || pdf <= T::zero() {
+            return Vec3::new();
+        }
+        if let Some(stats) = path.stats.as_deref_mut() {
+            stats.shadow_rays += 1;
+        }
+        let ray = self.offset_ray(hit, Ray::from_vec(Vec3::from_slice(hit.point.get_data()), Vec3::from_slice(direction.get_data())));
+        if self.get_hit_stats(&ray, &self.ray_interval(), path.layer_mask, ActorFlags::CASTS_SHADOWS, path.stats.as_deref_mut()).is_some() {
+            return Vec3::new();
+        }
+        let weight = pdf * pdf / (pdf * pdf + scatter_pdf * scatter_pdf);
+        brdf * self.get_background_radiance(&direction) * (cos * weight / pdf)
+    }
+
+    // Bounce points of a single path starting at start in the direction of end,
+    // to see where the light travels. The path stops after n_bounces, when it
+    // leaves the scene or when it reaches a material that doesn't scatter.
+    pub fn trace_path(&self, start: Vec3<T>, end: Vec3<T>, n_bounces: usize, rng: &mut dyn RngCore) -> Vec<Vec3<T>> {
+        sampling::seed_thread_rng(rng.next_u64());
+        let mut points = vec!();
+        let mut media = vec!();
+        let direction = &end - &start;
+        let mut ray = Ray::from_vec(start, direction);
+
+        while points.len() < n_bounces {
+            let (actor, hit) = match self.tree.get_hit(&ray, &self.ray_interval(), ALL_LAYERS) {
+                Some(current_hit) => current_hit,
+                None => break
+            };
+            points.push(Vec3::from_slice(hit.point.get_data()));
+
+            let n_outside = Scene::outside_index(&media, &hit);
+            let ray_out = match actor.get_material().scatter_in_medium(&ray, &hit, n_outside, rng).scattered {
+                Some(ray_out) => self.offset_ray(&hit, ray_out),
+                None => break
+            };
+            if let Some(n) = Scene::crossed_index(actor, &hit, &ray_out) {
+                if hit.front_face {
+                    media.push(n);
+                } else {
+                    media.pop();
+                }
+            }
+            ray = ray_out;
+        }
+        points
+    }
+
+    // Base color of the first surface along the ray without any lighting,
+    // the background if the ray doesn't hit anything
+    pub fn get_layer_albedo(&self, ray: &Ray<T>, layer_mask: u32) -> Vec3<T> {
+        match self.cast_layer_ray(ray, layer_mask) {
+            Some(scene_hit) => scene_hit.get_material().get_base_color(&scene_hit.hit),
+            None => self.get_background_radiance(ray.get_direction())
+        }
+    }
+
+    // Emits photons from the lights and stores the ones that reach a diffuse
+    // surface through at least one specular one. Afterwards the renders gather
+    // the caustics from the photons within radius of the diffuse hits instead
+    // of waiting for the paths to find the lights through the specular
+    // surfaces. The map isn't updated when the scene changes, build it again.
+    pub fn build_caustics(&mut self, photons: usize, radius: T, rng: &mut dyn RngCore) {
+        self.emit_photons(photons, radius, false, rng);
+    }
+
+    // Like build_caustics, but every photon carries a wavelength and the
+    // color of it, i.e. for the rainbow of a dispersive prism. Renders with
+    // Renderer::set_spectral to also disperse the light the paths find.
+    pub fn build_spectral_caustics(&mut self, photons: usize, radius: T, rng: &mut dyn RngCore) {
+        self.emit_photons(photons, radius, true, rng);
+    }
+
+    fn emit_photons(&mut self, photons: usize, radius: T, spectral: bool, rng: &mut dyn RngCore) {
+        let (areas, total_area) = self.light_areas();
+        if photons == 0 || total_area <= T::zero() {
+            self.caustics = None;
+            return;
+        }
+
+        // Lights emit on both sides of their surface, like a diffuse
+        // emitter: the power is the radiance times 2 pi per area
+        let scale = T::from_f64(2.0 * std::f64::consts::PI) * total_area / T::from_f64(photons as f64);
+        let mut stored = vec!();
+        sampling::seed_thread_rng(rng.next_u64());
+        for _ in 0..photons {
+            let (hit, radiance) = match self.sample_light(&areas, total_area, rng) {
+                Some(sample) => sample,
+                None => continue
+            };
+            let direction = cosine_hemisphere::<T, dyn RngCore>(rng, &hit.normal);
+            let mut ray = self.offset_ray(&hit, Ray::from_vec(Vec3::from_slice(hit.point.get_data()), direction));
+            let mut power = radiance * scale;
+            if spectral {
+                let (wavelength, weight) = spectral::sample_wavelength(rng);
+                ray.set_wavelength(Some(wavelength));
+                power = power * weight;
+            }
+            self.trace_photon(ray, power, rng, &mut stored);
+        }
+        self.caustics = Some(PhotonMap::new(stored, radius));
+    }
+
+    // Surface area of each light and their sum
+    fn light_areas(&self) -> (Vec<T>, T) {
+        let areas: Vec<T> = self.lights.iter().map(|id| self.actors[*id].hitable.surface_area()).collect();
+        let total_area = areas.iter().fold(T::zero(), |sum, area| sum + *area);
+        (areas, total_area)
+    }
+
+    // A point on the lights, uniform over their total area, with the normal of
+    // a random side of the surface and the radiance emitted there. None if
+    // the light can't be sampled or isn't an emitter.
+    fn sample_light(&self, areas: &[T], total_area: T, rng: &mut dyn RngCore) -> Option<(Hit<T>, Vec3<T>)> {
+        // Pick a light proportionally to its area
+        let mut area = uniform::<T, dyn RngCore>(rng) * total_area;
+        let mut light = self.lights.len() - 1;
+        for (i, light_area) in areas.iter().enumerate() {
+            area = area - *light_area;
+            if area < T::zero() {
+                light = i;
+                break;
+            }
+        }
+        let actor = &self.actors[self.lights[light]];
+        let mut hit = actor.hitable.sample_surface(rng)?;
+        if uniform::<T, dyn RngCore>(rng) < T::from_f64(0.5) {
+            hit.normal = &hit.normal * (-T::one());
+            hit.front_face = false;
+        }
+
+        // Only emitters don't scatter, they return their color
+        let incident = Ray::from_vec(&hit.point + &hit.normal, &hit.normal * (-T::one()));
+        let scatter = actor.get_material().scatter(&incident, &hit, rng);
+        match scatter.scattered {
+            Some(_) => None,
+            None => Some((hit, scatter.attenuation))
+        }
+    }
+
+    pub fn clear_caustics(&mut self) {
+        self.caustics = None;
+    }
+
+    pub fn get_caustics(&self) -> Option<&PhotonMap<T>> {
+        self.caustics.as_ref()
+    }
+
+    fn trace_photon(&self, ray: Ray<T>, power: Vec3<T>, rng: &mut dyn RngCore, stored: &mut Vec<Photon<T>>) {
+        let max_bounces = 16;
+        let mut ray = ray;
+        let mut power = power;
+        let mut media = vec!();
+
+        for bounce in 0..max_bounces {
+            let (actor, hit) = match self.get_flagged_hit(&ray, &self.ray_interval(), ALL_LAYERS, ActorFlags::VISIBLE_IN_REFLECTIONS, &mut TraversalCounts::default()) {
+                Some(current_hit) => current_hit,
+                None => return
+            };
+            if let Some(ray_out) = actor.hitable.teleport(&ray, &hit.point) {
+                ray = ray_out;
+                continue;
+            }
+            let n_outside = Scene::outside_index(&media, &hit);
+            let (scatter, specular) = {
+                let material = actor.get_material();
+                (material.scatter_in_medium(&ray, &hit, n_outside, rng), material.is_specular())
+            };
+            let mut ray_out = match scatter.scattered {
+                Some(ray_out) => self.offset_ray(&hit, ray_out),
+                None => return
+            };
+            ray_out.set_wavelength(ray.get_wavelength());
+            if !specular {
+                // Light reaching a diffuse surface directly is found by the paths
+                if bounce > 0 {
+                    let direction = Vec3::from_slice(ray.get_direction().get_data());
+                    stored.push(Photon { position: hit.point, direction, power });
+                }
+                return;
+            }
+
+            if let Some(n) = Scene::crossed_index(actor, &hit, &ray_out) {
+                if hit.front_face {
+                    media.push(n);
+                } else {
+                    media.pop();
+                }
+            }
+            power = power * scatter.attenuation;
+            ray = ray_out;
+        }
+    }
+
+    // Closest hit of the ray among the actors seen by it, the work of the
+    // tree is added to the stats
+    fn get_hit_stats(&self, ray: &Ray<T>, interval: &Interval<T>, layer_mask: u32, seen_by: ActorFlags, stats: Option<&mut SceneStats>) -> Option<(&Actor<T>, Hit<T>)> {
+        self.ray_count.fetch_add(1, Ordering::Relaxed);
+        let mut counts = TraversalCounts::default();
+        let stats = match stats {
+            Some(stats) => stats,
+            None => return self.get_flagged_hit(ray, interval, layer_mask, seen_by, &mut counts)
+        };
+        let result = self.get_flagged_hit(ray, interval, layer_mask, seen_by, &mut counts);
+        stats.bvh_traversal_steps += counts.steps;
+        stats.intersection_tests += counts.intersection_tests;
+        stats.triangle_tests += counts.triangle_tests;
+        if result.is_some() {
+            stats.hits += 1;
+        }
+        result
+    }
+
+    // The ray continues behind actors without the flags, just past the hit
+    // as the interval is closed
+    fn get_flagged_hit(&self, ray: &Ray<T>, interval: &Interval<T>, layer_mask: u32, seen_by: ActorFlags, counts: &mut TraversalCounts) -> Option<(&Actor<T>, Hit<T>)> {
+        let mut interval = *interval;
+        loop {
+            let (actor, hit) = self.tree.get_hit_counted(ray, &interval, layer_mask, counts)?;
+            if actor.get_flags().contains(seen_by) {
+                return Some((actor, hit));
+            }
+            interval.min = hit.t + hit.t.abs().max(T::one()) * T::epsilon() * T::from_f64(4.0);
+        }
+    }
+
+    // When entering, the medium on the other side of the surface is the
+    // current one, when leaving it's the one below on the stack
+    fn outside_index(media: &[T], hit: &Hit<T>) -> T {
+        let n_outside = match hit.front_face {
+            true => media.last(),
+            false => media.len().checked_sub(2).map(|i| &media[i])
+        };
+        n_outside.copied().unwrap_or_else(T::one)
+    }
+
+    // Refractive index of the medium whose surface the scattered ray crosses,
+    // None if it stays on the same side (reflections) or isn't a medium
+    fn crossed_index(actor: &Actor<T>, hit: &Hit<T>, ray_out: &Ray<T>) -> Option<T> {
+        let n = actor.get_material().get_refractive_index_at(ray_out.get_wavelength())?;
+        if ray_out.get_direction().dot(&hit.normal) < T::zero() {
+            Some(n)
+        } else {
+            None
+        }
+    }
+
+    // True if anything in the layers blocks the ray within the interval, i.e.
+    // a shadow ray from a surface point limited to the distance of the light
+    pub fn is_occluded(&self, ray: &Ray<T>, interval: &Interval<T>, layer_mask: u32) -> bool {
+        self.get_flagged_hit(ray, interval, layer_mask, ActorFlags::CASTS_SHADOWS, &mut TraversalCounts::default()).is_some()
+    }
+
+    // True if something blocks the segment from the hit to the point, i.e.
+    // the hit is in the shadow of a point light there
+    pub fn is_shadowed(&self, hit: &Hit<T>, point: &Vec3<T>, layer_mask: u32) -> bool {
+        let mut direction = point - &hit.point;
+        let distance = direction.norm();
+        direction.normalize();
+        let ray = self.offset_ray(hit, Ray::from_vec(Vec3::from_slice(hit.point.get_data()), direction));
+        let interval = Interval::new(self.ray_interval().min, distance);
+        self.get_flagged_hit(&ray, &interval, layer_mask, ActorFlags::CASTS_SHADOWS, &mut TraversalCounts::default()).is_some()
+    }
+
+    // True if the hit is on a light or sees the center of one, i.e. for a
+    // shadow mask of the scene's own lights. Without lights all is lit.
+    pub fn sees_light(&self, scene_hit: &SceneHit<'_, T>, layer_mask: u32) -> bool {
+        if self.lights.is_empty() || self.lights.contains(&scene_hit.actor_id) {
+            return true;
+        }
+        let hit = &scene_hit.hit;
+        self.lights.iter().any(|light| {
+            let bounds = self.actors[*light].hitable.get_bounds();
+            let center = (bounds.get_p0() + bounds.get_p1()) * T::from_f64(0.5);
+            let mut direction = &center - &hit.point;
+            direction.normalize();
+            let ray = self.offset_ray(hit, Ray::from_vec(Vec3::from_slice(hit.point.get_data()), direction));
+            match self.get_flagged_hit(&ray, &self.ray_interval(), layer_mask, ActorFlags::CASTS_SHADOWS, &mut TraversalCounts::default()) {
+                Some((actor, _)) => std::ptr::eq(actor, self.actors[*light].as_ref()),
+                None => false
+            }
+        })
+    }
+
+    // Traces n lines through the bounding sphere of the scene, from points
+    // stratified over it into cosine distributed directions (uniform lines),
+    // and checks the first hit of each and the material there. The work of
+    // the tree is counted into the stats, see Renderer::warm_up_scene.
+    pub fn warm_up(&self, n: usize, layer_mask: u32, rng: &mut dyn RngCore) -> Result<SceneStats, Error> {
+        let (p0, p1) = (self.bounds.get_p0(), self.bounds.get_p1());
+        if p0.get_data().iter().chain(p1.get_data()).any(|x| !x.is_finite()) {
+            return Err(Error::InvalidScene("has bounds that aren't finite"));
+        }
+        let center = self.bounds.centroid();
+        // Start a bit outside of the sphere
+        let radius = (p1 - p0).norm() * T::from_f64(0.5 * 1.01) + self.ray_epsilon;
+        let two_pi = T::from_f64(2.0 * std::f64::consts::PI);
+        let strata = (n as f64).sqrt().ceil().max(1.0) as usize;
+        let size = T::from_f64(strata as f64);
+
+        let mut stats = SceneStats::default();
+        sampling::seed_thread_rng(rng.next_u64());
+        for k in 0..n {
+            let (a, b) = (T::from_f64((k % strata) as f64), T::from_f64((k / strata) as f64));
+            let z = T::one() - T::from_f64(2.0) * (a + uniform::<T, dyn RngCore>(rng)) / size;
+            let phi = two_pi * (b + uniform::<T, dyn RngCore>(rng)) / size;
+            let r = (T::one() - z * z).max(T::zero()).sqrt();
+            let outward = Vec3::from_array([phi.cos() * r, phi.sin() * r, z]);
+            let direction = cosine_hemisphere::<T, dyn RngCore>(rng, &(&outward * (-T::one())));
+            let ray = Ray::from_vec(&center + &outward * radius, direction);
+
+            stats.primary_rays += 1;
+            // Every actor counts, whatever rays it's hidden from
+            let (actor, hit) = match self.get_hit_stats(&ray, &self.ray_interval(), layer_mask, ActorFlags::empty(), Some(&mut stats)) {
+                Some(current_hit) => current_hit,
+                None => continue
+            };
+            let finite = |v: &Vec3<T>| v.get_data().iter().all(|x| x.is_finite());
+            if !hit.t.is_finite() || !finite(&hit.point) || !finite(&hit.normal) || hit.normal.norm() == T::zero() {
+                return Err(Error::InvalidScene("has a hit that isn't finite"));
+            }
+            let scatter = actor.get_material().scatter(&ray, &hit, rng);
+            if !finite(&scatter.attenuation) {
+                return Err(Error::InvalidScene("has a material with a color that isn't finite"));
+            }
+        }
+        Ok(stats)
+    }
+
+    pub fn get_ray_epsilon(&self) -> T {
+        self.ray_epsilon
+    }
+
+    // Hits closer than epsilon to the ray origin are ignored, so that rays
+    // leaving a surface don't hit it again due to round-off (surface acne).
+    // Large scenes and f32 need a larger epsilon.
+    pub fn set_ray_epsilon(&mut self, ray_epsilon: T) {
+        self.ray_epsilon = ray_epsilon;
+    }
+
+    pub fn get_self_intersect_policy(&self) -> SelfIntersectPolicy {
+        self.self_intersect_policy
+    }
+
+    pub fn set_self_intersect_policy(&mut self, policy: SelfIntersectPolicy) {
+        self.self_intersect_policy = policy;
+    }
+
+    // Valid ray parameters
+    fn ray_interval(&self) -> Interval<T> {
+        let t_min = match self.self_intersect_policy {
+            SelfIntersectPolicy::NormalOffset => T::zero(),
+            SelfIntersectPolicy::Epsilon | SelfIntersectPolicy::Both => self.ray_epsilon
+        };
+        Interval::new(t_min, T::from_f64(10000000000.0))
+    }
+
+    // Moves the origin of a ray scattered at the hit to the side of the
+    // surface the ray is going to, if the policy asks for it
+    fn offset_ray(&self, hit: &Hit<T>, ray: Ray<T>) -> Ray<T> {
+        if self.self_intersect_policy == SelfIntersectPolicy::Epsilon {
+            return ray;
+        }
+        // The round-off of the hit point is relative to its coordinates
+        let mut scale = T::one();
+        for x in ray.get_origin().get_data().iter() {
+            scale = scale.max(x.abs());
+        }
+        let offset = T::epsilon() * T::from_f64(256.0) * scale;
+
+        let normal = hit.get_outward_normal();
+        let side = if ray.get_direction().dot(&normal) >= T::zero() { T::one() } else { -T::one() };
+        let origin = ray.get_origin() + &normal * (offset * side);
+        ray.with_origin(origin)
+    }
+
+    fn survival_probability(attenuation: &Vec3<T>) -> T {
+        let min_probability = T::from_f64(0.05);
+        let mut probability = min_probability;
+        for value in attenuation.get_data() {
+            if *value > probability {
+                probability = *value;
+            }
+        }
+        if probability > T::one() {
+            probability = T::one();
+        }
+        probability
+    }
+
+    pub fn get_time(&self) -> T {
+        self.time
+    }
+
+    // Moves the animated actors to their placement at the given time
+    // and rebuilds the tree, call it before rendering each frame. Without
+    // animated actors the tree stays as it is.
+    pub fn set_time(&mut self, time: T) {
+        self.time = time;
+        if !self.actors.iter().any(|actor| actor.hitable.is_animated()) {
+            return;
+        }
+        // The tree shares the actors, drop it to be able to change them
+        self.tree = Box::new(LinearTree::new());
+        let mut moved = false;
+        for actor in self.actors.iter_mut() {
+            moved |= Arc::get_mut(actor).unwrap().hitable.set_time(time);
+        }
+        if moved {
+            // The actors may have left parts of the old bounds empty
+            self.bounds = BoundingBox::new(Vec3::new(), Vec3::new());
+            for actor in self.actors.iter() {
+                if let Some(bounds) = actor.hitable.bounding_box() {
+                    let _expanded = self.bounds.expand(&bounds);
+                }
             }
         }
+        self.rebuild_tree();
+    }
+
+    // Rays traced by get_color and the renderer since the scene was created
+    // or the counters reset, including the shadow rays. Divided by the
+    // render time this gives the rays per second.
+    pub fn ray_count(&self) -> u64 {
+        self.ray_count.load(Ordering::Relaxed)
+    }
+
+    pub fn reset_counters(&self) {
+        self.ray_count.store(0, Ordering::Relaxed);
+    }
+
+    // Number of times the tree was built from scratch, adding actors
+    // to an existing tree doesn't count
+    pub fn get_tree_builds(&self) -> usize {
+        self.tree_builds
+    }
+
+    // Total time spent building trees while timing was enabled
+    pub fn get_tree_build_time(&self) -> Duration {
+        self.tree_build_time
+    }
+
+    pub fn get_timing(&self) -> bool {
+        self.timing
+    }
+
+    pub fn set_timing(&mut self, timing: bool) {
+        self.timing = timing;
     }
 
     pub fn set_tree_type(&mut self, tree_type: TreeType) {
@@ -83,6 +956,7 @@ impl<T> Scene<T>
     }
 
     fn rebuild_tree(&mut self) {
+        let start = if self.timing { Some(Instant::now()) } else { None };
         let mut tree: Box<dyn Tree<T>> = match self.tree_type {
             TreeType::Linear => {
                 Box::new(LinearTree::new())
@@ -97,19 +971,566 @@ impl<T> Scene<T>
                 );
                 tree_bounds.make_cube();
                 let length = tree_bounds.get_axis_length(0);
-                let pad = length * T::from(0.1).unwrap();
+                let pad = length * T::from_f64(0.1);
                 for i in 0..3 {
                     tree_bounds.pad_axis(pad, i);
                 }
                 Box::new(Octree::<T>::new(tree_bounds))
             }
+            #[cfg(feature = "embree")]
+            TreeType::Embree => {
+                Box::new(EmbreeTree::new())
+            }
         };
 
         for i in 0..self.actors.len() {
-            let actor = Rc::clone(&self.actors[i]);
-            tree.add_actor(actor);
+            if self.actors[i].is_visible() {
+                let actor = Arc::clone(&self.actors[i]);
+                tree.add_actor(actor);
+            }
         }
 
         self.tree = tree;
+        self.tree_builds += 1;
+        if let Some(start) = start {
+            self.tree_build_time += start.elapsed();
+        }
+    }
+}
+
+// The copy has its own actors and tree, changing the actors or the materials
+// of one scene leaves the other as it is
+impl<T> Clone for Scene<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        let mut scene = Scene::new();
+        scene.set_background(Vec3::from_slice(self.background.get_data()));
+        for actor in self.actors.iter() {
+            scene.add_actor(actor.as_ref().clone());
+        }
+        scene.bounds = self.bounds.clone();
+        scene.set_tree_type(self.tree_type);
+        scene.set_ray_epsilon(self.ray_epsilon);
+        scene.time = self.time;
+        scene.set_self_intersect_policy(self.self_intersect_policy);
+        scene.set_timing(self.timing);
+        scene.lights = self.lights.clone();
+        scene.caustics = self.caustics.clone();
+        scene.environment = self.environment.clone();
+        scene.portals = self.portals.clone();
+        scene
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for Scene<T>
+    where T: Float, U: Precision
+{
+    type Output = Scene<U>;
+
+    fn convert_precision(&self) -> Result<Scene<U>, PrecisionError> {
+        let mut scene = Scene::<U>::new();
+        scene.set_background(self.background.convert());
+        for actor in self.actors.iter() {
+            scene.add_actor(actor.convert_precision()?);
+        }
+        scene.set_tree_type(self.tree_type);
+        scene.set_ray_epsilon(convert(self.ray_epsilon));
+        scene.time = convert(self.time);
+        scene.set_self_intersect_policy(self.self_intersect_policy);
+        scene.set_timing(self.timing);
+        // The caustics need to be built again
+        scene.lights = self.lights.clone();
+        scene.environment = self.environment.as_ref().map(|environment| environment.convert());
+        for portal in self.portals.iter() {
+            scene.portals.push(portal.convert_precision()?);
+        }
+        Ok(scene)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::hitable::Hitable;
+    use crate::hitable::primitive::{Sphere, Portal};
+    use crate::hitable::transform::Translation;
+    use crate::actor::ActorBuilder;
+    use crate::constants::Axis;
+    use crate::camera::Camera;
+    use crate::camera::perspective::PerspectiveCamera;
+    use crate::material::metal::MetalMaterial;
+    use crate::material::plain::PlainMaterial;
+    use crate::material::null::NullMaterial;
+    use crate::texture::uniform::UniformTexture;
+
+    fn mirror_ball() -> Scene<f64> {
+        // The rays bounce forever inside the sphere, each time halving the color
+        let mut scene = Scene::<f64>::new();
+        let hitable = Box::new(Sphere::new(10.0));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        let material = Box::new(MetalMaterial::new(texture, 0.0));
+        scene.add_actor(Actor::new(hitable, material));
+        scene
+    }
+
+    #[test]
+    fn russian_roulette() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let scene = mirror_ball();
+        let ray = Ray::from_array([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        let max_reflection = 3;
+        let expected = 0.5f64.powi(max_reflection as i32 + 1);
+
+        let color = scene.get_color(&ray, 0, max_reflection, max_reflection, &mut rng);
+        assert!((color.get_data()[0] - expected).abs() < 1e-12);
+
+        let n = 20000;
+        let mut sum = 0.0;
+        for _i in 0..n {
+            sum += scene.get_color(&ray, 0, 0, max_reflection, &mut rng).get_data()[0];
+        }
+        let mean = sum / n as f64;
+        assert!((mean - expected).abs() < 0.006);
+    }
+
+    #[test]
+    fn ray_count() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let scene = mirror_ball();
+        let ray = Ray::from_array([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        assert_eq!(scene.ray_count(), 0);
+        // No russian roulette, the camera ray and three reflections
+        scene.get_color(&ray, 0, 3, 3, &mut rng);
+        scene.get_color(&ray, 0, 3, 3, &mut rng);
+        assert_eq!(scene.ray_count(), 8);
+        scene.reset_counters();
+        assert_eq!(scene.ray_count(), 0);
+    }
+
+    #[test]
+    fn occluded() {
+        let mut scene = Scene::<f64>::new();
+        let hitable = Box::new(Sphere::new(1.0));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        let material = Box::new(MetalMaterial::new(texture, 0.0));
+        let mut actor = Actor::new(hitable, material);
+        actor.set_layer(2);
+        scene.add_actor(actor);
+
+        // The sphere is between 4 and 6 units away
+        let ray = Ray::from_array([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        assert!(scene.is_occluded(&ray, &Interval::new(0.0, 10.0), ALL_LAYERS));
+        assert!(scene.is_occluded(&ray, &Interval::new(5.0, 10.0), ALL_LAYERS));
+        assert!(!scene.is_occluded(&ray, &Interval::new(0.0, 3.5), ALL_LAYERS));
+        assert!(!scene.is_occluded(&ray, &Interval::new(6.5, 10.0), ALL_LAYERS));
+
+        // Only blockers in the layers of the mask count
+        assert!(scene.is_occluded(&ray, &Interval::new(0.0, 10.0), 2));
+        assert!(!scene.is_occluded(&ray, &Interval::new(0.0, 10.0), 1));
+    }
+
+    #[test]
+    fn cast_ray() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut scene = mirror_ball();
+        let hitable = Box::new(Sphere::new(1.0));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.1, 0.2, 0.3])));
+        let material = Box::new(PlainMaterial::new(texture));
+        let actor_id = scene.add_actor(Actor::new(hitable, material));
+        assert_eq!(actor_id, 1);
+
+        // From the inside of the mirror ball the small sphere is hit first
+        let ray = Ray::from_array([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        let result = scene.cast_ray(&ray).unwrap();
+        assert_eq!(result.hit.t, 4.0);
+        assert_eq!(result.hit.point.get_data(), [-1.0, 0.0, 0.0]);
+        assert_eq!(result.actor_id, 1);
+        let color = result.get_material().scatter(&ray, &result.hit, &mut rng).attenuation;
+        assert_eq!(color.get_data(), [0.1, 0.2, 0.3]);
+
+        let ray = Ray::from_array([-5.0, 5.0, 0.0], [1.0, 0.0, 0.0]);
+        let result = scene.cast_ray(&ray).unwrap();
+        assert!((result.hit.point.norm() - 10.0).abs() < 1e-12);
+        assert_eq!(result.actor_id, 0);
+        assert!(std::ptr::eq(result.actor, scene.get_actor(0).unwrap()));
+
+        let ray = Ray::from_array([-20.0, 0.0, 0.0], [-1.0, 0.0, 0.0]);
+        assert!(scene.cast_ray(&ray).is_none());
+    }
+
+    #[test]
+    fn max_actors() {
+        let mut scene = mirror_ball();
+        scene.set_max_actors(100);
+        assert!(scene.actors.capacity() >= 100);
+        assert!(scene.actor_ids.capacity() >= 100);
+        let capacity = scene.actors.capacity();
+        for _i in 0..99 {
+            scene.add_actor(Actor::new(Box::new(Sphere::new(1.0)), Box::new(NullMaterial::new())));
+        }
+        assert_eq!(scene.actors.len(), 100);
+        assert_eq!(scene.actors.capacity(), capacity);
+
+        // Smaller than the current number of actors
+        scene.set_max_actors(10);
+        assert_eq!(scene.actors.capacity(), capacity);
+    }
+
+    #[test]
+    fn picking() {
+        let mut scene = Scene::<f64>::new();
+        let mut ids = vec![];
+        for x in [-2.0, 2.0].iter() {
+            let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::new(1.0));
+            let hitable = Box::new(Translation::new(hitable, Vec3::from_array([*x, 0.0, -5.0])));
+            let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+            let material = Box::new(PlainMaterial::new(texture));
+            ids.push(scene.add_actor(Actor::new(hitable, material)));
+        }
+        scene.set_tree_type(TreeType::Oct);
+
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 0.0]);
+        camera.set_direction(&[0.0, 0.0, -1.0]);
+
+        // Pick the pixels below the sphere centers
+        let (r, _s) = camera.project(&Vec3::from_array([-2.0, 0.0, -5.0])).unwrap();
+        let picked = scene.cast_ray(&camera.get_ray(r, 0.0)).unwrap();
+        assert_eq!(picked.actor_id, ids[0]);
+        let picked = scene.cast_ray(&camera.get_ray(-r, 0.0)).unwrap();
+        assert_eq!(picked.actor_id, ids[1]);
+        assert!(scene.cast_ray(&camera.get_ray(0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn visible() {
+        let mut scene = Scene::<f64>::new();
+        let mut ids = vec![];
+        for radius in [1.0, 2.0].iter() {
+            let mut actor = Actor::new(Box::new(Sphere::new(*radius)), Box::new(NullMaterial::new()));
+            actor.set_visible(*radius > 1.5);
+            ids.push(scene.add_actor(actor));
+        }
+
+        let ray = Ray::from_array([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        for tree_type in [TreeType::Linear, TreeType::Binary, TreeType::Oct].iter() {
+            scene.set_tree_type(*tree_type);
+            assert_eq!(scene.cast_ray(&ray).unwrap().actor_id, ids[1]);
+
+            // Hide the large sphere, the small one is still hidden
+            scene.set_actor_visible(ids[1], false);
+            assert!(scene.cast_ray(&ray).is_none());
+            assert!(!scene.get_actor(ids[1]).unwrap().is_visible());
+
+            scene.set_actor_visible(ids[0], true);
+            assert_eq!(scene.cast_ray(&ray).unwrap().actor_id, ids[0]);
+
+            scene.set_actor_visible(ids[0], false);
+            scene.set_actor_visible(ids[1], true);
+        }
+    }
+
+    #[test]
+    fn teleport() {
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.2, 0.2, 0.7]));
+        scene.add_actor(Actor::sphere(1.0).at([10.0, 0.0, 0.0]).emissive([1.0, 0.0, 0.0]));
+        // The exit is relative to the entry, at (6, 0, 0) facing the sphere
+        let portal = Portal::new(Rectangle::new(2.0, Axis::X, 2.0, Axis::Y), Vec3::from_array([6.0, 0.0, 3.0]), Vec3::from_array([1.0, 0.0, 0.0]));
+        scene.add_actor(ActorBuilder::new(portal).at([0.0, 0.0, -3.0]).emissive([0.0, 1.0, 0.0]));
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let ray = Ray::from_array([0.0, 0.0, 0.0], [0.0, 0.0, -1.0]);
+        assert_eq!(scene.get_color(&ray, 0, 4, 4, &mut rng).get_data(), [1.0, 0.0, 0.0]);
+        // Passing the portal takes a reflection
+        assert_eq!(scene.get_color(&ray, 0, 0, 0, &mut rng).get_data(), [0.0, 0.0, 0.0]);
+        // From behind there is no portal
+        let ray = Ray::from_array([0.0, 0.0, -6.0], [0.0, 0.0, 1.0]);
+        assert_eq!(scene.get_color(&ray, 0, 4, 4, &mut rng).get_data(), [0.2, 0.2, 0.7]);
+    }
+
+    #[test]
+    fn actor_flags() {
+        // The light of the cube scene hidden from the camera still lights
+        // the floor, only the pixels seeing the light change
+        let (scene, camera) = crate::scenes::cube_scene(24, 16).unwrap();
+        let light = scene.get_lights()[0];
+        let mut renderer = crate::renderer::Renderer::new(0, 24, 0, 16, 24, 16, 1, 4, false).unwrap();
+        renderer.set_seed(Some(3));
+        let before = renderer.render(&scene, &camera);
+        scene.set_actor_flags(light, ActorFlags::CASTS_SHADOWS | ActorFlags::VISIBLE_IN_REFLECTIONS);
+        let after = renderer.render(&scene, &camera);
+        let mut changed = 0;
+        for (pixel0, pixel1) in before.data.chunks(3).zip(after.data.chunks(3)) {
+            if pixel0 != pixel1 {
+                assert_eq!(pixel0, [2.0, 2.0, 2.0]);
+                assert_eq!(pixel1, [0.2, 0.2, 0.7]);
+                changed += 1;
+            }
+        }
+        assert!(changed > 10 && changed < 24 * 16 / 2);
+
+        // Without shadows the sphere is passed by occlusion tests only
+        let mut scene = Scene::<f64>::new();
+        let id = scene.add_actor(Actor::new(Box::new(Sphere::new(1.0)), Box::new(NullMaterial::new())));
+        let ray = Ray::from_array([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        scene.set_actor_flags(id, ActorFlags::VISIBLE_TO_CAMERA);
+        assert!(!scene.is_occluded(&ray, &Interval::new(0.0, 10.0), ALL_LAYERS));
+        assert_eq!(scene.cast_ray(&ray).unwrap().hit.t, 4.0);
+        scene.set_actor_flags(id, ActorFlags::CASTS_SHADOWS);
+        assert!(scene.is_occluded(&ray, &Interval::new(0.0, 10.0), ALL_LAYERS));
+        assert!(scene.cast_ray(&ray).is_none());
+    }
+
+    #[test]
+    fn set_actor_material() {
+        let mut scene = Scene::<f64>::new();
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.0, 0.0])));
+        let id = scene.add_actor(Actor::new(Box::new(Sphere::new(1.0)), Box::new(PlainMaterial::new(texture))));
+        scene.set_tree_type(TreeType::Binary);
+        let builds = scene.get_tree_builds();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let ray = Ray::from_array([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        assert_eq!(scene.get_color(&ray, 0, usize::MAX, 4, &mut rng).get_data(), [1.0, 0.0, 0.0]);
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.0, 0.0, 1.0])));
+        scene.set_actor_material(id, Box::new(PlainMaterial::new(texture)));
+        assert_eq!(scene.get_color(&ray, 0, usize::MAX, 4, &mut rng).get_data(), [0.0, 0.0, 1.0]);
+        assert_eq!(scene.get_tree_builds(), builds);
+    }
+
+    #[test]
+    fn set_time() {
+        use crate::hitable::transform::KeyframedTransform;
+
+        // Nothing moves, the tree stays
+        let mut scene = Scene::<f64>::new();
+        scene.add_actor(Actor::new(Box::new(Sphere::new(1.0)), Box::new(NullMaterial::new())));
+        let builds = scene.get_tree_builds();
+        scene.set_time(1.0);
+        assert_eq!(scene.get_time(), 1.0);
+        assert_eq!(scene.get_tree_builds(), builds);
+
+        // The bounds follow the sphere there and back
+        let keyframes = vec![(0.0, Vec3::from_array([0.0, 0.0, 0.0])), (1.0, Vec3::from_array([10.0, 0.0, 0.0]))];
+        let hitable = KeyframedTransform::new(Box::new(Sphere::new(1.0)), keyframes).unwrap();
+        scene.add_actor(Actor::new(Box::new(hitable), Box::new(NullMaterial::new())));
+        let builds = scene.get_tree_builds();
+        scene.set_time(1.0);
+        assert_eq!(scene.get_tree_builds(), builds + 1);
+        assert_eq!(scene.bounds.get_p1().get_data(), [11.0, 1.0, 1.0]);
+        scene.set_time(0.0);
+        assert_eq!(scene.bounds.get_p1().get_data(), [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn caustics() {
+        use crate::hitable::primitive::Rectangle;
+        use crate::material::dielectric::DielectricMaterial;
+        use crate::material::lambertian::LambertianMaterial;
+        use crate::constants::Axis;
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut scene = Scene::<f64>::new();
+        let white = || Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+
+        // A glass ball on the floor, under a light
+        let floor: Box<dyn Hitable<f64>> = Box::new(Rectangle::new(10.0, Axis::X, 10.0, Axis::Y));
+        let floor = Box::new(Translation::new(floor, Vec3::from_array([0.0, 0.0, -1.0])));
+        scene.add_actor(Actor::new(floor, Box::new(LambertianMaterial::new(white(), 1.0))));
+        let light: Box<dyn Hitable<f64>> = Box::new(Rectangle::new(2.0, Axis::X, 2.0, Axis::Y));
+        let light = Box::new(Translation::new(light, Vec3::from_array([0.0, 0.0, 5.0])));
+        let light = scene.add_actor(Actor::new(light, Box::new(PlainMaterial::new(white()))));
+
+        // No photons without lights
+        scene.build_caustics(1000, 0.2, &mut rng);
+        assert!(scene.get_caustics().is_none());
+        scene.add_light(light);
+        scene.add_light(light);
+        assert_eq!(scene.get_lights(), [light]);
+        // Without glass the floor is only lit directly, which the paths find
+        scene.build_caustics(1000, 0.2, &mut rng);
+        assert!(scene.get_caustics().unwrap().is_empty());
+
+        scene.add_actor(Actor::new(Box::new(Sphere::new(1.0)), Box::new(DielectricMaterial::new(white(), 1.5))));
+        scene.build_caustics(20000, 0.2, &mut rng);
+        let caustics = scene.get_caustics().unwrap();
+        assert!(!caustics.is_empty());
+        let floor_hit = |x: f64| Hit {
+            point: Vec3::from_array([x, 0.0, -1.0]),
+            normal: Vec3::from_array([0.0, 0.0, 1.0]),
+            t: 1.0, u: 0.0, v: 0.0, front_face: true, barycentric: None, face: None, footprint: None
+        };
+        // Focused under the ball
+        let center = caustics.estimate_irradiance(&floor_hit(0.0)).get_data()[0];
+        let outside = caustics.estimate_irradiance(&floor_hit(3.0)).get_data()[0];
+        assert!(center > 10.0 * outside);
+
+        // Gone in a copy, it needs to be built again
+        let copy: Scene<f32> = scene.convert_precision().unwrap();
+        assert_eq!(copy.get_lights(), [light]);
+        assert!(copy.get_caustics().is_none());
+    }
+
+    #[test]
+    fn bdpt() {
+        use crate::hitable::primitive::Rectangle;
+        use crate::material::lambertian::LambertianMaterial;
+        use crate::constants::Axis;
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut scene = Scene::<f64>::new();
+        let white = || Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+        let floor: Box<dyn Hitable<f64>> = Box::new(Rectangle::new(10.0, Axis::X, 10.0, Axis::Y));
+        let floor = Box::new(Translation::new(floor, Vec3::from_array([0.0, 0.0, -1.0])));
+        scene.add_actor(Actor::new(floor, Box::new(LambertianMaterial::new(white(), 0.8))));
+        let light: Box<dyn Hitable<f64>> = Box::new(Rectangle::new(2.0, Axis::X, 2.0, Axis::Y));
+        let light = Box::new(Translation::new(light, Vec3::from_array([0.0, 0.0, 2.0])));
+        let light = scene.add_actor(Actor::new(light, Box::new(PlainMaterial::new(white()))));
+
+        let ray = Ray::from_array([2.0, 0.0, 0.0], [-1.0, 0.0, -1.0]);
+        let n = 50000;
+        let path = (0..n).map(|_| scene.get_color(&ray, 0, 4, 4, &mut rng).get_data()[0]).sum::<f64>() / n as f64;
+        let mut mean_bdpt = |scene: &Scene<f64>, n: usize| {
+            (0..n).map(|_| scene.get_bdpt_color(&ray, 4, ALL_LAYERS, &mut rng).get_data()[0]).sum::<f64>() / n as f64
+        };
+        // Without lights only the camera paths find the emitter
+        let unconnected = mean_bdpt(&scene, n);
+        scene.add_light(light);
+        let connected = mean_bdpt(&scene, 2000);
+        assert!((unconnected - path).abs() < 0.05 * path);
+        assert!((connected - path).abs() < 0.05 * path);
+
+        // The light itself, seen directly
+        let ray = Ray::from_array([0.0, 0.0, 0.0], [0.0, 0.0, 1.0]);
+        assert_eq!(scene.get_bdpt_color(&ray, 4, ALL_LAYERS, &mut rng).get_data(), [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn nested_media() {
+        use crate::hitable::primitive::Rectangle;
+        use crate::material::dielectric::DielectricMaterial;
+        use crate::constants::Axis;
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut scene = Scene::<f64>::new();
+        let white = || Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+
+        // An air bubble inside a glass ball, placed so that the ray travelling
+        // along the x axis hits it at 60 degrees, beyond the critical angle
+        // of glass to air
+        let glass = Box::new(DielectricMaterial::new(white(), 1.5));
+        scene.add_actor(Actor::new(Box::new(Sphere::new(2.0)), glass));
+        let bubble: Box<dyn Hitable<f64>> = Box::new(Sphere::new(0.5));
+        let bubble = Box::new(Translation::new(bubble, Vec3::from_array([0.0, 0.25 * 3.0f64.sqrt(), 0.0])));
+        let air = Box::new(DielectricMaterial::new(white(), 1.0));
+        scene.add_actor(Actor::new(bubble, air));
+
+        // Straight rays end up on the red screen, totally reflected ones on the green one
+        let red: Box<dyn Hitable<f64>> = Box::new(Rectangle::new(10.0, Axis::Y, 10.0, Axis::Z));
+        let red = Box::new(Translation::new(red, Vec3::from_array([10.0, 0.0, 0.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.0, 0.0])));
+        scene.add_actor(Actor::new(red, Box::new(PlainMaterial::new(texture))));
+        let green: Box<dyn Hitable<f64>> = Box::new(Rectangle::new(100.0, Axis::X, 100.0, Axis::Z));
+        let green = Box::new(Translation::new(green, Vec3::from_array([0.0, -10.0, 0.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.0, 1.0, 0.0])));
+        scene.add_actor(Actor::new(green, Box::new(PlainMaterial::new(texture))));
+
+        let ray = Ray::from_array([-10.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        let n = 1000;
+        let mut sum = Vec3::new();
+        for _i in 0..n {
+            sum = sum + scene.get_color(&ray, 0, usize::MAX, 10, &mut rng);
+        }
+        let mean = sum / n as f64;
+        assert!(mean.get_data()[1] > 0.9);
+        assert!(mean.get_data()[0] < 0.1);
+    }
+
+    #[test]
+    fn layers() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.0, 0.0, 1.0]));
+        for (i, x) in [0.0, 5.0].iter().enumerate() {
+            let hitable: Box<dyn Hitable<f64>> = Box::new(Sphere::new(1.0));
+            let hitable = Box::new(Translation::new(hitable, Vec3::from_array([*x, 0.0, 0.0])));
+            let mut color = Vec3::new();
+            color.get_data_mut()[i] = 1.0;
+            let material = Box::new(PlainMaterial::new(Box::new(UniformTexture::new(color))));
+            let mut actor = Actor::new(hitable, material);
+            actor.set_layer(1 << i);
+            scene.add_actor(actor);
+        }
+
+        let ray = Ray::from_array([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        assert_eq!(scene.get_color(&ray, 0, 10, 10, &mut rng).get_data(), [1.0, 0.0, 0.0]);
+        assert_eq!(scene.get_layer_color(&ray, 0, 10, 10, 0b11, &mut rng).get_data(), [1.0, 0.0, 0.0]);
+        assert_eq!(scene.get_layer_color(&ray, 0, 10, 10, 0b10, &mut rng).get_data(), [0.0, 1.0, 0.0]);
+        assert_eq!(scene.get_layer_color(&ray, 0, 10, 10, 0, &mut rng).get_data(), [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn trace_path() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // Back and forth through the center of the mirror ball
+        let scene = mirror_ball();
+        let points = scene.trace_path(Vec3::new(), Vec3::from_array([2.0, 0.0, 0.0]), 3, &mut rng);
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].get_data(), [10.0, 0.0, 0.0]);
+        assert_eq!(points[1].get_data(), [-10.0, 0.0, 0.0]);
+        assert_eq!(points[2].get_data(), [10.0, 0.0, 0.0]);
+
+        // Lights end the path, as does leaving the scene
+        let mut scene = Scene::<f64>::new();
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+        scene.add_actor(Actor::new(Box::new(Sphere::new(1.0)), Box::new(PlainMaterial::new(texture))));
+        let points = scene.trace_path(Vec3::from_array([-5.0, 0.0, 0.0]), Vec3::new(), 10, &mut rng);
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].get_data(), [-1.0, 0.0, 0.0]);
+        assert!(scene.trace_path(Vec3::from_array([-5.0, 0.0, 0.0]), Vec3::from_array([-6.0, 0.0, 0.0]), 10, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn seeded() {
+        use crate::material::lambertian::LambertianMaterial;
+
+        let mut scene = Scene::<f64>::new();
+        scene.set_background(Vec3::from_array([0.5, 0.7, 1.0]));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.8, 0.3, 0.3])));
+        scene.add_actor(Actor::new(Box::new(Sphere::new(1.0)), Box::new(LambertianMaterial::new(texture, 0.5))));
+        // The diffuse ground makes the paths bounce between the two spheres
+        let ground: Box<dyn Hitable<f64>> = Box::new(Sphere::new(100.0));
+        let ground = Box::new(Translation::new(ground, Vec3::from_array([0.0, -101.0, 0.0])));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        scene.add_actor(Actor::new(ground, Box::new(LambertianMaterial::new(texture, 0.5))));
+        let mut camera = PerspectiveCamera::<f64>::new();
+        camera.set_position(&[0.0, 0.0, 3.0]);
+        camera.set_lookat(&[0.0, 0.0, 0.0]);
+
+        // The same seed gives the same paths
+        let colors = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..100).map(|i| {
+                let ray = camera.get_ray(0.01 * i as f64 - 0.5, -0.3);
+                scene.get_color(&ray, 0, usize::MAX, 10, &mut rng)
+            }).collect::<Vec<_>>()
+        };
+        let a = colors(1);
+        let b = colors(1);
+        let c = colors(2);
+        assert!(a.iter().zip(b.iter()).all(|(x, y)| x.get_data() == y.get_data()));
+        assert!(a.iter().zip(c.iter()).any(|(x, y)| x.get_data() != y.get_data()));
+    }
+
+    #[test]
+    fn assert_sync() {
+        // Needed to render a scene from several threads
+        fn check<T: Send + Sync>() {}
+        check::<Scene<f64>>();
+        check::<Scene<f32>>();
     }
 }
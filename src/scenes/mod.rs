@@ -0,0 +1,535 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::vector::Vec3;
+use crate::constants::Axis;
+use crate::hitable::Hitable;
+use crate::hitable::primitive::{Sphere, Rectangle, Cube, Group, Wedge};
+use crate::hitable::transform::Translation;
+use crate::material::Material;
+use crate::material::plain::PlainMaterial;
+use crate::material::lambertian::LambertianMaterial;
+use crate::material::metal::MetalMaterial;
+use crate::material::dielectric::{DielectricMaterial, Dispersion};
+use crate::texture::uniform::UniformTexture;
+use crate::texture::checker::CheckerTexture;
+use crate::texture::image::ImageTexture;
+use crate::renderer::Image;
+use crate::actor::{Actor, ActorBuilder};
+use crate::scene::Scene;
+use crate::tree::TreeType;
+use crate::camera::Camera;
+use crate::camera::perspective::PerspectiveCamera;
+use crate::error::Error;
+
+// Demo scenes, shared by the tests and the raytrace binary. The camera
+// aspect ratio matches an image of width x height pixels.
+
+pub const NAMES: [&str; 6] = ["rectangle_room", "cube_scene", "sphere_in_box", "random_scene", "doorway_room", "prism"];
+
+pub type SceneResult = Result<(Scene<f64>, PerspectiveCamera<f64>), Error>;
+
+// The function creating the scene with the given name, from the image width and height
+pub fn by_name(name: &str) -> Option<fn(usize, usize) -> SceneResult> {
+    match name {
+        "rectangle_room" => Some(rectangle_room),
+        "cube_scene" => Some(cube_scene),
+        "sphere_in_box" => Some(sphere_in_box),
+        "random_scene" => Some(|width, height| random_scene(width, height, 0)),
+        "doorway_room" => Some(doorway_room),
+        "prism" => Some(prism),
+        _ => None
+    }
+}
+
+fn rectangle_room_actors(length: f64, width: f64, height: f64, light: f64) -> Vec<Actor<f64>> {
+    let mut actors = vec![];
+
+    let dimming = 1.0;
+
+    // Cube used as light
+    let hitable = Box::new(Cube::new(light, light, 0.125 * light));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, width / 4.0, height / 2.0])));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+    let material = Box::new(PlainMaterial::<f64>::new(texture));
+    actors.push(Actor::<f64>::new(hitable, material));
+
+    // Rectangle used as floor
+    let hitable = Box::new(Rectangle::new(length, Axis::X, width, Axis::Y));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -height / 2.0])));
+    let texture0 = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+    let texture1 = Box::new(UniformTexture::new(Vec3::from_array([0.8, 0.8, 0.8])));
+    let mut texture = Box::new(CheckerTexture::new(texture0, texture1));
+    texture.set_period(Vec3::from_array([length / 8.0, length / 8.0, 1.0]));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, dimming));
+    actors.push(Actor::<f64>::new(hitable, material));
+
+    // Rectangle used as front wall
+    let rectangle = Box::new(Rectangle::new(length, Axis::X, height, Axis::Z));
+    let rectangle = Box::new(Translation::new(rectangle, Vec3::from_array([0.0, width / 2.0, 0.0])));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, dimming));
+    actors.push(Actor::<f64>::new(rectangle, material));
+
+    // Rectangle used as left wall
+    let rectangle = Box::new(Rectangle::new(width, Axis::Y, height, Axis::Z));
+    let rectangle = Box::new(Translation::new(rectangle, Vec3::from_array([-length / 2.0, 0.0, 0.0])));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([0.1, 1.0, 0.1])));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, dimming));
+    actors.push(Actor::<f64>::new(rectangle, material));
+
+    // Rectangle used as right wall
+    let rectangle = Box::new(Rectangle::new(height, Axis::Z, width, Axis::Y));
+    let rectangle = Box::new(Translation::new(rectangle, Vec3::from_array([length / 2.0, 0.0, 0.0])));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.1, 0.1])));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, dimming));
+    actors.push(Actor::<f64>::new(rectangle, material));
+
+    // Rectangle used as ceiling
+    let rectangle = Box::new(Rectangle::new(width, Axis::Y, length, Axis::X));
+    let rectangle = Box::new(Translation::new(rectangle, Vec3::from_array([0.0, 0.0, height / 2.0])));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, dimming));
+    actors.push(Actor::<f64>::new(rectangle, material));
+
+    actors
+}
+
+// Floor, ceiling, left and right walls of a box, open at the front and at the back
+pub fn cube_box(length: f64, width: f64, height: f64, thickness: f64) -> Box<Group<f64>> {
+    let mut group : Box<Group<f64>> = Box::new(Group::<f64>::new());
+
+    // cube used as floor
+    let hitable = Box::new(Cube::new(length, width, thickness));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -height / 2.0])));
+    group.add_hitable(hitable);
+
+    // cube used as ceiling
+    let hitable = Box::new(Cube::new(length, width, thickness));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, height / 2.0])));
+    group.add_hitable(hitable);
+
+    // cube used as left wall
+    let hitable = Box::new(Cube::new(thickness, width, height));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([- length / 2.0, 0.0, 0.0])));
+    group.add_hitable(hitable);
+
+    // cube used as right wall
+    let hitable = Box::new(Cube::new(thickness, width, height));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([length / 2.0, 0.0, 0.0])));
+    group.add_hitable(hitable);
+
+    group
+}
+
+fn aspect(width: usize, height: usize) -> f64 {
+    width as f64 / height as f64
+}
+
+pub fn rectangle_room(width: usize, height: usize) -> SceneResult {
+    let room_length = 16.0;
+    let room_width = 16.0;
+    let room_height = 9.0;
+
+    let mut scene = Scene::<f64>::new();
+    // The light is the first of the room actors, added last
+    let mut light = 0;
+    for actor in rectangle_room_actors(room_length, room_width, room_height, 6.5).into_iter().rev() {
+        light = scene.add_actor(actor);
+    }
+    scene.add_light(light);
+
+    let box_size = 4.0;
+    let box_thickness = 0.05 * box_size;
+    let box_center = [- 0.3 * room_length, 0.3 * room_width, - 0.5 * room_height + 0.5 * box_size];
+    let hitable = cube_box(box_size, box_size, box_size, box_thickness);
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array(box_center)));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([0.2, 0.2, 1.0])));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, 1.0));
+    scene.add_actor(Actor::new(hitable, material));
+
+    // cubes used as front and back glass walls
+    for side in [-1.0, 1.0].iter() {
+        let hitable = Box::new(Cube::new(box_size, box_thickness, box_size));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, side * box_size / 2.0, 0.0])));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array(box_center)));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+        let material = Box::new(DielectricMaterial::<f64>::new(texture, 1.6));
+        scene.add_actor(Actor::new(hitable, material));
+    }
+
+    let hitable = Box::new(Sphere::new(1.0));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array(box_center)));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.2, 0.2])));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, 1.0));
+    scene.add_actor(Actor::new(hitable, material));
+
+    // Large glass sphere in the front
+    let sphere_size = 3.0;
+    let hitable = Box::new(Sphere::new(sphere_size));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.1 * room_width, - 0.5 * room_height + sphere_size])));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+    let material = Box::new(DielectricMaterial::<f64>::new(texture, 2.4));
+    scene.add_actor(Actor::new(hitable, material));
+
+    // Large metal sphere in the front
+    let sphere_size = 2.0;
+    let hitable = Box::new(Sphere::new(sphere_size));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.3 * room_length, 0.3 * room_width, - 0.5 * room_height + sphere_size])));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([0.9, 0.9, 0.9])));
+    let material = Box::new(MetalMaterial::<f64>::new(texture, 0.0));
+    scene.add_actor(Actor::new(hitable, material));
+
+    scene.set_tree_type(TreeType::Oct);
+
+    let mut camera = PerspectiveCamera::<f64>::new();
+    camera.set_aspect(aspect(width, height))?;
+    camera.set_position(&[0.0, - 0.49 * room_width, 0.0]);
+    camera.set_direction(&[0.0, 1.0, 0.0]);
+    camera.set_up(&[0.0, 0.0, 1.0]);
+    camera.set_fov(0.3 * std::f64::consts::PI)?;
+    camera.set_focus(1.0)?;
+
+    Ok((scene, camera))
+}
+
+pub fn cube_scene(width: usize, height: usize) -> SceneResult {
+    let mut scene = Scene::<f64>::new();
+    scene.set_background(Vec3::from_array([0.2, 0.2, 0.7]));
+
+    let room_size = 15.0;
+    let light_size = 2.0 * room_size / 3.0;
+
+    // Rectangle used as floor
+    let texture0 = Box::new(UniformTexture::new(Vec3::from_array([0.9, 0.9, 0.9])));
+    let texture1 = Box::new(UniformTexture::new(Vec3::from_array([0.75, 0.75, 0.75])));
+    let texture = Box::new(CheckerTexture::new(texture0, texture1));
+    scene.add_actor(Actor::rectangle(room_size, Axis::X, room_size, Axis::Y)
+        .at([0.0, 0.0, -room_size / 2.0])
+        .material(LambertianMaterial::new(texture, 0.65)));
+
+    // Box on the floor
+    scene.add_actor(Actor::cube(6.0, 3.0, 5.0)
+        .at([4.0, room_size / 3.0, -room_size / 2.0])
+        .lambertian([0.0, 1.0, 0.0], 0.65));
+
+    // Rectangle used as light
+    let light = scene.add_actor(Actor::rectangle(light_size, Axis::X, light_size, Axis::Y)
+        .at([0.0, 0.0, room_size / 2.0])
+        .emissive([2.0, 2.0, 2.0]));
+    scene.add_light(light);
+
+    scene.set_tree_type(TreeType::Oct);
+
+    let mut camera = PerspectiveCamera::<f64>::new();
+    camera.set_aspect(aspect(width, height))?;
+    camera.set_position(&[0.0, - 0.5 * room_size, 0.0]);
+    camera.set_direction(&[0.0, 1.0, 0.0]);
+    camera.set_up(&[0.0, 0.0, 1.0]);
+    camera.set_fov(0.4 * std::f64::consts::PI)?;
+    camera.set_focus(1.0)?;
+
+    Ok((scene, camera))
+}
+
+pub fn sphere_in_box(width: usize, height: usize) -> SceneResult {
+    let mut scene = Scene::<f64>::new();
+    scene.set_background(Vec3::from_array([0.2, 0.2, 0.8]));
+
+    let box_size = 5.0;
+    let box_thickness = 0.05 * box_size;
+    let hitable = cube_box(box_size, box_size, box_size, box_thickness);
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([0.9, 0.9, 0.9])));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, 0.75));
+    scene.add_actor(Actor::new(hitable, material));
+
+    // cube used as front glass wall
+    let hitable = Box::new(Cube::new(box_size, box_thickness, box_size));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, - box_size / 2.0, 0.0])));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+    let material = Box::new(DielectricMaterial::<f64>::new(texture, 1.5));
+    scene.add_actor(Actor::new(hitable, material));
+
+    let hitable = Box::new(Sphere::new(1.0));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.2, 0.2])));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, 0.65));
+    scene.add_actor(Actor::new(hitable, material));
+
+    // Spheres used as lights, in front and on the left of the box
+    let sphere_size = 3.0;
+    let offset = - 2.5 * box_size + sphere_size + 0.1;
+    for center in [[0.0, offset, 0.0], [offset, 0.0, 0.0]].iter() {
+        let hitable = Box::new(Sphere::new(sphere_size));
+        let hitable = Box::new(Translation::new(hitable, Vec3::from_array(*center)));
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([2.0, 2.0, 2.0])));
+        let material = Box::new(PlainMaterial::<f64>::new(texture));
+        let light = scene.add_actor(Actor::new(hitable, material));
+        scene.add_light(light);
+    }
+
+    scene.set_tree_type(TreeType::Oct);
+
+    let mut camera = PerspectiveCamera::<f64>::new();
+    camera.set_aspect(aspect(width, height))?;
+    camera.set_position(&[-4.0, - 1.5 * box_size, 0.0]);
+    camera.set_lookat(&[0.0, 0.0, 0.0]);
+    camera.set_up(&[0.0, 0.0, 1.0]);
+    camera.set_fov(0.4 * std::f64::consts::PI)?;
+    camera.set_focus(1.0)?;
+
+    Ok((scene, camera))
+}
+
+// Small spheres and cubes of random materials on a grid, around three larger
+// spheres. The same seed gives the same scene.
+pub fn random_scene(width: usize, height: usize, seed: u64) -> SceneResult {
+    let mut scene = Scene::<f64>::new();
+    scene.set_background(Vec3::from_array([0.5, 0.7, 0.9]));
+
+    const N_SPHERES_X : usize = 20;
+    const N_SPHERES_Y : usize = N_SPHERES_X;
+
+    const MIN_X : f64 = -20.0;
+    const MAX_X : f64 = 20.0;
+
+    const MIN_Y : f64 = MIN_X;
+    const MAX_Y : f64 = MAX_X;
+
+    const MIN_RADIUS : f64 = 0.2;
+    const MAX_RADIUS : f64 = 0.4;
+
+    const SPHERE_PROBABILITY : f64 = 0.66666666;
+
+    const LAMBERTIAN_PROBABILITY : f64 = 0.3333;
+    const METAL_PROBABILITY : f64 = 0.3333;
+    // DIELECTRIC_PROBABILITY is 1 - LAMBERTIAN_PROBABILITY - METAL_PROBABILITY
+
+    const MIN_FUZZINESS : f64 = 0.0;
+    const MAX_FUZZINESS : f64 = 0.4;
+
+    const MIN_REFRACTIVE : f64 = 1.2;
+    const MAX_REFRACTIVE : f64 = 2.4;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    for i in 0..N_SPHERES_X {
+        for j in 0..N_SPHERES_Y {
+            let radius = MIN_RADIUS + (MAX_RADIUS - MIN_RADIUS) * rng.gen::<f64>();
+            let mut x = i as f64 + rng.gen::<f64>() * (1.0 - radius);
+            x = MIN_X + (MAX_X - MIN_X) * x / N_SPHERES_X as f64;
+            let mut y = j as f64 + rng.gen::<f64>() * (1.0 - radius);
+            y = MIN_Y + (MAX_Y - MIN_Y) * y / N_SPHERES_Y as f64;
+
+            let hitable_select = rng.gen::<f64>();
+            let hitable : Box<dyn Hitable<f64>> = if hitable_select < SPHERE_PROBABILITY {
+                let hitable = Box::new(Sphere::<f64>::new(radius));
+                Box::new(Translation::new(hitable, Vec3::from_array([x, y, radius])))
+            } else {
+                let l = radius * 2.0 * 0.8;
+                let hitable = Box::new(Cube::<f64>::new(l, l, l));
+                Box::new(Translation::new(hitable, Vec3::from_array([x, y, radius * 0.8])))
+            };
+
+            let color = Vec3::from_array([rng.gen::<f64>(), rng.gen::<f64>(), rng.gen::<f64>()]);
+            let texture = Box::new(UniformTexture::new(color));
+            let material_select = rng.gen::<f64>();
+            let material : Box<dyn Material<f64>> = if material_select < LAMBERTIAN_PROBABILITY {
+                Box::new(LambertianMaterial::<f64>::new(texture, 0.5))
+            } else if material_select < LAMBERTIAN_PROBABILITY + METAL_PROBABILITY {
+                let fuzziness = MIN_FUZZINESS + (MAX_FUZZINESS - MIN_FUZZINESS) * rng.gen::<f64>();
+                Box::new(MetalMaterial::<f64>::new(texture, fuzziness))
+            } else {
+                let n = MIN_REFRACTIVE + (MAX_REFRACTIVE - MIN_REFRACTIVE) * rng.gen::<f64>();
+                Box::new(DielectricMaterial::<f64>::new(texture, n))
+            };
+            scene.add_actor(Actor::<f64>::new(hitable, material));
+        }
+    }
+
+    // Three larger spheres in the center
+    let radius = 2.0;
+    let sphere = Box::new(Sphere::<f64>::new(radius));
+    let sphere = Translation::new(sphere, Vec3::from_array([0.0, 0.0, radius]));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([0.78, 1.0, 0.78])));
+    scene.add_actor(Actor::<f64>::new(Box::new(sphere), Box::new(DielectricMaterial::<f64>::new(texture, 2.4))));
+
+    let sphere = Box::new(Sphere::<f64>::new(radius));
+    let sphere = Translation::new(sphere, Vec3::from_array([0.0, - 2.0 * radius, radius]));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([0.9, 0.9, 0.9])));
+    scene.add_actor(Actor::<f64>::new(Box::new(sphere), Box::new(MetalMaterial::<f64>::new(texture, 0.0))));
+
+    let sphere = Box::new(Sphere::<f64>::new(radius));
+    let sphere = Translation::new(sphere, Vec3::from_array([0.0, 2.0 * radius, radius]));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.15, 0.15])));
+    scene.add_actor(Actor::<f64>::new(Box::new(sphere), Box::new(MetalMaterial::<f64>::new(texture, 0.1))));
+
+    // Sphere used as light
+    let radius = 4.0;
+    let sphere = Box::new(Sphere::<f64>::new(radius));
+    let sphere = Translation::new(sphere, Vec3::from_array([0.0, 1.0, 12.5]));
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+    let light = scene.add_actor(Actor::<f64>::new(Box::new(sphere), Box::new(PlainMaterial::<f64>::new(texture))));
+    scene.add_light(light);
+
+    // Rectangle used as floor, a checker of unit squares tiled over it. Far
+    // away many squares fall into a pixel, the mipmap blends them to gray.
+    let length = 2000.0;
+    let size = 16;
+    let mut image = Image::new(size, size);
+    for y in 0..size {
+        for x in 0..size {
+            let value = if (2 * x / size + 2 * y / size) % 2 == 0 { 1.0 } else { 0.8 };
+            image.set_pixel(x, y, [value; 3])?;
+        }
+    }
+    let mut texture = ImageTexture::new(image)?;
+    texture.set_repeat(length / 2.0);
+    let texture = Box::new(texture);
+    let hitable = Box::new(Rectangle::<f64>::new(length, Axis::X, length, Axis::Y));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -radius])));
+    let material = Box::new(LambertianMaterial::<f64>::new(texture, 0.75));
+    scene.add_actor(Actor::<f64>::new(hitable, material));
+
+    scene.set_tree_type(TreeType::Oct);
+
+    let mut camera = PerspectiveCamera::<f64>::new();
+    camera.set_aspect(aspect(width, height))?;
+    camera.set_fov(0.25 * std::f64::consts::PI)?;
+    camera.set_position(&[-6.0, -10.0, 3.0]);
+    camera.set_lookat(&[0.0, 0.0, 2.0]);
+    camera.set_up(&[0.0, 0.0, 1.0]);
+    let focus = (camera.get_lookat() - camera.get_position()).norm();
+    camera.set_focus(focus)?;
+
+    Ok((scene, camera))
+}
+
+// A closed room lit only through a doorway by a light outside, out of
+// sight of the camera. Hard for paths from the camera, see Integrator::Bdpt.
+pub fn doorway_room(width: usize, height: usize) -> SceneResult {
+    let room_size = 8.0;
+    let room_height = 4.0;
+    let door_width = 1.5;
+    let door_height = 3.0;
+    let half = room_size / 2.0;
+
+    let mut scene = Scene::<f64>::new();
+    let color = [0.8, 0.8, 0.8];
+
+    // Floor, ceiling and the walls without a door
+    scene.add_actor(Actor::rectangle(room_size, Axis::X, room_size, Axis::Y)
+        .at([0.0, 0.0, -room_height / 2.0])
+        .lambertian(color, 0.8));
+    scene.add_actor(Actor::rectangle(room_size, Axis::X, room_size, Axis::Y)
+        .at([0.0, 0.0, room_height / 2.0])
+        .lambertian(color, 0.8));
+    scene.add_actor(Actor::rectangle(room_size, Axis::X, room_height, Axis::Z)
+        .at([0.0, -half, 0.0])
+        .lambertian(color, 0.8));
+    for side in [-1.0, 1.0].iter() {
+        scene.add_actor(Actor::rectangle(room_size, Axis::Y, room_height, Axis::Z)
+            .at([side * half, 0.0, 0.0])
+            .lambertian(color, 0.8));
+    }
+
+    // The wall with the door, left and right of it and above it
+    let part_width = half - door_width / 2.0;
+    for side in [-1.0, 1.0].iter() {
+        scene.add_actor(Actor::rectangle(part_width, Axis::X, room_height, Axis::Z)
+            .at([side * (half - part_width / 2.0), half, 0.0])
+            .lambertian(color, 0.8));
+    }
+    let lintel = room_height - door_height;
+    scene.add_actor(Actor::rectangle(door_width, Axis::X, lintel, Axis::Z)
+        .at([0.0, half, room_height / 2.0 - lintel / 2.0])
+        .lambertian(color, 0.8));
+
+    // Box in the room
+    scene.add_actor(Actor::cube(1.5, 1.5, 1.5)
+        .at([-1.5, -1.0, -room_height / 2.0 + 0.75])
+        .lambertian([0.8, 0.3, 0.2], 0.8));
+
+    // Light outside, facing the door
+    let light = scene.add_actor(Actor::rectangle(3.0, Axis::X, door_height, Axis::Z)
+        .at([0.0, half + 1.5, -room_height / 2.0 + door_height / 2.0])
+        .emissive([10.0, 10.0, 10.0]));
+    scene.add_light(light);
+
+    let mut camera = PerspectiveCamera::<f64>::new();
+    camera.set_aspect(aspect(width, height))?;
+    camera.set_position(&[0.0, 0.9 * half, 0.5]);
+    camera.set_direction(&[0.0, -1.0, -0.2]);
+    camera.set_up(&[0.0, 0.0, 1.0]);
+    camera.set_fov(0.4 * std::f64::consts::PI)?;
+    camera.set_focus(1.0)?;
+
+    Ok((scene, camera))
+}
+
+// A small light shining through a glass prism, split into a rainbow on the
+// floor with spectral rendering: Renderer::set_spectral and the photons of
+// Scene::build_spectral_caustics. The glass disperses about 5 times as
+// much as real glass, to spread the colors widely.
+pub fn prism(width: usize, height: usize) -> SceneResult {
+    let mut scene = Scene::<f64>::new();
+    scene.set_background(Vec3::from_array([0.0, 0.0, 0.0]));
+
+    scene.add_actor(Actor::rectangle(24.0, Axis::X, 12.0, Axis::Z)
+        .at([-4.0, 0.0, 0.0])
+        .lambertian([0.9, 0.9, 0.9], 1.0));
+
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+    let mut glass = DielectricMaterial::new(texture, 1.6);
+    glass.set_dispersion(Some(Dispersion::Cauchy { a: 1.52, b: 0.03 }));
+    scene.add_actor(ActorBuilder::new(Wedge::new(1.0, 1.0, 6.0))
+        .at([0.0, 3.0, 0.0])
+        .material(glass));
+
+    // Far from the prism, so the light arrives at almost the same angle.
+    // A wall keeps it from reaching the floor around the prism directly.
+    let light = scene.add_actor(Actor::cube(0.1, 0.1, 0.5)
+        .at([8.0, 1.2, 0.0])
+        .emissive([40000.0, 40000.0, 40000.0]));
+    scene.add_light(light);
+    scene.add_actor(Actor::rectangle(2.4, Axis::Y, 12.0, Axis::Z)
+        .at([1.5, 1.2, 0.0])
+        .lambertian([0.2, 0.2, 0.2], 1.0));
+
+    let mut camera = PerspectiveCamera::<f64>::new();
+    camera.set_aspect(aspect(width, height))?;
+    camera.set_position(&[-2.0, 7.0, 8.0]);
+    camera.set_lookat(&[-5.0, 0.0, 2.0]);
+    camera.set_up(&[0.0, 1.0, 0.0]);
+    camera.set_fov(0.3 * std::f64::consts::PI)?;
+
+    Ok((scene, camera))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn by_name() {
+        for name in NAMES.iter() {
+            let (scene, camera) = super::by_name(name).unwrap()(16, 9).unwrap();
+            assert!(scene.get_actor(0).is_some());
+            assert_eq!(camera.get_aspect(), 16.0 / 9.0);
+        }
+        assert!(super::by_name("missing").is_none());
+        // The aspect ratio of an empty image isn't valid
+        assert!(cube_scene(16, 0).is_err());
+    }
+
+    #[test]
+    fn random_scene() {
+        let (scene0, _camera) = super::random_scene(16, 9, 5).unwrap();
+        let (scene1, _camera) = super::random_scene(16, 9, 5).unwrap();
+        for i in 0..20 * 20 {
+            let bounds0 = scene0.get_actor(i).unwrap().hitable.get_bounds();
+            let bounds1 = scene1.get_actor(i).unwrap().hitable.get_bounds();
+            assert_eq!(bounds0.get_p0().get_data(), bounds1.get_p0().get_data());
+            assert_eq!(bounds0.get_p1().get_data(), bounds1.get_p1().get_data());
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::float::Float;
 use crate::vector::Vec3;
 use super::Texture;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError};
 
 pub struct CheckerTexture<T>
     where T: Float
@@ -11,6 +12,18 @@ pub struct CheckerTexture<T>
 
 }
 
+impl<T> Clone for CheckerTexture<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        CheckerTexture {
+            texture0: self.texture0.clone(),
+            texture1: self.texture1.clone(),
+            period: Vec3::from_slice(self.period.get_data())
+        }
+    }
+}
+
 impl<T> CheckerTexture<T>
     where T: Float
 {
@@ -26,24 +39,58 @@ impl<T> CheckerTexture<T>
     pub fn set_period(&mut self, period: Vec3<T>) {
         self.period = period;
     }
+
+    // The texture of the square the point lies in
+    fn pick(&self, point: &Vec3<T>) -> &dyn Texture<T> {
+        let x = T::to_i32(&((point.get_data()[0] / self.period.get_data()[0]).floor())).unwrap_or(0).abs() % 2;
+        let y = T::to_i32(&((point.get_data()[1] / self.period.get_data()[1]).floor())).unwrap_or(0).abs() % 2;
+        let z = T::to_i32(&((point.get_data()[2] / self.period.get_data()[2]).floor())).unwrap_or(0).abs() % 2;
+        let sign = (x * 2 - 1) * (y * 2 - 1) * (z * 2 - 1);
+        if sign > 0 {
+            self.texture0.as_ref()
+        } else {
+            self.texture1.as_ref()
+        }
+    }
 }
 
 impl<T> Texture<T> for CheckerTexture<T>
     where T: Float
 {
     fn get_color(&self, u: T, v: T, point: &Vec3<T>) -> Vec3<T> {
-        let _two = T::from(2.0).unwrap();
-        let x = T::to_i32(&((point.get_data()[0] / self.period.get_data()[0]).floor())).unwrap().abs() % 2;
-        let y = T::to_i32(&((point.get_data()[1] / self.period.get_data()[1]).floor())).unwrap().abs() % 2;
-        let z = T::to_i32(&((point.get_data()[2] / self.period.get_data()[2]).floor())).unwrap().abs() % 2;
-        let sign = (x * 2 - 1) * (y * 2 - 1) * (z * 2 - 1);
-        if sign > 0 {
-            return self.texture0.get_color(u, v, point);
-        } else {
-            return self.texture1.get_color(u, v, point);
-        }
-        // let y = T::to_i32(&point.get_data()[1]).unwrap();
-        // let z = T::to_i32(&point.get_data()[2]).unwrap();
-        
+        self.pick(point).get_color(u, v, point)
+    }
+
+    fn get_color_footprint(&self, u: T, v: T, point: &Vec3<T>, footprint: T) -> Vec3<T> {
+        self.pick(point).get_color_footprint(u, v, point, footprint)
+    }
+
+    fn clone_box(&self) -> Box<dyn Texture<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Texture<f32>>, PrecisionError> {
+        let texture: CheckerTexture<f32> = self.convert_precision()?;
+        Ok(Box::new(texture))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Texture<f64>>, PrecisionError> {
+        let texture: CheckerTexture<f64> = self.convert_precision()?;
+        Ok(Box::new(texture))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for CheckerTexture<T>
+    where T: Float, U: Precision
+{
+    type Output = CheckerTexture<U>;
+
+    fn convert_precision(&self) -> Result<CheckerTexture<U>, PrecisionError> {
+        let mut texture = CheckerTexture::new(
+            self.texture0.convert_precision()?,
+            self.texture1.convert_precision()?
+        );
+        texture.set_period(self.period.convert());
+        Ok(texture)
     }
 }
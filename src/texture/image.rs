@@ -0,0 +1,251 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::renderer::Image;
+use crate::error::Error;
+use super::Texture;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
+
+// An image wrapped around the UVs, repeating outside of [0, 1]: u goes
+// left to right, v bottom to top. The image is filtered bilinearly. For
+// the footprint of a pixel covering many texels a level of the mipmap
+// (the image halved again and again down to one texel) is picked instead,
+// so fine patterns fade to their average instead of sparkling.
+#[derive(Clone)]
+pub struct ImageTexture<T>
+    where T: Float
+{
+    levels: Vec<Image<T>>,
+    // Times the image is tiled along u and v
+    repeat: T
+}
+
+impl<T> ImageTexture<T>
+    where T: Float
+{
+    pub fn new(image: Image<T>) -> Result<Self, Error> {
+        if image.width == 0 || image.height == 0 {
+            return Err(Error::EmptyImage { width: image.width, height: image.height });
+        }
+        let mut levels = vec![image];
+        while let Some(level) = levels.last() {
+            if level.width == 1 && level.height == 1 {
+                break;
+            }
+            let next = ImageTexture::downsample(level);
+            levels.push(next);
+        }
+        Ok(ImageTexture { levels, repeat: T::one() })
+    }
+
+    // Half the size, rounded up: each texel is the mean of 2 x 2 texels, at
+    // an odd border the last texel is used twice
+    fn downsample(image: &Image<T>) -> Image<T> {
+        let width = image.width.div_ceil(2);
+        let height = image.height.div_ceil(2);
+        let mut half = Image::new(width, height);
+        let quarter = T::from_f64(0.25);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [T::zero(); 3];
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter() {
+                    let sx = usize::min(2 * x + dx, image.width - 1);
+                    let sy = usize::min(2 * y + dy, image.height - 1);
+                    let texel = image.get_pixel(sx, sy).unwrap();
+                    for c in 0..3 {
+                        sum[c] = sum[c] + texel[c];
+                    }
+                }
+                half.set_pixel(x, y, [sum[0] * quarter, sum[1] * quarter, sum[2] * quarter]).unwrap();
+            }
+        }
+        half
+    }
+
+    pub fn get_levels(&self) -> &[Image<T>] {
+        &self.levels
+    }
+
+    pub fn get_repeat(&self) -> T {
+        self.repeat
+    }
+
+    // Tiles the image over the UVs, i.e. a small pattern over a large floor
+    pub fn set_repeat(&mut self, repeat: T) {
+        self.repeat = repeat;
+    }
+
+    // The level whose texels are as large as the footprint, the width of the
+    // area seen by a pixel in UV units. Fractional between two levels,
+    // clamped to the finest and the coarsest one.
+    pub fn level_for_footprint(&self, footprint: T) -> T {
+        let size = usize::max(self.levels[0].width, self.levels[0].height);
+        let texels = footprint * T::from_f64(size as f64);
+        let coarsest = T::from_f64((self.levels.len() - 1) as f64);
+        if texels.is_nan() || texels <= T::one() {
+            return T::zero();
+        }
+        (texels.ln() / T::from_f64(std::f64::consts::LN_2)).min(coarsest)
+    }
+
+    // Bilinear between the four texels around the point
+    pub fn sample_level(&self, level: usize, u: T, v: T) -> Vec3<T> {
+        let image = &self.levels[usize::min(level, self.levels.len() - 1)];
+        let half = T::from_f64(0.5);
+        let x = u * T::from_f64(image.width as f64) - half;
+        let y = (T::one() - v) * T::from_f64(image.height as f64) - half;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let wrap = |value: T, length: usize| {
+            let length = length as i64;
+            value.to_i64().unwrap_or(0).rem_euclid(length) as usize
+        };
+        let (x0, y0) = (wrap(x0, image.width), wrap(y0, image.height));
+        let (x1, y1) = ((x0 + 1) % image.width, (y0 + 1) % image.height);
+        let texel = |x: usize, y: usize| Vec3::from_array(image.get_pixel(x, y).unwrap());
+        let top = texel(x0, y0) * (T::one() - fx) + texel(x1, y0) * fx;
+        let bottom = texel(x0, y1) * (T::one() - fx) + texel(x1, y1) * fx;
+        top * (T::one() - fy) + bottom * fy
+    }
+}
+
+impl<T> Texture<T> for ImageTexture<T>
+    where T: Float
+{
+    fn get_color(&self, u: T, v: T, _point: &Vec3<T>) -> Vec3<T> {
+        self.sample_level(0, u * self.repeat, v * self.repeat)
+    }
+
+    // Trilinear: blended between the two levels around the footprint
+    fn get_color_footprint(&self, u: T, v: T, _point: &Vec3<T>, footprint: T) -> Vec3<T> {
+        let (u, v) = (u * self.repeat, v * self.repeat);
+        let level = self.level_for_footprint(footprint * self.repeat);
+        let fine = level.floor();
+        let blend = level - fine;
+        let fine = fine.to_usize().unwrap_or(0);
+        if blend == T::zero() {
+            return self.sample_level(fine, u, v);
+        }
+        self.sample_level(fine, u, v) * (T::one() - blend) + self.sample_level(fine + 1, u, v) * blend
+    }
+
+    fn clone_box(&self) -> Box<dyn Texture<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Texture<f32>>, PrecisionError> {
+        let texture: ImageTexture<f32> = self.convert_precision()?;
+        Ok(Box::new(texture))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Texture<f64>>, PrecisionError> {
+        let texture: ImageTexture<f64> = self.convert_precision()?;
+        Ok(Box::new(texture))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for ImageTexture<T>
+    where T: Float, U: Precision
+{
+    type Output = ImageTexture<U>;
+
+    fn convert_precision(&self) -> Result<ImageTexture<U>, PrecisionError> {
+        Ok(ImageTexture {
+            levels: self.levels.iter().map(|level| level.convert()).collect(),
+            repeat: convert(self.repeat)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Black and white texels
+    fn checker(width: usize, height: usize) -> Image<f64> {
+        let mut image = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let value = ((x + y) % 2) as f64;
+                image.set_pixel(x, y, [value; 3]).unwrap();
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn levels() {
+        let texture = ImageTexture::new(checker(5, 3)).unwrap();
+        let sizes: Vec<(usize, usize)> = texture.get_levels().iter().map(|level| (level.width, level.height)).collect();
+        assert_eq!(sizes, [(5, 3), (3, 2), (2, 1), (1, 1)]);
+        // The last column and row are used twice
+        assert_eq!(texture.get_levels()[1].get_pixel(2, 1), Some([0.0; 3]));
+        assert_eq!(texture.get_levels()[1].get_pixel(2, 0), Some([0.5; 3]));
+
+        let texture = ImageTexture::new(checker(8, 8)).unwrap();
+        assert_eq!(texture.get_levels().len(), 4);
+        for level in texture.get_levels()[1..].iter() {
+            assert!(level.data.iter().all(|value| *value == 0.5));
+        }
+        assert!(ImageTexture::new(Image::<f64>::new(0, 4)).is_err());
+    }
+
+    #[test]
+    fn level_for_footprint() {
+        let texture = ImageTexture::new(checker(16, 8)).unwrap();
+        assert_eq!(texture.get_levels().len(), 5);
+        // One texel of level 0 or less
+        assert_eq!(texture.level_for_footprint(1.0 / 16.0), 0.0);
+        assert_eq!(texture.level_for_footprint(0.001), 0.0);
+        assert_eq!(texture.level_for_footprint(0.0), 0.0);
+        assert_eq!(texture.level_for_footprint(f64::NAN), 0.0);
+        assert_eq!(texture.level_for_footprint(2.0 / 16.0), 1.0);
+        assert_eq!(texture.level_for_footprint(4.0 / 16.0), 2.0);
+        assert!((texture.level_for_footprint(3.0 / 16.0) - 3.0f64.log2()).abs() < 1e-12);
+        // Clamped to the coarsest level
+        assert_eq!(texture.level_for_footprint(4.0), 4.0);
+        assert_eq!(texture.level_for_footprint(f64::INFINITY), 4.0);
+    }
+
+    #[test]
+    fn sample() {
+        let mut image = Image::<f64>::new(2, 2);
+        // Top row red and green, bottom row blue and white
+        image.data.copy_from_slice(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0]);
+        let texture = ImageTexture::new(image).unwrap();
+        let point = Vec3::new();
+        // Texel centers
+        assert_eq!(texture.get_color(0.25, 0.75, &point).get_data(), [1.0, 0.0, 0.0]);
+        assert_eq!(texture.get_color(0.75, 0.75, &point).get_data(), [0.0, 1.0, 0.0]);
+        assert_eq!(texture.get_color(0.25, 0.25, &point).get_data(), [0.0, 0.0, 1.0]);
+        // Between them, and repeated over the edge
+        assert_eq!(texture.get_color(0.5, 0.75, &point).get_data(), [0.5, 0.5, 0.0]);
+        assert_eq!(texture.get_color(0.0, 0.75, &point).get_data(), [0.5, 0.5, 0.0]);
+        assert_eq!(texture.get_color(1.25, -0.75, &point).get_data(), [0.0, 0.0, 1.0]);
+
+        // Trilinear between level 0 and the average of the image
+        let blended = texture.get_color_footprint(0.25, 0.75, &point, 2.0f64.sqrt() / 2.0);
+        let expected = [0.5 * 1.0 + 0.5 * 0.5, 0.5 * 0.0 + 0.5 * 0.5, 0.5 * 0.0 + 0.5 * 0.5];
+        for c in 0..3 {
+            assert!((blended.get_data()[c] - expected[c]).abs() < 1e-12);
+        }
+        assert_eq!(texture.get_color_footprint(0.25, 0.75, &point, 0.1).get_data(), [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn receding_checker() {
+        // A fine checker seen at growing footprints sparkles between black
+        // and white at level 0, and fades to gray with the mipmap
+        let texture = ImageTexture::new(checker(64, 64)).unwrap();
+        let point = Vec3::new();
+        let (mut plain, mut filtered) = (vec![], vec![]);
+        for k in 0..100 {
+            let u = 0.37 + k as f64 * 0.031;
+            let v = 0.11 + k as f64 * 0.017;
+            plain.push(texture.get_color(u, v, &point).get_data()[0]);
+            filtered.push(texture.get_color_footprint(u, v, &point, 0.1).get_data()[0]);
+        }
+        let spread = |values: &[f64]| values.iter().fold(0.0f64, |max, value| max.max((value - 0.5).abs()));
+        assert!(spread(&plain) > 0.3);
+        assert!(spread(&filtered) < 1e-12);
+    }
+}
@@ -1,11 +1,54 @@
 use crate::float::Float;
 use crate::vector::Vec3;
+use crate::hit::Hit;
+use crate::precision::PrecisionError;
 
 pub mod uniform;
 pub mod checker;
+pub mod perlin;
+pub mod image;
 
-pub trait Texture<T>
+pub trait Texture<T>: Send + Sync
     where T: Float
 {
     fn get_color(&self, u: T, v: T, point: &Vec3<T>) -> Vec3<T>;
+
+    // The color averaged over the footprint, the width in UV units of the
+    // area a pixel sees. Only filtered by textures that know how.
+    fn get_color_footprint(&self, u: T, v: T, point: &Vec3<T>, _footprint: T) -> Vec3<T> {
+        self.get_color(u, v, point)
+    }
+
+    // The color at the hit, filtered over its footprint if it has one
+    fn get_color_at_hit(&self, hit: &Hit<T>) -> Vec3<T> {
+        match hit.footprint {
+            Some(footprint) => self.get_color_footprint(hit.u, hit.v, &hit.point, footprint),
+            None => self.get_color(hit.u, hit.v, &hit.point)
+        }
+    }
+
+    // Hint that the color doesn't depend on u, v or the point
+    fn is_spatially_uniform(&self) -> bool {
+        false
+    }
+
+    // A copy in a new box, to clone actors and scenes. Required of every
+    // texture, for one that is Clone it's Box::new(self.clone()).
+    fn clone_box(&self) -> Box<dyn Texture<T>>;
+
+    fn convert_f32(&self) -> Result<Box<dyn Texture<f32>>, PrecisionError> {
+        Err(PrecisionError::unsupported::<Self>())
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Texture<f64>>, PrecisionError> {
+        Err(PrecisionError::unsupported::<Self>())
+    }
+}
+
+impl<T> Clone for Box<dyn Texture<T>>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
 }
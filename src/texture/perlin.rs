@@ -0,0 +1,240 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::float::Float;
+use crate::vector::Vec3;
+use crate::sampling;
+use super::Texture;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError, convert};
+
+const POINT_COUNT: usize = 256;
+const TURBULENCE_DEPTH: usize = 7;
+
+// Gradient noise on the integer lattice, the permutation tables and the
+// gradients are generated from the seed
+pub struct PerlinNoise<T>
+    where T: Float
+{
+    gradients: Vec<Vec3<T>>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>
+}
+
+impl<T> Clone for PerlinNoise<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        PerlinNoise {
+            gradients: self.gradients.iter().map(|gradient| Vec3::from_slice(gradient.get_data())).collect(),
+            perm_x: self.perm_x.clone(),
+            perm_y: self.perm_y.clone(),
+            perm_z: self.perm_z.clone()
+        }
+    }
+}
+
+impl<T> PerlinNoise<T>
+    where T: Float
+{
+    pub fn new_seeded(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let gradients = (0..POINT_COUNT).map(|_| sampling::unit_sphere_surface(&mut rng)).collect();
+        let mut permutation = || {
+            let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+            p.shuffle(&mut rng);
+            p
+        };
+        let perm_x = permutation();
+        let perm_y = permutation();
+        let perm_z = permutation();
+        PerlinNoise {
+            gradients,
+            perm_x,
+            perm_y,
+            perm_z
+        }
+    }
+
+    // Value in [-1, 1], zero on the lattice points
+    pub fn noise(&self, point: &Vec3<T>) -> T {
+        let data = point.get_data();
+        let mut corner = [0i64; 3];
+        let mut fraction = [T::zero(); 3];
+        for i in 0..3 {
+            let floor = data[i].floor();
+            // Far away or invalid points wrap to the origin
+            corner[i] = floor.to_i64().unwrap_or(0);
+            fraction[i] = data[i] - floor;
+        }
+        // Hermite smoothing of the interpolation weights
+        let three = T::from_f64(3.0);
+        let two = T::from_f64(2.0);
+        let smooth: Vec<T> = fraction.iter().map(|&f| f * f * (three - two * f)).collect();
+
+        let mask = POINT_COUNT as i64 - 1;
+        let mut sum = T::zero();
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let index = self.perm_x[((corner[0] + di) & mask) as usize]
+                        ^ self.perm_y[((corner[1] + dj) & mask) as usize]
+                        ^ self.perm_z[((corner[2] + dk) & mask) as usize];
+                    let offset = Vec3::from_array([
+                        fraction[0] - T::from_f64(di as f64),
+                        fraction[1] - T::from_f64(dj as f64),
+                        fraction[2] - T::from_f64(dk as f64)
+                    ]);
+                    let weight = |d: i64, s: T| if d == 1 { s } else { T::one() - s };
+                    sum = sum + weight(di, smooth[0]) * weight(dj, smooth[1]) * weight(dk, smooth[2])
+                        * self.gradients[index].dot(&offset);
+                }
+            }
+        }
+        sum
+    }
+
+    // Sum of the absolute noise over several octaves
+    pub fn turbulence(&self, point: &Vec3<T>, depth: usize) -> T {
+        let half = T::from_f64(0.5);
+        let two = T::from_f64(2.0);
+        let mut sum = T::zero();
+        let mut weight = T::one();
+        let mut point = Vec3::from_slice(point.get_data());
+        for _ in 0..depth {
+            sum = sum + weight * self.noise(&point).abs();
+            weight = weight * half;
+            point = point * two;
+        }
+        sum
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for PerlinNoise<T>
+    where T: Float, U: Precision
+{
+    type Output = PerlinNoise<U>;
+
+    fn convert_precision(&self) -> Result<PerlinNoise<U>, PrecisionError> {
+        Ok(PerlinNoise {
+            gradients: self.gradients.iter().map(|g| g.convert()).collect(),
+            perm_x: self.perm_x.clone(),
+            perm_y: self.perm_y.clone(),
+            perm_z: self.perm_z.clone()
+        })
+    }
+}
+
+// Gray marble like stripes along z, disturbed by turbulence
+#[derive(Clone)]
+pub struct PerlinNoiseTexture<T>
+    where T: Float
+{
+    noise: PerlinNoise<T>,
+    scale: T
+}
+
+impl<T> PerlinNoiseTexture<T>
+    where T: Float
+{
+    // All textures created with new share the same pattern
+    pub fn new(scale: T) -> Self {
+        PerlinNoiseTexture::new_seeded(scale, 0)
+    }
+
+    pub fn new_seeded(scale: T, seed: u64) -> Self {
+        PerlinNoiseTexture {
+            noise: PerlinNoise::new_seeded(seed),
+            scale
+        }
+    }
+}
+
+impl<T> Texture<T> for PerlinNoiseTexture<T>
+    where T: Float
+{
+    fn get_color(&self, _u: T, _v: T, point: &Vec3<T>) -> Vec3<T> {
+        let half = T::from_f64(0.5);
+        let phase = self.scale * point.get_data()[2] + T::from_f64(10.0) * self.noise.turbulence(point, TURBULENCE_DEPTH);
+        let value = half * (T::one() + phase.sin());
+        Vec3::from_array([value, value, value])
+    }
+
+    fn clone_box(&self) -> Box<dyn Texture<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Texture<f32>>, PrecisionError> {
+        let texture: PerlinNoiseTexture<f32> = self.convert_precision()?;
+        Ok(Box::new(texture))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Texture<f64>>, PrecisionError> {
+        let texture: PerlinNoiseTexture<f64> = self.convert_precision()?;
+        Ok(Box::new(texture))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for PerlinNoiseTexture<T>
+    where T: Float, U: Precision
+{
+    type Output = PerlinNoiseTexture<U>;
+
+    fn convert_precision(&self) -> Result<PerlinNoiseTexture<U>, PrecisionError> {
+        Ok(PerlinNoiseTexture {
+            noise: self.noise.convert_precision()?,
+            scale: convert(self.scale)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> Vec<Vec3<f64>> {
+        (0..100).map(|i| {
+            let x = i as f64 * 0.37;
+            Vec3::from_array([x, 1.3 * x - 2.0, 0.5 - 0.7 * x])
+        }).collect()
+    }
+
+    #[test]
+    fn noise() {
+        let noise = PerlinNoise::<f64>::new_seeded(42);
+        for point in points().iter() {
+            let value = noise.noise(point);
+            assert!(value >= -1.0 && value <= 1.0);
+        }
+        assert_eq!(noise.noise(&Vec3::from_array([3.0, -2.0, 7.0])), 0.0);
+        // Points out of the range of the lattice indices don't panic
+        assert!(noise.noise(&Vec3::from_array([1e300, 0.5, 0.5])).is_finite());
+        assert!(noise.noise(&Vec3::from_array([f64::NAN, 0.5, 0.5])).is_nan());
+    }
+
+    #[test]
+    fn seeded() {
+        let a = PerlinNoiseTexture::<f64>::new_seeded(4.0, 1);
+        let b = PerlinNoiseTexture::<f64>::new_seeded(4.0, 1);
+        let c = PerlinNoiseTexture::<f64>::new_seeded(4.0, 2);
+        let mut differ = false;
+        for point in points().iter() {
+            let color = a.get_color(0.0, 0.0, point);
+            assert_eq!(color.get_data(), b.get_color(0.0, 0.0, point).get_data());
+            differ |= color.get_data() != c.get_color(0.0, 0.0, point).get_data();
+        }
+        assert!(differ);
+    }
+
+    #[test]
+    fn convert() {
+        let texture = PerlinNoiseTexture::<f64>::new_seeded(4.0, 7);
+        let converted: PerlinNoiseTexture<f32> = texture.convert_precision().unwrap();
+        for point in points().iter() {
+            let expected = texture.get_color(0.0, 0.0, point).get_data()[0];
+            let actual = converted.get_color(0.0, 0.0, &point.to_f32()).get_data()[0];
+            assert!((expected - actual as f64).abs() < 1e-3);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::float::Float;
 use crate::vector::Vec3;
 use super::Texture;
+use crate::precision::{ConvertPrecision, Precision, PrecisionError};
 
 pub struct UniformTexture<T>
     where T: Float
@@ -8,6 +9,16 @@ pub struct UniformTexture<T>
     color: Vec3<T>
 }
 
+impl<T> Clone for UniformTexture<T>
+    where T: Float
+{
+    fn clone(&self) -> Self {
+        UniformTexture {
+            color: Vec3::from_slice(self.color.get_data())
+        }
+    }
+}
+
 impl<T> UniformTexture<T>
     where T: Float
 {
@@ -24,4 +35,49 @@ impl<T> Texture<T> for UniformTexture<T>
     fn get_color(&self, _u: T, _v: T, _point: &Vec3<T>) -> Vec3<T> {
         Vec3::<T>::from_slice(self.color.get_data())
     }
+
+    fn is_spatially_uniform(&self) -> bool {
+        true
+    }
+
+    fn clone_box(&self) -> Box<dyn Texture<T>> {
+        Box::new(self.clone())
+    }
+
+    fn convert_f32(&self) -> Result<Box<dyn Texture<f32>>, PrecisionError> {
+        let texture: UniformTexture<f32> = self.convert_precision()?;
+        Ok(Box::new(texture))
+    }
+
+    fn convert_f64(&self) -> Result<Box<dyn Texture<f64>>, PrecisionError> {
+        let texture: UniformTexture<f64> = self.convert_precision()?;
+        Ok(Box::new(texture))
+    }
+}
+
+impl<T, U> ConvertPrecision<U> for UniformTexture<T>
+    where T: Float, U: Precision
+{
+    type Output = UniformTexture<U>;
+
+    fn convert_precision(&self) -> Result<UniformTexture<U>, PrecisionError> {
+        Ok(UniformTexture::new(self.color.convert()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::checker::CheckerTexture;
+
+    #[test]
+    fn spatially_uniform() {
+        let texture = UniformTexture::<f64>::new(Vec3::from_array([0.1, 0.2, 0.3]));
+        assert!(texture.is_spatially_uniform());
+
+        let texture0 = Box::new(UniformTexture::<f64>::new(Vec3::from_array([0.1, 0.2, 0.3])));
+        let texture1 = Box::new(UniformTexture::<f64>::new(Vec3::from_array([0.3, 0.2, 0.1])));
+        let texture = CheckerTexture::new(texture0, texture1);
+        assert!(!texture.is_spatially_uniform());
+    }
 }
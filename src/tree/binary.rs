@@ -1,48 +1,45 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::hit::Hit;
-use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
 use crate::actor::Actor;
-use crate::tree::Tree;
+use crate::interval::Interval;
+use crate::tree::{Tree, TraversalCounts};
 
 pub struct BinaryTree<T>
     where T: Float
 {
-    bounds: BoundingBox<T>, // The bounds of this node
+    bounds: Aabb<T>, // The bounds of this node
     pub children: [Option<Box<BinaryTree<T>>>; 2], // The children binary trees
-    pub actor: Option<Rc<Actor<T>>> // The actor stored in the leaf nodes
+    pub actor: Option<Arc<Actor<T>>>, // The actor stored in the leaf nodes
+    unbounded: Vec<Arc<Actor<T>>> // The actors without bounding box, only in the root node
 }
 
 impl<T> BinaryTree<T>
     where T: Float
 {
     pub fn new() -> Self {
-        let bounds = BoundingBox::<T>::new(Vec3::<T>::new(), Vec3::<T>::new());
+        let bounds = Aabb::<T>::new(Vec3::<T>::new(), Vec3::<T>::new());
         let children: [Option<Box<BinaryTree<T>>>; 2] = [None, None];
         let actor = None;
         BinaryTree {
             bounds,
             children,
-            actor
+            actor,
+            unbounded: Vec::new()
         }
     }
 
-    pub fn get_bounds(&self) -> &BoundingBox<T> {
+    pub fn get_bounds(&self) -> &Aabb<T> {
         &self.bounds
     }
-}
-
-impl<T> Tree<T> for BinaryTree<T>
-    where T: Float
-{
-    fn add_actor(&mut self, actor: Rc<Actor<T>>) -> bool {
-        let actor_bounds = actor.hitable.get_bounds();
 
+    fn insert(&mut self, actor: Arc<Actor<T>>, actor_bounds: &Aabb<T>) -> bool {
         // Expand the node bounds so the new actor is guaranteed to fit
-        self.bounds.expand(&actor_bounds);
+        self.bounds.expand(actor_bounds);
 
         let (left_child, rest) = self.children.split_first_mut().unwrap();
         let (right_child, _) = rest.split_first_mut().unwrap();
@@ -59,8 +56,9 @@ impl<T> Tree<T> for BinaryTree<T>
             (Some(current_actor), None, None) => {
                 let mut left = Box::new(BinaryTree::<T>::new());
                 let mut right = Box::new(BinaryTree::<T>::new());
-                left.add_actor(Rc::clone(current_actor));
-                right.add_actor(actor);
+                let current_bounds = current_actor.hitable.bounding_box().unwrap();
+                left.insert(Arc::clone(current_actor), &current_bounds);
+                right.insert(actor, actor_bounds);
                 self.actor = None;
                 self.children[0] = Some(left);
                 self.children[1] = Some(right);
@@ -86,22 +84,13 @@ impl<T> Tree<T> for BinaryTree<T>
                 //     return true;
                 // }
 
-                let mut left_bounds = BoundingBox::new(
-                    Vec3::from_slice(&left.get_bounds().get_p0().get_data()),
-                    Vec3::from_slice(&left.get_bounds().get_p1().get_data())
-                );
-                left_bounds.expand(&actor_bounds);
-
-                let mut right_bounds = BoundingBox::new(
-                    Vec3::from_slice(&right.get_bounds().get_p0().get_data()),
-                    Vec3::from_slice(&right.get_bounds().get_p1().get_data())
-                );
-                right_bounds.expand(&actor_bounds);
+                let left_bounds = left.get_bounds().union(actor_bounds);
+                let right_bounds = right.get_bounds().union(actor_bounds);
 
                 if left_bounds.get_volume() < right_bounds.get_volume() {
-                    left.add_actor(actor);
+                    left.insert(actor, actor_bounds);
                 } else {
-                    right.add_actor(actor);
+                    right.insert(actor, actor_bounds);
                 }
                 return true;
             },
@@ -111,26 +100,53 @@ impl<T> Tree<T> for BinaryTree<T>
             }
         }
     }
+}
+
+impl<T> Tree<T> for BinaryTree<T>
+    where T: Float
+{
+    fn add_actor(&mut self, actor: Arc<Actor<T>>) -> bool {
+        match actor.hitable.bounding_box() {
+            Some(actor_bounds) => self.insert(actor, &actor_bounds),
+            None => {
+                self.unbounded.push(actor);
+                true
+            }
+        }
+    }
 
-    fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)> {
-        if !self.get_bounds().hit(ray, t_min, t_max) {
-            return None;
+    fn get_hit_counted(&self, ray: &Ray<T>, interval: &Interval<T>, layer_mask: u32, counts: &mut TraversalCounts) -> Option<(&Actor<T>, Hit<T>)> {
+        counts.steps += 1;
+        let mut interval = *interval;
+        let mut result : Option<(&Actor<T>, Hit<T>)> = None;
+
+        for actor in self.unbounded.iter() {
+            if !actor.in_layers(layer_mask) {
+                continue;
+            }
+            counts.intersection_tests += 1;
+            if let Some(hit) = actor.hitable.hit_counted(ray, &interval, counts) {
+                interval.max = hit.t;
+                result = Some((actor.as_ref(), hit));
+            }
         }
 
-        let mut t_max = t_max;
-        let mut result : Option<(Rc<Actor<T>>, Hit<T>)> = None;
+        if !self.get_bounds().hit(ray, interval.min, interval.max) {
+            return result;
+        }
 
-        if let Some(actor) = &self.actor {
-            if let Some(hit) = actor.hitable.hit(ray, t_min, t_max) {
-                t_max = hit.t;
-                result = Some((Rc::clone(actor), hit));
+        if let Some(actor) = self.actor.as_ref().filter(|actor| actor.in_layers(layer_mask)) {
+            counts.intersection_tests += 1;
+            if let Some(hit) = actor.hitable.hit_counted(ray, &interval, counts) {
+                interval.max = hit.t;
+                result = Some((actor.as_ref(), hit));
             }
         }
 
         for i in 0..2 {
             if let Some(child) = &self.children[i] {
-                if let Some((actor, hit)) = child.get_hit(ray, t_min, t_max) {
-                    t_max = hit.t;
+                if let Some((actor, hit)) = child.get_hit_counted(ray, &interval, layer_mask, counts) {
+                    interval.max = hit.t;
                     result = Some((actor, hit));
                 }
             }
@@ -0,0 +1,468 @@
+use std::os::raw::{c_char, c_uint, c_void};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::float::Float;
+use crate::ray::Ray;
+use crate::hit::Hit;
+use crate::actor::Actor;
+use crate::interval::Interval;
+use crate::precision::convert;
+use crate::tree::{Tree, TraversalCounts};
+
+// The part of the Embree 3 API the tree needs, linked from libembree3
+mod sys {
+    use std::os::raw::{c_char, c_int, c_uint, c_void};
+
+    pub type RTCDevice = *mut c_void;
+    pub type RTCScene = *mut c_void;
+    pub type RTCGeometry = *mut c_void;
+
+    pub const RTC_GEOMETRY_TYPE_TRIANGLE: c_uint = 0;
+    pub const RTC_GEOMETRY_TYPE_USER: c_uint = 120;
+    pub const RTC_BUFFER_TYPE_INDEX: c_uint = 0;
+    pub const RTC_BUFFER_TYPE_VERTEX: c_uint = 1;
+    pub const RTC_FORMAT_UINT3: c_uint = 0x5003;
+    pub const RTC_FORMAT_FLOAT3: c_uint = 0x9003;
+    pub const RTC_INVALID_GEOMETRY_ID: c_uint = c_uint::MAX;
+
+    #[repr(C)]
+    pub struct RTCIntersectContext {
+        pub flags: c_uint,
+        pub filter: Option<extern "C" fn(*const RTCFilterFunctionNArguments)>,
+        pub inst_id: [c_uint; 1]
+    }
+
+    #[repr(C, align(16))]
+    pub struct RTCRay {
+        pub org_x: f32,
+        pub org_y: f32,
+        pub org_z: f32,
+        pub tnear: f32,
+        pub dir_x: f32,
+        pub dir_y: f32,
+        pub dir_z: f32,
+        pub time: f32,
+        pub tfar: f32,
+        pub mask: c_uint,
+        pub id: c_uint,
+        pub flags: c_uint
+    }
+
+    #[repr(C, align(16))]
+    pub struct RTCHit {
+        pub ng_x: f32,
+        pub ng_y: f32,
+        pub ng_z: f32,
+        pub u: f32,
+        pub v: f32,
+        pub prim_id: c_uint,
+        pub geom_id: c_uint,
+        pub inst_id: [c_uint; 1]
+    }
+
+    #[repr(C)]
+    pub struct RTCRayHit {
+        pub ray: RTCRay,
+        pub hit: RTCHit
+    }
+
+    #[repr(C, align(16))]
+    pub struct RTCBounds {
+        pub lower_x: f32,
+        pub lower_y: f32,
+        pub lower_z: f32,
+        pub align0: f32,
+        pub upper_x: f32,
+        pub upper_y: f32,
+        pub upper_z: f32,
+        pub align1: f32
+    }
+
+    #[repr(C)]
+    pub struct RTCBoundsFunctionArguments {
+        pub geometry_user_ptr: *mut c_void,
+        pub prim_id: c_uint,
+        pub time_step: c_uint,
+        pub bounds_o: *mut RTCBounds
+    }
+
+    // Rays are traced one at a time with rtcIntersect1, so the callbacks
+    // get N = 1 and the ray and the hit laid out as RTCRay and RTCHit
+    #[repr(C)]
+    pub struct RTCIntersectFunctionNArguments {
+        pub valid: *mut c_int,
+        pub geometry_user_ptr: *mut c_void,
+        pub prim_id: c_uint,
+        pub context: *mut RTCIntersectContext,
+        pub rayhit: *mut RTCRayHit,
+        pub n: c_uint,
+        pub geom_id: c_uint
+    }
+
+    #[repr(C)]
+    pub struct RTCFilterFunctionNArguments {
+        pub valid: *mut c_int,
+        pub geometry_user_ptr: *mut c_void,
+        pub context: *const RTCIntersectContext,
+        pub ray: *mut RTCRay,
+        pub hit: *mut RTCHit,
+        pub n: c_uint
+    }
+
+    #[link(name = "embree3")]
+    extern "C" {
+        pub fn rtcNewDevice(config: *const c_char) -> RTCDevice;
+        pub fn rtcReleaseDevice(device: RTCDevice);
+        pub fn rtcNewScene(device: RTCDevice) -> RTCScene;
+        pub fn rtcReleaseScene(scene: RTCScene);
+        pub fn rtcCommitScene(scene: RTCScene);
+        pub fn rtcAttachGeometryByID(scene: RTCScene, geometry: RTCGeometry, geom_id: c_uint);
+        pub fn rtcNewGeometry(device: RTCDevice, geometry_type: c_uint) -> RTCGeometry;
+        pub fn rtcReleaseGeometry(geometry: RTCGeometry);
+        pub fn rtcCommitGeometry(geometry: RTCGeometry);
+        pub fn rtcSetNewGeometryBuffer(geometry: RTCGeometry, buffer_type: c_uint, slot: c_uint, format: c_uint, byte_stride: usize, item_count: usize) -> *mut c_void;
+        pub fn rtcSetGeometryUserData(geometry: RTCGeometry, ptr: *mut c_void);
+        pub fn rtcSetGeometryUserPrimitiveCount(geometry: RTCGeometry, count: c_uint);
+        pub fn rtcSetGeometryBoundsFunction(geometry: RTCGeometry, bounds: extern "C" fn(*const RTCBoundsFunctionArguments), user_ptr: *mut c_void);
+        pub fn rtcSetGeometryIntersectFunction(geometry: RTCGeometry, intersect: extern "C" fn(*const RTCIntersectFunctionNArguments));
+        pub fn rtcSetGeometryIntersectFilterFunction(geometry: RTCGeometry, filter: extern "C" fn(*const RTCFilterFunctionNArguments));
+        pub fn rtcIntersect1(scene: RTCScene, context: *mut RTCIntersectContext, rayhit: *mut RTCRayHit);
+    }
+}
+
+use sys::*;
+
+// Hit search through the BVH of Embree, built from the bounding boxes of the
+// actors. Bare triangle meshes hand Embree their triangles, every other
+// actor is a box Embree calls back into for the analytical intersection.
+// Embree works in f32: the boxes and the triangle tests only pick the
+// candidates, the hits themselves are computed in T.
+//
+// Embree builds its hierarchy on the first query after actors were added.
+pub struct EmbreeTree<T>
+    where T: Float
+{
+    device: RTCDevice,
+    scene: RTCScene,
+    // Index i is the geometry with ID i
+    actors: Vec<Arc<Actor<T>>>,
+    // The actors without bounding box, tested against every ray
+    unbounded: Vec<Arc<Actor<T>>>,
+    committed: AtomicBool,
+    commit: Mutex<()>
+}
+
+// Embree scenes are safe to query from several threads once committed,
+// committing is guarded by the mutex
+unsafe impl<T> Send for EmbreeTree<T> where T: Float {}
+unsafe impl<T> Sync for EmbreeTree<T> where T: Float {}
+
+// What the callbacks need during a query, Embree hands it back as the
+// context it starts with
+#[repr(C)]
+struct Query<'a, T>
+    where T: Float
+{
+    context: RTCIntersectContext,
+    actors: &'a [Arc<Actor<T>>],
+    ray: &'a Ray<T>,
+    interval: Interval<T>,
+    layer_mask: u32,
+    counts: TraversalCounts,
+    closest: Option<(usize, Hit<T>)>
+}
+
+// Below and above x once rounded to f32, so rounded boxes still contain
+// everything and rounded ray ranges don't cut off hits
+fn lower<T>(x: T) -> f32
+    where T: Float
+{
+    let x: f64 = convert(x);
+    (x - 1e-5 * (1.0 + x.abs())) as f32
+}
+
+fn upper<T>(x: T) -> f32
+    where T: Float
+{
+    let x: f64 = convert(x);
+    (x + 1e-5 * (1.0 + x.abs())) as f32
+}
+
+extern "C" fn actor_bounds<T>(args: *const RTCBoundsFunctionArguments)
+    where T: Float
+{
+    unsafe {
+        let args = &*args;
+        let actor = &*(args.geometry_user_ptr as *const Actor<T>);
+        let bounds = actor.hitable.bounding_box().unwrap();
+        let (p0, p1) = (bounds.get_p0().get_data(), bounds.get_p1().get_data());
+        *args.bounds_o = RTCBounds {
+            lower_x: lower(p0[0]), lower_y: lower(p0[1]), lower_z: lower(p0[2]), align0: 0.0,
+            upper_x: upper(p1[0]), upper_y: upper(p1[1]), upper_z: upper(p1[2]), align1: 0.0
+        };
+    }
+}
+
+extern "C" fn actor_intersect<T>(args: *const RTCIntersectFunctionNArguments)
+    where T: Float
+{
+    unsafe {
+        let args = &*args;
+        if *args.valid == 0 {
+            return;
+        }
+        let query = &mut *(args.context as *mut Query<T>);
+        let actor = &*(args.geometry_user_ptr as *const Actor<T>);
+        if !actor.in_layers(query.layer_mask) {
+            return;
+        }
+        query.counts.intersection_tests += 1;
+        if let Some(hit) = actor.hitable.hit_counted(query.ray, &query.interval, &mut query.counts) {
+            let rayhit = &mut *args.rayhit;
+            rayhit.ray.tfar = upper(hit.t);
+            rayhit.hit.prim_id = args.prim_id;
+            rayhit.hit.geom_id = args.geom_id;
+            rayhit.hit.inst_id = query.context.inst_id;
+            query.interval.max = hit.t;
+            query.closest = Some((args.geom_id as usize, hit));
+        }
+    }
+}
+
+// Embree found a triangle of a mesh, it's only taken if it's also hit in T
+extern "C" fn triangle_filter<T>(args: *const RTCFilterFunctionNArguments)
+    where T: Float
+{
+    unsafe {
+        let args = &*args;
+        if *args.valid == 0 {
+            return;
+        }
+        let query = &mut *(args.context as *mut Query<T>);
+        let hit = &*args.hit;
+        let actor = &query.actors[hit.geom_id as usize];
+        let mesh = actor.hitable.get_mesh().unwrap();
+        if !actor.in_layers(query.layer_mask) {
+            *args.valid = 0;
+            return;
+        }
+        query.counts.intersection_tests += 1;
+        query.counts.triangle_tests += 1;
+        match mesh.hit_triangle(hit.prim_id as usize, query.ray, query.interval.min, query.interval.max) {
+            Some(hit) => {
+                query.interval.max = hit.t;
+                query.closest = Some((args.geometry_user_ptr as usize, hit));
+            },
+            None => *args.valid = 0
+        }
+    }
+}
+
+impl<T> EmbreeTree<T>
+    where T: Float
+{
+    pub fn new() -> Self {
+        let device = unsafe { rtcNewDevice(ptr::null::<c_char>()) };
+        assert!(!device.is_null(), "failed to create the Embree device");
+        let scene = unsafe { rtcNewScene(device) };
+        EmbreeTree {
+            device,
+            scene,
+            actors: Vec::new(),
+            unbounded: Vec::new(),
+            committed: AtomicBool::new(true),
+            commit: Mutex::new(())
+        }
+    }
+
+    fn commit(&self) {
+        if self.committed.load(Ordering::Acquire) {
+            return;
+        }
+        let _guard = self.commit.lock().unwrap();
+        if !self.committed.load(Ordering::Acquire) {
+            unsafe { rtcCommitScene(self.scene) };
+            self.committed.store(true, Ordering::Release);
+        }
+    }
+
+    fn add_mesh(&self, geom_id: usize, vertices: &[[T; 3]], triangles: &[[usize; 3]]) -> RTCGeometry {
+        unsafe {
+            let geometry = rtcNewGeometry(self.device, RTC_GEOMETRY_TYPE_TRIANGLE);
+            let buffer = rtcSetNewGeometryBuffer(geometry, RTC_BUFFER_TYPE_VERTEX, 0, RTC_FORMAT_FLOAT3, 3 * 4, vertices.len()) as *mut f32;
+            for (i, vertex) in vertices.iter().enumerate() {
+                for (c, x) in vertex.iter().enumerate() {
+                    *buffer.add(3 * i + c) = convert(*x);
+                }
+            }
+            let buffer = rtcSetNewGeometryBuffer(geometry, RTC_BUFFER_TYPE_INDEX, 0, RTC_FORMAT_UINT3, 3 * 4, triangles.len()) as *mut c_uint;
+            for (k, triangle) in triangles.iter().enumerate() {
+                for (c, index) in triangle.iter().enumerate() {
+                    *buffer.add(3 * k + c) = *index as c_uint;
+                }
+            }
+            // The filter gets the geometry ID back as user data
+            rtcSetGeometryUserData(geometry, geom_id as *mut c_void);
+            rtcSetGeometryIntersectFilterFunction(geometry, triangle_filter::<T>);
+            geometry
+        }
+    }
+
+    fn add_user(&self, actor: &Arc<Actor<T>>) -> RTCGeometry {
+        unsafe {
+            let geometry = rtcNewGeometry(self.device, RTC_GEOMETRY_TYPE_USER);
+            rtcSetGeometryUserPrimitiveCount(geometry, 1);
+            rtcSetGeometryUserData(geometry, Arc::as_ptr(actor) as *mut c_void);
+            rtcSetGeometryBoundsFunction(geometry, actor_bounds::<T>, ptr::null_mut());
+            rtcSetGeometryIntersectFunction(geometry, actor_intersect::<T>);
+            geometry
+        }
+    }
+}
+
+impl<T> Default for EmbreeTree<T>
+    where T: Float
+{
+    fn default() -> Self {
+        EmbreeTree::new()
+    }
+}
+
+impl<T> Drop for EmbreeTree<T>
+    where T: Float
+{
+    fn drop(&mut self) {
+        unsafe {
+            rtcReleaseScene(self.scene);
+            rtcReleaseDevice(self.device);
+        }
+    }
+}
+
+impl<T> Tree<T> for EmbreeTree<T>
+    where T: Float
+{
+    fn add_actor(&mut self, actor: Arc<Actor<T>>) -> bool {
+        if actor.hitable.bounding_box().is_none() {
+            self.unbounded.push(actor);
+            return true;
+        }
+
+        let geom_id = self.actors.len();
+        let geometry = match actor.hitable.get_mesh() {
+            Some(mesh) => self.add_mesh(geom_id, mesh.get_vertices(), mesh.get_triangles()),
+            None => self.add_user(&actor)
+        };
+        unsafe {
+            rtcCommitGeometry(geometry);
+            rtcAttachGeometryByID(self.scene, geometry, geom_id as c_uint);
+            rtcReleaseGeometry(geometry);
+        }
+        self.actors.push(actor);
+        *self.committed.get_mut() = false;
+        true
+    }
+
+    fn get_hit_counted(&self, ray: &Ray<T>, interval: &Interval<T>, layer_mask: u32, counts: &mut TraversalCounts) -> Option<(&Actor<T>, Hit<T>)> {
+        counts.steps += 1;
+        let mut interval = *interval;
+        let mut result : Option<(&Actor<T>, Hit<T>)> = None;
+
+        for actor in self.unbounded.iter() {
+            if !actor.in_layers(layer_mask) {
+                continue;
+            }
+            counts.intersection_tests += 1;
+            if let Some(hit) = actor.hitable.hit_counted(ray, &interval, counts) {
+                interval.max = hit.t;
+                result = Some((actor.as_ref(), hit));
+            }
+        }
+
+        if self.actors.is_empty() {
+            return result;
+        }
+        self.commit();
+
+        let context = RTCIntersectContext { flags: 0, filter: None, inst_id: [RTC_INVALID_GEOMETRY_ID] };
+        let mut query = Query { context, actors: &self.actors, ray, interval, layer_mask, counts: TraversalCounts::default(), closest: None };
+        let (origin, direction) = (ray.get_origin().get_data(), ray.get_direction().get_data());
+        let mut rayhit = RTCRayHit {
+            ray: RTCRay {
+                org_x: convert(origin[0]), org_y: convert(origin[1]), org_z: convert(origin[2]),
+                tnear: lower(interval.min).max(0.0),
+                dir_x: convert(direction[0]), dir_y: convert(direction[1]), dir_z: convert(direction[2]),
+                time: 0.0,
+                tfar: upper(interval.max),
+                mask: c_uint::MAX,
+                id: 0,
+                flags: 0
+            },
+            hit: RTCHit {
+                ng_x: 0.0, ng_y: 0.0, ng_z: 0.0, u: 0.0, v: 0.0,
+                prim_id: RTC_INVALID_GEOMETRY_ID,
+                geom_id: RTC_INVALID_GEOMETRY_ID,
+                inst_id: [RTC_INVALID_GEOMETRY_ID]
+            }
+        };
+        unsafe {
+            rtcIntersect1(self.scene, &mut query as *mut Query<T> as *mut RTCIntersectContext, &mut rayhit);
+        }
+        counts.intersection_tests += query.counts.intersection_tests;
+        counts.triangle_tests += query.counts.triangle_tests;
+
+        match query.closest {
+            Some((index, hit)) => Some((self.actors[index].as_ref(), hit)),
+            None => result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+    use crate::vector::Vec3;
+    use crate::hitable::primitive::{Sphere, TriangleMesh};
+    use crate::hitable::transform::Translation;
+    use crate::material::lambertian::LambertianMaterial;
+    use crate::texture::uniform::UniformTexture;
+    use crate::tree::linear::LinearTree;
+
+    fn actor(hitable: Box<dyn crate::hitable::Hitable<f64>>) -> Arc<Actor<f64>> {
+        let texture = Box::new(UniformTexture::new(Vec3::from_array([0.5, 0.5, 0.5])));
+        Arc::new(Actor::new(hitable, Box::new(LambertianMaterial::new(texture, 0.5))))
+    }
+
+    #[test]
+    fn same_hits_as_linear() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut embree = EmbreeTree::<f64>::new();
+        let mut linear = LinearTree::<f64>::new();
+        for _ in 0..50 {
+            let center = Vec3::from_array([rng.gen_range(-5.0, 5.0), rng.gen_range(-5.0, 5.0), rng.gen_range(-5.0, 5.0)]);
+            let sphere = actor(Box::new(Translation::new(Box::new(Sphere::new(rng.gen_range(0.1, 0.8))), center)));
+            embree.add_actor(Arc::clone(&sphere));
+            linear.add_actor(sphere);
+        }
+        let mesh = actor(Box::new(TriangleMesh::icosphere(1.5, 2)));
+        embree.add_actor(Arc::clone(&mesh));
+        linear.add_actor(mesh);
+
+        let interval = Interval::new(0.001, f64::INFINITY);
+        let mut differences = 0;
+        for _ in 0..2000 {
+            let direction = [rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0)];
+            let ray = Ray::from_array([0.0, 0.0, 8.0], direction);
+            let expected = linear.get_hit(&ray, &interval, u32::MAX).map(|(_, hit)| hit.t);
+            let found = embree.get_hit(&ray, &interval, u32::MAX).map(|(_, hit)| hit.t);
+            if expected != found {
+                differences += 1;
+            }
+        }
+        // Only rays grazing the edges of triangles may be rounded apart
+        assert!(differences < 5, "{} different hits", differences);
+    }
+}
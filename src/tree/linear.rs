@@ -1,15 +1,16 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::float::Float;
 use crate::ray::Ray;
 use crate::hit::Hit;
 use crate::actor::Actor;
-use crate::tree::Tree;
+use crate::interval::Interval;
+use crate::tree::{Tree, TraversalCounts};
 
 pub struct LinearTree<T>
     where T: Float
 {
-    actors: Vec<Rc<Actor<T>>>
+    actors: Vec<Arc<Actor<T>>>
 }
 
 impl<T> LinearTree<T>
@@ -26,19 +27,24 @@ impl<T> LinearTree<T>
 impl<T> Tree<T> for LinearTree<T>
     where T: Float
 {
-    fn add_actor(&mut self, actor: Rc<Actor<T>>) -> bool {
+    fn add_actor(&mut self, actor: Arc<Actor<T>>) -> bool {
         self.actors.push(actor);
         return true;
     }
 
-    fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)> {
-        let mut t_max = t_max;
-        let mut result : Option<(Rc<Actor<T>>, Hit<T>)> = None;
+    fn get_hit_counted(&self, ray: &Ray<T>, interval: &Interval<T>, layer_mask: u32, counts: &mut TraversalCounts) -> Option<(&Actor<T>, Hit<T>)> {
+        counts.steps += 1;
+        let mut interval = *interval;
+        let mut result : Option<(&Actor<T>, Hit<T>)> = None;
 
         for i in 0..self.actors.len() {
-            if let Some(hit) = self.actors[i].hitable.hit(ray, t_min, t_max) {
-                t_max = hit.t;
-                result = Some((Rc::clone(&self.actors[i]), hit));
+            if !self.actors[i].in_layers(layer_mask) {
+                continue;
+            }
+            counts.intersection_tests += 1;
+            if let Some(hit) = self.actors[i].hitable.hit_counted(ray, &interval, counts) {
+                interval.max = hit.t;
+                result = Some((self.actors[i].as_ref(), hit));
             }
         }
 
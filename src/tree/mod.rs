@@ -1,24 +1,48 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::float::Float;
 use crate::ray::Ray;
 use crate::hit::Hit;
 use crate::actor::Actor;
+use crate::interval::Interval;
 
 pub mod linear;
 pub mod oct;
 pub mod binary;
+#[cfg(feature = "embree")]
+pub mod embree;
 
-pub trait Tree<T>
+// Work done by a tree to find the hit of rays, i.e. to compare tree types
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TraversalCounts {
+    // Nodes visited
+    pub steps: u64,
+    // Actors tested against the ray
+    pub intersection_tests: u64,
+    // Triangles of meshes tested against the ray
+    pub triangle_tests: u64
+}
+
+pub trait Tree<T>: Send + Sync
     where T: Float
 {
-    fn add_actor(&mut self, actor: Rc<Actor<T>>) -> bool;
+    fn add_actor(&mut self, actor: Arc<Actor<T>>) -> bool;
+
+    // Only actors in one of the layers of the mask are hit
+    fn get_hit(&self, ray: &Ray<T>, interval: &Interval<T>, layer_mask: u32) -> Option<(&Actor<T>, Hit<T>)> {
+        self.get_hit_counted(ray, interval, layer_mask, &mut TraversalCounts::default())
+    }
 
-    fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)>;
+    // Like get_hit, adding the work done to counts
+    fn get_hit_counted(&self, ray: &Ray<T>, interval: &Interval<T>, layer_mask: u32, counts: &mut TraversalCounts) -> Option<(&Actor<T>, Hit<T>)>;
 }
 
+#[derive(Copy, Clone)]
 pub enum TreeType {
     Linear,
     Binary,
-    Oct
+    Oct,
+    // Needs the Embree 3 library
+    #[cfg(feature = "embree")]
+    Embree
 }
@@ -1,68 +1,42 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::float::Float;
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::hit::Hit;
-use crate::boundingbox::BoundingBox;
+use crate::aabb::Aabb;
 use crate::actor::Actor;
-use crate::tree::Tree;
+use crate::interval::Interval;
+use crate::tree::{Tree, TraversalCounts};
 
 pub struct Octree<T>
     where T: Float
 {
-    bounds: BoundingBox<T>, // The bounds of this node
+    bounds: Aabb<T>, // The bounds of this node
     pub children: [Option<Box<Octree<T>>>; 8], // The children octrees
-    pub actors: Vec<Rc<Actor<T>>> // The actors that are too large to be placed in the children nodes
+    pub actors: Vec<Arc<Actor<T>>>, // The actors that are too large to be placed in the children nodes
+    unbounded: Vec<Arc<Actor<T>>> // The actors without bounding box, only in the root node
 }
 
 impl<T> Octree<T>
     where T: Float
 {
-    pub fn new(bounds: BoundingBox<T>) -> Self {
+    pub fn new(bounds: Aabb<T>) -> Self {
         let children: [Option<Box<Octree<T>>>; 8] = [None, None, None, None, None, None, None, None];
         let actors = Vec::new();
         Octree {
             bounds,
             children,
-            actors
+            actors,
+            unbounded: Vec::new()
         }
     }
 
-    pub fn get_bounds(&self) -> &BoundingBox<T> {
+    pub fn get_bounds(&self) -> &Aabb<T> {
         &self.bounds
     }
 
-    fn get_child_bounds(&self, child_index: usize) -> BoundingBox<T> {
-        let k = child_index % 2;
-        let j = (child_index / 2) % 2;
-        let i = (child_index / 4) % 2;
-
-        let k = T::from(k).unwrap();
-        let j = T::from(j).unwrap();
-        let i = T::from(i).unwrap();
-
-        let half = T::from(0.5).unwrap();
-
-        let (min_x, max_x) = self.get_bounds().get_axis_bounds(0);
-        let (min_y, max_y) = self.get_bounds().get_axis_bounds(1);
-        let (min_z, max_z) = self.get_bounds().get_axis_bounds(2);
-        let len = [half * (max_x - min_x), half * (max_y - min_y), half * (max_z - min_z)];
-
-        let offset = Vec3::<T>::from_array([len[0] * i, len[1] * j, len[2] * k]);
-
-        let p0 = Vec3::<T>::from_array([min_x, min_y, min_z]) + &offset;
-        let p1 = &p0 + Vec3::from_slice(&len);
-        BoundingBox::<T>::new(p0, p1)
-    }
-}
-
-impl<T> Tree<T> for Octree<T>
-    where T: Float
-{
-    fn add_actor(&mut self, actor: Rc<Actor<T>>) -> bool {
-        let actor_bounds = actor.hitable.get_bounds();
-
+    fn insert(&mut self, actor: Arc<Actor<T>>, actor_bounds: &Aabb<T>) -> bool {
         // If this node can't fully contain the actor, do nothing
         if !self.bounds.contains(actor_bounds) {
             return false;
@@ -75,7 +49,7 @@ impl<T> Tree<T> for Octree<T>
             match child {
                 Some(node) => {
                     if node.get_bounds().contains(actor_bounds) {
-                        node.add_actor(actor);
+                        node.insert(actor, actor_bounds);
                         return true;
                     }
                 },
@@ -83,7 +57,7 @@ impl<T> Tree<T> for Octree<T>
                     let child_bounds = self.get_child_bounds(i);
                     if child_bounds.contains(actor_bounds) {
                         let mut node = Octree::<T>::new(child_bounds);
-                        node.add_actor(actor);
+                        node.insert(actor, actor_bounds);
                         self.children[i] = Some(Box::new(node));
                         return true;
                     }
@@ -96,25 +70,78 @@ impl<T> Tree<T> for Octree<T>
         return true;
     }
 
-    fn get_hit(&self, ray: &Ray<T>, t_min: T, t_max: T) -> Option<(Rc<Actor<T>>, Hit<T>)> {
-        if !self.get_bounds().hit(ray, t_min, t_max) {
-            return None;
+    fn get_child_bounds(&self, child_index: usize) -> Aabb<T> {
+        let k = child_index % 2;
+        let j = (child_index / 2) % 2;
+        let i = (child_index / 4) % 2;
+
+        let k = T::from_f64(k as f64);
+        let j = T::from_f64(j as f64);
+        let i = T::from_f64(i as f64);
+
+        let half = T::from_f64(0.5);
+
+        let (min_x, max_x) = self.get_bounds().get_axis_bounds(0);
+        let (min_y, max_y) = self.get_bounds().get_axis_bounds(1);
+        let (min_z, max_z) = self.get_bounds().get_axis_bounds(2);
+        let len = [half * (max_x - min_x), half * (max_y - min_y), half * (max_z - min_z)];
+
+        let offset = Vec3::<T>::from_array([len[0] * i, len[1] * j, len[2] * k]);
+
+        let p0 = Vec3::<T>::from_array([min_x, min_y, min_z]) + &offset;
+        let p1 = &p0 + Vec3::from_slice(&len);
+        Aabb::<T>::new(p0, p1)
+    }
+}
+
+impl<T> Tree<T> for Octree<T>
+    where T: Float
+{
+    fn add_actor(&mut self, actor: Arc<Actor<T>>) -> bool {
+        match actor.hitable.bounding_box() {
+            Some(actor_bounds) => self.insert(actor, &actor_bounds),
+            None => {
+                self.unbounded.push(actor);
+                true
+            }
+        }
+    }
+
+    fn get_hit_counted(&self, ray: &Ray<T>, interval: &Interval<T>, layer_mask: u32, counts: &mut TraversalCounts) -> Option<(&Actor<T>, Hit<T>)> {
+        counts.steps += 1;
+        let mut interval = *interval;
+        let mut result : Option<(&Actor<T>, Hit<T>)> = None;
+
+        for actor in self.unbounded.iter() {
+            if !actor.in_layers(layer_mask) {
+                continue;
+            }
+            counts.intersection_tests += 1;
+            if let Some(hit) = actor.hitable.hit_counted(ray, &interval, counts) {
+                interval.max = hit.t;
+                result = Some((actor.as_ref(), hit));
+            }
         }
 
-        let mut t_max = t_max;
-        let mut result : Option<(Rc<Actor<T>>, Hit<T>)> = None;
+        if !self.get_bounds().hit(ray, interval.min, interval.max) {
+            return result;
+        }
 
         for i in 0..self.actors.len() {
-            if let Some(hit) = self.actors[i].hitable.hit(ray, t_min, t_max) {
-                t_max = hit.t;
-                result = Some((Rc::clone(&self.actors[i]), hit));
+            if !self.actors[i].in_layers(layer_mask) {
+                continue;
+            }
+            counts.intersection_tests += 1;
+            if let Some(hit) = self.actors[i].hitable.hit_counted(ray, &interval, counts) {
+                interval.max = hit.t;
+                result = Some((self.actors[i].as_ref(), hit));
             }
         }
 
         for i in 0..8 {
             if let Some(child) = &self.children[i] {
-                if let Some((actor, hit)) = child.get_hit(ray, t_min, t_max) {
-                    t_max = hit.t;
+                if let Some((actor, hit)) = child.get_hit_counted(ray, &interval, layer_mask, counts) {
+                    interval.max = hit.t;
                     result = Some((actor, hit));
                 }
             }
@@ -127,15 +154,48 @@ impl<T> Tree<T> for Octree<T>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::hitable::Hitable;
+    use crate::material::null::NullMaterial;
+
+    // Hitable from outside the crate, counting the intersection tests
+    #[derive(Clone)]
+    struct Probe {
+        bounds: Option<Aabb<f64>>,
+        tests: Arc<AtomicUsize>
+    }
+
+    impl Hitable<f64> for Probe {
+        fn hit(&self, _ray: &Ray<f64>, _t_min: f64, _t_max: f64) -> Option<Hit<f64>> {
+            self.tests.fetch_add(1, Ordering::Relaxed);
+            None
+        }
+
+        fn get_bounds(&self) -> &Aabb<f64> {
+            unreachable!()
+        }
+
+        fn bounding_box(&self) -> Option<Aabb<f64>> {
+            self.bounds.clone()
+        }
+
+        fn unwrap(self: Box<Self>) -> Box<dyn Hitable<f64>> {
+            self
+        }
+
+        fn clone_box(&self) -> Box<dyn Hitable<f64>> {
+            Box::new(self.clone())
+        }
+    }
 
     #[test]
     fn child_bounds() {
         let p0 = Vec3::<f64>::from_array([0.,0.,0.]);
         let p1 = Vec3::<f64>::from_array([4.,6.,8.]);
-        let bounds = BoundingBox::new(p0, p1);
+        let bounds = Aabb::new(p0, p1);
         let node = Octree::new(bounds);
 
-        let mut children_bounds = Vec::<BoundingBox<f64>>::new();
+        let mut children_bounds = Vec::<Aabb<f64>>::new();
 
         // Ensure children are contained in the parent node
         for i in 0..8 {
@@ -158,4 +218,40 @@ mod tests {
         }
         assert_eq!(node.get_bounds().get_volume(), children_volume);
     }
+
+    #[test]
+    fn external_hitable() {
+        let bounds = Aabb::new(Vec3::from_array([-8.0, -8.0, -8.0]), Vec3::from_array([8.0, 8.0, 8.0]));
+        let mut tree = Octree::new(bounds);
+        let bounded = Arc::new(AtomicUsize::new(0));
+        let unbounded = Arc::new(AtomicUsize::new(0));
+
+        let probe_bounds = Aabb::new(Vec3::from_array([1.0, 1.0, 1.0]), Vec3::from_array([2.0, 2.0, 2.0]));
+        let probe = Probe { bounds: Some(probe_bounds), tests: Arc::clone(&bounded) };
+        assert!(tree.add_actor(Arc::new(Actor::new(Box::new(probe), Box::new(NullMaterial::new())))));
+        let probe = Probe { bounds: None, tests: Arc::clone(&unbounded) };
+        assert!(tree.add_actor(Arc::new(Actor::new(Box::new(probe), Box::new(NullMaterial::new())))));
+
+        // The bounded probe ends up deep in the tree, only tested by rays through its box
+        assert!(tree.actors.is_empty());
+        let interval = Interval::new(0.0, 100.0);
+        tree.get_hit(&Ray::from_array([1.5, 1.5, -10.0], [0.0, 0.0, 1.0]), &interval, 1);
+        assert_eq!(bounded.load(Ordering::Relaxed), 1);
+        tree.get_hit(&Ray::from_array([-1.5, 1.5, -10.0], [0.0, 0.0, 1.0]), &interval, 1);
+        assert_eq!(bounded.load(Ordering::Relaxed), 1);
+
+        // The unbounded one is always tested, even by rays missing the whole tree
+        tree.get_hit(&Ray::from_array([20.0, 20.0, -10.0], [0.0, 0.0, 1.0]), &interval, 1);
+        assert_eq!(bounded.load(Ordering::Relaxed), 1);
+        assert_eq!(unbounded.load(Ordering::Relaxed), 3);
+
+        // Counted like the probes count themselves
+        let mut counts = TraversalCounts::default();
+        tree.get_hit_counted(&Ray::from_array([20.0, 20.0, -10.0], [0.0, 0.0, 1.0]), &interval, 1, &mut counts);
+        assert_eq!(counts, TraversalCounts { steps: 1, intersection_tests: 1, triangle_tests: 0 });
+        let mut counts = TraversalCounts::default();
+        tree.get_hit_counted(&Ray::from_array([1.5, 1.5, -10.0], [0.0, 0.0, 1.0]), &interval, 1, &mut counts);
+        assert_eq!(counts.intersection_tests, 2);
+        assert!(counts.steps > 1);
+    }
 }
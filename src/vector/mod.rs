@@ -1,7 +1,15 @@
 use crate::float::Float;
+use crate::sampling;
 use std::ops;
+use std::fmt;
+use rand::Rng;
+
+pub mod onb;
+
+pub use onb::Onb;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
 pub struct Vec3<T: Float>
     where T: Float
 {
@@ -20,6 +28,10 @@ impl<T> Vec3<T>
         Vec3{data: input}
     }
 
+    pub fn to_array(&self) -> [T; 3] {
+        self.data
+    }
+
     pub fn from_slice(input: &[T]) -> Self {
         assert!(input.len() >= 3);
         let mut data = [T::zero(); 3];
@@ -29,6 +41,32 @@ impl<T> Vec3<T>
         Vec3{data}
     }
 
+    pub fn convert<U>(&self) -> Vec3<U>
+        where U: Float
+    {
+        let mut data = [U::zero(); 3];
+        for (d, x) in data.iter_mut().zip(self.data.iter()) {
+            *d = U::from(*x).unwrap();
+        }
+        Vec3::from_array(data)
+    }
+
+    pub fn to_f32(&self) -> Vec3<f32> {
+        self.convert()
+    }
+
+    pub fn to_f64(&self) -> Vec3<f64> {
+        self.convert()
+    }
+
+    pub fn from_f32(input: &Vec3<f32>) -> Self {
+        input.convert()
+    }
+
+    pub fn from_f64(input: &Vec3<f64>) -> Self {
+        input.convert()
+    }
+
     pub fn get_data(&self) -> &[T] {
         &self.data
     }
@@ -108,6 +146,122 @@ impl<T> Vec3<T>
             self.data[i] = self.data[i] / n;
         }
     }
+
+    pub fn abs(&self) -> Vec3<T> {
+        let mut result = [T::zero(); 3];
+        for (r, x) in result.iter_mut().zip(self.data.iter()) {
+            *r = x.abs();
+        }
+        Vec3::from_array(result)
+    }
+
+    pub fn sign(&self) -> Vec3<T> {
+        let mut result = [T::zero(); 3];
+        for (r, x) in result.iter_mut().zip(self.data.iter()) {
+            *r = x.signum();
+        }
+        Vec3::from_array(result)
+    }
+
+    pub fn sqrt(&self) -> Vec3<T> {
+        let mut result = [T::zero(); 3];
+        for (r, x) in result.iter_mut().zip(self.data.iter()) {
+            *r = x.sqrt();
+        }
+        Vec3::from_array(result)
+    }
+
+    pub fn lerp(a: &Vec3<T>, b: &Vec3<T>, t: T) -> Vec3<T> {
+        a * (T::one() - t) + b * t
+    }
+
+    // NaN components are left untouched
+    pub fn clamp(&self, min: T, max: T) -> Vec3<T> {
+        let mut result = [T::zero(); 3];
+        for (r, x) in result.iter_mut().zip(self.data.iter()) {
+            *r = if *x < min { min } else if *x > max { max } else { *x };
+        }
+        Vec3::from_array(result)
+    }
+
+    // Unlike T::min, a NaN in either vector propagates to the result
+    pub fn min(&self, other: &Vec3<T>) -> Vec3<T> {
+        let mut result = [T::zero(); 3];
+        for (i, r) in result.iter_mut().enumerate() {
+            let (x, y) = (self.data[i], other.data[i]);
+            *r = if x.is_nan() || y.is_nan() { T::nan() } else if y < x { y } else { x };
+        }
+        Vec3::from_array(result)
+    }
+
+    // Unlike T::max, a NaN in either vector propagates to the result
+    pub fn max(&self, other: &Vec3<T>) -> Vec3<T> {
+        let mut result = [T::zero(); 3];
+        for (i, r) in result.iter_mut().enumerate() {
+            let (x, y) = (self.data[i], other.data[i]);
+            *r = if x.is_nan() || y.is_nan() { T::nan() } else if y > x { y } else { x };
+        }
+        Vec3::from_array(result)
+    }
+
+    pub fn is_near_zero(&self, epsilon: T) -> bool {
+        self.data.iter().all(|x| x.abs() < epsilon)
+    }
+
+    // Relative luminance of a linear RGB color (Rec. 709 weights)
+    pub fn luminance(&self) -> T {
+        let r = T::from_f64(0.2126);
+        let g = T::from_f64(0.7152);
+        let b = T::from_f64(0.0722);
+        r * self.data[0] + g * self.data[1] + b * self.data[2]
+    }
+
+    // 8 bit color channels mapped to [0, 1]
+    pub fn from_rgb_u8(r: u8, g: u8, b: u8) -> Self {
+        let max = T::from_f64(255.0);
+        Vec3::from_array([T::from_f64(f64::from(r)) / max, T::from_f64(f64::from(g)) / max, T::from_f64(f64::from(b)) / max])
+    }
+
+    // The channels clamped to [0, 1] (NaN to 0) and scaled to 0..=255,
+    // rounding down
+    pub fn to_rgb_u8(&self) -> [u8; 3] {
+        let max = T::from_f64(255.0);
+        let byte = |value: T| (value.max(T::zero()).min(T::one()) * max).to_u8().unwrap_or(0);
+        [byte(self.data[0]), byte(self.data[1]), byte(self.data[2])]
+    }
+
+    pub fn random_in_unit_sphere<R>(rng: &mut R) -> Self
+        where R: Rng + ?Sized
+    {
+        sampling::unit_sphere(rng)
+    }
+
+    // Lies on the XY plane
+    pub fn random_in_unit_disk<R>(rng: &mut R) -> Self
+        where R: Rng + ?Sized
+    {
+        sampling::unit_disk(rng)
+    }
+
+    pub fn random_on_unit_sphere<R>(rng: &mut R) -> Self
+        where R: Rng + ?Sized
+    {
+        sampling::unit_sphere_surface(rng)
+    }
+
+    pub fn random_cosine_hemisphere<R>(rng: &mut R, normal: &Vec3<T>) -> Self
+        where R: Rng + ?Sized
+    {
+        sampling::cosine_hemisphere(rng, normal)
+    }
+}
+
+impl<T> fmt::Display for Vec3<T>
+    where T: Float + fmt::Display
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.data[0], self.data[1], self.data[2])
+    }
 }
 
 // Vec3 + Vec3
@@ -382,6 +536,14 @@ mod tests {
         let data = [4.0, 5.0, 6.0];
         let v = Vec3::<f64>::from_array(data);
         assert_eq!(v.get_data(), [4.0, 5.0, 6.0]);
+        assert_eq!(v.to_array(), data);
+    }
+
+    #[test]
+    fn display() {
+        let v = Vec3::<f64>::from_array([1.0, -2.5, 0.0]);
+        assert_eq!(v.to_string(), "(1, -2.5, 0)");
+        assert_eq!(format!("{:?}", v), "Vec3 { data: [1.0, -2.5, 0.0] }");
     }
 
     #[test]
@@ -551,6 +713,137 @@ mod tests {
         assert_eq!(v1.norm(), 1.0);
     }
 
+    #[test]
+    fn abs() {
+        let v = Vec3::<f64>::from_array([-1.5, 0.0, 2.0]);
+        assert_eq!(v.abs().get_data(), [1.5, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn sign() {
+        let v = Vec3::<f64>::from_array([-1.5, 0.0, 2.0]);
+        assert_eq!(v.sign().get_data(), [-1.0, 1.0, 1.0]);
+        let v = Vec3::<f64>::from_array([-0.0, -3.0, 1e-300]);
+        assert_eq!(v.sign().get_data(), [-1.0, -1.0, 1.0]);
+    }
+
+    #[test]
+    fn sqrt() {
+        let v = Vec3::<f64>::from_array([4.0, 0.0, 2.25]);
+        assert_eq!(v.sqrt().get_data(), [2.0, 0.0, 1.5]);
+        let v = Vec3::<f64>::from_array([-1.0, f64::NAN, f64::INFINITY]);
+        let r = v.sqrt();
+        assert!(r.get_data()[0].is_nan());
+        assert!(r.get_data()[1].is_nan());
+        assert_eq!(r.get_data()[2], f64::INFINITY);
+    }
+
+    #[test]
+    fn lerp() {
+        let a = Vec3::<f64>::from_array([0.0, 1.0, -2.0]);
+        let b = Vec3::<f64>::from_array([1.0, 3.0, 2.0]);
+        assert_eq!(Vec3::lerp(&a, &b, 0.0).get_data(), a.get_data());
+        assert_eq!(Vec3::lerp(&a, &b, 1.0).get_data(), b.get_data());
+        assert_eq!(Vec3::lerp(&a, &b, 0.5).get_data(), [0.5, 2.0, 0.0]);
+        assert_eq!(Vec3::lerp(&a, &b, 2.0).get_data(), [2.0, 5.0, 6.0]);
+        assert!(Vec3::lerp(&a, &b, f64::NAN).get_data()[0].is_nan());
+    }
+
+    #[test]
+    fn clamp() {
+        let v = Vec3::<f64>::from_array([-1.0, 0.5, 2.0]);
+        assert_eq!(v.clamp(0.0, 1.0).get_data(), [0.0, 0.5, 1.0]);
+        let v = Vec3::<f64>::from_array([f64::NAN, f64::NEG_INFINITY, f64::INFINITY]);
+        let r = v.clamp(0.0, 1.0);
+        assert!(r.get_data()[0].is_nan());
+        assert_eq!(r.get_data()[1], 0.0);
+        assert_eq!(r.get_data()[2], 1.0);
+    }
+
+    #[test]
+    fn min_max() {
+        let a = Vec3::<f64>::from_array([0.0, 3.0, -2.0]);
+        let b = Vec3::<f64>::from_array([1.0, 1.0, -2.0]);
+        assert_eq!(a.min(&b).get_data(), [0.0, 1.0, -2.0]);
+        assert_eq!(a.max(&b).get_data(), [1.0, 3.0, -2.0]);
+
+        let c = Vec3::<f64>::from_array([f64::NAN, 0.0, f64::INFINITY]);
+        let r = a.min(&c);
+        assert!(r.get_data()[0].is_nan());
+        assert_eq!(r.get_data()[1..], [0.0, -2.0]);
+        let r = c.max(&a);
+        assert!(r.get_data()[0].is_nan());
+        assert_eq!(r.get_data()[1..], [3.0, f64::INFINITY]);
+    }
+
+    #[test]
+    fn is_near_zero() {
+        let v = Vec3::<f64>::from_array([1e-9, -1e-9, 0.0]);
+        assert!(v.is_near_zero(1e-8));
+        assert!(!v.is_near_zero(1e-10));
+        let v = Vec3::<f64>::from_array([0.0, 0.0, 1.0]);
+        assert!(!v.is_near_zero(1e-8));
+        let v = Vec3::<f64>::from_array([0.0, f64::NAN, 0.0]);
+        assert!(!v.is_near_zero(1e-8));
+    }
+
+    #[test]
+    fn luminance() {
+        let v = Vec3::<f64>::from_array([1.0, 1.0, 1.0]);
+        assert!((v.luminance() - 1.0).abs() < 1e-12);
+        let v = Vec3::<f64>::from_array([0.0, 1.0, 0.0]);
+        assert_eq!(v.luminance(), 0.7152);
+        let v = Vec3::<f64>::from_array([f64::NAN, 1.0, 0.0]);
+        assert!(v.luminance().is_nan());
+    }
+
+    #[test]
+    fn rgb_u8() {
+        let v = Vec3::<f64>::from_rgb_u8(0, 51, 255);
+        assert_eq!(v.get_data(), [0.0, 0.2, 1.0]);
+        assert_eq!(v.to_rgb_u8(), [0, 51, 255]);
+        let v = Vec3::<f32>::from_rgb_u8(1, 128, 254);
+        assert_eq!(v.to_rgb_u8(), [1, 128, 254]);
+        // Clamped, NaN is black
+        let v = Vec3::<f64>::from_array([-0.5, 1.5, f64::NAN]);
+        assert_eq!(v.to_rgb_u8(), [0, 255, 0]);
+        assert_eq!(Vec3::<f64>::from_array([0.999, 0.5, f64::INFINITY]).to_rgb_u8(), [254, 127, 255]);
+    }
+
+    #[test]
+    fn random() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let normal = Vec3::<f64>::from_array([0.0, 1.0, 0.0]);
+        for _i in 0..1000 {
+            assert!(Vec3::<f64>::random_in_unit_sphere(&mut rng).norm() < 1.0);
+            let v = Vec3::<f64>::random_in_unit_disk(&mut rng);
+            assert!(v.norm() < 1.0);
+            assert_eq!(v.get_data()[2], 0.0);
+            assert!((Vec3::<f64>::random_on_unit_sphere(&mut rng).norm() - 1.0).abs() < 1e-12);
+            assert!(Vec3::random_cosine_hemisphere(&mut rng, &normal).dot(&normal) >= 0.0);
+        }
+
+        // The same seed gives the same vectors
+        let v1 = Vec3::<f64>::random_in_unit_sphere(&mut StdRng::seed_from_u64(5));
+        let v2 = Vec3::<f64>::random_in_unit_sphere(&mut StdRng::seed_from_u64(5));
+        assert_eq!(v1.get_data(), v2.get_data());
+    }
+
+    #[test]
+    fn convert() {
+        let v = Vec3::<f64>::from_array([1.0, 0.1, -2.5]);
+        let w = v.to_f32();
+        assert_eq!(w.get_data(), [1.0f32, 0.1f32, -2.5f32]);
+        let u = Vec3::<f64>::from_f32(&w);
+        assert!((u - &v).norm() < 1e-6);
+        let u = Vec3::<f32>::from_f64(&v);
+        assert_eq!(u.get_data(), w.get_data());
+        assert_eq!(w.to_f64().get_data(), [1.0, 0.1f32 as f64, -2.5]);
+    }
+
     #[test]
     fn set() {
         let mut v = Vec3::<f64>::new();
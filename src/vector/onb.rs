@@ -0,0 +1,131 @@
+use crate::float::Float;
+use crate::vector::Vec3;
+
+pub struct Onb<T>
+    where T: Float
+{
+    u: Vec3<T>,
+    v: Vec3<T>,
+    w: Vec3<T>
+}
+
+impl<T> Onb<T>
+    where T: Float
+{
+    pub fn from_w(normal: &Vec3<T>) -> Self {
+        // Branchless construction from Duff et al. (2017),
+        // "Building an Orthonormal Basis, Revisited".
+        // Stable for any unit normal, including the ones close to +/-Z.
+        let mut w = Vec3::from_slice(normal.get_data());
+        w.normalize();
+        let n = w.get_data();
+        let (x, y, z) = (n[0], n[1], n[2]);
+
+        let sign = if z.is_sign_negative() { -T::one() } else { T::one() };
+        let a = -T::one() / (sign + z);
+        let b = x * y * a;
+
+        let u = Vec3::from_array([T::one() + sign * x * x * a, sign * b, -sign * x]);
+        let v = Vec3::from_array([b, sign + y * y * a, -y]);
+
+        Onb { u, v, w }
+    }
+
+    pub fn get_u(&self) -> &Vec3<T> {
+        &self.u
+    }
+
+    pub fn get_v(&self) -> &Vec3<T> {
+        &self.v
+    }
+
+    pub fn get_w(&self) -> &Vec3<T> {
+        &self.w
+    }
+
+    pub fn local_to_world(&self, local: &Vec3<T>) -> Vec3<T> {
+        let a = local.get_data();
+        &self.u * a[0] + &self.v * a[1] + &self.w * a[2]
+    }
+
+    pub fn world_to_local(&self, world: &Vec3<T>) -> Vec3<T> {
+        Vec3::from_array([world.dot(&self.u), world.dot(&self.v), world.dot(&self.w)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    fn check_onb(onb: &Onb<f64>) {
+        let eps = 1e-12;
+        assert!((onb.get_u().norm() - 1.0).abs() < eps);
+        assert!((onb.get_v().norm() - 1.0).abs() < eps);
+        assert!((onb.get_w().norm() - 1.0).abs() < eps);
+        assert!(onb.get_u().dot(onb.get_v()).abs() < eps);
+        assert!(onb.get_u().dot(onb.get_w()).abs() < eps);
+        assert!(onb.get_v().dot(onb.get_w()).abs() < eps);
+        // Right handed
+        let w = onb.get_u().cross(onb.get_v());
+        assert!((&w - onb.get_w()).norm() < eps);
+    }
+
+    #[test]
+    fn init() {
+        let normal = Vec3::<f64>::from_array([0.0, 0.0, 1.0]);
+        let onb = Onb::from_w(&normal);
+        check_onb(&onb);
+        assert_eq!(onb.get_w().get_data(), [0.0, 0.0, 1.0]);
+
+        let normal = Vec3::<f64>::from_array([0.0, 0.0, -1.0]);
+        let onb = Onb::from_w(&normal);
+        check_onb(&onb);
+        assert_eq!(onb.get_w().get_data(), [0.0, 0.0, -1.0]);
+
+        let normal = Vec3::<f64>::from_array([0.0, 0.0, 3.0]);
+        let onb = Onb::from_w(&normal);
+        check_onb(&onb);
+    }
+
+    #[test]
+    fn random_normals() {
+        let mut rng = rand::thread_rng();
+        for _i in 0..10000 {
+            let mut normal = Vec3::<f64>::from_array([
+                rng.gen::<f64>() * 2.0 - 1.0,
+                rng.gen::<f64>() * 2.0 - 1.0,
+                rng.gen::<f64>() * 2.0 - 1.0
+            ]);
+            if normal.is_near_zero(1e-6) {
+                continue;
+            }
+            normal.normalize();
+            check_onb(&Onb::from_w(&normal));
+        }
+
+        // Normals very close to the poles
+        for i in 0..1000 {
+            let delta = 1e-9 * (i as f64 - 500.0);
+            for z in [1.0, -1.0].iter() {
+                let mut normal = Vec3::<f64>::from_array([delta, -delta, *z]);
+                normal.normalize();
+                check_onb(&Onb::from_w(&normal));
+            }
+        }
+    }
+
+    #[test]
+    fn transform() {
+        let normal = Vec3::<f64>::from_array([1.0, 2.0, -2.0]);
+        let onb = Onb::from_w(&normal);
+
+        let local = Vec3::<f64>::from_array([0.0, 0.0, 1.0]);
+        let world = onb.local_to_world(&local);
+        assert!((&world - onb.get_w()).norm() < 1e-12);
+
+        let point = Vec3::<f64>::from_array([0.3, -1.5, 4.0]);
+        let back = onb.local_to_world(&onb.world_to_local(&point));
+        assert!((&back - &point).norm() < 1e-12);
+    }
+}
@@ -1,34 +1,9 @@
 use std::fs::OpenOptions;
 use std::io::prelude::*;
 use rand::prelude::*;
-use std::time::Instant;
-
-use ray_tracer::vector::Vec3;
-use ray_tracer::scene::Scene;
-use ray_tracer::hitable::Hitable;
-use ray_tracer::hitable::primitive::Sphere;
-use ray_tracer::hitable::primitive::Rectangle;
-use ray_tracer::hitable::primitive::Cube;
-use ray_tracer::hitable::primitive::Group;
-use ray_tracer::hitable::transform::Translation;
-use ray_tracer::camera::Camera;
-use ray_tracer::camera::perspective::PerspectiveCamera;
-use ray_tracer::renderer::Renderer;
-use ray_tracer::renderer::Image;
-use ray_tracer::material::Material;
-use ray_tracer::material::plain::PlainMaterial;
-use ray_tracer::material::lambertian::LambertianMaterial;
-use ray_tracer::material::metal::MetalMaterial;
-use ray_tracer::material::dielectric::DielectricMaterial;
-use ray_tracer::actor::Actor;
-use ray_tracer::tree::TreeType;
-use ray_tracer::texture::uniform::UniformTexture;
-use ray_tracer::texture::checker::CheckerTexture;
-use ray_tracer::constants::Axis;
-
-fn to_u8(f: f64) -> u8 {
-    (f * 255.0) as u8
-}
+
+use ray_tracer::prelude::*;
+use ray_tracer::scenes;
 
 fn mix_images(image: &mut Image<f64>, delta: &Image<f64>, iteration: usize) {
     assert_eq!(delta.height, image.height);
@@ -62,6 +37,43 @@ fn image_diff(reference: &Image<f64>, image: &Image<f64>) -> f64 {
     diff
 }
 
+fn count_different_pixels(reference: &Image<f64>, image: &Image<f64>, tolerance: f64) -> usize {
+    assert_eq!(reference.height, image.height);
+    assert_eq!(reference.width, image.width);
+
+    let mut count = 0;
+    for index in 0..image.width * image.height {
+        let ref_color = Vec3::from_array([reference.data[3 * index], reference.data[3 * index + 1], reference.data[3 * index + 2]]);
+        let image_color = Vec3::from_array([image.data[3 * index], image.data[3 * index + 1], image.data[3 * index + 2]]);
+        if (ref_color - image_color).norm() > tolerance {
+            count += 1;
+        }
+    }
+    count
+}
+
+// 16 independent estimates of the red radiance along the ray, each the mean
+// of the given number of samples
+fn estimates(scene: &Scene<f64>, ray: &Ray<f64>, samples: usize, rng: &mut StdRng) -> Vec<f64> {
+    (0..16).map(|_| (0..samples).map(|_| scene.get_color(ray, 0, 2, 2, rng).get_data()[0]).sum::<f64>() / samples as f64).collect()
+}
+
+// Mean and standard deviation of the estimates
+fn stats(values: &[f64]) -> (f64, f64) {
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / (values.len() - 1) as f64;
+    (mean, variance.sqrt())
+}
+
+// A hit on a floor, to look up the caustics there
+fn floor_hit(point: [f64; 3], normal: [f64; 3]) -> Hit<f64> {
+    Hit {
+        point: Vec3::from_array(point),
+        normal: Vec3::from_array(normal),
+        t: 1.0, u: 0.0, v: 0.0, front_face: true, barycentric: None, face: None, footprint: None
+    }
+}
+
 fn print_ppm(image: &Image<f64>, gamma: f64, filename: &str) {
     let mut file = OpenOptions::new()
         .write(true)
@@ -77,229 +89,33 @@ fn print_ppm(image: &Image<f64>, gamma: f64, filename: &str) {
     for j in 0..image.height {
         for i in 0..image.width {
             let index = j * image.width + i;
-            if let Err(e) = writeln!(
-                file, "{} {} {}",
-                to_u8(image.data[3 * index].powf(1.0 / gamma)),
-                to_u8(image.data[3 * index + 1].powf(1.0 / gamma)),
-                to_u8(image.data[3 * index + 2].powf(1.0 / gamma))
-            ) {
+            let color = Vec3::from_array([
+                image.data[3 * index].powf(1.0 / gamma),
+                image.data[3 * index + 1].powf(1.0 / gamma),
+                image.data[3 * index + 2].powf(1.0 / gamma)
+            ]);
+            let [r, g, b] = color.to_rgb_u8();
+            if let Err(e) = writeln!(file, "{} {} {}", r, g, b) {
                 eprintln!("Couldn't write to file: {}", e);
             }
         }
     }
 }
 
-fn create_rectangle_room(length: f64, width: f64, height: f64, light: f64) -> Vec<Actor<f64>> {
-    let mut actors = vec![];
-
-    let dimming = 1.0;
-
-    // Rectangle used as light
-    let _width_axis = Axis::X;
-    let _height_axis = Axis::Y;
-    // let hitable = Box::new(Rectangle::new(light, width_axis, light, height_axis));
-    let hitable = Box::new(Cube::new(light, light, 0.125 * light));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, width / 4.0, height / 2.0])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
-    let material = Box::new(PlainMaterial::<f64>::new(texture));
-    let actor = Actor::<f64> { hitable, material};
-    actors.push(actor);
-
-    // Rectangle used as floor
-    let width_axis = Axis::X;
-    let height_axis = Axis::Y;
-    let hitable = Box::new(Rectangle::new(length, width_axis, width, height_axis));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -height / 2.0])));
-    let texture0 = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
-    let texture1 = Box::new(UniformTexture::new(Vec3::from_array([0.8, 0.8, 0.8])));
-    let mut texture = Box::new(CheckerTexture::new(texture0, texture1));
-    texture.set_period(Vec3::from_array([length / 8.0, length / 8.0, 1.0]));
-    let material = Box::new(LambertianMaterial::<f64>::new(texture, dimming));
-    let actor = Actor::<f64> { hitable, material};
-    actors.push(actor);
-
-    // Rectangle used as front wall
-    let width_axis = Axis::X;
-    let height_axis = Axis::Z;
-    let rectangle = Box::new(Rectangle::new(length, width_axis, height, height_axis));
-    let rectangle = Box::new(Translation::new(rectangle, Vec3::from_array([0.0, width / 2.0, 0.0])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
-    let material = Box::new(LambertianMaterial::<f64>::new(texture, dimming));
-    let actor = Actor::<f64> { hitable: rectangle, material};
-    actors.push(actor);
-
-    // Rectangle used as back wall
-    let width_axis = Axis::Z;
-    let height_axis = Axis::X;
-    let rectangle = Box::new(Rectangle::new(height, width_axis, length, height_axis));
-    let rectangle = Box::new(Translation::new(rectangle, Vec3::from_array([0.0, - width / 2.0, 0.0])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
-    let material = Box::new(LambertianMaterial::<f64>::new(texture, dimming));
-    let _actor = Actor::<f64> { hitable: rectangle, material};
-    // actors.push(actor);
-
-    // Rectangle used as left wall
-    let width_axis = Axis::Y;
-    let height_axis = Axis::Z;
-    let rectangle = Box::new(Rectangle::new(width, width_axis, height, height_axis));
-    let rectangle = Box::new(Translation::new(rectangle, Vec3::from_array([-length / 2.0, 0.0, 0.0])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([0.1, 1.0, 0.1])));
-    let material = Box::new(LambertianMaterial::<f64>::new(texture, dimming));
-    let actor = Actor::<f64> { hitable: rectangle, material};
-    actors.push(actor);
-
-    // Rectangle used as right wall
-    let width_axis = Axis::Z;
-    let height_axis = Axis::Y;
-    let rectangle = Box::new(Rectangle::new(height, width_axis, width, height_axis));
-    let rectangle = Box::new(Translation::new(rectangle, Vec3::from_array([length / 2.0, 0.0, 0.0])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.1, 0.1])));
-    let material = Box::new(LambertianMaterial::<f64>::new(texture, dimming));
-    let actor = Actor::<f64> { hitable: rectangle, material};
-    actors.push(actor);
-
-    // Rectangle used as ceiling
-    let width_axis = Axis::Y;
-    let height_axis = Axis::X;
-    let rectangle = Box::new(Rectangle::new(width, width_axis, length, height_axis));
-    let rectangle = Box::new(Translation::new(rectangle, Vec3::from_array([0.0, 0.0, height / 2.0])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
-    let material = Box::new(LambertianMaterial::<f64>::new(texture, dimming));
-    let actor = Actor::<f64> { hitable: rectangle, material};
-    actors.push(actor);
-
-    actors
-}
-
-fn create_cube_box(length: f64, width: f64, height: f64, thickness: f64) -> Box<Group<f64>> {
-    let mut group : Box<Group<f64>> = Box::new(Group::<f64>::new());
-
-    // cube used as floor
-    let hitable = Box::new(Cube::new(length, width, thickness));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -height / 2.0])));
-    group.add_hitable(hitable);
-
-    // cube used as ceiling
-    let hitable = Box::new(Cube::new(length, width, thickness));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, height / 2.0])));
-    group.add_hitable(hitable);
-
-    // cube used as left wall
-    let hitable = Box::new(Cube::new(thickness, width, height));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([- length / 2.0, 0.0, 0.0])));
-    group.add_hitable(hitable);
-
-    // cube used as right wall
-    let hitable = Box::new(Cube::new(thickness, width, height));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([length / 2.0, 0.0, 0.0])));
-    group.add_hitable(hitable);
-
-    // cube used as back wall
-    let hitable = Box::new(Cube::new(length, thickness, height));
-    let _hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, width / 2.0, 0.0])));
-    // group.add_hitable(hitable);
-
-    group
-}
-
 #[test]
 fn rectangle_room() {
-    let room_length = 16.0;
-    let room_width = 16.0;
-    let room_height = 9.0;
-    let mut actors = create_rectangle_room(room_length, room_width, room_height, 6.5);
-
-    let mut scene = Scene::<f64>::new();
-    // scene.set_background(Vec3::from_array([0.1, 0.1, 0.1]));
-
-    loop {
-        let actor = actors.pop();
-        match actor {
-            Some(actor) => {
-                scene.add_actor(actor);
-            },
-            None => {
-                break;
-            }
-        }
-    };
-
-    let box_size = 4.0;
-    let box_thickness = 0.05 * box_size;
-    let hitable = create_cube_box(box_size, box_size, box_size, box_thickness);
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([- 0.3 * room_length, 0.3 * room_width, - 0.5 * room_height + 0.5 * box_size])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([0.2, 0.2, 1.0])));
-    let material = Box::new(LambertianMaterial::<f64>::new(texture, 1.0));
-    let actor = Actor {hitable, material};
-    scene.add_actor(actor);
-
-    // cube used as front glass wall
-    let hitable = Box::new(Cube::new(box_size, box_thickness, box_size));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, - box_size / 2.0, 0.0])));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([- 0.3 * room_length, 0.3 * room_width, - 0.5 * room_height + 0.5 * box_size])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
-    let material = Box::new(DielectricMaterial::<f64>::new(texture, 1.6));
-    let actor = Actor {hitable, material};
-    scene.add_actor(actor);
-
-    // cube used as back glass wall
-    let hitable = Box::new(Cube::new(box_size, box_thickness, box_size));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, box_size / 2.0, 0.0])));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([- 0.3 * room_length, 0.3 * room_width, - 0.5 * room_height + 0.5 * box_size])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
-    let material = Box::new(DielectricMaterial::<f64>::new(texture, 1.6));
-    let actor = Actor {hitable, material};
-    scene.add_actor(actor);
-
-    let sphere_size = 1.0;
-    let hitable = Box::new(Sphere::new(sphere_size));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([- 0.3 * room_length, 0.3 * room_width, - 0.5 * room_height + 0.5 * box_size])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.2, 0.2])));
-    let material = Box::new(LambertianMaterial::<f64>::new(texture, 1.0));
-    let actor = Actor {hitable, material};
-    scene.add_actor(actor);
-
-    // Large glass sphere in the front
-    let sphere_size = 3.0;
-    let hitable = Box::new(Sphere::new(sphere_size));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.1 * room_width, - 0.5 * room_height + sphere_size])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
-    let material = Box::new(DielectricMaterial::<f64>::new(texture, 2.4));
-    let actor = Actor {hitable, material};
-    scene.add_actor(actor);
-
-    // Large metal sphere in the front;
-    let sphere_size = 2.0;
-    let hitable = Box::new(Sphere::new(sphere_size));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.3 * room_length, 0.3 * room_width, - 0.5 * room_height + sphere_size])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([0.9, 0.9, 0.9])));
-    let material = Box::new(MetalMaterial::<f64>::new(texture, 0.0));
-    let actor = Actor {hitable, material};
-    scene.add_actor(actor);
-
     let mul = 4;
     let width = 12 * mul;
     let height = 8 * mul;
-    let aspect = width as f64 / height as f64;
-    let mut camera = PerspectiveCamera::<f64>::new();
-    camera.set_aspect(aspect);
-    camera.set_fov(0.37 * std::f64::consts::PI);
-    camera.set_position(&[0.0, - 0.49 * room_width, 0.0]);
-    camera.set_direction(&[0.0, 1.0, 0.0]);
-    // camera.set_lookat(&[0.0, 0.0, 0.0]);
-    camera.set_up(&[0.0, 0.0, 1.0]);
-    camera.set_fov(0.3 * std::f64::consts::PI);
-    camera.set_focus(1.0);
-
-    scene.set_tree_type(TreeType::Oct);
+    let (scene, camera) = scenes::rectangle_room(width, height).unwrap();
 
-    let renderer = Renderer::new(0, width, 0, height, width, height, 0, 0, false);
-    let image = renderer.render(&mut scene, &camera);
+    let renderer = Renderer::new(0, width, 0, height, width, height, 0, 0, false).unwrap();
+    let image = renderer.render(&scene, &camera);
     let gamma = 2.0;
     print_ppm(&image, gamma, "rectangle_room_preview.ppm");
 
     let gamma = 2.6;
-    let renderer = Renderer::new(0, width, 0, height, width, height, 1, 32, false);
+    let renderer = Renderer::new(0, width, 0, height, width, height, 1, 32, false).unwrap();
     let sampling = 1024;
     let mut image = Image::new(width, height);
     for i in 0..sampling {
@@ -309,8 +125,8 @@ fn rectangle_room() {
     }
 }
 
-#[test]
-fn cube_scene() {
+// Same as scenes::cube_scene, without the actor builder
+fn create_cube_scene_verbose(width: usize, height: usize) -> (Scene<f64>, PerspectiveCamera<f64>) {
     let mut scene = Scene::<f64>::new();
     scene.set_background(Vec3::from_array([0.2, 0.2, 0.7]));
 
@@ -326,18 +142,18 @@ fn cube_scene() {
     let texture1 = Box::new(UniformTexture::new(Vec3::from_array([0.75, 0.75, 0.75])));
     let texture = Box::new(CheckerTexture::new(texture0, texture1));
     let material = Box::new(LambertianMaterial::<f64>::new(texture, 0.65));
-    let actor = Actor::<f64> { hitable, material};
+    let actor = Actor::<f64>::new(hitable, material);
     scene.add_actor(actor);
 
     // Box on the floor
     let length = 6.0;
-    let width = 3.0;
+    let width_box = 3.0;
     let heigth = 5.0;
-    let hitable = Box::new(Cube::new(length, width, heigth));
+    let hitable = Box::new(Cube::new(length, width_box, heigth));
     let hitable = Box::new(Translation::new(hitable, Vec3::from_array([4.0, room_size / 3.0, -room_size / 2.0])));
     let texture = Box::new(UniformTexture::new(Vec3::from_array([0.0, 1.0, 0.0])));
     let material = Box::new(LambertianMaterial::<f64>::new(texture, 0.65));
-    let actor = Actor::<f64> { hitable, material};
+    let actor = Actor::<f64>::new(hitable, material);
     scene.add_actor(actor);
 
     // Rectangle used as light
@@ -347,272 +163,388 @@ fn cube_scene() {
     let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, room_size / 2.0])));
     let texture = Box::new(UniformTexture::new(Vec3::from_array([2.0, 2.0, 2.0])));
     let material = Box::new(PlainMaterial::<f64>::new(texture));
-    let actor = Actor::<f64> { hitable, material};
+    let actor = Actor::<f64>::new(hitable, material);
     scene.add_actor(actor);
 
-    let mul = 4;
-    let width = 12 * mul;
-    let height = 8 * mul;
     let aspect = width as f64 / height as f64;
     let mut camera = PerspectiveCamera::<f64>::new();
-    camera.set_aspect(aspect);
-    camera.set_fov(0.35 * std::f64::consts::PI);
+    camera.set_aspect(aspect).unwrap();
+    camera.set_fov(0.35 * std::f64::consts::PI).unwrap();
     camera.set_position(&[0.0, - 0.5 * room_size, 0.0]);
     camera.set_direction(&[0.0, 1.0, 0.0]);
     // camera.set_lookat(&[0.0, 0.0, 0.0]);
     camera.set_up(&[0.0, 0.0, 1.0]);
-    camera.set_fov(0.4 * std::f64::consts::PI);
-    camera.set_focus(1.0);
+    camera.set_fov(0.4 * std::f64::consts::PI).unwrap();
+    camera.set_focus(1.0).unwrap();
 
     scene.set_tree_type(TreeType::Oct);
 
-    let renderer = Renderer::new(0, width, 0, height, width, height, 0, 0, false);
-    let image = renderer.render(&mut scene, &camera);
+    (scene, camera)
+}
+
+#[test]
+fn cube_scene() {
+    let mul = 4;
+    let width = 12 * mul;
+    let height = 8 * mul;
+    let (scene, camera) = scenes::cube_scene(width, height).unwrap();
+
+    let renderer = Renderer::new(0, width, 0, height, width, height, 0, 0, false).unwrap();
+    let image = renderer.render(&scene, &camera);
     let gamma = 2.0;
     print_ppm(&image, gamma, "cube_scene_preview.ppm");
 
-    let renderer = Renderer::new(0, width, 0, height, width, height, 32, 8, false);
-    let image = renderer.render(&mut scene, &camera);
+    let renderer = Renderer::new(0, width, 0, height, width, height, 32, 8, false).unwrap();
+    let image = renderer.render(&scene, &camera);
     print_ppm(&image, gamma, "cube_scene.ppm");
 }
 
 #[test]
-fn basic_scene() {
-    let mut scene = Scene::<f64>::new();
-    scene.set_background(Vec3::from_array([0.2, 0.2, 0.7]));
-    scene.set_background(Vec3::from_array([0.75, 0.75, 0.75]));
+fn edges() {
+    let width = 120;
+    let height = 80;
+    let (mut scene, camera) = scenes::cube_scene(width, height).unwrap();
+    let center = Vec3::from_array([-4.0, 5.5, -5.5]);
+    scene.add_actor(Actor::sphere(2.0).at(center.to_array()).lambertian([1.0, 0.2, 0.2], 0.65));
+
+    let renderer = Renderer::new(0, width, 0, height, width, height, 8, 4, false).unwrap();
+    let normals = renderer.render_normal_pass(&scene, &camera);
+    let depths = renderer.render_depth_pass(&scene, &camera, DepthEncoding::Distance, DepthFilter::Min);
+    let mut image = renderer.render(&scene, &camera);
+    image.overlay_edges(&normals, &depths, &EdgeParams::new(Vec3::from_array([1.0, 0.0, 1.0]))).unwrap();
+    print_ppm(&image, 2.0, "cube_scene_edges.ppm");
+
+    let mut edges = Image::new(width, height);
+    edges.overlay_edges(&normals, &depths, &EdgeParams::new(Vec3::from_array([1.0, 1.0, 1.0]))).unwrap();
+    let pixel = |point: [f64; 3]| {
+        let (r, s) = camera.project(&Vec3::from_array(point)).unwrap();
+        (((r + 1.0) * 0.5 * width as f64) as usize, ((s + 1.0) * 0.5 * height as f64) as usize)
+    };
+    let is_edge = |i: usize, j: usize| edges.data[3 * (j * width + i)] == 1.0;
+    // Any edge pixel next to the point
+    let on_edge = |point: [f64; 3]| {
+        let (i, j) = pixel(point);
+        (i - 1..=i + 1).any(|i| (j - 1..=j + 1).any(|j| is_edge(i, j)))
+    };
 
-    let r = 1.0;
-    let sphere = Box::new(Sphere::<f64>::new(r));
-    let sphere = Translation::new(sphere, Vec3::from_array([0.0, r, -4.0]));
-    let texture = UniformTexture::new(Vec3::from_array([1.0, 0.2, 0.2]));
-    let material = LambertianMaterial::<f64>::new(Box::new(texture), 0.5);
-    let actor = Actor::<f64> { hitable: Box::new(sphere), material: Box::new(material)};
-    scene.add_actor(actor);
+    // The edges between the faces of the cube, and where it meets the floor
+    assert!(on_edge([1.0, 3.5, -6.25]));
+    assert!(on_edge([4.0, 3.5, -5.0]));
+    assert!(on_edge([4.0, 3.5, -7.5]));
+    assert!(on_edge([7.0, 3.5, -6.25]));
+    // The outline of the sphere, but nothing inside of it
+    assert!(on_edge([-6.0, 5.5, -5.5]));
+    let (ic, jc) = pixel(center.to_array());
+    let (ir, _) = pixel([-2.0, 5.5, -5.5]);
+    let radius = (ir - ic) as f64;
+    for j in 0..height {
+        for i in 0..width {
+            let distance = ((i as f64 - ic as f64).powi(2) + (j as f64 - jc as f64).powi(2)).sqrt();
+            if distance < 0.7 * radius {
+                assert!(!is_edge(i, j), "edge inside of the sphere at {}, {}", i, j);
+            }
+        }
+    }
+    // Nor on the floor between the two
+    assert!(!on_edge([-0.5, 5.0, -7.5]));
 }
 
 #[test]
-fn sphere_in_box() {
-    let mut scene = Scene::<f64>::new();
-    scene.set_background(Vec3::from_array([0.2, 0.2, 0.8]));
-
-    let box_size = 5.0;
-    let box_thickness = 0.05 * box_size;
-    let hitable = create_cube_box(box_size, box_size, box_size, box_thickness);
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([0.9, 0.9, 0.9])));
-    let material = Box::new(LambertianMaterial::<f64>::new(texture, 0.75));
-    let actor = Actor {hitable, material};
-    scene.add_actor(actor);
-
-    // cube used as front glass wall
-    let hitable = Box::new(Cube::new(box_size, box_thickness, box_size));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, - box_size / 2.0, 0.0])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
-    let material = Box::new(DielectricMaterial::<f64>::new(texture, 1.5));
-    let actor = Actor {hitable, material};
-    scene.add_actor(actor);
-
-    let sphere_size = 1.0;
-    let hitable = Box::new(Sphere::new(sphere_size));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 0.2, 0.2])));
-    let material = Box::new(LambertianMaterial::<f64>::new(texture, 0.65));
-    let actor = Actor {hitable, material};
-    scene.add_actor(actor);
-
-    // Light
-    let sphere_size = 3.0;
-    let hitable = Box::new(Sphere::new(sphere_size));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, - 2.5 * box_size + sphere_size + 0.1, 0.0])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([2.0, 2.0, 2.0])));
-    let material = Box::new(PlainMaterial::<f64>::new(texture));
-    let actor = Actor {hitable, material};
-    scene.add_actor(actor);
-
-    // Light
-    let sphere_size = 3.0;
-    let hitable = Box::new(Sphere::new(sphere_size));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([- 2.5 * box_size + sphere_size + 0.1, 0.0, 0.0])));
-    let texture = Box::new(UniformTexture::new(Vec3::from_array([2.0, 2.0, 2.0])));
-    let material = Box::new(PlainMaterial::<f64>::new(texture));
-    let actor = Actor {hitable, material};
-    scene.add_actor(actor);
+fn actor_builder() {
+    let width = 48;
+    let height = 32;
+    let (scene, camera) = scenes::cube_scene(width, height).unwrap();
+    let (verbose_scene, verbose_camera) = create_cube_scene_verbose(width, height);
+
+    // Without reflections the colors don't depend on random numbers
+    let renderer = Renderer::new(0, width, 0, height, width, height, 0, 0, false).unwrap();
+    let image = renderer.render(&scene, &camera);
+    let verbose_image = renderer.render(&verbose_scene, &verbose_camera);
+    assert_eq!(image.data, verbose_image.data);
+}
 
+#[test]
+fn precision() {
     let mul = 4;
     let width = 12 * mul;
     let height = 8 * mul;
-    let aspect = width as f64 / height as f64;
-    let mut camera = PerspectiveCamera::<f64>::new();
-    camera.set_aspect(aspect);
-    camera.set_fov(0.35 * std::f64::consts::PI);
-    camera.set_position(&[-4.0, - 1.5 * box_size, 0.0]);
-    // camera.set_direction(&[0.0, 1.0, 0.0]);
-    camera.set_lookat(&[0.0, 0.0, 0.0]);
-    camera.set_up(&[0.0, 0.0, 1.0]);
-    camera.set_fov(0.4 * std::f64::consts::PI);
-    camera.set_focus(1.0);
+    let (mut scene, camera) = scenes::cube_scene(width, height).unwrap();
+    scene.set_timing(true);
+
+    let scene_f32: Scene<f32> = scene.convert_precision().unwrap();
+    assert!(scene_f32.get_timing());
+    let camera_f32: PerspectiveCamera<f32> = camera.convert_precision().unwrap();
+
+    let renderer = Renderer::new(0, width, 0, height, width, height, 0, 0, false).unwrap();
+    let image = renderer.render(&scene, &camera);
+    let image_f32 = renderer.render(&scene_f32, &camera_f32);
+
+    // Apart from a few pixels on the edges of objects and of the checker
+    // pattern, the two precisions produce the same colors
+    let n_pixels = width * height;
+    let different = count_different_pixels(&image, &image_f32.to_f64(), 1e-4);
+    assert!(different < n_pixels / 50);
+
+    // And back to the original precision
+    let scene_f64: Scene<f64> = scene_f32.convert_precision().unwrap();
+    let image_f64 = renderer.render(&scene_f64, &camera);
+    let different = count_different_pixels(&image, &image_f64, 1e-4);
+    assert!(different < n_pixels / 50);
+}
 
-    scene.set_tree_type(TreeType::Oct);
+#[test]
+fn clone_scene() {
+    let width = 48;
+    let height = 32;
+    let (scene, camera) = scenes::cube_scene(width, height).unwrap();
+    let mut renderer = Renderer::new(0, width, 0, height, width, height, 4, 4, false).unwrap();
+    renderer.set_seed(Some(7));
+    let image = renderer.render(&scene, &camera);
+
+    // A mirror floor in the copy only
+    let mut copy = scene.clone();
+    copy.set_actor_material(0, MaterialBuilder::metal().color([0.9, 0.9, 0.9]).fuzziness(0.0).build());
+    let copy_image = renderer.render(&copy, &camera);
+    assert!(count_different_pixels(&image, &copy_image, 1e-2) > width * height / 20);
+    print_ppm(&copy_image, 2.0, "cube_scene_mirror.ppm");
+
+    let original_image = renderer.render(&scene, &camera);
+    assert_eq!(image.data, original_image.data);
+}
 
-    let renderer = Renderer::new(0, width, 0, height, width, height, 0, 0, false);
-    let image = renderer.render(&mut scene, &camera);
-    let gamma = 2.0;
-    print_ppm(&image, gamma, "sphere_in_box_preview.ppm");
+fn create_basic_scene<T>(width: usize, height: usize) -> (Scene<T>, PerspectiveCamera<T>)
+    where T: Float
+{
+    let mut scene = Scene::<T>::new();
+    scene.set_background(Vec3::from_array([T::from_f64(0.75), T::from_f64(0.75), T::from_f64(0.75)]));
+
+    let r = T::one();
+    let sphere = Box::new(Sphere::<T>::new(r));
+    let sphere = Translation::new(sphere, Vec3::from_array([T::zero(), r, T::from_f64(-4.0)]));
+    let texture = UniformTexture::new(Vec3::from_array([T::one(), T::from_f64(0.2), T::from_f64(0.2)]));
+    let material = LambertianMaterial::<T>::new(Box::new(texture), T::from_f64(0.5));
+    scene.add_actor(Actor::<T>::new(Box::new(sphere), Box::new(material)));
+
+    let floor = Rectangle::<T>::new(T::from_f64(20.0), Axis::X, T::from_f64(20.0), Axis::Z);
+    let texture = UniformTexture::new(Vec3::from_array([T::from_f64(0.8), T::from_f64(0.8), T::from_f64(0.8)]));
+    let material = LambertianMaterial::<T>::new(Box::new(texture), T::from_f64(0.65));
+    scene.add_actor(Actor::<T>::new(Box::new(floor), Box::new(material)));
+
+    let mut camera = PerspectiveCamera::<T>::new();
+    camera.set_position(&[T::zero(), r, T::zero()]);
+    camera.set_lookat(&[T::zero(), r, T::from_f64(-4.0)]);
+    camera.set_aspect(T::from_f64(width as f64 / height as f64)).unwrap();
+    (scene, camera)
+}
 
-    let renderer = Renderer::new(0, width, 0, height, width, height, 1, 8, false);
-    let sampling = 128;
-    let mut image = Image::new(width, height);
-    for i in 0..sampling {
-        let delta = renderer.render(&scene, &camera);
-        mix_images(&mut image, &delta, i);
-        print_ppm(&image, gamma, "sphere_in_box.ppm");
-    }
+#[test]
+fn basic_scene() {
+    let (scene, _camera) = create_basic_scene::<f64>(4, 3);
+    let scene_hit = scene.cast_ray(&Ray::from_array([0.0, 1.0, 0.0], [0.0, 0.0, -1.0])).unwrap();
+    assert_eq!(scene_hit.hit.t, 3.0);
 }
 
+// The scene built with f32 directly, not converted from f64
 #[test]
-fn random_scene() {
+fn f32_scene() {
+    let width = 48;
+    let height = 32;
+    let mut renderer = Renderer::new(0, width, 0, height, width, height, 16, 4, false).unwrap();
+    renderer.set_seed(Some(1));
+
+    let (scene, camera) = create_basic_scene::<f64>(width, height);
+    let image = renderer.render(&scene, &camera);
+    let (scene_f32, camera_f32) = create_basic_scene::<f32>(width, height);
+    let image_f32 = renderer.render(&scene_f32, &camera_f32).to_f64();
+
+    // Both see the same things, only the noise of the bounces differs
+    let rmse = (image.data.iter().zip(image_f32.data.iter()).map(|(a, b)| (a - b) * (a - b)).sum::<f64>() / image.data.len() as f64).sqrt();
+    assert!(rmse > 0.0 && rmse < 0.05, "rmse {}", rmse);
+    let different = count_different_pixels(&image, &image_f32, 0.2);
+    assert!(different < width * height / 20);
+}
+
+#[test]
+fn back_face() {
+    // A diffuse rectangle seen from behind, while its front side faces a black wall.
+    // Light bounced off the back side must come from the camera side.
     let mut scene = Scene::<f64>::new();
-    // scene.set_background(Vec3::from_array([0.2, 0.2, 0.7]));
-    scene.set_background(Vec3::from_array([0.5, 0.7, 0.9]));
+    scene.set_background(Vec3::from_array([1.0, 1.0, 1.0]));
 
-    const N_SPHERES_X : usize = 20;
-    const N_SPHERES_Y : usize = N_SPHERES_X;
+    let hitable = Box::new(Rectangle::new(4.0, Axis::X, 4.0, Axis::Y));
+    let texture = UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0]));
+    let material = LambertianMaterial::<f64>::new(Box::new(texture), 0.5);
+    scene.add_actor(Actor::<f64>::new(hitable, Box::new(material)));
 
-    const MIN_X : f64 = -20.0;
-    const MAX_X : f64 = 20.0;
+    let hitable = Box::new(Rectangle::new(100.0, Axis::X, 100.0, Axis::Y));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, 0.5])));
+    let texture = UniformTexture::new(Vec3::from_array([0.0, 0.0, 0.0]));
+    let material = PlainMaterial::<f64>::new(Box::new(texture));
+    scene.add_actor(Actor::<f64>::new(hitable, Box::new(material)));
 
-    const MIN_Y : f64 = MIN_X;
-    const MAX_Y : f64 = MAX_X;
+    let width = 16;
+    let height = 16;
+    let mut camera = PerspectiveCamera::<f64>::new();
+    camera.set_position(&[0.0, 0.0, -3.0]);
+    camera.set_lookat(&[0.0, 0.0, 0.0]);
 
-    const MIN_RADIUS : f64 = 0.2;
-    const MAX_RADIUS : f64 = 0.4;
+    let renderer = Renderer::new(0, width, 0, height, width, height, 16, 2, false).unwrap();
+    let image = renderer.render(&scene, &camera);
+    print_ppm(&image, 2.0, "back_face.ppm");
 
-    const SPHERE_PROBABILITY : f64 = 0.66666666;
+    // The center of the image only sees the back of the rectangle
+    let mut sum = 0.0;
+    for j in 6..10 {
+        for i in 6..10 {
+            sum += image.data[3 * (j * width + i)];
+        }
+    }
+    let mean = sum / 16.0;
+    assert!((mean - 0.5).abs() < 0.05);
+}
 
-    const LAMBERTIAN_PROBABILITY : f64 = 0.3333;
-    const METAL_PROBABILITY : f64 = 0.3333;
-    // DIELECTRIC_PROBABILITY is 1 - LAMBERTIAN_PROBABILITY - METAL_PROBABILITY
+fn create_floor_scene(length: f32, width: usize, height: usize) -> (Scene<f32>, PerspectiveCamera<f32>) {
+    // A huge diffuse floor in single precision, seen at grazing angles.
+    // Rays bounce off the floor straight into the white background, so every
+    // floor pixel has the color of the floor, unless the bounced rays hit the
+    // floor again due to round-off.
+    let mut scene = Scene::<f32>::new();
+    scene.set_background(Vec3::from_array([1.0, 1.0, 1.0]));
+
+    let hitable = Box::new(Rectangle::<f32>::new(length, Axis::X, length, Axis::Y));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -4.3])));
+    let texture = UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0]));
+    let material = LambertianMaterial::<f32>::new(Box::new(texture), 0.5);
+    scene.add_actor(Actor::<f32>::new(hitable, Box::new(material)));
+
+    let mut camera = PerspectiveCamera::<f32>::new();
+    camera.set_position(&[0.0, -0.45 * length, 1.7]);
+    camera.set_lookat(&[0.0, 0.05 * length, -4.3]);
+    camera.set_up(&[0.0, 0.0, 1.0]);
+    camera.set_aspect(width as f32 / height as f32).unwrap();
 
-    const MIN_FUZZINESS : f64 = 0.0;
-    const MAX_FUZZINESS : f64 = 0.4;
+    (scene, camera)
+}
 
-    const MIN_REFRACTIVE : f64 = 1.2;
-    const MAX_REFRACTIVE : f64 = 2.4;
+fn create_tiny_sphere_scene(width: usize, height: usize) -> (Scene<f32>, PerspectiveCamera<f32>) {
+    // Same as the floor, a diffuse sphere seen with a white background has a uniform color
+    let mut scene = Scene::<f32>::new();
+    scene.set_background(Vec3::from_array([1.0, 1.0, 1.0]));
+
+    let center = [0.0123, 0.0456, -0.0789];
+    let hitable = Box::new(Sphere::<f32>::new(0.001));
+    let hitable = Box::new(Translation::new(hitable, Vec3::from_array(center)));
+    let texture = UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0]));
+    let material = LambertianMaterial::<f32>::new(Box::new(texture), 0.5);
+    scene.add_actor(Actor::<f32>::new(hitable, Box::new(material)));
+
+    let mut camera = PerspectiveCamera::<f32>::new();
+    camera.set_position(&[center[0], center[1] - 0.004, center[2]]);
+    camera.set_lookat(&center);
+    camera.set_up(&[0.0, 0.0, 1.0]);
+    camera.set_aspect(width as f32 / height as f32).unwrap();
 
-    let mut rng = rand::thread_rng();
+    (scene, camera)
+}
 
-    for i in 0..N_SPHERES_X {
-        for j in 0..N_SPHERES_Y {
-            let radius = MIN_RADIUS + (MAX_RADIUS - MIN_RADIUS) * rng.gen::<f64>();
-            let mut x = i as f64 + rng.gen::<f64>() * (1.0 - radius);
-            x = MIN_X + (MAX_X - MIN_X) * x / N_SPHERES_X as f64;
-            let mut y = j as f64 + rng.gen::<f64>() * (1.0 - radius);
-            y = MIN_Y + (MAX_Y - MIN_Y) * y / N_SPHERES_Y as f64;
-
-            let hitable_select = rng.gen::<f64>();
-            let hitable : Box<dyn Hitable<f64>> = if hitable_select < SPHERE_PROBABILITY {
-                let hitable = Box::new(Sphere::<f64>::new(radius));
-                Box::new(Translation::new(hitable, Vec3::from_array([x, y, radius])))
-            } else {
-                let l = radius * 2.0 * 0.8;
-                let hitable = Box::new(Cube::<f64>::new(l, l, l));
-                Box::new(Translation::new(hitable, Vec3::from_array([x, y, radius * 0.8])))
-            };
-
-            let color = Vec3::from_array([rng.gen::<f64>(), rng.gen::<f64>(), rng.gen::<f64>()]);
-            let texture = Box::new(UniformTexture::new(color));
-            let material_select = rng.gen::<f64>();
-            let material : Box<dyn Material<f64>> = if material_select < LAMBERTIAN_PROBABILITY {
-                Box::new(LambertianMaterial::<f64>::new(texture, 0.5))
-            } else if material_select < LAMBERTIAN_PROBABILITY + METAL_PROBABILITY {
-                let fuzziness = MIN_FUZZINESS + (MAX_FUZZINESS - MIN_FUZZINESS) * rng.gen::<f64>();
-                Box::new(MetalMaterial::<f64>::new(texture, fuzziness))
-            } else {
-                let n = MIN_REFRACTIVE + (MAX_REFRACTIVE - MIN_REFRACTIVE) * rng.gen::<f64>();
-                Box::new(DielectricMaterial::<f64>::new(texture, n))
-            };
-            let actor = Actor::<f64> { hitable, material};
-            scene.add_actor(actor);
-        }
-    }
+// Number of color channels with the color of the diffuse object (seen) and
+// with neither the color of the object nor of the background (wrong)
+fn count_uniform_colors(scene: &Scene<f32>, camera: &PerspectiveCamera<f32>, width: usize, height: usize, filename: &str) -> (usize, usize) {
+    let renderer = Renderer::new(0, width, 0, height, width, height, 4, 2, false).unwrap();
+    let image = renderer.render(scene, camera).to_f64();
+    print_ppm(&image, 2.0, filename);
+    let seen = image.data.iter().filter(|x| (**x - 0.5).abs() <= 1e-3).count();
+    let wrong = image.data.iter().filter(|x| (**x - 0.5).abs() > 1e-3 && (**x - 1.0).abs() > 1e-3).count();
+    (seen, wrong)
+}
 
-    // Three larger spheres in the center
-    let radius = 2.0;
-    let sphere = Box::new(Sphere::<f64>::new(radius));
-    let sphere = Translation::new(sphere, Vec3::from_array([0.0, 0.0, radius]));
-    let color = Vec3::from_array([0.78, 1.0, 0.78]);
-    let texture = Box::new(UniformTexture::new(color));
-    let material = DielectricMaterial::<f64>::new(texture, 2.4);
-    let actor = Actor::<f64> { hitable: Box::new(sphere), material: Box::new(material)};
-    scene.add_actor(actor);
+#[test]
+fn floor_acne() {
+    let width = 64;
+    let height = 32;
+    let (mut scene, camera) = create_floor_scene(2000.0, width, height);
+
+    // The default epsilon is too small for single precision at this scale
+    scene.set_ray_epsilon(1e-9);
+    let (_seen, wrong) = count_uniform_colors(&scene, &camera, width, height, "floor_acne_before.ppm");
+    assert!(wrong > 0);
+    scene.set_ray_epsilon(1e-2);
+    let (_seen, wrong) = count_uniform_colors(&scene, &camera, width, height, "floor_acne.ppm");
+    assert_eq!(wrong, 0);
+}
 
-    let sphere = Box::new(Sphere::<f64>::new(radius));
-    let sphere = Translation::new(sphere, Vec3::from_array([0.0, - 2.0 * radius, radius]));
-    let color = Vec3::from_array([0.9, 0.9, 0.9]);
-    let texture = Box::new(UniformTexture::new(color));
-    let material = MetalMaterial::<f64>::new(texture, 0.0);
-    let actor = Actor::<f64> { hitable: Box::new(sphere), material: Box::new(material)};
-    scene.add_actor(actor);
+#[test]
+fn self_intersection() {
+    let width = 64;
+    let height = 32;
+    let (mut floor, floor_camera) = create_floor_scene(10000.0, width, height);
+    let (mut sphere, sphere_camera) = create_tiny_sphere_scene(width, height);
+
+    // A small epsilon causes acne on the floor, a large one makes the sphere disappear
+    floor.set_ray_epsilon(1e-9);
+    let (_seen, wrong) = count_uniform_colors(&floor, &floor_camera, width, height, "self_intersection_floor_epsilon.ppm");
+    assert!(wrong > 0);
+    sphere.set_ray_epsilon(1e-2);
+    let (seen, _wrong) = count_uniform_colors(&sphere, &sphere_camera, width, height, "self_intersection_sphere_epsilon.ppm");
+    assert_eq!(seen, 0);
+
+    // Offsetting the scattered rays works for both
+    for policy in [SelfIntersectPolicy::NormalOffset, SelfIntersectPolicy::Both].iter() {
+        floor.set_self_intersect_policy(*policy);
+        sphere.set_self_intersect_policy(*policy);
+        sphere.set_ray_epsilon(1e-9);
+
+        let (seen, wrong) = count_uniform_colors(&floor, &floor_camera, width, height, "self_intersection_floor.ppm");
+        assert!(seen > 0);
+        assert_eq!(wrong, 0);
+        let (seen, wrong) = count_uniform_colors(&sphere, &sphere_camera, width, height, "self_intersection_sphere.ppm");
+        assert!(seen > 0);
+        assert_eq!(wrong, 0);
+    }
+}
 
-    let sphere = Box::new(Sphere::<f64>::new(radius));
-    let sphere = Translation::new(sphere, Vec3::from_array([0.0, 2.0 * radius, radius]));
-    let color = Vec3::from_array([1.0, 0.15, 0.15]);
-    let texture = Box::new(UniformTexture::new(color));
-    let material = MetalMaterial::<f64>::new(texture, 0.1);
-    let actor = Actor::<f64> { hitable: Box::new(sphere), material: Box::new(material)};
-    scene.add_actor(actor);
+#[test]
+fn sphere_in_box() {
+    let mul = 4;
+    let width = 12 * mul;
+    let height = 8 * mul;
+    let (scene, camera) = scenes::sphere_in_box(width, height).unwrap();
 
-    // Sphere used as light
-    let radius = 4.0;
-    let sphere = Box::new(Sphere::<f64>::new(radius));
-    let sphere = Translation::new(sphere, Vec3::from_array([0.0, 1.0, 12.5]));
-    let color = Vec3::from_array([1.0, 1.0, 1.0]);
-    let texture = Box::new(UniformTexture::new(color));
-    let material = PlainMaterial::<f64>::new(texture);
-    let actor = Actor::<f64> { hitable: Box::new(sphere), material: Box::new(material)};
-    scene.add_actor(actor);
+    let renderer = Renderer::new(0, width, 0, height, width, height, 0, 0, false).unwrap();
+    let image = renderer.render(&scene, &camera);
+    let gamma = 2.0;
+    print_ppm(&image, gamma, "sphere_in_box_preview.ppm");
 
-    // Rectangle used as floor
-    let length = 2000.0;
-    let color0 = Vec3::from_array([1.0, 1.0, 1.0]);
-    let color1 = Vec3::from_array([0.8, 0.8, 0.8]);
-    let texture0 = UniformTexture::new(color0);
-    let texture1 = UniformTexture::new(color1);
-    let texture = Box::new(CheckerTexture::new(Box::new(texture0), Box::new(texture1)));
-
-    let hitable = Box::new(Rectangle::<f64>::new(length, Axis::X, length, Axis::Y));
-    let hitable = Box::new(Translation::new(hitable, Vec3::from_array([0.0, 0.0, -radius])));
-    let material = Box::new(LambertianMaterial::<f64>::new(texture, 0.75));
-    let actor = Actor::<f64> { hitable, material };
-    scene.add_actor(actor);
+    let renderer = Renderer::new(0, width, 0, height, width, height, 1, 8, false).unwrap();
+    let sampling = 128;
+    let mut image = Image::new(width, height);
+    for i in 0..sampling {
+        let delta = renderer.render(&scene, &camera);
+        mix_images(&mut image, &delta, i);
+        print_ppm(&image, gamma, "sphere_in_box.ppm");
+    }
+
+    // The two lights glow, the rest of the box stays the same
+    let bloomed = image.bloom(1.0, width / 12, 0.5);
+    print_ppm(&bloomed, gamma, "sphere_in_box_bloom.ppm");
+    let brighter = bloomed.data.iter().zip(image.data.iter()).filter(|(b, a)| b > a).count();
+    assert!(brighter > 0 && brighter < image.data.len());
+}
 
+#[test]
+fn random_scene() {
     let mul = 4;
     let width = 16 * mul;
     let height = 9 * mul;
-    let aspect = width as f64 / height as f64;
-    let mut camera = PerspectiveCamera::<f64>::new();
-    camera.set_aspect(aspect);
-    camera.set_fov(0.25 * std::f64::consts::PI);
-    camera.set_position(&[-6.0, -10.0, 3.0]);
-    camera.set_lookat(&[0.0, 0.0, 2.0]);
-    camera.set_up(&[0.0, 0.0, 1.0]);
-
-    // camera.set_position(&[0.0, 0.0, 20.0]);
-    // camera.set_lookat(&[0.0, 0.0, 0.0]);
-    // camera.set_up(&[0.0, 1.0, 0.0]);
-
-    camera.set_aperture(0.0);
-    let focus = (camera.get_lookat() - camera.get_position()).norm();
-    camera.set_focus(focus);
-
-    scene.set_tree_type(TreeType::Oct);
+    let (scene, camera) = scenes::random_scene(width, height, rand::thread_rng().gen()).unwrap();
 
-    let renderer = Renderer::new(0, width/4, 0, height/4, width/4, height/4, 0, 2, false);
-    let image = renderer.render(&mut scene, &camera);
+    let renderer = Renderer::new(0, width/4, 0, height/4, width/4, height/4, 0, 2, false).unwrap();
+    let image = renderer.render(&scene, &camera);
     let gamma = 2.0;
     print_ppm(&image, gamma, "random_scene_preview.ppm");
 
     let mut image = Image::new(width, height);
-    let renderer = Renderer::new(0, width, 0, height, width, height, 1, 16, false);
+    let renderer = Renderer::new(0, width, 0, height, width, height, 1, 16, false).unwrap();
     let sampling = 1024;
     for i in 0..sampling {
         let delta = renderer.render(&scene, &camera);
@@ -621,6 +553,107 @@ fn random_scene() {
     }
 }
 
+#[test]
+fn mipmap() {
+    let (width, height) = (160, 90);
+    let (scene, camera) = scenes::random_scene(width, height, 1).unwrap();
+    // Without reflections the floor shows the color of its texture
+    let renderer = Renderer::new(0, width, 0, height, width, height, 1, 0, false).unwrap();
+    let image = renderer.render(&scene, &camera);
+    print_ppm(&image, 2.0, "random_scene_mipmap.ppm");
+
+    // Far away along the floor, to the right of the large spheres in the
+    // middle. Rays without a spread pick single texels: the squares sparkle.
+    let mut rng = StdRng::seed_from_u64(1);
+    let (mut filtered, mut plain) = (vec![], vec![]);
+    for k in 0..200 {
+        let angle = 1.44 + 0.001 * k as f64;
+        let distance = 200.0 + 2.0 * k as f64;
+        let (x, y) = (-6.0 + distance * angle.cos(), -10.0 + distance * angle.sin());
+        let (r, s) = match camera.project(&Vec3::from_array([x, y, -4.0])) {
+            Some(rs) => rs,
+            None => continue
+        };
+        let (i, j) = (((r + 1.0) * 0.5 * width as f64).round() as usize, ((s + 1.0) * 0.5 * height as f64).round() as usize);
+        if i >= width || j >= height {
+            continue;
+        }
+        filtered.push(image.data[3 * (j * width + i)]);
+        let ray = camera.get_ray(2.0 * i as f64 / width as f64 - 1.0, 2.0 * j as f64 / height as f64 - 1.0);
+        plain.push(scene.get_color(&ray, 0, 0, 0, &mut rng).get_data()[0]);
+    }
+    assert!(filtered.len() > 100);
+    // The mean of the two colors, dimmed by the material
+    let gray = 0.9 * 0.75;
+    let spread = |values: &[f64]| values.iter().fold(0.0f64, |max, value| max.max((value - gray).abs()));
+    assert!(spread(&plain) > 0.05);
+    assert!(spread(&filtered) < 0.01);
+}
+
+#[test]
+fn orbit() {
+    let (width, height) = (32, 18);
+    let (mut scene, _) = scenes::random_scene(width, height, 1).unwrap();
+
+    // One turn around the spheres in the center in 8 keyframes
+    let n_keyframes = 8;
+    let keyframes = (0..=n_keyframes).map(|i| {
+        let angle = 2.0 * std::f64::consts::PI * i as f64 / n_keyframes as f64;
+        let position = Vec3::from_array([12.0 * angle.cos(), 12.0 * angle.sin(), 3.0]);
+        Keyframe::new(i as f64, position, Vec3::from_array([0.0, 0.0, 2.0]), Vec3::from_array([0.0, 0.0, 1.0]), 0.25 * std::f64::consts::PI)
+    }).collect();
+    let mut track = CameraTrack::new(keyframes).unwrap();
+    track.set_interpolation(Interpolation::CatmullRom);
+
+    let renderer = Renderer::new(0, width, 0, height, width, height, 4, 4, false).unwrap();
+    let mut frames = Vec::new();
+    render_sequence(&mut scene, &track, 24, 3.0, &renderer, |_, image| frames.push(image)).unwrap();
+    assert_eq!(frames.len(), 24);
+
+    for pair in frames.windows(2) {
+        let diff = image_diff(&pair[0], &pair[1]) / (width * height) as f64;
+        assert!(diff > 0.0);
+        assert!(diff < 0.5, "consecutive frames differ by {}", diff);
+    }
+}
+
+#[test]
+fn keyframed_sphere() {
+    let (width, height) = (48, 32);
+    let mut scene = Scene::<f64>::new();
+    scene.set_background(Vec3::new());
+
+    // A bright sphere moving one unit along x per frame
+    let keyframes = vec![(0.0, Vec3::from_array([-1.0, 0.0, 0.0])), (2.0, Vec3::from_array([1.0, 0.0, 0.0]))];
+    let hitable = KeyframedTransform::new(Box::new(Sphere::new(0.5)), keyframes).unwrap();
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+    scene.add_actor(Actor::new(Box::new(hitable), Box::new(PlainMaterial::new(texture))));
+    scene.set_tree_type(TreeType::Oct);
+
+    let keyframe = Keyframe::new(0.0, Vec3::from_array([0.0, 0.0, 5.0]), Vec3::new(), Vec3::from_array([0.0, 1.0, 0.0]), std::f64::consts::PI / 3.0);
+    let track = CameraTrack::new(vec![keyframe]).unwrap();
+    let renderer = Renderer::new(0, width, 0, height, width, height, 1, 1, false).unwrap();
+
+    // Mean column of the bright pixels of each frame
+    let mut centroids = Vec::new();
+    render_sequence(&mut scene, &track, 3, 1.0, &renderer, |_, image| {
+        let (mut sum, mut count) = (0.0, 0.0);
+        for index in 0..width * height {
+            if image.data[3 * index] > 0.5 {
+                sum += (index % width) as f64;
+                count += 1.0;
+            }
+        }
+        assert!(count > 0.0);
+        centroids.push(sum / count);
+    }).unwrap();
+
+    let shift0 = centroids[1] - centroids[0];
+    let shift1 = centroids[2] - centroids[1];
+    assert!(shift0 > 2.0, "centroids {:?}", centroids);
+    assert!((shift0 - shift1).abs() < 1.0, "centroids {:?}", centroids);
+}
+
 #[test]
 fn tree() {
     let mut scene = Scene::<f64>::new();
@@ -662,7 +695,7 @@ fn tree() {
                 let texture = Box::new(UniformTexture::new(color));
                 let material : Box<dyn Material<f64>> = Box::new(MetalMaterial::new(texture, 0.0));
 
-                let actor = Actor::<f64> { hitable: Box::new(sphere), material};
+                let actor = Actor::<f64>::new(Box::new(sphere), material);
                 scene.add_actor(actor);
             }
         }
@@ -673,39 +706,305 @@ fn tree() {
     let height = 9 * mul;
     let aspect = width as f64 / height as f64;
     let mut camera = PerspectiveCamera::<f64>::new();
-    camera.set_aspect(aspect);
-    camera.set_fov(0.3 * std::f64::consts::PI);
+    camera.set_aspect(aspect).unwrap();
+    camera.set_fov(0.3 * std::f64::consts::PI).unwrap();
     camera.set_position(&[-6.0, -10.0, 3.0]);
     camera.set_lookat(&[0.0, 0.0, 2.0]);
     camera.set_up(&[0.0, 0.0, 1.0]);
 
-    camera.set_aperture(0.0);
+    camera.set_aperture(0.0).unwrap();
     let focus = (camera.get_lookat() - camera.get_position()).norm();
-    camera.set_focus(focus);
+    camera.set_focus(focus).unwrap();
 
-    let renderer = Renderer::new(0, width, 0, height, width, height, 0, 0, false);
+    let renderer = Renderer::new(0, width, 0, height, width, height, 0, 0, false).unwrap();
 
+    // Each tree is built once when it's selected, rendering doesn't rebuild it
     scene.set_tree_type(TreeType::Linear);
-    let now = Instant::now();
-    let image_linear = renderer.render(&scene, &camera);
-    let _t_linear = now.elapsed().as_millis();
-    // println!("Linear: {}", t_linear);
+    let builds = scene.get_tree_builds();
+    let (image_linear, report_linear) = renderer.render_with_stats(&scene, &camera);
+    assert_eq!(report_linear.tree_builds, builds);
 
     scene.set_tree_type(TreeType::Binary);
-    let now = Instant::now();
-    let image_binary = renderer.render(&mut scene, &camera);
-    let _t_binary = now.elapsed().as_millis();
-    let diff = image_diff(&image_linear, &image_binary);
-    //assert!(t_binary < t_linear);
-    assert_eq!(diff, 0.0);
-    // println!("Binary -  t: {}  diff: {}", t_binary, diff);
+    assert_eq!(scene.get_tree_builds(), builds + 1);
+    let (image_binary, report_binary) = renderer.render_with_stats(&scene, &camera);
+    assert_eq!(report_binary.tree_builds, builds + 1);
+    assert_eq!(image_diff(&image_linear, &image_binary), 0.0);
+    // The same rays, whatever the tree
+    assert_eq!(report_binary.rays_per_depth, report_linear.rays_per_depth);
 
     scene.set_tree_type(TreeType::Oct);
-    let now = Instant::now();
-    let image_oct = renderer.render(&scene, &camera);
-    let _t_oct = now.elapsed().as_millis();
-    let diff = image_diff(&image_linear, &image_oct);
-    //assert!(t_oct < t_linear);
-    assert_eq!(diff, 0.0);
-    // println!("Oct -  t: {}  diff: {}", t_oct, diff);
+    let (image_oct, report_oct) = renderer.render_with_stats(&scene, &camera);
+    assert_eq!(report_oct.tree_builds, builds + 2);
+    assert_eq!(image_diff(&image_linear, &image_oct), 0.0);
+    assert_eq!(report_oct.rays_per_depth, report_linear.rays_per_depth);
+
+    // The trees test fewer spheres than the linear search, for the same hits
+    // and the same number of rays
+    scene.reset_counters();
+    let (_, stats_oct) = renderer.render_verbose(&scene, &camera);
+    let rays_oct = scene.ray_count();
+    assert_eq!(rays_oct, report_oct.get_ray_count());
+    scene.set_tree_type(TreeType::Binary);
+    scene.reset_counters();
+    let (_, stats_binary) = renderer.render_verbose(&scene, &camera);
+    assert_eq!(scene.ray_count(), rays_oct);
+    scene.set_tree_type(TreeType::Linear);
+    scene.reset_counters();
+    let (_, stats_linear) = renderer.render_verbose(&scene, &camera);
+    assert_eq!(scene.ray_count(), rays_oct);
+    assert_eq!(stats_linear.intersection_tests, (N_SPHERES_X * N_SPHERES_Y * N_SPHERES_Z) as u64 * stats_linear.primary_rays);
+    for stats in [stats_binary, stats_oct].iter() {
+        assert_eq!(stats.hits, stats_linear.hits);
+        assert!(2 * stats.intersection_tests < stats_linear.intersection_tests);
+        assert!(stats.bvh_traversal_steps > stats.primary_rays);
+    }
+}
+
+#[test]
+fn caustics() {
+    let (mut scene, _camera) = scenes::rectangle_room(160, 90).unwrap();
+    let mut rng = StdRng::seed_from_u64(1);
+
+    // Floor right in front of the glass sphere, only lit through the sphere.
+    // The sphere hides it from the camera, look at it from close by.
+    let floor = |y: f64| floor_hit([0.0, y, -4.5], [0.0, 0.0, 1.0]);
+    let ray = Ray::from_array([0.0, -0.5, -4.0], [0.0, 1.0, -0.5]);
+    let mean_color = |scene: &Scene<f64>, samples: usize, rng: &mut StdRng| {
+        (0..samples).map(|_| scene.get_color(&ray, 0, 4, 16, rng).get_data()[0]).sum::<f64>() / samples as f64
+    };
+    let reference = mean_color(&scene, 50000, &mut rng);
+    let plain: Vec<f64> = (0..8).map(|_| mean_color(&scene, 64, &mut rng)).collect();
+
+    scene.build_caustics(3000000, 0.2, &mut rng);
+    let caustics = scene.get_caustics().unwrap();
+    assert!(caustics.len() > 50000);
+    let irradiance = caustics.estimate_irradiance(&floor(0.5)).get_data()[0];
+    assert!(irradiance > 2.0);
+    // Hardly any away from the sphere
+    assert!(caustics.estimate_irradiance(&floor(-4.0)).get_data()[0] < 0.05 * irradiance);
+
+    // The paths don't find the light through the sphere again: the result
+    // matches path tracing alone, with much less noise. The photons leave a
+    // bias of a few percent that depends on the map, it isn't noise.
+    let gathered: Vec<f64> = (0..8).map(|_| mean_color(&scene, 64, &mut rng)).collect();
+    let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+    let spread = |values: &[f64]| values.iter().fold(0.0, |max: f64, v| max.max((v - mean(values)).abs()));
+    assert!((mean(&gathered) - reference).abs() < 0.1 * reference);
+    assert!(spread(&gathered) < spread(&plain));
+
+    scene.clear_caustics();
+    assert!(scene.get_caustics().is_none());
+}
+
+#[test]
+fn bdpt() {
+    let (width, height) = (12, 8);
+    let (scene, camera) = scenes::doorway_room(width, height).unwrap();
+    let render = |integrator: Integrator, samples: usize, seed: u64| {
+        let mut renderer = Renderer::new(0, width, 0, height, width, height, samples, 32, true).unwrap();
+        renderer.set_integrator(integrator);
+        renderer.set_seed(Some(seed));
+        let (image, stats) = renderer.render_verbose(&scene, &camera);
+        (image, stats.primary_rays + stats.shadow_rays + stats.bounce_rays)
+    };
+    let mean = |image: &Image<f64>| image.data.iter().sum::<f64>() / image.data.len() as f64;
+
+    // Both converge to the same image
+    let (reference, _) = render(Integrator::Path, 4000, 1);
+    let (converged, _) = render(Integrator::Bdpt, 1000, 2);
+    assert!((mean(&converged) - mean(&reference)).abs() < 0.02 * mean(&reference));
+    assert!(image_diff(&reference, &converged) < 0.1 * mean(&reference) * (3 * width * height) as f64);
+
+    // For the same number of rays the bidirectional paths are much less noisy.
+    // The request asks for equal render times, but the clock depends on the
+    // build and on the tests running next to this one. Tracing the rays is
+    // most of the work of both integrators, so the rays stand in for the time.
+    let (bdpt, bdpt_rays) = render(Integrator::Bdpt, 16, 3);
+    let (_, path_rays) = render(Integrator::Path, 16, 4);
+    let samples = (16.0 * bdpt_rays as f64 / path_rays as f64).round() as usize;
+    let (path, _) = render(Integrator::Path, samples, 5);
+    assert!(image_diff(&reference, &bdpt) < 0.5 * image_diff(&reference, &path));
+}
+
+#[test]
+fn environment() {
+    // Dark sky with a single bright texel high above the +x axis
+    let (width, height) = (64, 32);
+    let (sun_i, sun_j) = (32, 4);
+    let mut image = Image::new(width, height);
+    for value in image.data.iter_mut() {
+        *value = 0.001;
+    }
+    let index = 3 * (sun_j * width + sun_i);
+    image.data[index..index + 3].copy_from_slice(&[5000.0, 5000.0, 5000.0]);
+    let environment = Environment::new(image);
+
+    let mut scene = Scene::<f64>::new();
+    scene.add_actor(Actor::sphere(1.0).at([0.0, 0.0, 0.0]).lambertian([1.0, 1.0, 1.0], 0.5));
+
+    // Look at the sphere from the sun: the light arrives straight along the
+    // normal, and the rest of the sky hardly adds anything
+    let pi = std::f64::consts::PI;
+    let theta = pi * (sun_j as f64 + 0.5) / height as f64;
+    let phi = 2.0 * pi * (sun_i as f64 + 0.5) / width as f64 - pi;
+    let sun = Vec3::from_array([theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()]);
+    let ray = Ray::from_vec(&sun * 10.0, &sun * (-1.0));
+    let solid_angle = (2.0 * pi / width as f64) * (pi / height as f64) * theta.sin();
+    let expected = 0.5 * 2.0 / pi * 5000.0 * solid_angle;
+
+    let mut rng = StdRng::seed_from_u64(2);
+
+    scene.set_environment(Some(environment));
+    let (importance_mean, importance_std) = stats(&estimates(&scene, &ray, 256, &mut rng));
+    let mut environment = scene.get_environment().unwrap().convert::<f64>();
+    environment.set_importance_sampling(false);
+    scene.set_environment(Some(environment));
+    let (uniform_mean, uniform_std) = stats(&estimates(&scene, &ray, 25600, &mut rng));
+
+    // Both agree with the analytic radiance, importance sampling with 100
+    // times fewer samples and still less noise
+    assert!((importance_mean - expected).abs() < 0.02 * expected);
+    assert!((uniform_mean - expected).abs() < 0.05 * expected);
+    assert!(importance_std < 0.1 * uniform_std);
+}
+
+#[test]
+fn portals() {
+    // A closed room of 2 x 2 x 2 with a window of 0.5 x 0.5 in the wall at
+    // x = 1 onto a bright sky
+    let mut scene = Scene::<f64>::new();
+    let wall = |scene: &mut Scene<f64>, size: (f64, Axis, f64, Axis), at: [f64; 3]| {
+        scene.add_actor(Actor::rectangle(size.0, size.1, size.2, size.3).at(at).lambertian([0.5, 0.5, 0.5], 1.0));
+    };
+    wall(&mut scene, (2.0, Axis::X, 2.0, Axis::Y), [0.0, 0.0, -1.0]);
+    wall(&mut scene, (2.0, Axis::X, 2.0, Axis::Y), [0.0, 0.0, 1.0]);
+    wall(&mut scene, (2.0, Axis::X, 2.0, Axis::Z), [0.0, -1.0, 0.0]);
+    wall(&mut scene, (2.0, Axis::X, 2.0, Axis::Z), [0.0, 1.0, 0.0]);
+    wall(&mut scene, (2.0, Axis::Y, 2.0, Axis::Z), [-1.0, 0.0, 0.0]);
+    wall(&mut scene, (2.0, Axis::Y, 0.75, Axis::Z), [1.0, 0.0, 0.625]);
+    wall(&mut scene, (2.0, Axis::Y, 0.75, Axis::Z), [1.0, 0.0, -0.625]);
+    wall(&mut scene, (0.75, Axis::Y, 0.5, Axis::Z), [1.0, 0.625, 0.0]);
+    wall(&mut scene, (0.75, Axis::Y, 0.5, Axis::Z), [1.0, -0.625, 0.0]);
+    let mut sky = Image::new(2, 1);
+    for value in sky.data.iter_mut() {
+        *value = 10.0;
+    }
+    scene.set_environment(Some(Environment::new(sky)));
+
+    // The floor in the middle of the room
+    let ray = Ray::from_array([0.0, 0.0, 0.0], [0.0, 0.0, -1.0]);
+    let mut rng = StdRng::seed_from_u64(3);
+
+    let (_, plain_std) = stats(&estimates(&scene, &ray, 256, &mut rng));
+    let (reference_mean, reference_std) = stats(&estimates(&scene, &ray, 16384, &mut rng));
+    scene.add_portal(Rectangle::new(0.5, Axis::Y, 0.5, Axis::Z), Vec3::from_array([1.0, 0.0, 0.0]));
+    let (portal_mean, portal_std) = stats(&estimates(&scene, &ray, 256, &mut rng));
+
+    // The same light as without the portal, with 10 times less noise
+    assert!((portal_mean - reference_mean).abs() < 3.0 * (portal_std + reference_std) / 4.0);
+    assert!(portal_std < 0.1 * plain_std);
+}
+
+#[test]
+fn sampler() {
+    // Without reflections the colors are fixed, the noise comes only from
+    // the position in the pixel (edges) and on the lens (out of focus)
+    let (width, height) = (32, 18);
+    let (scene, mut camera) = scenes::cube_scene(width, height).unwrap();
+    let render = |camera: &PerspectiveCamera<f64>, sampler: Sampler, samples: usize, seed: u64| {
+        let mut renderer = Renderer::new(0, width, 0, height, width, height, samples, 0, true).unwrap();
+        renderer.set_sampler(sampler);
+        renderer.set_seed(Some(seed));
+        renderer.render(&scene, camera)
+    };
+    let rmse = |reference: &Image<f64>, image: &Image<f64>| {
+        let sum: f64 = reference.data.iter().zip(image.data.iter()).map(|(a, b)| (a - b) * (a - b)).sum();
+        (sum / reference.data.len() as f64).sqrt()
+    };
+    let mean_rmse = |camera: &PerspectiveCamera<f64>, reference: &Image<f64>, sampler: Sampler| {
+        (0..4).map(|seed| rmse(reference, &render(camera, sampler, 16, seed))).sum::<f64>() / 4.0
+    };
+
+    for aperture in [0.0, 1.0].iter() {
+        camera.set_aperture(*aperture).unwrap();
+        camera.set_focus(7.5).unwrap();
+        let reference = render(&camera, Sampler::Independent, 4096, 100);
+        let independent = mean_rmse(&camera, &reference, Sampler::Independent);
+        let stratified = mean_rmse(&camera, &reference, Sampler::Stratified);
+        let halton = mean_rmse(&camera, &reference, Sampler::Halton);
+        assert!(stratified < 0.8 * independent);
+        assert!(halton < 0.75 * independent);
+        // All converge to the same image
+        let halton = render(&camera, Sampler::Halton, 4096, 200);
+        assert!(rmse(&reference, &halton) < 0.2 * independent);
+    }
+}
+
+#[test]
+fn blue_noise() {
+    let (width, height) = (48, 32);
+    let (scene, camera) = scenes::rectangle_room(width, height).unwrap();
+    let render = |sampler: Sampler, seed: u64| {
+        let mut renderer = Renderer::new(0, width, 0, height, width, height, 1, 8, true).unwrap();
+        renderer.set_sampler(sampler);
+        renderer.set_seed(Some(seed));
+        renderer.render(&scene, &camera)
+    };
+    let accumulate = |sampler: Sampler, seed: u64| {
+        let mut image = Image::new(width, height);
+        for i in 0..256 {
+            mix_images(&mut image, &render(sampler, seed + i as u64), i);
+        }
+        image
+    };
+    // One sample per pixel of each
+    print_ppm(&render(Sampler::Independent, 0), 2.0, "rectangle_room_white_noise.ppm");
+    print_ppm(&render(Sampler::BlueNoise, 0), 2.0, "rectangle_room_blue_noise.ppm");
+
+    // Both converge to the same image, as close as two independent renders
+    let white = accumulate(Sampler::Independent, 1000);
+    let blue = accumulate(Sampler::BlueNoise, 2000);
+    let other = accumulate(Sampler::Independent, 3000);
+    assert!(image_diff(&white, &blue) < 1.2 * image_diff(&white, &other));
+    let mean = |image: &Image<f64>| image.data.iter().sum::<f64>() / image.data.len() as f64;
+    assert!((mean(&blue) - mean(&white)).abs() < 0.01 * mean(&white));
+}
+
+#[test]
+fn dispersion() {
+    // White light through the prism: the violet end of the rainbow lands
+    // closest to it, the red end farthest away
+    let (mut scene, _camera) = scenes::prism(16, 9).unwrap();
+    let mut rng = StdRng::seed_from_u64(1);
+
+    // The light of the caustic on the floor between x0 and x1, around z = 0
+    let band = |scene: &Scene<f64>, x0: f64, x1: f64| {
+        let caustics = scene.get_caustics().unwrap();
+        let mut sum = Vec3::<f64>::new();
+        for i in 0..=8 {
+            for k in 0..=4 {
+                let x = x0 + (x1 - x0) * i as f64 / 8.0;
+                let z = -0.5 + 0.25 * k as f64;
+                sum = sum + caustics.estimate_irradiance(&floor_hit([x, 0.0, z], [0.0, 1.0, 0.0]));
+            }
+        }
+        sum.get_data().to_vec()
+    };
+
+    scene.build_spectral_caustics(2000000, 0.15, &mut rng);
+    let near = band(&scene, -4.5, -3.5);
+    let far = band(&scene, -8.5, -7.5);
+    assert!(near[2] > 4.0 * near[0].max(near[1]));
+    assert!(far[0] > 2.0 * far[1].max(far[2]));
+
+    // Without dispersion it's white, the same as without wavelengths
+    let texture = Box::new(UniformTexture::new(Vec3::from_array([1.0, 1.0, 1.0])));
+    scene.set_actor_material(1, Box::new(DielectricMaterial::new(texture, 1.6)));
+    scene.build_spectral_caustics(8000000, 0.15, &mut rng);
+    let white = band(&scene, -8.5, -3.5);
+    scene.build_caustics(8000000, 0.15, &mut rng);
+    let rgb = band(&scene, -8.5, -3.5);
+    for k in 0..3 {
+        assert!((white[k] / rgb[k] - 1.0).abs() < 0.1);
+    }
 }